@@ -5,8 +5,11 @@
 #![allow(clippy::useless_let_if_seq)]
 
 use mod_ansi as ansi;
+use mod_common::LayoutStrategy;
+use mod_utils::{ ElidedDebug, Either, };
 use mod_declaration_builder::generate_declarations;
 use mod_codegen::Codegen;
+use mod_bytecode::{ Encode, optimizer::instruction_count, };
 
 use mod_frontend::{
   session::SESSION,
@@ -14,44 +17,146 @@ use mod_frontend::{
   lexer::Lexer,
   parser::Parser,
   analyzer::Analyzer,
+  timing::TimingReport,
   ast,
 };
 
 use mod_rc::context::Context;
 
 
+const USAGE: &str = "\
+Usage: mod_language <input> [options]
+
+Options:
+  -o, --out <path>       Write the encoded bytecode module to a file
+  --color=<mode>         Use ansi colors: always, never, or auto
+  --layout=<strategy>    Arrange struct fields: c_order or optimized
+  --warnings-as-errors   Stop before codegen if there are any warnings
+  --timings              Print the time taken by each compilation phase";
+
+
+/// The files read and written by a compilation
+struct Paths {
+  input: String,
+  output: Option<String>,
+}
+
+fn parse_paths () -> Result<Paths, String> {
+  let mut input = None;
+  let mut output = None;
+
+  let mut args = std::env::args().skip(1);
+
+  while let Some(arg) = args.next() {
+    if arg == "-o" || arg == "--out" {
+      match args.next() {
+        Some(path) => output = Some(path),
+        None => return Err(format!("Expected a path to follow `{}`", arg))
+      }
+    } else if let Some(path) = arg.strip_prefix("--out=") {
+      output = Some(path.to_owned())
+    } else if arg.starts_with('-') {
+      // other flags are handled by their own parsers
+      continue
+    } else if input.is_none() {
+      input = Some(arg)
+    } else {
+      return Err(format!("Unexpected argument `{}`, only one input path can be given", arg))
+    }
+  }
+
+  match input {
+    Some(input) => Ok(Paths { input, output }),
+    None => Err("No input path given".to_owned())
+  }
+}
+
+fn parse_color_mode () -> ansi::ColorMode {
+  let mut mode = ansi::ColorMode::default();
+
+  for arg in std::env::args().skip(1) {
+    if let Some(value) = arg.strip_prefix("--color=") {
+      match value.parse() {
+        Ok(parsed) => mode = parsed,
+        Err(e) => println!("{}, using {}", e, mode),
+      }
+    }
+  }
+
+  mode
+}
+
+fn parse_layout_strategy () -> LayoutStrategy {
+  let mut strategy = LayoutStrategy::default();
+
+  for arg in std::env::args().skip(1) {
+    if let Some(value) = arg.strip_prefix("--layout=") {
+      match value.parse() {
+        Ok(parsed) => strategy = parsed,
+        Err(e) => println!("{}, using {}", e, strategy),
+      }
+    }
+  }
+
+  strategy
+}
+
+
 fn main () -> std::io::Result<()> {
-  if !ansi::enable() { println!("Failed to enable ansi coloring for terminal") }
-  else { println!("\n{}Ansi coloring enabled for terminal{}\n", ansi::Foreground::Green, ansi::Foreground::Reset) }
+  let paths = parse_paths().unwrap_or_else(|e| {
+    eprintln!("{}\n\n{}", e, USAGE);
+    std::process::exit(2)
+  });
+
+  let color_mode = parse_color_mode();
+  let warnings_as_errors = std::env::args().skip(1).any(|arg| arg == "--warnings-as-errors");
+  let mut timings = if std::env::args().skip(1).any(|arg| arg == "--timings") { TimingReport::enabled() } else { TimingReport::disabled() };
+
+  if ansi::configure(color_mode) { println!("\n{}Ansi coloring enabled for terminal{}\n", ansi::Foreground::Green, ansi::Foreground::Reset) }
+  else if color_mode != ansi::ColorMode::Never { println!("Ansi coloring disabled for terminal") }
   
 
   SESSION.init();
+
+  if let Some(width) = std::env::var("COLUMNS").ok().and_then(|columns| columns.parse().ok()) {
+    SESSION.set_render_width(width)
+  }
+  SESSION.set_layout_strategy(parse_layout_strategy());
   SOURCE_MANAGER.init("./test_scripts/modules/".into());
 
 
-  let source = SOURCE_MANAGER.load_source("./test_scripts/body_analysis.ms").expect("Could not find entry source file");
+  let source = SOURCE_MANAGER.load_source(&paths.input).unwrap_or_else(|e| {
+    match e {
+      Either::A(_) => eprintln!("Source file `{}` was already loaded", paths.input),
+      Either::B(e) => eprintln!("Could not read source file `{}`: {}", paths.input, e),
+    }
+
+    std::process::exit(1)
+  });
 
 
   let mut lexer = Lexer::new(source);
 
-  let stream = lexer.lex_stream();
+  let stream = timings.time("lex", || lexer.lex_stream());
+  timings.count("tokens", stream.len());
 
   println!("Got token stream, dumping to ./log/stream");
   if !std::path::Path::new("./log").exists() { std::fs::create_dir("./log").expect("Failed to create ./log dir"); }
-  std::fs::write("./log/stream", format!("{:#?}", stream)).expect("Failed to dump token stream to ./log/stream");
+  std::fs::write("./log/stream", format!("{:#?}", ElidedDebug::new(&stream))).expect("Failed to dump token stream to ./log/stream");
 
 
   let mut parser = Parser::new(&stream);
 
-  let ast_vec = parser.parse_ast();
+  let ast_vec = timings.time("parse", || parser.parse_ast());
+  timings.count("items", ast_vec.len());
 
   println!("Got ast, dumping to ./log/ast");
-  std::fs::write("./log/ast", format!("{:#?}", ast_vec)).expect("Failed to dump token ast to ./log/ast");
+  std::fs::write("./log/ast", format!("{:#?}", ElidedDebug::new(&ast_vec))).expect("Failed to dump token ast to ./log/ast");
 
 
   let analyzer = Analyzer::new();
 
-  let (context, transformed_ast) = analyzer.analyze(ast_vec);
+  let (context, transformed_ast) = timings.time("analyze", || analyzer.analyze(ast_vec));
 
   println!("Got context, dumping to ./log/context");
   std::fs::write("./log/context", format!("{:#?}", context)).expect("Failed to dump context to ./log/context");
@@ -62,9 +167,16 @@ fn main () -> std::io::Result<()> {
 
   if !SESSION.messages().is_empty() {
     SESSION.print_messages();
-    if SESSION.count_errors() > 0 {
-      println!("Cannot procede to codegen due to errors");
-      return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput))
+
+    let exit_code = SESSION.exit_code_with(warnings_as_errors);
+
+    if exit_code != 0 {
+      if SESSION.count_errors() > 0 {
+        println!("Cannot proceed to codegen due to errors");
+      } else {
+        println!("Cannot proceed to codegen due to warnings (--warnings-as-errors is set)");
+      }
+      std::process::exit(exit_code)
     }
   }
 
@@ -77,14 +189,28 @@ fn main () -> std::io::Result<()> {
 
   let codegen = Codegen::new(&context, "test_module".to_owned(), (0, 0, 0).into());
 
-  let bc = codegen.generate();
+  let bc = timings.time("codegen", || codegen.generate());
+  timings.count("instructions", instruction_count(&bc));
 
   println!("Got bytecode, dumping to ./log/bc");
   std::fs::write("./log/bc", format!("{}", bc)).expect("Failed to dump bytecode to ./log/bc");
 
+  if let Some(output) = paths.output.as_ref() {
+    let mut encoded = Vec::new();
+    bc.encode(&mut encoded);
+
+    println!("Writing encoded bytecode to {}", output);
+    std::fs::write(output, encoded)?;
+  }
+
 
   let _context = Context::default();
 
 
+  if timings.is_enabled() {
+    println!("Timings:\n{}", timings);
+  }
+
+
   Ok(())
 }
\ No newline at end of file
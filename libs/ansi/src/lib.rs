@@ -6,6 +6,7 @@
 
 use std::{
   fmt::{ Display, Debug, Formatter, Result as FMTResult, },
+  str::{ FromStr, },
 };
 
 
@@ -22,6 +23,13 @@ mod windows {
     fn SetConsoleMode (h: Handle, in_mode: u32) -> i32;
   }
 
+  pub fn stdout_is_console () -> bool {
+    unsafe {
+      let mut mode = 0u32;
+      GetConsoleMode(GetStdHandle(STD_OUTPUT_HANDLE), &mut mode) != 0
+    }
+  }
+
   pub fn enable_ansi_colors () -> bool {
     unsafe {
       let handle = GetStdHandle(STD_OUTPUT_HANDLE);
@@ -54,6 +62,12 @@ pub fn enable () -> bool {
 }
 
 
+/// Disable ansi color codes
+pub fn disable () {
+  unsafe { ANSI_ENABLED = false }
+}
+
+
 /// Check if ansi color coding is enabled
 #[inline]
 pub fn is_enabled () -> bool {
@@ -61,6 +75,95 @@ pub fn is_enabled () -> bool {
 }
 
 
+/// Determine if stdout is connected to a terminal
+#[cfg(windows)]
+pub fn stdout_is_terminal () -> bool {
+  windows::stdout_is_console()
+}
+
+/// Determine if stdout is connected to a terminal
+#[cfg(unix)]
+pub fn stdout_is_terminal () -> bool {
+  extern "C" { fn isatty (fd: i32) -> i32; }
+  unsafe { isatty(1) != 0 }
+}
+
+/// Determine if stdout is connected to a terminal
+#[cfg(not(any(windows, unix)))]
+pub fn stdout_is_terminal () -> bool {
+  false
+}
+
+
+/// Determines when ansi color codes should be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+  /// Always use color codes, even if stdout is not a terminal
+  Always,
+  /// Never use color codes
+  Never,
+  /// Use color codes if stdout is a terminal and the `NO_COLOR` environment variable is not set
+  Auto,
+}
+
+impl Default for ColorMode { fn default () -> Self { Self::Auto } }
+
+impl ColorMode {
+  /// Get a ColorMode in str form
+  pub fn get_name (self) -> &'static str {
+    match self {
+      ColorMode::Always => "always",
+      ColorMode::Never => "never",
+      ColorMode::Auto => "auto",
+    }
+  }
+}
+
+impl Display for ColorMode {
+  fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    write!(f, "{}", self.get_name())
+  }
+}
+
+impl FromStr for ColorMode {
+  type Err = String;
+
+  fn from_str (s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "always" => Ok(ColorMode::Always),
+      "never" => Ok(ColorMode::Never),
+      "auto" => Ok(ColorMode::Auto),
+      _ => Err(format!("Invalid color mode `{}`, expected always, never, or auto", s))
+    }
+  }
+}
+
+/// Enable or disable ansi color codes according to a ColorMode
+/// 
+/// Returns whether ansi color codes are enabled after configuration
+pub fn configure (mode: ColorMode) -> bool {
+  match mode {
+    ColorMode::Always => {
+      enable();
+      // even if the platform failed to enable processing, the caller asked for the codes to be emitted
+      unsafe { ANSI_ENABLED = true }
+    },
+
+    ColorMode::Never => disable(),
+
+    ColorMode::Auto => {
+      if std::env::var_os("NO_COLOR").is_none() && stdout_is_terminal() {
+        enable();
+      } else {
+        disable();
+      }
+    },
+  }
+
+  is_enabled()
+}
+
+
 /// An ansi foreground color, printing this will colorize future text output
 #[repr(u8)]
 #[allow(missing_docs)]
@@ -229,4 +332,54 @@ impl Background {
   pub fn wrap<D: Display> (self, value: D) -> WrapBackground<D> {
     WrapBackground { value, color: self }
   }
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::{
+    sync::atomic::{ AtomicBool, Ordering, },
+    thread::{ yield_now, },
+  };
+
+  static LOCK: AtomicBool = AtomicBool::new(false);
+
+  fn configured_output (mode: ColorMode) -> String {
+    while LOCK.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() { yield_now() }
+
+    configure(mode);
+    let output = format!("{}", Foreground::Red.wrap("text"));
+
+    LOCK.store(false, Ordering::SeqCst);
+
+    output
+  }
+
+  #[test]
+  fn color_mode_parse () {
+    assert_eq!("always".parse(), Ok(ColorMode::Always));
+    assert_eq!("never".parse(), Ok(ColorMode::Never));
+    assert_eq!("auto".parse(), Ok(ColorMode::Auto));
+    assert!("sometimes".parse::<ColorMode>().is_err());
+  }
+
+  #[test]
+  fn color_mode_always () {
+    assert_eq!(configured_output(ColorMode::Always), "\x1b[31mtext\x1b[39m");
+  }
+
+  #[test]
+  fn color_mode_never () {
+    assert_eq!(configured_output(ColorMode::Never), "text");
+  }
+
+  #[test]
+  fn color_mode_auto () {
+    let expect_color = std::env::var_os("NO_COLOR").is_none() && stdout_is_terminal();
+    let output = configured_output(ColorMode::Auto);
+
+    assert_eq!(output.contains('\x1b'), expect_color);
+  }
 }
\ No newline at end of file
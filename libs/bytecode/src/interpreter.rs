@@ -0,0 +1,575 @@
+//! A stack-based interpreter executing the instructions of a bytecode `Module`
+
+use std::{
+  fmt::{ Display, Formatter, Result as FMTResult, },
+  collections::{ HashMap, HashSet, },
+};
+
+use super::{
+  Module, Function, Instruction, InstructionKind, ImmediateValue, IntrinsicType, TypeData,
+  GlobalID, FunctionID, LocalID, VerifyError,
+  optimizer::{ fold_unary, fold_binary, fold_pow, pow_intrinsics, },
+};
+
+
+/// An error which stopped the execution of a `Module` by an `Interpreter`
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterpretError {
+  /// A Function was referenced which is imported by the Module rather than defined in it
+  UnresolvedFunction(FunctionID),
+  /// A Global was referenced which is imported by the Module rather than defined in it
+  UnresolvedGlobal(GlobalID),
+  /// A Function was called whose Type is not a function signature
+  InvalidSignature(FunctionID),
+  /// A Function was called with the wrong number of arguments
+  ArgumentCount {
+    /// The Function being called
+    function: FunctionID,
+    /// The number of parameters of the Function
+    expected: usize,
+    /// The number of arguments given
+    found: usize,
+  },
+  /// An Instruction tried to pop a value off an empty stack
+  StackUnderflow(InstructionKind),
+  /// An Instruction was given operands of types it does not operate on
+  InvalidOperands(InstructionKind),
+  /// An integer division or remainder was by zero, or overflowed
+  InvalidDivision(InstructionKind),
+  /// A LocalAddress referenced a local which does not exist in the current stack frame
+  InvalidLocal(LocalID),
+  /// A Load or Store used the address of a local whose stack frame has ended
+  DanglingAddress,
+  /// A Load read a local which has not been stored to
+  UninitializedLocal(LocalID),
+  /// A Load read a Global which has not been initialized
+  UninitializedGlobal(GlobalID),
+  /// A Break or Continue was executed outside of a LoopBlock
+  OutsideLoop(InstructionKind),
+  /// An Instruction is not yet supported by the Interpreter
+  Unsupported(InstructionKind),
+}
+
+impl Display for InterpretError {
+  fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    match self {
+      InterpretError::UnresolvedFunction(id) => write!(f, "function {} is imported and cannot be executed", id),
+      InterpretError::UnresolvedGlobal(id) => write!(f, "global {} is imported and cannot be accessed", id),
+      InterpretError::InvalidSignature(id) => write!(f, "function {} does not have a function type", id),
+      InterpretError::ArgumentCount { function, expected, found } => write!(f, "function {} expects {} arguments, but {} were given", function, expected, found),
+      InterpretError::StackUnderflow(kind) => write!(f, "{} popped a value off an empty stack", kind.name()),
+      InterpretError::InvalidOperands(kind) => write!(f, "{} was given invalid operands", kind.name()),
+      InterpretError::InvalidDivision(kind) => write!(f, "{} divided by zero or overflowed", kind.name()),
+      InterpretError::InvalidLocal(id) => write!(f, "local {} does not exist in the current stack frame", id),
+      InterpretError::DanglingAddress => write!(f, "address of a local used after its stack frame ended"),
+      InterpretError::UninitializedLocal(id) => write!(f, "local {} was read before it was initialized", id),
+      InterpretError::UninitializedGlobal(id) => write!(f, "global {} was read before it was initialized", id),
+      InterpretError::OutsideLoop(kind) => write!(f, "{} executed outside of a loop_block", kind.name()),
+      InterpretError::Unsupported(kind) => write!(f, "{} is not supported by the interpreter", kind.name()),
+    }
+  }
+}
+
+
+/// A value on the stack of an `Interpreter`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+  Immediate(ImmediateValue),
+  /// The address of a local, identified by the serial number of its stack frame
+  Local(usize, LocalID),
+  Global(GlobalID),
+  Function(FunctionID),
+}
+
+/// The locals of a Function call or Global initializer
+struct Frame {
+  serial: usize,
+  locals: Vec<Option<Value>>,
+}
+
+/// How execution continues after a sequence of Instructions
+enum Flow {
+  Next,
+  Break,
+  Continue,
+  Return,
+}
+
+
+/// Executes the Functions and Global initializers of a `Module`
+///
+/// Locals are numbered statically, in the same way as codegen assigns `LocalID`s:
+/// a stack frame holds its parameters, followed by one local for each `CreateLocal` in its body in serialized order
+///
+/// Calls to the integer exponentiation intrinsics imported by the Module (see `optimizer::pow_intrinsics`)
+/// are evaluated by the Interpreter itself; any other imported Function is unresolved
+pub struct Interpreter<'m> {
+  module: &'m Module,
+  pow_intrinsics: HashSet<FunctionID>,
+  globals: HashMap<GlobalID, Value>,
+  stack: Vec<Value>,
+  frames: Vec<Frame>,
+  frame_counter: usize,
+}
+
+impl<'m> Interpreter<'m> {
+  /// Create a new Interpreter for a `Module`, after checking it with `Module::verify`
+  ///
+  /// Globals are uninitialized until `initialize_globals` is called
+  pub fn new (module: &'m Module) -> Result<Self, Vec<VerifyError>> {
+    module.verify()?;
+
+    Ok(Self {
+      module,
+      pow_intrinsics: pow_intrinsics(module),
+      globals: HashMap::default(),
+      stack: Vec::default(),
+      frames: Vec::default(),
+      frame_counter: 0,
+    })
+  }
+
+  /// Run the initializer of each `Global` in the `Module`, in order
+  pub fn initialize_globals (&mut self) -> Result<(), InterpretError> {
+    let module = self.module;
+
+    for global in module.globals.iter() {
+      let result = self.run_initializer(&global.initializer);
+      self.reset();
+
+      self.globals.insert(global.id, Value::Immediate(result?));
+    }
+
+    Ok(())
+  }
+
+  /// Get the value of an initialized `Global`
+  pub fn get_global (&self, id: GlobalID) -> Option<ImmediateValue> {
+    match self.globals.get(&id) {
+      Some(&Value::Immediate(value)) => Some(value),
+      _ => None
+    }
+  }
+
+  /// Call a `Function` of the `Module` with a list of arguments,
+  /// returning its result if its Type has one
+  pub fn call (&mut self, id: FunctionID, arguments: Vec<ImmediateValue>) -> Result<Option<ImmediateValue>, InterpretError> {
+    let result = self.run_call(id, arguments);
+    self.reset();
+
+    result
+  }
+
+
+  fn reset (&mut self) {
+    self.stack.clear();
+    self.frames.clear();
+  }
+
+  fn run_initializer (&mut self, initializer: &'m [Instruction]) -> Result<ImmediateValue, InterpretError> {
+    self.push_frame(Vec::new(), initializer);
+
+    match self.execute(initializer)? {
+      Flow::Break => return Err(InterpretError::OutsideLoop(InstructionKind::Break)),
+      Flow::Continue => return Err(InterpretError::OutsideLoop(InstructionKind::Continue)),
+      Flow::Next | Flow::Return => { }
+    }
+
+    self.pop_immediate(InstructionKind::Return)
+  }
+
+  fn run_call (&mut self, id: FunctionID, arguments: Vec<ImmediateValue>) -> Result<Option<ImmediateValue>, InterpretError> {
+    let function = self.get_function(id)?;
+    let (parameters, has_result) = self.get_signature(function)?;
+
+    if arguments.len() != parameters {
+      return Err(InterpretError::ArgumentCount { function: id, expected: parameters, found: arguments.len() })
+    }
+
+    self.stack.extend(arguments.into_iter().map(Value::Immediate));
+    self.call_function(function)?;
+
+    if has_result {
+      self.pop_immediate(InstructionKind::Return).map(Some)
+    } else {
+      Ok(None)
+    }
+  }
+
+
+  /// Call a Function by its id, evaluating calls to intrinsics directly
+  fn call_id (&mut self, id: FunctionID, kind: InstructionKind) -> Result<(), InterpretError> {
+    if self.pow_intrinsics.contains(&id) {
+      let exponent = self.pop_immediate(kind)?;
+      let base = self.pop_immediate(kind)?;
+
+      let result = fold_pow(&base, &exponent).ok_or(InterpretError::InvalidOperands(kind))?;
+
+      self.stack.push(Value::Immediate(result));
+
+      return Ok(())
+    }
+
+    let function = self.get_function(id)?;
+    self.call_function(function)
+  }
+
+  fn get_function (&self, id: FunctionID) -> Result<&'m Function, InterpretError> {
+    self.module.functions.iter().find(|function| function.id == id).ok_or(InterpretError::UnresolvedFunction(id))
+  }
+
+  /// Get the parameter count of a Function, and whether it has a result
+  fn get_signature (&self, function: &Function) -> Result<(usize, bool), InterpretError> {
+    match self.module.get_type(function.ty).map(|ty| &ty.data) {
+      Some(TypeData::Function { parameters, result }) => Ok((parameters.len(), result.is_some())),
+      _ => Err(InterpretError::InvalidSignature(function.id))
+    }
+  }
+
+  /// Pop a Function's arguments off the stack and execute its body,
+  /// then push its result back on the stack if it has one
+  fn call_function (&mut self, function: &'m Function) -> Result<(), InterpretError> {
+    let (parameters, has_result) = self.get_signature(function)?;
+
+    if self.stack.len() < parameters {
+      return Err(InterpretError::StackUnderflow(InstructionKind::CallDirect))
+    }
+
+    let arguments = self.stack.split_off(self.stack.len() - parameters).into_iter().map(Some).collect();
+    let base = self.stack.len();
+
+    self.push_frame(arguments, &function.body);
+
+    match self.execute(&function.body)? {
+      Flow::Break => return Err(InterpretError::OutsideLoop(InstructionKind::Break)),
+      Flow::Continue => return Err(InterpretError::OutsideLoop(InstructionKind::Continue)),
+      Flow::Next | Flow::Return => { }
+    }
+
+    self.frames.pop();
+
+    // A Return pops the result if there is one, any other values left on the stack by the body are discarded
+    let result = if has_result { Some(self.pop(InstructionKind::Return)?) } else { None };
+
+    self.stack.truncate(base);
+    self.stack.extend(result);
+
+    Ok(())
+  }
+
+  fn push_frame (&mut self, mut locals: Vec<Option<Value>>, body: &[Instruction]) {
+    let created = body.iter().flat_map(Instruction::walk).filter(|instruction| matches!(instruction, Instruction::CreateLocal(_))).count();
+    locals.resize(locals.len() + created, None);
+
+    self.frames.push(Frame { serial: self.frame_counter, locals });
+    self.frame_counter += 1;
+  }
+
+  fn get_local (&mut self, serial: usize, id: LocalID) -> Result<&mut Option<Value>, InterpretError> {
+    let frame = self.frames.iter_mut().rev().find(|frame| frame.serial == serial).ok_or(InterpretError::DanglingAddress)?;
+
+    frame.locals.get_mut(id.0 as usize).ok_or(InterpretError::InvalidLocal(id))
+  }
+
+
+  fn pop (&mut self, kind: InstructionKind) -> Result<Value, InterpretError> {
+    self.stack.pop().ok_or(InterpretError::StackUnderflow(kind))
+  }
+
+  fn pop_immediate (&mut self, kind: InstructionKind) -> Result<ImmediateValue, InterpretError> {
+    match self.pop(kind)? {
+      Value::Immediate(value) => Ok(value),
+      _ => Err(InterpretError::InvalidOperands(kind))
+    }
+  }
+
+
+  fn execute (&mut self, instructions: &'m [Instruction]) -> Result<Flow, InterpretError> {
+    for instruction in instructions.iter() {
+      let kind = instruction.get_kind();
+
+      match instruction {
+        // locals are allocated when their frame is created
+        Instruction::NoOp | Instruction::CreateLocal(_) => { },
+
+        &Instruction::ImmediateValue(value) => self.stack.push(Value::Immediate(value)),
+
+        &Instruction::LocalAddress(id) => {
+          let frame = self.frames.last().unwrap();
+
+          if id.0 as usize >= frame.locals.len() {
+            return Err(InterpretError::InvalidLocal(id))
+          }
+
+          self.stack.push(Value::Local(frame.serial, id))
+        },
+
+        &Instruction::GlobalAddress(id) => {
+          if !self.module.globals.iter().any(|global| global.id == id) {
+            return Err(InterpretError::UnresolvedGlobal(id))
+          }
+
+          self.stack.push(Value::Global(id))
+        },
+
+        &Instruction::FunctionAddress(id) => self.stack.push(Value::Function(id)),
+
+        Instruction::Load => {
+          let value = match self.pop(kind)? {
+            Value::Local(serial, id) => (*self.get_local(serial, id)?).ok_or(InterpretError::UninitializedLocal(id))?,
+            Value::Global(id) => *self.globals.get(&id).ok_or(InterpretError::UninitializedGlobal(id))?,
+            _ => return Err(InterpretError::InvalidOperands(kind))
+          };
+
+          self.stack.push(value)
+        },
+
+        Instruction::Store => {
+          let value = self.pop(kind)?;
+
+          match self.pop(kind)? {
+            Value::Local(serial, id) => *self.get_local(serial, id)? = Some(value),
+            Value::Global(id) => { self.globals.insert(id, value); },
+            _ => return Err(InterpretError::InvalidOperands(kind))
+          }
+        },
+
+        Instruction::Duplicate => {
+          let value = *self.stack.last().ok_or(InterpretError::StackUnderflow(kind))?;
+          self.stack.push(value)
+        },
+
+        Instruction::Discard => { self.pop(kind)?; },
+
+        Instruction::Neg | Instruction::Not => {
+          let a = self.pop_immediate(kind)?;
+          let result = fold_unary(instruction, &a).ok_or(InterpretError::InvalidOperands(kind))?;
+
+          self.stack.push(Value::Immediate(result))
+        },
+
+        | Instruction::Add | Instruction::Sub | Instruction::Mul | Instruction::Div | Instruction::Rem
+        | Instruction::And | Instruction::Or | Instruction::Xor
+        | Instruction::EQ | Instruction::NEQ | Instruction::LT | Instruction::GT | Instruction::LEQ | Instruction::GEQ
+        => {
+          let b = self.pop(kind)?;
+          let a = self.pop(kind)?;
+
+          let result = match (a, b) {
+            (Value::Immediate(a), Value::Immediate(b)) => binary_op(instruction, a, b)?,
+
+            // addresses may only be compared for equality
+            (a, b) if std::mem::discriminant(&a) == std::mem::discriminant(&b) => match instruction {
+              Instruction::EQ => ImmediateValue::Bool(a == b),
+              Instruction::NEQ => ImmediateValue::Bool(a != b),
+              _ => return Err(InterpretError::InvalidOperands(kind))
+            },
+
+            _ => return Err(InterpretError::InvalidOperands(kind))
+          };
+
+          self.stack.push(Value::Immediate(result))
+        },
+
+        &Instruction::CallDirect(id) => self.call_id(id, kind)?,
+
+        Instruction::CallIndirect => match self.pop(kind)? {
+          Value::Function(id) => self.call_id(id, kind)?,
+
+          _ => return Err(InterpretError::InvalidOperands(kind))
+        },
+
+        Instruction::IfBlock(then_branch, else_branch) => {
+          let branch = match self.pop_immediate(kind)? {
+            ImmediateValue::Bool(true) => then_branch,
+            ImmediateValue::Bool(false) => else_branch,
+            _ => return Err(InterpretError::InvalidOperands(kind))
+          };
+
+          match self.execute(branch)? {
+            Flow::Next => { },
+            flow => return Ok(flow)
+          }
+        },
+
+        Instruction::LoopBlock(body) => loop {
+          match self.execute(body)? {
+            Flow::Next | Flow::Continue => { },
+            Flow::Break => break,
+            Flow::Return => return Ok(Flow::Return)
+          }
+        },
+
+        Instruction::Break => return Ok(Flow::Break),
+        Instruction::Continue => return Ok(Flow::Continue),
+        Instruction::Return => return Ok(Flow::Return),
+
+        | Instruction::GetElement(_)
+        | Instruction::GetIndex
+        | Instruction::GetTag
+        | Instruction::GetPayload(_)
+        | Instruction::Cast(_)
+        | Instruction::LShift
+        | Instruction::RShift
+        => return Err(InterpretError::Unsupported(kind)),
+      }
+    }
+
+    Ok(Flow::Next)
+  }
+}
+
+
+/// Evaluate a binary arithmetic, bitwise, or comparison Instruction on two ImmediateValues
+fn binary_op (op: &Instruction, a: ImmediateValue, b: ImmediateValue) -> Result<ImmediateValue, InterpretError> {
+  fold_binary(op, &a, &b).ok_or_else(|| {
+    let is_integer = !matches!(a.get_intrinsic_type(), IntrinsicType::Null | IntrinsicType::Bool | IntrinsicType::Char | IntrinsicType::F32 | IntrinsicType::F64);
+
+    // integer division is the only operation on matching operands which can fail
+    if is_integer && a.get_intrinsic_type() == b.get_intrinsic_type() && matches!(op, Instruction::Div | Instruction::Rem) {
+      InterpretError::InvalidDivision(op.get_kind())
+    } else {
+      InterpretError::InvalidOperands(op.get_kind())
+    }
+  })
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{ Type, Global, };
+  use mod_common::Version;
+
+  fn test_module () -> Module {
+    use Instruction::*;
+
+    let mut module = Module::empty("test_module".to_owned(), Version::new(0, 0, 1));
+
+    module.types = vec! [
+      Type::new(0.into(), TypeData::Intrinsic(IntrinsicType::S32)),
+      Type::new(1.into(), TypeData::Function { parameters: vec! [ 0.into() ], result: Some(0.into()) }),
+      Type::new(2.into(), TypeData::Function { parameters: vec! [ ], result: Some(0.into()) }),
+      Type::new(3.into(), TypeData::Function { parameters: vec! [ ], result: None }),
+    ];
+
+    module.globals = vec! [
+      Global { id: 0.into(), ty: 0.into(), initializer: vec! [ ImmediateValue(2i32.into()), ImmediateValue(3i32.into()), Mul ] },
+    ];
+
+    module.functions = vec! [
+      // factorial
+      Function {
+        id: 0.into(),
+        ty: 1.into(),
+        body: vec! [
+          CreateLocal(0.into()),
+          LocalAddress(1.into()), ImmediateValue(1i32.into()), Store,
+          LoopBlock(vec! [
+            LocalAddress(0.into()), Load, ImmediateValue(1i32.into()), LEQ,
+            IfBlock(vec! [ Break ], vec! [ ]),
+            LocalAddress(1.into()), LocalAddress(1.into()), Load, LocalAddress(0.into()), Load, Mul, Store,
+            LocalAddress(0.into()), LocalAddress(0.into()), Load, ImmediateValue(1i32.into()), Sub, Store,
+            Continue,
+          ]),
+          LocalAddress(1.into()), Load,
+          Return,
+        ]
+      },
+
+      Function {
+        id: 1.into(),
+        ty: 2.into(),
+        body: vec! [ GlobalAddress(0.into()), Load, ImmediateValue(5i32.into()), CallDirect(0.into()), Add ]
+      },
+
+      Function {
+        id: 2.into(),
+        ty: 3.into(),
+        body: vec! [ GlobalAddress(0.into()), ImmediateValue(7i32.into()), Store, Return, NoOp ]
+      },
+
+      Function {
+        id: 3.into(),
+        ty: 1.into(),
+        body: vec! [ LocalAddress(0.into()), Load, ImmediateValue(0i32.into()), Div ]
+      },
+
+      Function {
+        id: 4.into(),
+        ty: 3.into(),
+        body: vec! [ Break ]
+      },
+    ];
+
+    module
+  }
+
+  #[test]
+  fn test_interpret_calls () {
+    let module = test_module();
+    let mut interpreter = Interpreter::new(&module).unwrap();
+
+    assert_eq!(interpreter.call(0.into(), vec! [ ImmediateValue::S32(5) ]), Ok(Some(ImmediateValue::S32(120))));
+    assert_eq!(interpreter.call(0.into(), vec! [ ImmediateValue::S32(0) ]), Ok(Some(ImmediateValue::S32(1))));
+
+    assert_eq!(interpreter.call(1.into(), vec! [ ]), Err(InterpretError::UninitializedGlobal(0.into())));
+
+    interpreter.initialize_globals().unwrap();
+    assert_eq!(interpreter.get_global(0.into()), Some(ImmediateValue::S32(6)));
+    assert_eq!(interpreter.call(1.into(), vec! [ ]), Ok(Some(ImmediateValue::S32(126))));
+
+    assert_eq!(interpreter.call(2.into(), vec! [ ]), Ok(None));
+    assert_eq!(interpreter.get_global(0.into()), Some(ImmediateValue::S32(7)));
+  }
+
+  #[test]
+  fn test_interpret_errors () {
+    let module = test_module();
+    let mut interpreter = Interpreter::new(&module).unwrap();
+
+    assert_eq!(interpreter.call(3.into(), vec! [ ImmediateValue::S32(1) ]), Err(InterpretError::InvalidDivision(InstructionKind::Div)));
+    assert_eq!(interpreter.call(4.into(), vec! [ ]), Err(InterpretError::OutsideLoop(InstructionKind::Break)));
+    assert_eq!(interpreter.call(5.into(), vec! [ ]), Err(InterpretError::UnresolvedFunction(5.into())));
+    assert_eq!(
+      interpreter.call(0.into(), vec! [ ]),
+      Err(InterpretError::ArgumentCount { function: 0.into(), expected: 1, found: 0 })
+    );
+    assert_eq!(interpreter.call(0.into(), vec! [ ImmediateValue::U8(1) ]), Err(InterpretError::InvalidOperands(InstructionKind::LEQ)));
+
+    // the interpreter is left in a usable state after an error
+    assert_eq!(interpreter.call(0.into(), vec! [ ImmediateValue::S32(3) ]), Ok(Some(ImmediateValue::S32(6))));
+  }
+
+  #[test]
+  fn test_interpret_pow_intrinsic () {
+    use crate::{ ImportModule, Import, ImportData, optimizer::INTRINSIC_MODULE_NAME, };
+
+    let mut module = test_module();
+
+    let mut core = ImportModule::empty(INTRINSIC_MODULE_NAME.to_owned(), Version::new(0, 0, 0));
+    core.items.push(Import::new("pow_s32".to_owned(), ImportData::Function(5.into(), 4.into())));
+    module.imports.push(core);
+
+    module.types.push(Type::new(4.into(), TypeData::Function { parameters: vec! [ 0.into(), 0.into() ], result: Some(0.into()) }));
+
+    module.functions.push(Function {
+      id: 6.into(),
+      ty: 1.into(),
+      body: vec! [
+        Instruction::ImmediateValue((-2i32).into()),
+        Instruction::LocalAddress(0.into()), Instruction::Load,
+        Instruction::CallDirect(5.into()),
+        Instruction::Return,
+      ]
+    });
+
+    let mut interpreter = Interpreter::new(&module).unwrap();
+
+    assert_eq!(interpreter.call(6.into(), vec! [ ImmediateValue::S32(3) ]), Ok(Some(ImmediateValue::S32(-8))));
+    assert_eq!(interpreter.call(6.into(), vec! [ ImmediateValue::S32(-1) ]), Ok(Some(ImmediateValue::S32(0))));
+
+    // the intrinsic is only evaluated when called, not as an entry point
+    assert_eq!(interpreter.call(5.into(), vec! [ ImmediateValue::S32(2), ImmediateValue::S32(2) ]), Err(InterpretError::UnresolvedFunction(5.into())));
+  }
+}
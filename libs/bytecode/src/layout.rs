@@ -0,0 +1,242 @@
+//! Memory layout of the types of a bytecode `Module`
+
+use mod_common::{ LayoutStrategy, };
+
+use super::{ Module, TypeID, TypeData, IntrinsicType, ElementID, };
+
+
+/// The size in bytes of a pointer or function pointer
+pub const POINTER_SIZE: u64 = 8;
+
+/// The size in bytes of the tag of an `Enum`
+pub const ENUM_TAG_SIZE: u64 = 4;
+
+
+/// The size and alignment of a value of some type, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Layout {
+  /// The number of bytes occupied by a value, including any trailing padding
+  pub size: u64,
+  /// The boundary a value must be placed on, always a power of two
+  pub align: u64,
+}
+
+impl Layout {
+  /// Create a new `Layout`
+  pub fn new (size: u64, align: u64) -> Self {
+    Self { size, align }
+  }
+
+  /// Get the `Layout` of an `IntrinsicType`
+  pub fn of_intrinsic (intrinsic: IntrinsicType) -> Self {
+    use IntrinsicType::*;
+
+    match intrinsic {
+      Void => Self::new(0, 1),
+      Null => Self::new(POINTER_SIZE, POINTER_SIZE),
+      Bool | U8 | S8 => Self::new(1, 1),
+      U16 | S16 => Self::new(2, 2),
+      U32 | S32 | F32 | Char | String => Self::new(4, 4),
+      U64 | S64 | F64 => Self::new(8, 8),
+      U128 | S128 => Self::new(16, 16),
+    }
+  }
+}
+
+
+/// The arrangement of the fields of a `Struct` type in memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructLayout {
+  /// The size and alignment of the whole struct
+  pub layout: Layout,
+  /// The offset of each field from the start of the struct, indexed by `ElementID`
+  pub offsets: Vec<u64>,
+  /// The `ElementID` of each field in the order they are placed in memory
+  ///
+  /// This is the identity permutation for `LayoutStrategy::COrder`
+  pub order: Vec<ElementID>,
+}
+
+impl StructLayout {
+  /// Arrange a list of field `Layout`s, given in declaration order, according to a `LayoutStrategy`
+  pub fn new (fields: &[Layout], strategy: LayoutStrategy) -> Self {
+    let mut order: Vec<usize> = (0..fields.len()).collect();
+
+    if strategy == LayoutStrategy::Optimized {
+      // stable, so fields with the same alignment and size keep their declaration order
+      order.sort_by(|&a, &b| fields[b].align.cmp(&fields[a].align).then(fields[b].size.cmp(&fields[a].size)));
+    }
+
+    let mut offsets = vec! [ 0; fields.len() ];
+    let mut size = 0;
+    let mut align = 1;
+
+    for &index in order.iter() {
+      let field = fields[index];
+
+      size = align_to(size, field.align);
+      offsets[index] = size;
+      size += field.size;
+      align = align.max(field.align);
+    }
+
+    Self {
+      layout: Layout::new(align_to(size, align), align),
+      offsets,
+      order: order.into_iter().map(|index| ElementID(index as u64)).collect(),
+    }
+  }
+
+  /// Get the offset of a field from the start of the struct by its `ElementID`
+  pub fn field_offset (&self, element: ElementID) -> Option<u64> {
+    self.offsets.get(element.0 as usize).copied()
+  }
+}
+
+
+/// Round an offset up to the next multiple of an alignment
+pub fn align_to (offset: u64, align: u64) -> u64 {
+  (offset + align - 1) / align * align
+}
+
+
+/// Get the `Layout` of a type in a `Module`
+///
+/// Returns None if the type, or any type it contains by value, does not exist,
+/// or if the type contains itself by value
+pub fn layout_of (module: &Module, id: TypeID, strategy: LayoutStrategy) -> Option<Layout> {
+  layout_of_impl(module, id, strategy, &mut Vec::new())
+}
+
+/// Get the size in bytes of a type in a `Module`, see `layout_of`
+pub fn size_of (module: &Module, id: TypeID, strategy: LayoutStrategy) -> Option<u64> {
+  layout_of(module, id, strategy).map(|layout| layout.size)
+}
+
+/// Get the alignment in bytes of a type in a `Module`, see `layout_of`
+pub fn align_of (module: &Module, id: TypeID, strategy: LayoutStrategy) -> Option<u64> {
+  layout_of(module, id, strategy).map(|layout| layout.align)
+}
+
+/// Get the `StructLayout` of a `Struct` type in a `Module`
+///
+/// Returns None if the type is not a `Struct`, or its layout cannot be determined (see `layout_of`)
+pub fn struct_layout (module: &Module, id: TypeID, strategy: LayoutStrategy) -> Option<StructLayout> {
+  struct_layout_impl(module, id, strategy, &mut Vec::new())
+}
+
+/// Get the offset in bytes of a field of a `Struct` type in a `Module` by its `ElementID`
+///
+/// Returns None if the type is not a `Struct`, it has no such field, or its layout cannot be determined
+pub fn field_offset (module: &Module, id: TypeID, element: ElementID, strategy: LayoutStrategy) -> Option<u64> {
+  struct_layout(module, id, strategy)?.field_offset(element)
+}
+
+
+fn struct_layout_impl (module: &Module, id: TypeID, strategy: LayoutStrategy, stack: &mut Vec<TypeID>) -> Option<StructLayout> {
+  let fields = match &module.get_type(id)?.data {
+    TypeData::Struct { fields, .. } => fields,
+    _ => return None
+  };
+
+  if stack.contains(&id) { return None }
+  stack.push(id);
+
+  let field_layouts = fields.iter().map(|&field| layout_of_impl(module, field, strategy, stack)).collect::<Option<Vec<_>>>();
+
+  stack.pop();
+
+  Some(StructLayout::new(&field_layouts?, strategy))
+}
+
+fn layout_of_impl (module: &Module, id: TypeID, strategy: LayoutStrategy, stack: &mut Vec<TypeID>) -> Option<Layout> {
+  if stack.contains(&id) { return None }
+
+  let layout = match &module.get_type(id)?.data {
+    &TypeData::Intrinsic(intrinsic) => Layout::of_intrinsic(intrinsic),
+
+    TypeData::Pointer { .. } | TypeData::Function { .. } => Layout::new(POINTER_SIZE, POINTER_SIZE),
+
+    TypeData::Struct { .. } => return struct_layout_impl(module, id, strategy, stack).map(|layout| layout.layout),
+
+    TypeData::Enum { variants } => {
+      stack.push(id);
+      let variant_layouts = variants.iter().map(|&(_, variant)| layout_of_impl(module, variant, strategy, stack)).collect::<Option<Vec<_>>>();
+      stack.pop();
+
+      let payload = variant_layouts?.into_iter().fold(Layout::new(0, 1), |payload, variant| {
+        Layout::new(payload.size.max(variant.size), payload.align.max(variant.align))
+      });
+
+      let align = payload.align.max(ENUM_TAG_SIZE);
+
+      Layout::new(align_to(align_to(ENUM_TAG_SIZE, payload.align) + payload.size, align), align)
+    },
+
+    &TypeData::Array { element, length } => {
+      stack.push(id);
+      let element = layout_of_impl(module, element, strategy, stack);
+      stack.pop();
+
+      let element = element?;
+
+      Layout::new(align_to(element.size, element.align) * length, element.align)
+    },
+  };
+
+  Some(layout)
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use super::super::{ Type, };
+  use mod_common::{ Version, };
+
+  fn padded_module () -> Module {
+    let mut module = Module::empty("layout".to_owned(), Version::new(0, 1, 0));
+
+    module.types = vec! [
+      Type::new(TypeID(0), TypeData::Intrinsic(IntrinsicType::U8)),
+      Type::new(TypeID(1), TypeData::Intrinsic(IntrinsicType::U64)),
+      Type::new(TypeID(2), TypeData::Intrinsic(IntrinsicType::U32)),
+      Type::new(TypeID(3), TypeData::Struct {
+        fields: vec! [ TypeID(0), TypeID(1), TypeID(0), TypeID(2), TypeID(0) ],
+        names: vec! [ "a".to_owned(), "b".to_owned(), "c".to_owned(), "d".to_owned(), "e".to_owned() ],
+      }),
+      Type::new(TypeID(4), TypeData::Struct {
+        fields: vec! [ TypeID(4) ],
+        names: vec! [ "this".to_owned() ],
+      }),
+    ];
+
+    module
+  }
+
+  #[test]
+  fn test_layout_strategies () {
+    let module = padded_module();
+    let id = TypeID(3);
+
+    assert_eq!(size_of(&module, id, LayoutStrategy::COrder), Some(32));
+    assert_eq!(size_of(&module, id, LayoutStrategy::Optimized), Some(16));
+    assert_eq!(align_of(&module, id, LayoutStrategy::COrder), Some(8));
+    assert_eq!(align_of(&module, id, LayoutStrategy::Optimized), Some(8));
+
+    let c_order = struct_layout(&module, id, LayoutStrategy::COrder).unwrap();
+    assert_eq!(c_order.offsets, vec! [ 0, 8, 16, 20, 24 ]);
+    assert_eq!(c_order.order, (0..5).map(ElementID).collect::<Vec<_>>());
+
+    let optimized = struct_layout(&module, id, LayoutStrategy::Optimized).unwrap();
+    assert_eq!(optimized.order, vec! [ ElementID(1), ElementID(3), ElementID(0), ElementID(2), ElementID(4) ]);
+    assert_eq!(optimized.offsets, vec! [ 12, 0, 13, 8, 14 ]);
+
+    let d = module.types[3].data.element_id("d").unwrap();
+    assert_eq!(field_offset(&module, id, d, LayoutStrategy::COrder), Some(20));
+    assert_eq!(field_offset(&module, id, d, LayoutStrategy::Optimized), Some(8));
+    assert_eq!(field_offset(&module, id, ElementID(5), LayoutStrategy::Optimized), None);
+
+    assert_eq!(size_of(&module, TypeID(4), LayoutStrategy::COrder), None);
+  }
+}
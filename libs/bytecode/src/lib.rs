@@ -8,23 +8,48 @@
 
 use std::{
   fmt::{ Display, Formatter, Result as FMTResult, },
+  io::{ Read, Write, Result as IOResult, Error as IOError, ErrorKind as IOErrorKind, },
   mem::{ transmute, },
+  collections::{ HashMap, HashSet, },
+  slice::{ Iter as SliceIter, from_ref as slice_from_ref, },
+  cell::{ Cell, },
 };
 
 use mod_common::{ HierarchicalDisplay, Padding, Version, Operator, };
 
 
+pub mod optimizer;
+pub mod interpreter;
+pub mod layout;
+
+
 
 /// Interface trait for encoding a bytecode value into a byte buffer
 pub trait Encode {
   /// Encodes a Bytecode value into a byte buffer
   fn encode (&self, buff: &mut Vec<u8>);
+
+  /// Encodes a Bytecode value into a writer, producing the same bytes as `encode`
+  /// 
+  /// By default the value is encoded into a temporary buffer which is then written all at once,
+  /// primitives, containers and `Module` write their parts directly instead
+  fn encode_to<W: Write> (&self, w: &mut W) -> IOResult<()> {
+    let mut buff = Vec::new();
+    self.encode(&mut buff);
+    w.write_all(&buff)
+  }
 }
 
-/// Interface trait for decoding a bytecode value from a byte buffer
+/// Interface trait for decoding a bytecode value from a byte buffer or reader
 pub trait Decode: Sized {
-  /// Decodes a bytecode value from a byte buffer
-  fn decode (buff: &mut &[u8]) -> Result<Self, DecodeError>;
+  /// Decodes a bytecode value from a reader, pulling bytes from it as they are needed
+  fn decode_from<R: Read> (buff: &mut R) -> Result<Self, DecodeError>;
+
+  /// Decodes a bytecode value from a byte buffer, advancing the buffer past the bytes consumed
+  #[inline]
+  fn decode (buff: &mut &[u8]) -> Result<Self, DecodeError> {
+    Self::decode_from(buff)
+  }
 }
 
 /// An error resulting from attempting to decode a bytecode value from an improperly formed byte buffer
@@ -37,69 +62,1178 @@ pub enum DecodeError {
   InvalidString,
   /// The decoder encountered an unexpected value
   UnexpectedValue,
+  /// The decoder did not find the magic signature at the start of an encoded `Module`
+  BadMagic,
+  /// The decoder found an encoded `Module` using a `FormatVersion` it does not support
+  UnsupportedFormatVersion,
+  /// The checksum at the end of an encoded `Module` did not match its contents
+  ChecksumMismatch,
+  /// The decoder found blocks nested deeper than the limit set by `set_max_decode_depth`
+  TooDeep,
+  /// The reader being decoded from failed for a reason other than running out of data
+  Io(IOErrorKind),
+}
+
+impl From<IOError> for DecodeError {
+  fn from (e: IOError) -> Self {
+    match e.kind() {
+      IOErrorKind::UnexpectedEof => DecodeError::EOF,
+      kind => DecodeError::Io(kind),
+    }
+  }
+}
+
+
+/// The maximum nesting depth of `IfBlock`s and `LoopBlock`s accepted by the decoder unless set otherwise
+pub const DEFAULT_MAX_DECODE_DEPTH: usize = 256;
+
+thread_local! {
+  static MAX_DECODE_DEPTH: Cell<usize> = Cell::new(DEFAULT_MAX_DECODE_DEPTH);
+  static DECODE_DEPTH: Cell<usize> = Cell::new(0);
+}
+
+/// Set the maximum nesting depth of `IfBlock`s and `LoopBlock`s accepted by the decoder on the current thread
+/// 
+/// Instructions are decoded recursively, so without a limit an untrusted buffer of deeply nested blocks
+/// could overflow the stack; encodings nested deeper than this fail with `DecodeError::TooDeep` instead
+pub fn set_max_decode_depth (depth: usize) {
+  MAX_DECODE_DEPTH.with(|max| max.set(depth))
+}
+
+/// Get the maximum nesting depth of `IfBlock`s and `LoopBlock`s accepted by the decoder on the current thread,
+/// `DEFAULT_MAX_DECODE_DEPTH` unless set with `set_max_decode_depth`
+pub fn max_decode_depth () -> usize {
+  MAX_DECODE_DEPTH.with(Cell::get)
+}
+
+/// Tracks entry into a nested block while decoding, releasing it when dropped
+struct DecodeDepthGuard;
+
+impl DecodeDepthGuard {
+  fn enter () -> Result<Self, DecodeError> {
+    DECODE_DEPTH.with(|depth| {
+      if depth.get() >= max_decode_depth() { return Err(DecodeError::TooDeep) }
+
+      depth.set(depth.get() + 1);
+
+      Ok(Self)
+    })
+  }
+}
+
+impl Drop for DecodeDepthGuard {
+  fn drop (&mut self) {
+    DECODE_DEPTH.with(|depth| depth.set(depth.get() - 1))
+  }
+}
+
+
+
+/// The main container of the in-memory representation of bytecode for a compilation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+  /// The uniquely identifying name of a Module
+  pub name: String,
+  /// The semantic Version number of a Module
+  pub version: Version,
+
+  /// All Type definitions used by a Module
+  pub types:     Vec<Type>,
+  /// All Modules a Module depends on
+  pub imports:   Vec<ImportModule>,
+  /// All Global definitions of a Module, in initialization order
+  pub globals:   Vec<Global>,
+  /// All Function defintions of a Module
+  pub functions: Vec<Function>,
+  /// All Exports a Module exposes
+  pub exports:   Vec<Export>,
+  /// The constant pool of a Module, containing the Strings referred to by `ImmediateValue::StringRef`
+  pub strings:   Vec<String>,
 }
 
+impl Module {
+  /// Create a new, empty `Module`
+  pub fn empty (name: String, version: Version) -> Self {
+    Self {
+      name,
+      version,
+
+      types: Vec::default(),
+      imports: Vec::default(),
+      globals: Vec::default(),
+      functions: Vec::default(),
+      exports: Vec::default(),
+      strings: Vec::default(),
+    }
+  }
+
+  /// Get a String from the constant pool of a `Module` by its index
+  pub fn get_string (&self, index: u32) -> Option<&str> {
+    self.strings.get(index as usize).map(String::as_str)
+  }
+
+  /// Add a String to the constant pool of a `Module`, returning its index for use in an `ImmediateValue::StringRef`
+  /// 
+  /// If an identical String is already in the pool, its existing index is returned instead
+  pub fn add_string (&mut self, string: &str) -> u32 {
+    if let Some(index) = self.strings.iter().position(|existing| existing == string) {
+      index as u32
+    } else {
+      self.strings.push(string.to_owned());
+      (self.strings.len() - 1) as u32
+    }
+  }
+
+  /// Compute a stable hash of the contents of a `Module`, for change detection and caching
+  /// 
+  /// The hash is taken over the encoded form of the Module using 64-bit FNV-1a,
+  /// so it does not vary between runs or platforms
+  pub fn content_hash (&self) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut buff = Vec::new();
+    self.encode(&mut buff);
+
+    buff.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME))
+  }
+
+  /// Get a `Type` defined in a `Module` by its `TypeID`
+  pub fn get_type (&self, id: TypeID) -> Option<&Type> {
+    self.types.iter().find(|ty| ty.id == id)
+  }
+
+  /// Verify that every id referenced by a `Module` resolves to an item it defines or imports
+  /// 
+  /// This checks the `TypeID`s referenced by types, imports, globals, functions and instructions,
+  /// the `GlobalID`s and `FunctionID`s referenced by instructions and exports,
+  /// and that each struct type has the same number of field names as fields.
+  /// `LocalID`s and `ElementID`s depend on the state of the stack where they are used, and are not checked
+  pub fn verify (&self) -> Result<(), Vec<VerifyError>> {
+    let mut verifier = Verifier {
+      types: self.types.iter().map(|ty| ty.id).collect(),
+      globals: self.globals.iter().map(|global| global.id).collect(),
+      functions: self.functions.iter().map(|function| function.id).collect(),
+      strings: self.strings.len(),
+      errors: Vec::new(),
+    };
+
+    for import_module in self.imports.iter() {
+      verifier.bind_imports(&import_module.items);
+    }
+
+    for ty in self.types.iter() {
+      verifier.verify_type_data(&VerifyLocation::Type(ty.id), &ty.data);
+    }
+
+    for import_module in self.imports.iter() {
+      verifier.verify_imports(&import_module.name, &import_module.items);
+    }
+
+    for global in self.globals.iter() {
+      let location = VerifyLocation::Global(global.id);
+
+      verifier.check(&location, VerifyReference::Type(global.ty));
+      verifier.verify_instructions(&location, &global.initializer);
+    }
+
+    for function in self.functions.iter() {
+      let location = VerifyLocation::Function(function.id);
+
+      verifier.check(&location, VerifyReference::Type(function.ty));
+      verifier.verify_instructions(&location, &function.body);
+    }
+
+    verifier.verify_exports(&self.exports);
+
+    if verifier.errors.is_empty() { Ok(()) } else { Err(verifier.errors) }
+  }
+
+  /// Check the items a `Module` imports from a dependency against the items the dependency exports
+  /// 
+  /// Only the `ImportModule`s with the same name as the dependency are checked.
+  /// `TypeID`s only refer to items of their own `Module`'s type table,
+  /// so the types of imported items are compared structurally rather than by id
+  pub fn check_imports (&self, dependency: &Module) -> Result<(), Vec<ImportError>> {
+    let mut checker = ImportChecker {
+      importer: self,
+      dependency,
+      assumed: HashSet::new(),
+      errors: Vec::new(),
+    };
+
+    for import_module in self.imports.iter().filter(|import_module| import_module.name == dependency.name) {
+      checker.check_imports(&import_module.name, "", &import_module.items, &dependency.exports);
+    }
+
+    if checker.errors.is_empty() { Ok(()) } else { Err(checker.errors) }
+  }
+
+  /// Find all Functions of a `Module` which can call themselves, either directly or through other Functions
+  /// 
+  /// The call graph is built from the `CallDirect` Instructions in Function bodies,
+  /// and a Function is recursive if it calls itself or shares a strongly connected component with another Function.
+  /// Calls to imported Functions and `CallIndirect`s are not followed
+  pub fn recursive_functions (&self) -> HashSet<FunctionID> {
+    let graph = self.call_graph();
+
+    let mut finder = ComponentFinder {
+      graph: &graph,
+      indices: HashMap::new(),
+      low_links: HashMap::new(),
+      stack: Vec::new(),
+      on_stack: HashSet::new(),
+      recursive: HashSet::new(),
+    };
+
+    for function in self.functions.iter() {
+      if !finder.indices.contains_key(&function.id) { finder.visit(function.id) }
+    }
+
+    finder.recursive
+  }
+
+  /// Find all Functions of a `Module` which can never return to their caller
+  /// 
+  /// A Function diverges if every path through its body reaches a `LoopBlock` which cannot be left,
+  /// or a direct call to another diverging Function.
+  /// A `LoopBlock` is considered to be left if it contains any `Return`, or a `Break` not nested in an inner `LoopBlock`,
+  /// so some diverging Functions may not be detected, but no returning Function is ever reported
+  pub fn diverging_functions (&self) -> HashSet<FunctionID> {
+    let mut diverging = HashSet::new();
+
+    loop {
+      let found: Vec<FunctionID> = self.functions.iter()
+        .filter(|function| !diverging.contains(&function.id) && sequence_diverges(&function.body, &diverging))
+        .map(|function| function.id)
+        .collect();
+
+      if found.is_empty() { break diverging }
+
+      diverging.extend(found);
+    }
+  }
+
+  /// Build the `CallGraph` of the Functions defined by a `Module`, from the call Instructions in their bodies
+  pub fn call_graph (&self) -> CallGraph {
+    let mut graph = CallGraph::default();
+
+    for function in self.functions.iter() {
+      let mut callees = Vec::new();
+
+      for instruction in function.body.iter().flat_map(Instruction::walk) {
+        match instruction {
+          &Instruction::CallDirect(callee) => if !callees.contains(&callee) {
+            callees.push(callee);
+            graph.callers.entry(callee).or_insert_with(Vec::new).push(function.id);
+          },
+
+          Instruction::CallIndirect => { graph.unknown.insert(function.id); },
+
+          _ => { }
+        }
+      }
+
+      graph.callees.insert(function.id, callees);
+    }
+
+    graph
+  }
+
+  /// Determine if two `Module`s are equal, ignoring differences which do not affect their meaning
+  /// 
+  /// Unlike the derived `PartialEq`, the order of Types, Functions, imports and exports is ignored,
+  /// and a `TypeID` referenced by a Type, Global, Function, import or Instruction only has to refer to a structurally equivalent Type in the other Module,
+  /// so Modules which number their Types differently can be equal.
+  /// Globals must still be in the same initialization order, and Instructions must otherwise be identical
+  pub fn semantically_eq (&self, other: &Self) -> bool {
+    let type_eq = |a: TypeID, b: TypeID| types_equivalent(self, a, other, b, &mut HashSet::new());
+
+    self.name == other.name
+    && self.version == other.version
+    && self.strings == other.strings
+
+    && unordered_eq(&self.types, &other.types, |a, b| type_eq(a.id, b.id))
+
+    && unordered_eq(&self.imports, &other.imports, |a, b| {
+      a.name == b.name
+      && a.version == b.version
+      && imports_equivalent(&a.items, &b.items, &type_eq)
+    })
+
+    && self.globals.len() == other.globals.len()
+    && self.globals.iter().zip(other.globals.iter()).all(|(a, b)| {
+      a.id == b.id && type_eq(a.ty, b.ty) && instructions_equivalent(&a.initializer, &b.initializer, &type_eq)
+    })
+
+    && unordered_eq(&self.functions, &other.functions, |a, b| {
+      a.id == b.id && type_eq(a.ty, b.ty) && instructions_equivalent(&a.body, &b.body, &type_eq)
+    })
+
+    && exports_equivalent(&self.exports, &other.exports)
+  }
+
+  /// The signature at the start of every encoded `Module`
+  pub const MAGIC: [u8; 4] = *b"MLBC";
+
+  /// Encode a `Module` into a byte buffer using a particular `FormatVersion`
+  /// 
+  /// `Encode` uses `FormatVersion::Standard`,
+  /// and `Decode` accepts any `FormatVersion` as it is recorded after the magic signature
+  /// 
+  /// The encoding ends with a `Crc32` checksum of all the bytes before it, which is verified by `Decode`
+  pub fn encode_with (&self, format: FormatVersion, buff: &mut Vec<u8>) {
+    let start = buff.len();
+
+    buff.extend_from_slice(&Self::MAGIC);
+    format.encode(buff);
+
+    self.name.encode(buff);
+    self.version.encode(buff);
+
+    match format {
+      FormatVersion::Standard => {
+        self.types.encode(buff);
+        self.imports.encode(buff);
+        self.globals.encode(buff);
+        self.functions.encode(buff);
+        self.exports.encode(buff);
+        self.strings.encode(buff);
+      },
+
+      FormatVersion::Compact => {
+        let present = [
+          !self.types.is_empty(),
+          !self.imports.is_empty(),
+          !self.globals.is_empty(),
+          !self.functions.is_empty(),
+          !self.exports.is_empty(),
+          !self.strings.is_empty(),
+        ];
+
+        present.iter().enumerate().fold(0u8, |bitmap, (index, &is_present)| bitmap | ((is_present as u8) << index)).encode(buff);
+
+        if present[0] { self.types.encode(buff) }
+        if present[1] { self.imports.encode(buff) }
+        if present[2] { self.globals.encode(buff) }
+        if present[3] { self.functions.encode(buff) }
+        if present[4] { self.exports.encode(buff) }
+        if present[5] { self.strings.encode(buff) }
+      },
+    }
+
+    let mut crc = Crc32::new();
+    crc.update(&buff[start ..]);
+    crc.finish().encode(buff);
+  }
+}
+
+impl Encode for Module {
+  fn encode (&self, buff: &mut Vec<u8>) {
+    self.encode_with(FormatVersion::Standard, buff)
+  }
+
+  fn encode_to<W: Write> (&self, w: &mut W) -> IOResult<()> {
+    let mut writer = ChecksumWriter { inner: w, crc: Crc32::new() };
+
+    writer.write_all(&Self::MAGIC)?;
+    FormatVersion::Standard.encode_to(&mut writer)?;
+
+    self.name.encode_to(&mut writer)?;
+    self.version.encode_to(&mut writer)?;
+
+    self.types.encode_to(&mut writer)?;
+    self.imports.encode_to(&mut writer)?;
+    self.globals.encode_to(&mut writer)?;
+    self.functions.encode_to(&mut writer)?;
+    self.exports.encode_to(&mut writer)?;
+    self.strings.encode_to(&mut writer)?;
+
+    let checksum = writer.crc.finish();
+    checksum.encode_to(w)
+  }
+}
+
+impl Decode for Module {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<Module, DecodeError> {
+    let mut reader = ChecksumReader { inner: buff, crc: Crc32::new() };
+    let module = Module::decode_unchecked(&mut reader)?;
+    let expected = reader.crc.finish();
+
+    if u32::decode_from(buff)? != expected { return Err(DecodeError::ChecksumMismatch) }
+
+    Ok(module)
+  }
+
+  /// A byte buffer must contain exactly one encoded `Module`;
+  /// its checksum is verified before the contents are decoded, so corrupted or truncated buffers are never parsed
+  fn decode (buff: &mut &[u8]) -> Result<Module, DecodeError> {
+    // the header is checked first so that buffers which are not Modules at all are reported as such
+    Module::decode_header_from(&mut &buff[..])?;
+
+    let (mut contents, checksum) = buff.split_at(buff.len().saturating_sub(4));
+
+    let mut crc = Crc32::new();
+    crc.update(contents);
+
+    if u32::decode(&mut &checksum[..])? != crc.finish() { return Err(DecodeError::ChecksumMismatch) }
+
+    let module = Module::decode_unchecked(&mut contents)?;
+
+    if !contents.is_empty() { return Err(DecodeError::UnexpectedValue) }
+
+    *buff = &buff[buff.len() ..];
+
+    Ok(module)
+  }
+}
+
+impl Module {
+  /// Decode only the name and `Version` at the start of an encoded `Module`, without reading anything following them
+  /// 
+  /// The rest of the buffer does not need to be valid, or even present,
+  /// and the checksum is not verified, so a successful result does not mean the whole `Module` can be decoded
+  pub fn decode_header (buff: &[u8]) -> Result<(String, Version), DecodeError> {
+    let (_, name, version) = Module::decode_header_from(&mut &buff[..])?;
+
+    Ok((name, version))
+  }
+
+  /// Decode the magic signature, `FormatVersion`, name and `Version` at the start of an encoded `Module`
+  fn decode_header_from<R: Read> (buff: &mut R) -> Result<(FormatVersion, String, Version), DecodeError> {
+    if ByteQuad::decode_from(buff)? != Module::MAGIC { return Err(DecodeError::BadMagic) }
+
+    let format = FormatVersion::decode_from(buff)?;
+
+    let name = String::decode_from(buff)?;
+    let version = Version::decode_from(buff)?;
+
+    Ok((format, name, version))
+  }
+
+  /// Decode the contents of an encoded `Module`, without reading or verifying the checksum following them
+  fn decode_unchecked<R: Read> (buff: &mut R) -> Result<Module, DecodeError> {
+    fn section<D: Decode, R: Read> (bitmap: u8, index: u32, buff: &mut R) -> Result<Vec<D>, DecodeError> {
+      if bitmap & (1 << index) != 0 { Vec::decode_from(buff) } else { Ok(Vec::default()) }
+    }
+
+    let (format, name, version) = Module::decode_header_from(buff)?;
+
+    match format {
+      FormatVersion::Standard => Ok(Module {
+        name,
+        version,
+        types: Vec::decode_from(buff)?,
+        imports: Vec::decode_from(buff)?,
+        globals: Vec::decode_from(buff)?,
+        functions: Vec::decode_from(buff)?,
+        exports: Vec::decode_from(buff)?,
+        strings: Vec::decode_from(buff)?,
+      }),
+
+      FormatVersion::Compact => {
+        let bitmap = u8::decode_from(buff)?;
+
+        if bitmap >> FormatVersion::COMPACT_SECTION_COUNT != 0 { return Err(DecodeError::UnexpectedValue) }
+
+        Ok(Module {
+          name,
+          version,
+          types: section(bitmap, 0, buff)?,
+          imports: section(bitmap, 1, buff)?,
+          globals: section(bitmap, 2, buff)?,
+          functions: section(bitmap, 3, buff)?,
+          exports: section(bitmap, 4, buff)?,
+          strings: section(bitmap, 5, buff)?,
+        })
+      },
+    }
+  }
+}
+
+/// Incrementally computes the CRC-32 (IEEE 802.3) checksum appended to encoded Modules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+  /// The reversed polynomial of the checksum
+  pub const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+  /// Create a new `Crc32` which has not been given any bytes
+  pub fn new () -> Self { Self(!0) }
+
+  /// Add bytes to the data being checksummed
+  pub fn update (&mut self, bytes: &[u8]) {
+    for &byte in bytes.iter() {
+      let mut crc = self.0 ^ u32::from(byte);
+
+      for _ in 0 .. 8 {
+        crc = if crc & 1 != 0 { (crc >> 1) ^ Self::POLYNOMIAL } else { crc >> 1 };
+      }
+
+      self.0 = crc;
+    }
+  }
+
+  /// Get the checksum of all bytes given so far
+  pub fn finish (self) -> u32 { !self.0 }
+}
+
+impl Default for Crc32 { fn default () -> Self { Self::new() } }
+
+/// Wraps a writer, computing a `Crc32` of all bytes written through it
+struct ChecksumWriter<'w, W: Write> {
+  inner: &'w mut W,
+  crc: Crc32,
+}
+
+impl<'w, W: Write> Write for ChecksumWriter<'w, W> {
+  fn write (&mut self, buf: &[u8]) -> IOResult<usize> {
+    let written = self.inner.write(buf)?;
+    self.crc.update(&buf[.. written]);
+    Ok(written)
+  }
+
+  fn flush (&mut self) -> IOResult<()> { self.inner.flush() }
+}
+
+/// Wraps a reader, computing a `Crc32` of all bytes read through it
+struct ChecksumReader<'r, R: Read> {
+  inner: &'r mut R,
+  crc: Crc32,
+}
+
+impl<'r, R: Read> Read for ChecksumReader<'r, R> {
+  fn read (&mut self, buf: &mut [u8]) -> IOResult<usize> {
+    let read = self.inner.read(buf)?;
+    self.crc.update(&buf[.. read]);
+    Ok(read)
+  }
+}
+
+
+/// The item of a `Module` containing a reference which failed verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyLocation {
+  /// The definition of a `Type`
+  Type(TypeID),
+  /// An `Import` binding; contains the name of its `ImportModule` and its own name
+  Import(String, String),
+  /// The type or initializer of a `Global`
+  Global(GlobalID),
+  /// The type or body of a `Function`
+  Function(FunctionID),
+  /// An `Export` binding; contains its name
+  Export(String),
+}
+
+impl Display for VerifyLocation {
+  fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    match self {
+      VerifyLocation::Type(id) => write!(f, "type {}", id),
+      VerifyLocation::Import(module, name) => write!(f, "import \"{}\" of module \"{}\"", name, module),
+      VerifyLocation::Global(id) => write!(f, "global {}", id),
+      VerifyLocation::Function(id) => write!(f, "function {}", id),
+      VerifyLocation::Export(name) => write!(f, "export \"{}\"", name),
+    }
+  }
+}
+
+/// An id referenced by an item of a `Module`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyReference {
+  /// A reference to a `Type`
+  Type(TypeID),
+  /// A reference to a `Global`, either defined or imported
+  Global(GlobalID),
+  /// A reference to a `Function`, either defined or imported
+  Function(FunctionID),
+  /// A reference to an entry of the constant pool
+  String(u32),
+  /// A field of a struct `Type`, referenced by a name given past the end of its fields
+  Field(ElementID),
+  /// The name of a field of a struct `Type`, referenced by a field given past the end of its names
+  FieldName(ElementID),
+}
+
+impl Display for VerifyReference {
+  fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    match self {
+      VerifyReference::Type(id) => write!(f, "type {}", id),
+      VerifyReference::Global(id) => write!(f, "global {}", id),
+      VerifyReference::Function(id) => write!(f, "function {}", id),
+      VerifyReference::String(index) => write!(f, "string {}", index),
+      VerifyReference::Field(id) => write!(f, "field {}", id),
+      VerifyReference::FieldName(id) => write!(f, "the name of field {}", id),
+    }
+  }
+}
+
+/// A reference found by `Module::verify` which does not resolve to an item of the `Module`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+  /// The item containing the reference
+  pub location: VerifyLocation,
+  /// The id which could not be resolved
+  pub reference: VerifyReference,
+}
+
+impl Display for VerifyError {
+  fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    write!(f, "{} references {}, which does not exist", self.location, self.reference)
+  }
+}
+
+/// The ids available to a `Module` and the errors found so far during `Module::verify`
+struct Verifier {
+  types: HashSet<TypeID>,
+  globals: HashSet<GlobalID>,
+  functions: HashSet<FunctionID>,
+  /// The length of the constant pool
+  strings: usize,
+  errors: Vec<VerifyError>,
+}
+
+impl Verifier {
+  fn bind_imports (&mut self, items: &[Import]) {
+    for item in items.iter() {
+      match &item.data {
+        ImportData::Namespace(items) => self.bind_imports(items),
+        &ImportData::Global(id, _) => { self.globals.insert(id); },
+        &ImportData::Function(id, _) => { self.functions.insert(id); },
+      }
+    }
+  }
+
+  fn check (&mut self, location: &VerifyLocation, reference: VerifyReference) {
+    let resolved = match reference {
+      VerifyReference::Type(id) => self.types.contains(&id),
+      VerifyReference::Global(id) => self.globals.contains(&id),
+      VerifyReference::Function(id) => self.functions.contains(&id),
+      VerifyReference::String(index) => (index as usize) < self.strings,
+      VerifyReference::Field(_) | VerifyReference::FieldName(_) => false,
+    };
+
+    if !resolved {
+      self.errors.push(VerifyError { location: location.clone(), reference })
+    }
+  }
+
+  fn verify_type_data (&mut self, location: &VerifyLocation, data: &TypeData) {
+    match data {
+      TypeData::Intrinsic(_) => { },
+      &TypeData::Pointer(id) => self.check(location, VerifyReference::Type(id)),
+      TypeData::Struct { fields, names } => {
+        for &id in fields.iter() { self.check(location, VerifyReference::Type(id)) }
+
+        // every field must have exactly one name
+        for index in names.len() .. fields.len() { self.check(location, VerifyReference::FieldName(ElementID(index as u64))) }
+        for index in fields.len() .. names.len() { self.check(location, VerifyReference::Field(ElementID(index as u64))) }
+      },
+      TypeData::Function { parameters, result } => for &id in parameters.iter().chain(result.iter()) { self.check(location, VerifyReference::Type(id)) },
+      TypeData::Enum { variants } => for &(_, id) in variants.iter() { self.check(location, VerifyReference::Type(id)) },
+      &TypeData::Array { element, .. } => self.check(location, VerifyReference::Type(element)),
+    }
+  }
+
+  fn verify_imports (&mut self, module_name: &str, items: &[Import]) {
+    for item in items.iter() {
+      let location = VerifyLocation::Import(module_name.to_owned(), item.name.clone());
+
+      match &item.data {
+        ImportData::Namespace(items) => self.verify_imports(module_name, items),
+        | &ImportData::Global(_, ty)
+        | &ImportData::Function(_, ty)
+        => self.check(&location, VerifyReference::Type(ty)),
+      }
+    }
+  }
+
+  fn verify_instructions (&mut self, location: &VerifyLocation, instructions: &[Instruction]) {
+    for instruction in InstructionWalker::new(instructions) {
+      if let Some(id) = instruction.type_operand() { self.check(location, VerifyReference::Type(id)) }
+      if let Some(id) = instruction.global_operand() { self.check(location, VerifyReference::Global(id)) }
+      if let Some(id) = instruction.function_operand() { self.check(location, VerifyReference::Function(id)) }
+      if let Some(ImmediateValue::StringRef(index)) = instruction.immediate_operand() { self.check(location, VerifyReference::String(index)) }
+    }
+  }
+
+  fn verify_exports (&mut self, exports: &[Export]) {
+    for export in exports.iter() {
+      let location = VerifyLocation::Export(export.name.clone());
+
+      match &export.data {
+        ExportData::Namespace(exports) => self.verify_exports(exports),
+        &ExportData::Global(id) => self.check(&location, VerifyReference::Global(id)),
+        &ExportData::Function(id) => self.check(&location, VerifyReference::Function(id)),
+      }
+    }
+  }
+}
+
+/// The reason an `Import` was rejected by `Module::check_imports`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportErrorKind {
+  /// The dependency has no `Export` with the name of the `Import`
+  Missing,
+  /// The dependency exports a different kind of item under the name of the `Import`
+  KindMismatch,
+  /// The type of the exported item is not structurally identical to the type expected by the `Import`
+  TypeMismatch,
+}
+
+/// An `Import` found by `Module::check_imports` which does not match the exports of its dependency
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportError {
+  /// The name of the `ImportModule` containing the `Import`
+  pub module: String,
+  /// The path of the `Import` through any namespaces, separated by `::`
+  pub path: String,
+  /// The reason the `Import` was rejected
+  pub kind: ImportErrorKind,
+}
+
+impl Display for ImportError {
+  fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    write!(f, "import \"{}\" of module \"{}\" ", self.path, self.module)?;
+
+    match self.kind {
+      ImportErrorKind::Missing => write!(f, "is not exported by the module"),
+      ImportErrorKind::KindMismatch => write!(f, "is exported as a different kind of item"),
+      ImportErrorKind::TypeMismatch => write!(f, "does not match the type of the exported item"),
+    }
+  }
+}
+
+/// The Modules being compared and the errors found so far during `Module::check_imports`
+struct ImportChecker<'m> {
+  importer: &'m Module,
+  dependency: &'m Module,
+  /// Pairs of TypeIDs currently being compared, which are assumed to be identical so recursive types terminate
+  assumed: HashSet<(TypeID, TypeID)>,
+  errors: Vec<ImportError>,
+}
+
+impl<'m> ImportChecker<'m> {
+  fn check_imports (&mut self, module_name: &str, prefix: &str, imports: &[Import], exports: &[Export]) {
+    for import in imports.iter() {
+      let path = if prefix.is_empty() { import.name.clone() } else { format!("{}::{}", prefix, import.name) };
+
+      let kind = match exports.iter().find(|export| export.name == import.name).map(|export| (&import.data, &export.data)) {
+        None => Some(ImportErrorKind::Missing),
+
+        Some((ImportData::Namespace(imports), ExportData::Namespace(exports))) => {
+          self.check_imports(module_name, &path, imports, exports);
+          None
+        },
+
+        Some((&ImportData::Global(_, expected), &ExportData::Global(id))) => {
+          let found = self.dependency.globals.iter().find(|global| global.id == id).map(|global| global.ty);
+          self.check_type(expected, found)
+        },
+
+        Some((&ImportData::Function(_, expected), &ExportData::Function(id))) => {
+          let found = self.dependency.functions.iter().find(|function| function.id == id).map(|function| function.ty);
+          self.check_type(expected, found)
+        },
+
+        Some(_) => Some(ImportErrorKind::KindMismatch),
+      };
+
+      if let Some(kind) = kind {
+        self.errors.push(ImportError { module: module_name.to_owned(), path, kind })
+      }
+    }
+  }
+
+  fn check_type (&mut self, expected: TypeID, found: Option<TypeID>) -> Option<ImportErrorKind> {
+    match found {
+      Some(found) if self.types_identical(expected, found) => None,
+      Some(_) => Some(ImportErrorKind::TypeMismatch),
+      None => Some(ImportErrorKind::Missing),
+    }
+  }
+
+  /// Structurally compare a Type of the importing Module with a Type of the dependency
+  fn types_identical (&mut self, expected: TypeID, found: TypeID) -> bool {
+    if !self.assumed.insert((expected, found)) { return true }
+
+    let (expected_ty, found_ty) = match (self.importer.get_type(expected), self.dependency.get_type(found)) {
+      (Some(expected_ty), Some(found_ty)) => (&expected_ty.data, &found_ty.data),
+      _ => return false
+    };
+
+    let identical = match (expected_ty, found_ty) {
+      (TypeData::Intrinsic(a), TypeData::Intrinsic(b)) => a == b,
+
+      (&TypeData::Pointer(a), &TypeData::Pointer(b)) => self.types_identical(a, b),
+
+      (TypeData::Struct { fields: a, names: a_names }, TypeData::Struct { fields: b, names: b_names }) => {
+        a_names == b_names
+        && a.len() == b.len()
+        && a.iter().zip(b.iter()).all(|(&a, &b)| self.types_identical(a, b))
+      },
+
+      (TypeData::Function { parameters: a_params, result: a_result }, TypeData::Function { parameters: b_params, result: b_result }) => {
+        a_params.len() == b_params.len()
+        && a_params.iter().zip(b_params.iter()).all(|(&a, &b)| self.types_identical(a, b))
+        && match (*a_result, *b_result) {
+          (Some(a), Some(b)) => self.types_identical(a, b),
+          (None, None) => true,
+          _ => false
+        }
+      },
+
+      (TypeData::Enum { variants: a }, TypeData::Enum { variants: b }) => {
+        a.len() == b.len()
+        && a.iter().zip(b.iter()).all(|(&(a_tag, a), &(b_tag, b))| a_tag == b_tag && self.types_identical(a, b))
+      },
+
+      (&TypeData::Array { element: a, length: a_length }, &TypeData::Array { element: b, length: b_length }) => {
+        a_length == b_length && self.types_identical(a, b)
+      },
+
+      _ => false
+    };
+
+    self.assumed.remove(&(expected, found));
+
+    identical
+  }
+}
+
+
+
+/// Determine whether every path through a sequence of Instructions ends in an inescapable loop or a call to a diverging Function,
+/// as described in `Module::diverging_functions`
+fn sequence_diverges (instructions: &[Instruction], diverging: &HashSet<FunctionID>) -> bool {
+  for instruction in instructions.iter() {
+    match instruction {
+      Instruction::LoopBlock(body) => if !loop_exits(body) { return true },
+      Instruction::IfBlock(then_branch, else_branch) => if sequence_diverges(then_branch, diverging) && sequence_diverges(else_branch, diverging) { return true },
+      Instruction::CallDirect(id) => if diverging.contains(id) { return true },
+      Instruction::Return | Instruction::Break | Instruction::Continue => return false,
+      _ => { }
+    }
+  }
+
+  false
+}
+
+/// Determine whether the body of a `LoopBlock` may leave the loop, by a `Return` or a `Break` targeting it
+fn loop_exits (body: &[Instruction]) -> bool {
+  body.iter().any(|instruction| match instruction {
+    Instruction::Break | Instruction::Return => true,
+    Instruction::IfBlock(then_branch, else_branch) => loop_exits(then_branch) || loop_exits(else_branch),
+    // a Break in a nested loop only leaves the nested loop
+    Instruction::LoopBlock(nested) => nested.iter().flat_map(Instruction::walk).any(|nested| *nested == Instruction::Return),
+    _ => false
+  })
+}
+
+/// Determine if two slices contain the same elements in any order, as compared by a given function
+fn unordered_eq<T> (a: &[T], b: &[T], mut eq: impl FnMut(&T, &T) -> bool) -> bool {
+  if a.len() != b.len() { return false }
+
+  let mut matched = vec! [ false; b.len() ];
+
+  a.iter().all(|a_item| {
+    let found = b.iter().enumerate().position(|(index, b_item)| !matched[index] && eq(a_item, b_item));
+
+    if let Some(index) = found { matched[index] = true }
+
+    found.is_some()
+  })
+}
+
+/// Determine if a Type in one `Module` is structurally equivalent to a Type in another,
+/// as described in `Module::semantically_eq`
+/// 
+/// Pairs of TypeIDs already being compared are assumed to be equivalent, so recursive Types terminate
+fn types_equivalent (a_module: &Module, a: TypeID, b_module: &Module, b: TypeID, assumed: &mut HashSet<(TypeID, TypeID)>) -> bool {
+  if !assumed.insert((a, b)) { return true }
+
+  let (a_data, b_data) = match (a_module.get_type(a), b_module.get_type(b)) {
+    (Some(a_ty), Some(b_ty)) => (&a_ty.data, &b_ty.data),
+    (None, None) => return a == b,
+    _ => return false
+  };
+
+  let mut ids_equivalent = |a_ids: &[TypeID], b_ids: &[TypeID]| {
+    a_ids.len() == b_ids.len()
+    && a_ids.iter().zip(b_ids.iter()).all(|(&a, &b)| types_equivalent(a_module, a, b_module, b, assumed))
+  };
+
+  match (a_data, b_data) {
+    (TypeData::Intrinsic(a), TypeData::Intrinsic(b)) => a == b,
+
+    (TypeData::Pointer(a), TypeData::Pointer(b)) => ids_equivalent(slice_from_ref(a), slice_from_ref(b)),
+
+    (TypeData::Struct { fields: a_fields, names: a_names }, TypeData::Struct { fields: b_fields, names: b_names }) => {
+      a_names == b_names && ids_equivalent(a_fields, b_fields)
+    },
+
+    (TypeData::Function { parameters: a_params, result: a_result }, TypeData::Function { parameters: b_params, result: b_result }) => {
+      ids_equivalent(a_params, b_params)
+      && match (a_result, b_result) {
+        (Some(a), Some(b)) => ids_equivalent(slice_from_ref(a), slice_from_ref(b)),
+        (None, None) => true,
+        _ => false
+      }
+    },
+
+    (TypeData::Enum { variants: a_variants }, TypeData::Enum { variants: b_variants }) => {
+      a_variants.len() == b_variants.len()
+      && a_variants.iter().zip(b_variants.iter()).all(|((a_tag, a), (b_tag, b))| a_tag == b_tag && ids_equivalent(slice_from_ref(a), slice_from_ref(b)))
+    },
+
+    (TypeData::Array { element: a, length: a_length }, TypeData::Array { element: b, length: b_length }) => {
+      a_length == b_length && ids_equivalent(slice_from_ref(a), slice_from_ref(b))
+    },
+
+    _ => false
+  }
+}
+
+/// Determine if two sequences of Instructions are identical, except for TypeID operands which only have to refer to equivalent Types,
+/// as described in `Module::semantically_eq`
+fn instructions_equivalent (a: &[Instruction], b: &[Instruction], type_eq: &dyn Fn(TypeID, TypeID) -> bool) -> bool {
+  a.len() == b.len()
+  && a.iter().zip(b.iter()).all(|(a, b)| match (a, b) {
+    (Instruction::IfBlock(a_then, a_else), Instruction::IfBlock(b_then, b_else)) => {
+      instructions_equivalent(a_then, b_then, type_eq) && instructions_equivalent(a_else, b_else, type_eq)
+    },
+
+    (Instruction::LoopBlock(a_body), Instruction::LoopBlock(b_body)) => instructions_equivalent(a_body, b_body, type_eq),
+
+    _ => match (a.type_operand(), b.type_operand()) {
+      (Some(a_id), Some(b_id)) => a.get_kind() == b.get_kind() && type_eq(a_id, b_id),
+      _ => a == b
+    }
+  })
+}
+
+/// Determine if two lists of `Import`s bind the same items in any order, as described in `Module::semantically_eq`
+fn imports_equivalent (a: &[Import], b: &[Import], type_eq: &dyn Fn(TypeID, TypeID) -> bool) -> bool {
+  unordered_eq(a, b, |a, b| {
+    a.name == b.name
+    && match (&a.data, &b.data) {
+      (ImportData::Namespace(a_items), ImportData::Namespace(b_items)) => imports_equivalent(a_items, b_items, type_eq),
+      (&ImportData::Global(a_id, a_ty), &ImportData::Global(b_id, b_ty)) => a_id == b_id && type_eq(a_ty, b_ty),
+      (&ImportData::Function(a_id, a_ty), &ImportData::Function(b_id, b_ty)) => a_id == b_id && type_eq(a_ty, b_ty),
+      _ => false
+    }
+  })
+}
+
+/// Determine if two lists of `Export`s bind the same items in any order, as described in `Module::semantically_eq`
+fn exports_equivalent (a: &[Export], b: &[Export]) -> bool {
+  unordered_eq(a, b, |a, b| {
+    a.name == b.name
+    && match (&a.data, &b.data) {
+      (ExportData::Namespace(a_items), ExportData::Namespace(b_items)) => exports_equivalent(a_items, b_items),
+      (a_data, b_data) => a_data == b_data
+    }
+  })
+}
+
+
+/// Incrementally constructs a `Module`, deduplicating its Types
+/// 
+/// Types are only added through `ModuleBuilder::intern_type`,
+/// so structurally equal `TypeData` always shares a single `TypeID`,
+/// and TypeIDs are assigned densely in the order Types are first interned
+#[derive(Debug, Clone)]
+pub struct ModuleBuilder {
+  module: Module,
+  type_ids: HashMap<TypeData, TypeID>,
+}
+
+impl ModuleBuilder {
+  /// Create a new `ModuleBuilder` for an empty `Module`
+  pub fn new (name: String, version: Version) -> Self {
+    Self {
+      module: Module::empty(name, version),
+      type_ids: HashMap::default(),
+    }
+  }
+
+  /// Get the `TypeID` of a Type with the given `TypeData`,
+  /// adding a new Type to the `Module` if no structurally equal Type exists yet
+  pub fn intern_type (&mut self, data: TypeData) -> TypeID {
+    if let Some(&id) = self.type_ids.get(&data) { return id }
+
+    let id = TypeID(self.module.types.len() as u64);
+
+    self.type_ids.insert(data.clone(), id);
+    self.module.types.push(Type::new(id, data));
+
+    id
+  }
+
+  /// Get a Type previously added to the `Module` by its id
+  pub fn get_type (&self, id: TypeID) -> Option<&Type> {
+    self.module.get_type(id)
+  }
+
+  /// Add an `ImportModule` to the `Module`
+  pub fn add_import (&mut self, import: ImportModule) {
+    self.module.imports.push(import)
+  }
+
+  /// Add a `Global` to the `Module`, after any previously added Globals in initialization order
+  pub fn add_global (&mut self, global: Global) {
+    self.module.globals.push(global)
+  }
+
+  /// Add a `Function` to the `Module`
+  pub fn add_function (&mut self, function: Function) {
+    self.module.functions.push(function)
+  }
+
+  /// Add an `Export` to the `Module`
+  pub fn add_export (&mut self, export: Export) {
+    self.module.exports.push(export)
+  }
+
+  /// Add a String to the constant pool of the `Module`, see `Module::add_string`
+  pub fn add_string (&mut self, string: &str) -> u32 {
+    self.module.add_string(string)
+  }
+
+  /// Finish building, returning the `Module`
+  pub fn finish (self) -> Module {
+    self.module
+  }
+}
+
+
+/// The calls between the Functions of a `Module`, as built by `Module::call_graph`
+/// 
+/// Edges come from `CallDirect` Instructions, and each caller and callee is listed once per Function in the order of the first call.
+/// The targets of `CallIndirect`s cannot be known statically,
+/// so a Function containing one is instead recorded as having an unknown edge
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallGraph {
+  callees: HashMap<FunctionID, Vec<FunctionID>>,
+  callers: HashMap<FunctionID, Vec<FunctionID>>,
+  unknown: HashSet<FunctionID>,
+}
+
+impl CallGraph {
+  /// Determine whether a Function is defined by the `Module` a `CallGraph` was built from
+  pub fn contains (&self, id: FunctionID) -> bool {
+    self.callees.contains_key(&id)
+  }
+
+  /// Get the Functions directly called by a Function, which may include imported Functions
+  pub fn callees_of (&self, id: FunctionID) -> &[FunctionID] {
+    self.callees.get(&id).map(Vec::as_slice).unwrap_or(&[])
+  }
+
+  /// Get the Functions containing a direct call to a Function
+  pub fn callers_of (&self, id: FunctionID) -> &[FunctionID] {
+    self.callers.get(&id).map(Vec::as_slice).unwrap_or(&[])
+  }
+
+  /// Determine whether a Function makes an indirect call, which may target any Function
+  pub fn has_unknown_callees (&self, id: FunctionID) -> bool {
+    self.unknown.contains(&id)
+  }
+}
+
+/// The state of Tarjan's strongly connected components algorithm over the call graph, during `Module::recursive_functions`
+struct ComponentFinder<'c> {
+  graph: &'c CallGraph,
+  indices: HashMap<FunctionID, usize>,
+  low_links: HashMap<FunctionID, usize>,
+  stack: Vec<FunctionID>,
+  on_stack: HashSet<FunctionID>,
+  recursive: HashSet<FunctionID>,
+}
 
+impl<'c> ComponentFinder<'c> {
+  fn visit (&mut self, id: FunctionID) {
+    let index = self.indices.len();
+    self.indices.insert(id, index);
+    self.low_links.insert(id, index);
+    self.stack.push(id);
+    self.on_stack.insert(id);
+
+    let graph = self.graph;
+
+    for &callee in graph.callees_of(id).iter() {
+      // calls to imported functions have no outgoing edges to follow
+      if !graph.contains(callee) { continue }
+
+      if callee == id { self.recursive.insert(id); }
+
+      let callee_link = if !self.indices.contains_key(&callee) {
+        self.visit(callee);
+        self.low_links[&callee]
+      } else if self.on_stack.contains(&callee) {
+        self.indices[&callee]
+      } else {
+        continue
+      };
 
-/// The main container of the in-memory representation of bytecode for a compilation
-#[derive(Debug, Clone, PartialEq)]
-pub struct Module {
-  /// The uniquely identifying name of a Module
-  pub name: String,
-  /// The semantic Version number of a Module
-  pub version: Version,
+      let low_link = self.low_links.get_mut(&id).unwrap();
+      *low_link = (*low_link).min(callee_link);
+    }
 
-  /// All Type definitions used by a Module
-  pub types:     Vec<Type>,
-  /// All Modules a Module depends on
-  pub imports:   Vec<ImportModule>,
-  /// All Global definitions of a Module, in initialization order
-  pub globals:   Vec<Global>,
-  /// All Function defintions of a Module
-  pub functions: Vec<Function>,
-  /// All Exports a Module exposes
-  pub exports:   Vec<Export>,
-}
+    if self.low_links[&id] == index {
+      let mut component = Vec::new();
 
-impl Module {
-  /// Create a new, empty `Module`
-  pub fn empty (name: String, version: Version) -> Self {
-    Self {
-      name,
-      version,
+      loop {
+        let member = self.stack.pop().unwrap();
+        self.on_stack.remove(&member);
+        component.push(member);
+        if member == id { break }
+      }
 
-      types: Vec::default(),
-      imports: Vec::default(),
-      globals: Vec::default(),
-      functions: Vec::default(),
-      exports: Vec::default(),
+      if component.len() > 1 { self.recursive.extend(component) }
     }
   }
 }
 
-impl Encode for Module {
+
+
+/// A version of the binary encoding of a `Module`, recorded after its magic signature
+/// 
+/// This is independent of the semantic `Version` of a `Module`
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FormatVersion {
+  /// Every section of a `Module` is encoded with a length prefix, even if it is empty
+  Standard = 1,
+  /// A bitmap of the sections present in a `Module` follows its name and version,
+  /// and empty sections are omitted entirely
+  Compact = 2,
+}
+
+impl FormatVersion {
+  /// The number of sections described by the presence bitmap of a `FormatVersion::Compact` encoding
+  pub const COMPACT_SECTION_COUNT: u32 = 6;
+}
+
+impl Default for FormatVersion { #[inline] fn default () -> Self { Self::Standard } }
+
+impl Encode for FormatVersion {
   fn encode (&self, buff: &mut Vec<u8>) {
-    self.name.encode(buff);
-    self.version.encode(buff);
-    self.types.encode(buff);
-    self.imports.encode(buff);
-    self.globals.encode(buff);
-    self.functions.encode(buff);
-    self.exports.encode(buff);
+    (*self as u16).encode(buff)
   }
 }
 
-impl Decode for Module {
-  fn decode (buff: &mut &[u8]) -> Result<Module, DecodeError> {
-    Ok(Module {
-      name: String::decode(buff)?,
-      version: Version::decode(buff)?,
-      types: Vec::decode(buff)?,
-      imports: Vec::decode(buff)?,
-      globals: Vec::decode(buff)?,
-      functions: Vec::decode(buff)?,
-      exports: Vec::decode(buff)?,
-    })
+impl Decode for FormatVersion {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<FormatVersion, DecodeError> {
+    let value = u16::decode_from(buff)?;
+
+    if value >= FormatVersion::Standard as _
+    && value <= FormatVersion::Compact  as _ {
+      Ok(unsafe { transmute(value) })
+    } else {
+      Err(DecodeError::UnsupportedFormatVersion)
+    }
   }
 }
 
@@ -195,6 +1329,23 @@ impl HierarchicalDisplay for Module {
       body = true;
     }
 
+    if !self.strings.is_empty() {
+      writeln!(f)?;
+      Padding.fmt_hierarchical(f, level)?;
+      write!(f, "(strings")?;
+      *level += 1;
+      for (index, string) in self.strings.iter().enumerate() {
+        writeln!(f)?;
+        Padding.fmt_hierarchical(f, level)?;
+        write!(f, "({} {:?})", index, string)?;
+      }
+      writeln!(f)?;
+      *level -= 1;
+      Padding.fmt_hierarchical(f, level)?;
+      write!(f, ")")?;
+      body = true;
+    }
+
     *level -= 1;
 
     if body {
@@ -220,14 +1371,20 @@ impl Encode for Version {
     self.minor.encode(buff);
     self.patch.encode(buff);
   }
+
+  fn encode_to<W: Write> (&self, w: &mut W) -> IOResult<()> {
+    self.major.encode_to(w)?;
+    self.minor.encode_to(w)?;
+    self.patch.encode_to(w)
+  }
 }
 
 impl Decode for Version {
-  fn decode (buff: &mut &[u8]) -> Result<Version, DecodeError> {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<Version, DecodeError> {
     Ok(Version {
-      major: u8::decode(buff)?,
-      minor: u8::decode(buff)?,
-      patch: u8::decode(buff)?,
+      major: u8::decode_from(buff)?,
+      minor: u8::decode_from(buff)?,
+      patch: u8::decode_from(buff)?,
     })
   }
 }
@@ -255,7 +1412,7 @@ impl From<ElementID> for ID { fn from (i: ElementID) -> Self { Self(i.0) } }
 
 impl Encode for ID { fn encode (&self, buff: &mut Vec<u8>) { self.0.encode(buff) } }
 
-impl Decode for ID { fn decode (buff: &mut &[u8]) -> Result<ID, DecodeError> { Ok(Self(u64::decode(buff)?)) }}
+impl Decode for ID { fn decode_from<R: Read> (buff: &mut R) -> Result<ID, DecodeError> { Ok(Self(u64::decode_from(buff)?)) }}
 
 impl HierarchicalDisplay for ID { fn fmt_hierarchical (&self, f: &mut Formatter, _level: &mut usize) -> FMTResult { write!(f, "(id {})", self.0) } }
 
@@ -312,11 +1469,11 @@ impl Encode for FunctionID { fn encode (&self, buff: &mut Vec<u8>) { self.0.enco
 impl Encode for LocalID { fn encode (&self, buff: &mut Vec<u8>) { self.0.encode(buff) } }
 impl Encode for ElementID { fn encode (&self, buff: &mut Vec<u8>) { self.0.encode(buff) } }
 
-impl Decode for TypeID { fn decode (buff: &mut &[u8]) -> Result<TypeID, DecodeError> { Ok(Self(u64::decode(buff)?)) }}
-impl Decode for GlobalID { fn decode (buff: &mut &[u8]) -> Result<GlobalID, DecodeError> { Ok(Self(u64::decode(buff)?)) }}
-impl Decode for FunctionID { fn decode (buff: &mut &[u8]) -> Result<FunctionID, DecodeError> { Ok(Self(u64::decode(buff)?)) }}
-impl Decode for LocalID { fn decode (buff: &mut &[u8]) -> Result<LocalID, DecodeError> { Ok(Self(u64::decode(buff)?)) }}
-impl Decode for ElementID { fn decode (buff: &mut &[u8]) -> Result<ElementID, DecodeError> { Ok(Self(u64::decode(buff)?)) }}
+impl Decode for TypeID { fn decode_from<R: Read> (buff: &mut R) -> Result<TypeID, DecodeError> { Ok(Self(u64::decode_from(buff)?)) }}
+impl Decode for GlobalID { fn decode_from<R: Read> (buff: &mut R) -> Result<GlobalID, DecodeError> { Ok(Self(u64::decode_from(buff)?)) }}
+impl Decode for FunctionID { fn decode_from<R: Read> (buff: &mut R) -> Result<FunctionID, DecodeError> { Ok(Self(u64::decode_from(buff)?)) }}
+impl Decode for LocalID { fn decode_from<R: Read> (buff: &mut R) -> Result<LocalID, DecodeError> { Ok(Self(u64::decode_from(buff)?)) }}
+impl Decode for ElementID { fn decode_from<R: Read> (buff: &mut R) -> Result<ElementID, DecodeError> { Ok(Self(u64::decode_from(buff)?)) }}
 
 impl HierarchicalDisplay for TypeID { fn fmt_hierarchical (&self, f: &mut Formatter, _level: &mut usize) -> FMTResult { write!(f, "(tid {})", self.0) } }
 impl HierarchicalDisplay for GlobalID { fn fmt_hierarchical (&self, f: &mut Formatter, _level: &mut usize) -> FMTResult { write!(f, "(gid {})", self.0) } }
@@ -356,10 +1513,10 @@ impl Encode for Type {
 }
 
 impl Decode for Type {
-  fn decode (buff: &mut &[u8]) -> Result<Type, DecodeError> {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<Type, DecodeError> {
     Ok(Type {
-      id: TypeID::decode(buff)?,
-      data: TypeData::decode(buff)?,
+      id: TypeID::decode_from(buff)?,
+      data: TypeData::decode_from(buff)?,
     })
   }
 }
@@ -384,7 +1541,12 @@ pub enum TypeData {
   /// The address of a value of another type
   Pointer(TypeID),
   /// An aggregate containing a list of values of other types
-  Struct(Vec<TypeID>),
+  Struct {
+    /// Types of each field of a struct, the index of a field is its `ElementID`
+    fields: Vec<TypeID>,
+    /// Source-level names of each field of a struct, in the same order as its fields
+    names: Vec<String>,
+  },
   /// A functional interface signature
   Function {
     /// Types of values provided to a function
@@ -392,9 +1554,30 @@ pub enum TypeData {
     /// Type of value returned by a function
     result: Option<TypeID>
   },
+  /// A tagged union holding a value of one of several types, identified by its tag
+  Enum {
+    /// The tag and value type of each variant,
+    /// variants without an associated value use the `Void` intrinsic type
+    variants: Vec<(u32, TypeID)>,
+  },
+  /// A contiguous sequence of a fixed number of values of another type
+  Array {
+    /// The type of each element of an array
+    element: TypeID,
+    /// The number of elements in an array
+    length: u64,
+  },
 }
 
 impl TypeData {
+  /// Get the `ElementID` of a named field of a `Struct`
+  pub fn element_id (&self, name: &str) -> Option<ElementID> {
+    match self {
+      TypeData::Struct { names, .. } => names.iter().position(|field_name| field_name == name).map(|index| ElementID(index as u64)),
+      _ => None
+    }
+  }
+
   /// Get the `TypeDataKind` of a `TypeData`
   pub fn get_kind (&self) -> TypeDataKind {
     match self {
@@ -402,6 +1585,8 @@ impl TypeData {
       TypeData::Pointer   { .. } => TypeDataKind::Pointer,
       TypeData::Struct    { .. } => TypeDataKind::Struct,
       TypeData::Function  { .. } => TypeDataKind::Function,
+      TypeData::Enum      { .. } => TypeDataKind::Enum,
+      TypeData::Array     { .. } => TypeDataKind::Array,
     }
   }
 }
@@ -417,24 +1602,44 @@ impl Encode for TypeData {
     match self {
       Intrinsic(ity) => ity.encode(buff),
       Pointer(t_id) => t_id.encode(buff),
-      Struct(fields) => fields.encode(buff),
+      Struct { fields, names } => {
+        fields.encode(buff);
+        names.encode(buff);
+      },
       Function { parameters, result } => {
         parameters.encode(buff);
         result.encode(buff);
       },
+      Enum { variants } => variants.encode(buff),
+      Array { element, length } => {
+        element.encode(buff);
+        length.encode(buff);
+      },
     }
   }
 }
 
 impl Decode for TypeData {
-  fn decode (buff: &mut &[u8]) -> Result<TypeData, DecodeError> {
-    Ok(match TypeDataKind::decode(buff)? {
-      TypeDataKind::Intrinsic => TypeData::Intrinsic(IntrinsicType::decode(buff)?),
-      TypeDataKind::Pointer => TypeData::Pointer(TypeID::decode(buff)?),
-      TypeDataKind::Struct => TypeData::Struct(Vec::decode(buff)?),
+  fn decode_from<R: Read> (buff: &mut R) -> Result<TypeData, DecodeError> {
+    Ok(match TypeDataKind::decode_from(buff)? {
+      TypeDataKind::Intrinsic => TypeData::Intrinsic(IntrinsicType::decode_from(buff)?),
+      TypeDataKind::Pointer => TypeData::Pointer(TypeID::decode_from(buff)?),
+      TypeDataKind::Struct => {
+        let fields: Vec<TypeID> = Vec::decode_from(buff)?;
+        let names: Vec<String> = Vec::decode_from(buff)?;
+
+        if names.len() != fields.len() { return Err(DecodeError::UnexpectedValue) }
+
+        TypeData::Struct { fields, names }
+      },
       TypeDataKind::Function => TypeData::Function {
-        parameters: Vec::decode(buff)?,
-        result: Option::decode(buff)?,
+        parameters: Vec::decode_from(buff)?,
+        result: Option::decode_from(buff)?,
+      },
+      TypeDataKind::Enum => TypeData::Enum { variants: Vec::decode_from(buff)? },
+      TypeDataKind::Array => TypeData::Array {
+        element: TypeID::decode_from(buff)?,
+        length: u64::decode_from(buff)?,
       },
     })
   }
@@ -449,12 +1654,15 @@ impl HierarchicalDisplay for TypeData {
     match self {
       Intrinsic(ity) => write!(f, " {})", ity.name()),
       Pointer(ity) => write!(f, " {})", ity),
-      Struct(fields) => {
+      Struct { fields, names } => {
         *level += 1;
-        for field in fields.iter() {
+        for (i, field) in fields.iter().enumerate() {
           writeln!(f)?;
           Padding.fmt_hierarchical(f, level)?;
-          write!(f, "(field {})", field)?;
+          match names.get(i) {
+            Some(name) => write!(f, "(field \"{}\" {})", name, field)?,
+            None => write!(f, "(field {})", field)?,
+          }
         }
         writeln!(f)?;
         *level -= 1;
@@ -469,7 +1677,20 @@ impl HierarchicalDisplay for TypeData {
           write!(f, " (result {})", result)?;
         }
         write!(f, ")")
-      }
+      },
+      Enum { variants } => {
+        *level += 1;
+        for (tag, variant) in variants.iter() {
+          writeln!(f)?;
+          Padding.fmt_hierarchical(f, level)?;
+          write!(f, "(variant {} {})", tag, variant)?;
+        }
+        writeln!(f)?;
+        *level -= 1;
+        Padding.fmt_hierarchical(f, level)?;
+        write!(f, ")")
+      },
+      Array { element, length } => write!(f, " {} (length {}))", element, length),
     }
   }
 }
@@ -489,6 +1710,8 @@ pub enum TypeDataKind {
   Pointer,
   Struct,
   Function,
+  Enum,
+  Array,
 }
 
 impl TypeDataKind {
@@ -501,6 +1724,8 @@ impl TypeDataKind {
       Pointer => "pointer",
       Struct => "struct",
       Function => "function",
+      Enum => "enum",
+      Array => "array",
     }
   }
 }
@@ -512,11 +1737,11 @@ impl Encode for TypeDataKind {
 }
 
 impl Decode for TypeDataKind {
-  fn decode (buff: &mut &[u8]) -> Result<TypeDataKind, DecodeError> {
-    let byte = u8::decode(buff)?;
+  fn decode_from<R: Read> (buff: &mut R) -> Result<TypeDataKind, DecodeError> {
+    let byte = u8::decode_from(buff)?;
     
     if byte >= TypeDataKind::Intrinsic as _
-    && byte <= TypeDataKind::Function  as _ {
+    && byte <= TypeDataKind::Array     as _ {
       Ok(unsafe { transmute(byte) })
     } else {
       Err(DecodeError::UnexpectedValue)
@@ -556,6 +1781,14 @@ pub enum IntrinsicType {
   F32,
   /// 64 bit real
   F64,
+  /// Unicode scalar value, represented as a 32 bit unsigned integer
+  Char,
+  /// 128 bit unsigned integer
+  U128,
+  /// 128 bit signed integer
+  S128,
+  /// A String in the constant pool of a Module, represented by its index
+  String,
 }
 
 impl IntrinsicType {
@@ -577,6 +1810,10 @@ impl IntrinsicType {
       S64 => "s64",
       F32 => "f32",
       F64 => "f64",
+      Char => "char",
+      U128 => "u128",
+      S128 => "s128",
+      String => "string",
     }
   }
 }
@@ -590,11 +1827,11 @@ impl Encode for IntrinsicType {
 }
 
 impl Decode for IntrinsicType {
-  fn decode (buff: &mut &[u8]) -> Result<IntrinsicType, DecodeError> {
-    let byte = u8::decode(buff)?;
+  fn decode_from<R: Read> (buff: &mut R) -> Result<IntrinsicType, DecodeError> {
+    let byte = u8::decode_from(buff)?;
     
     if byte >= IntrinsicType::Void as _
-    && byte <= IntrinsicType::F64  as _ {
+    && byte <= IntrinsicType::String as _ {
       Ok(unsafe { transmute(byte) })
     } else {
       Err(DecodeError::UnexpectedValue)
@@ -635,11 +1872,11 @@ impl Encode for ImportModule {
 }
 
 impl Decode for ImportModule {
-  fn decode (buff: &mut &[u8]) -> Result<ImportModule, DecodeError> {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<ImportModule, DecodeError> {
     Ok(ImportModule {
-      name: String::decode(buff)?,
-      version: Version::decode(buff)?,
-      items: Vec::decode(buff)?
+      name: String::decode_from(buff)?,
+      version: Version::decode_from(buff)?,
+      items: Vec::decode_from(buff)?
     })
   }
 }
@@ -690,10 +1927,10 @@ impl Encode for Import {
 }
 
 impl Decode for Import {
-  fn decode (buff: &mut &[u8]) -> Result<Import, DecodeError> {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<Import, DecodeError> {
     Ok(Import {
-      name: String::decode(buff)?,
-      data: ImportData::decode(buff)?,
+      name: String::decode_from(buff)?,
+      data: ImportData::decode_from(buff)?,
     })
   }
 }
@@ -763,25 +2000,18 @@ impl Encode for ImportData {
         items.encode(buff);
       },
 
-      Global(g_id, t_id) => {
-        g_id.encode(buff);
-        t_id.encode(buff);
-      },
-
-      Function(f_id, t_id) => {
-        f_id.encode(buff);
-        t_id.encode(buff);
-      },
+      &Global(g_id, t_id) => (g_id, t_id).encode(buff),
+      &Function(f_id, t_id) => (f_id, t_id).encode(buff),
     }
   }
 }
 
 impl Decode for ImportData {
-  fn decode (buff: &mut &[u8]) -> Result<ImportData, DecodeError> {
-    Ok(match AliasDataKind::decode(buff)? {
-      AliasDataKind::Namespace => ImportData::Namespace(Vec::decode(buff)?),
-      AliasDataKind::Global    => ImportData::Global(GlobalID::decode(buff)?, TypeID::decode(buff)?),
-      AliasDataKind::Function  => ImportData::Function(FunctionID::decode(buff)?, TypeID::decode(buff)?),
+  fn decode_from<R: Read> (buff: &mut R) -> Result<ImportData, DecodeError> {
+    Ok(match AliasDataKind::decode_from(buff)? {
+      AliasDataKind::Namespace => ImportData::Namespace(Vec::decode_from(buff)?),
+      AliasDataKind::Global    => { let (g_id, t_id) = Decode::decode_from(buff)?; ImportData::Global(g_id, t_id) },
+      AliasDataKind::Function  => { let (f_id, t_id) = Decode::decode_from(buff)?; ImportData::Function(f_id, t_id) },
     })
   }
 }
@@ -819,11 +2049,11 @@ impl Encode for Global {
 }
 
 impl Decode for Global {
-  fn decode (buff: &mut &[u8]) -> Result<Global, DecodeError> {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<Global, DecodeError> {
     Ok(Global {
-      id: GlobalID::decode(buff)?,
-      ty: TypeID::decode(buff)?,
-      initializer: Vec::decode(buff)?,
+      id: GlobalID::decode_from(buff)?,
+      ty: TypeID::decode_from(buff)?,
+      initializer: Vec::decode_from(buff)?,
     })
   }
 }
@@ -882,11 +2112,11 @@ impl Encode for Function {
 }
 
 impl Decode for Function {
-  fn decode (buff: &mut &[u8]) -> Result<Function, DecodeError> {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<Function, DecodeError> {
     Ok(Function {
-      id: FunctionID::decode(buff)?,
-      ty: TypeID::decode(buff)?,
-      body: Vec::decode(buff)?,
+      id: FunctionID::decode_from(buff)?,
+      ty: TypeID::decode_from(buff)?,
+      body: Vec::decode_from(buff)?,
     })
   }
 }
@@ -938,10 +2168,10 @@ impl Encode for Export {
 }
 
 impl Decode for Export {
-  fn decode (buff: &mut &[u8]) -> Result<Export, DecodeError> {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<Export, DecodeError> {
     Ok(Export {
-      name: String::decode(buff)?,
-      data: ExportData::decode(buff)?,
+      name: String::decode_from(buff)?,
+      data: ExportData::decode_from(buff)?,
     })
   }
 }
@@ -1019,11 +2249,11 @@ impl Encode for ExportData {
 }
 
 impl Decode for ExportData {
-  fn decode (buff: &mut &[u8]) -> Result<ExportData, DecodeError> {
-    Ok(match AliasDataKind::decode(buff)? {
-      AliasDataKind::Namespace => ExportData::Namespace(Vec::decode(buff)?),
-      AliasDataKind::Global    => ExportData::Global(GlobalID::decode(buff)?),
-      AliasDataKind::Function  => ExportData::Function(FunctionID::decode(buff)?),
+  fn decode_from<R: Read> (buff: &mut R) -> Result<ExportData, DecodeError> {
+    Ok(match AliasDataKind::decode_from(buff)? {
+      AliasDataKind::Namespace => ExportData::Namespace(Vec::decode_from(buff)?),
+      AliasDataKind::Global    => ExportData::Global(GlobalID::decode_from(buff)?),
+      AliasDataKind::Function  => ExportData::Function(FunctionID::decode_from(buff)?),
     })
   }
 }
@@ -1062,8 +2292,8 @@ impl Encode for AliasDataKind {
 }
 
 impl Decode for AliasDataKind {
-  fn decode (buff: &mut &[u8]) -> Result<AliasDataKind, DecodeError> {
-    let byte = u8::decode(buff)?;
+  fn decode_from<R: Read> (buff: &mut R) -> Result<AliasDataKind, DecodeError> {
+    let byte = u8::decode_from(buff)?;
     
     if byte >= AliasDataKind::Namespace as _
     && byte <= AliasDataKind::Function  as _ {
@@ -1102,6 +2332,17 @@ pub enum Instruction {
   /// then pushes it back on the stack with the type of the struct element
   GetElement(ElementID),
 
+  /// Pops an array address and an index off the stack `(Addr, Index)`, offsets the address to the element at the index,
+  /// then pushes it back on the stack with the type of the array element
+  GetIndex,
+
+  /// Pops an enum address off the stack and offsets it to the tag of the enum,
+  /// then pushes it back on the stack with the type of the tag, `U32`
+  GetTag,
+  /// Pops an enum address off the stack and offsets it to the payload of the variant with the specified tag,
+  /// then pushes it back on the stack with the type of the variant's payload
+  GetPayload(u32),
+
   /// Pops a value off the stack and casts it to the type given by an id,
   /// then pushes the newly typed value back on the stack
   Cast(TypeID),
@@ -1215,7 +2456,7 @@ impl Instruction {
   /// Create an Instruction from an Operator, if a matching variant exists
   pub fn from_operator (operator: Operator) -> Option<Instruction> {
     match operator {
-      Operator::Not => Some(Instruction::Not),
+      Operator::Not | Operator::BitNot => Some(Instruction::Not),
       Operator::And => Some(Instruction::And),
       Operator::Xor => Some(Instruction::Xor),
       Operator::Or => Some(Instruction::Or),
@@ -1230,13 +2471,18 @@ impl Instruction {
       Operator::LesserOrEqual => Some(Instruction::LEQ),
       Operator::Greater => Some(Instruction::GT),
       Operator::Lesser => Some(Instruction::LT),
+      Operator::ShiftLeft => Some(Instruction::LShift),
+      Operator::ShiftRight => Some(Instruction::RShift),
       Operator::Dereference => Some(Instruction::Load),
       
       // cant be directly converted to an instruction
       | Operator::As
+      | Operator::Pow
       | Operator::DoubleColon
       | Operator::RightArrow
+      | Operator::FatArrow
       | Operator::AddressOf 
+      | Operator::Question
       | Operator::Assign
       | Operator::Comma
       | Operator::Colon
@@ -1259,6 +2505,9 @@ impl Instruction {
       Instruction::GlobalAddress { .. } => InstructionKind::GlobalAddress,
       Instruction::FunctionAddress { .. } => InstructionKind::FunctionAddress,
       Instruction::GetElement { .. } => InstructionKind::GetElement,
+      Instruction::GetIndex { .. } => InstructionKind::GetIndex,
+      Instruction::GetTag { .. } => InstructionKind::GetTag,
+      Instruction::GetPayload { .. } => InstructionKind::GetPayload,
       Instruction::Cast { .. } => InstructionKind::Cast,
       Instruction::Load { .. } => InstructionKind::Load,
       Instruction::Store { .. } => InstructionKind::Store,
@@ -1291,6 +2540,185 @@ impl Instruction {
       Instruction::Return { .. } => InstructionKind::Return,
     }
   }
+
+  /// Get the TypeID operand of an Instruction, if it has one
+  pub fn type_operand (&self) -> Option<TypeID> {
+    match *self {
+      Instruction::CreateLocal(id) | Instruction::Cast(id) => Some(id),
+      _ => None
+    }
+  }
+
+  /// Get the FunctionID operand of an Instruction, if it has one
+  pub fn function_operand (&self) -> Option<FunctionID> {
+    match *self {
+      Instruction::FunctionAddress(id) | Instruction::CallDirect(id) => Some(id),
+      _ => None
+    }
+  }
+
+  /// Get the GlobalID operand of an Instruction, if it has one
+  pub fn global_operand (&self) -> Option<GlobalID> {
+    match *self {
+      Instruction::GlobalAddress(id) => Some(id),
+      _ => None
+    }
+  }
+
+  /// Get the LocalID operand of an Instruction, if it has one
+  pub fn local_operand (&self) -> Option<LocalID> {
+    match *self {
+      Instruction::LocalAddress(id) => Some(id),
+      _ => None
+    }
+  }
+
+  /// Get the ElementID operand of an Instruction, if it has one
+  pub fn element_operand (&self) -> Option<ElementID> {
+    match *self {
+      Instruction::GetElement(id) => Some(id),
+      _ => None
+    }
+  }
+
+  /// Get the ImmediateValue operand of an Instruction, if it has one
+  pub fn immediate_operand (&self) -> Option<ImmediateValue> {
+    match *self {
+      Instruction::ImmediateValue(value) => Some(value),
+      _ => None
+    }
+  }
+
+  /// Get the then and else branches of an IfBlock Instruction
+  pub fn block_arms (&self) -> Option<(&[Instruction], &[Instruction])> {
+    match self {
+      Instruction::IfBlock(then_branch, else_branch) => Some((then_branch, else_branch)),
+      _ => None
+    }
+  }
+
+  /// Get the body of a LoopBlock Instruction
+  pub fn loop_body (&self) -> Option<&[Instruction]> {
+    match self {
+      Instruction::LoopBlock(body) => Some(body),
+      _ => None
+    }
+  }
+
+  /// Get an iterator over an Instruction and all Instructions nested inside it,
+  /// in the order they appear in serialized form
+  pub fn walk (&self) -> InstructionWalker {
+    InstructionWalker::new(slice_from_ref(self))
+  }
+
+  /// Call a function on an Instruction and all Instructions nested inside it,
+  /// in the order they appear in serialized form
+  /// 
+  /// Each Instruction is visited before its nested Instructions,
+  /// so any changes made to a block's contents are reflected in the walk
+  pub fn walk_mut<F: FnMut(&mut Instruction)> (&mut self, mut f: F) {
+    fn walk_mut_inner (instruction: &mut Instruction, f: &mut dyn FnMut(&mut Instruction)) {
+      f(instruction);
+
+      match instruction {
+        Instruction::IfBlock(then_branch, else_branch) => {
+          for nested in then_branch.iter_mut().chain(else_branch.iter_mut()) { walk_mut_inner(nested, f) }
+        },
+
+        Instruction::LoopBlock(body) => {
+          for nested in body.iter_mut() { walk_mut_inner(nested, f) }
+        },
+
+        _ => { }
+      }
+    }
+
+    walk_mut_inner(self, &mut f)
+  }
+}
+
+
+/// A mapping from old to new ids, used to relocate the operands of Instructions
+/// 
+/// Ids without an entry are left unchanged
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IdRemap {
+  /// Replacements for TypeIDs
+  pub types: HashMap<TypeID, TypeID>,
+  /// Replacements for GlobalIDs
+  pub globals: HashMap<GlobalID, GlobalID>,
+  /// Replacements for FunctionIDs
+  pub functions: HashMap<FunctionID, FunctionID>,
+}
+
+impl IdRemap {
+  /// Apply the remap to the id operand of a single Instruction, ignoring any nested Instructions
+  pub fn apply (&self, instruction: &mut Instruction) {
+    match instruction {
+      | Instruction::CreateLocal(id)
+      | Instruction::Cast(id)
+      => if let Some(&new_id) = self.types.get(id) { *id = new_id },
+
+      Instruction::GlobalAddress(id)
+      => if let Some(&new_id) = self.globals.get(id) { *id = new_id },
+
+      | Instruction::FunctionAddress(id)
+      | Instruction::CallDirect(id)
+      => if let Some(&new_id) = self.functions.get(id) { *id = new_id },
+
+      _ => { }
+    }
+  }
+}
+
+/// Rewrite every TypeID, GlobalID, and FunctionID operand in a sequence of Instructions,
+/// including those nested in IfBlocks and LoopBlocks, according to an IdRemap
+pub fn remap_ids (instructions: &mut [Instruction], map: &IdRemap) {
+  for instruction in instructions.iter_mut() {
+    instruction.walk_mut(|nested| map.apply(nested))
+  }
+}
+
+
+/// An iterator over a sequence of Instructions which also yields all Instructions nested in IfBlocks and LoopBlocks,
+/// in the order they appear in serialized form
+pub struct InstructionWalker<'a> {
+  stack: Vec<SliceIter<'a, Instruction>>,
+}
+
+impl<'a> InstructionWalker<'a> {
+  /// Create a new InstructionWalker over a sequence of Instructions
+  pub fn new (instructions: &'a [Instruction]) -> Self {
+    Self { stack: vec! [ instructions.iter() ] }
+  }
+}
+
+impl<'a> Iterator for InstructionWalker<'a> {
+  type Item = &'a Instruction;
+
+  fn next (&mut self) -> Option<Self::Item> {
+    loop {
+      let instruction = if let Some(instruction) = self.stack.last_mut()?.next() {
+        instruction
+      } else {
+        self.stack.pop();
+        continue
+      };
+
+      match instruction {
+        Instruction::IfBlock(then_branch, else_branch) => {
+          self.stack.push(else_branch.iter());
+          self.stack.push(then_branch.iter());
+        },
+
+        Instruction::LoopBlock(body) => self.stack.push(body.iter()),
+
+        _ => { }
+      }
+
+      return Some(instruction)
+    }
+  }
 }
 
 impl Encode for Instruction {
@@ -1323,6 +2751,8 @@ impl Encode for Instruction {
       | GT
       | LEQ
       | GEQ
+      | GetIndex
+      | GetTag
       | CallIndirect
       | Break
       | Continue
@@ -1336,6 +2766,7 @@ impl Encode for Instruction {
       GlobalAddress(g_id) => g_id.encode(buff),
       FunctionAddress(f_id) => f_id.encode(buff),
       GetElement(e_id) => e_id.encode(buff),
+      GetPayload(tag) => tag.encode(buff),
       Cast(t_id) => t_id.encode(buff),
       CallDirect(f_id) => f_id.encode(buff),
 
@@ -1350,8 +2781,8 @@ impl Encode for Instruction {
 }
 
 impl Decode for Instruction {
-  fn decode (buff: &mut &[u8]) -> Result<Instruction, DecodeError> {
-    Ok(match InstructionKind::decode(buff)? {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<Instruction, DecodeError> {
+    Ok(match InstructionKind::decode_from(buff)? {
       InstructionKind::NoOp => Instruction::NoOp,
       InstructionKind::Load => Instruction::Load,
       InstructionKind::Store => Instruction::Store,
@@ -1379,19 +2810,29 @@ impl Decode for Instruction {
       InstructionKind::Break => Instruction::Break,
       InstructionKind::Continue => Instruction::Continue,
       InstructionKind::Return => Instruction::Return,
+      InstructionKind::GetIndex => Instruction::GetIndex,
+      InstructionKind::GetTag => Instruction::GetTag,
+
+      InstructionKind::ImmediateValue => Instruction::ImmediateValue(ImmediateValue::decode_from(buff)?),
+
+      InstructionKind::CreateLocal => Instruction::CreateLocal(TypeID::decode_from(buff)?),
+      InstructionKind::LocalAddress => Instruction::LocalAddress(LocalID::decode_from(buff)?),
+      InstructionKind::GlobalAddress => Instruction::GlobalAddress(GlobalID::decode_from(buff)?),
+      InstructionKind::FunctionAddress => Instruction::FunctionAddress(FunctionID::decode_from(buff)?),
+      InstructionKind::GetElement => Instruction::GetElement(ElementID::decode_from(buff)?),
+      InstructionKind::GetPayload => Instruction::GetPayload(u32::decode_from(buff)?),
+      InstructionKind::Cast => Instruction::Cast(TypeID::decode_from(buff)?),
+      InstructionKind::CallDirect => Instruction::CallDirect(FunctionID::decode_from(buff)?),
+
+      InstructionKind::IfBlock => {
+        let _guard = DecodeDepthGuard::enter()?;
+        Instruction::IfBlock(Vec::decode_from(buff)?, Vec::decode_from(buff)?)
+      },
 
-      InstructionKind::ImmediateValue => Instruction::ImmediateValue(ImmediateValue::decode(buff)?),
-
-      InstructionKind::CreateLocal => Instruction::CreateLocal(TypeID::decode(buff)?),
-      InstructionKind::LocalAddress => Instruction::LocalAddress(LocalID::decode(buff)?),
-      InstructionKind::GlobalAddress => Instruction::GlobalAddress(GlobalID::decode(buff)?),
-      InstructionKind::FunctionAddress => Instruction::FunctionAddress(FunctionID::decode(buff)?),
-      InstructionKind::GetElement => Instruction::GetElement(ElementID::decode(buff)?),
-      InstructionKind::Cast => Instruction::Cast(TypeID::decode(buff)?),
-      InstructionKind::CallDirect => Instruction::CallDirect(FunctionID::decode(buff)?),
-
-      InstructionKind::IfBlock => Instruction::IfBlock(Vec::decode(buff)?, Vec::decode(buff)?),
-      InstructionKind::LoopBlock => Instruction::LoopBlock(Vec::decode(buff)?),
+      InstructionKind::LoopBlock => {
+        let _guard = DecodeDepthGuard::enter()?;
+        Instruction::LoopBlock(Vec::decode_from(buff)?)
+      },
     })
   }
 }
@@ -1426,6 +2867,8 @@ impl HierarchicalDisplay for Instruction {
       | GT
       | LEQ
       | GEQ
+      | GetIndex
+      | GetTag
       | CallIndirect
       | Break
       | Continue
@@ -1439,6 +2882,7 @@ impl HierarchicalDisplay for Instruction {
         | GlobalAddress { .. }
         | FunctionAddress { .. }
         | GetElement { .. }
+        | GetPayload { .. }
         | Cast { .. }
         | CallDirect { .. }
       ) => {
@@ -1452,6 +2896,7 @@ impl HierarchicalDisplay for Instruction {
           GlobalAddress(g_id) => write!(f, "{}", g_id)?,
           FunctionAddress(f_id) => write!(f, "{}", f_id)?,
           GetElement(e_id) => write!(f, "{}", e_id)?,
+          GetPayload(tag) => write!(f, "{}", tag)?,
           Cast(t_id) => write!(f, "{}", t_id)?,
           CallDirect(f_id) => write!(f, "{}", f_id)?,
           
@@ -1592,6 +3037,11 @@ pub enum InstructionKind {
   Continue,
 
   Return,
+
+  // kinds added after the initial format are appended, so the encoding of existing kinds is unchanged
+  GetIndex,
+  GetTag,
+  GetPayload,
 }
 
 impl InstructionKind {
@@ -1637,6 +3087,9 @@ impl InstructionKind {
       Break => "break",
       Continue => "continue",
       Return => "return",
+      GetIndex => "get_index",
+      GetTag => "get_tag",
+      GetPayload => "get_payload",
     }
   }
 }
@@ -1650,11 +3103,11 @@ impl Encode for InstructionKind {
 }
 
 impl Decode for InstructionKind {
-  fn decode (buff: &mut &[u8]) -> Result<InstructionKind, DecodeError> {
-    let byte = u8::decode(buff)?;
+  fn decode_from<R: Read> (buff: &mut R) -> Result<InstructionKind, DecodeError> {
+    let byte = u8::decode_from(buff)?;
     
     if byte >= InstructionKind::NoOp as _
-    && byte <= InstructionKind::Return  as _ {
+    && byte <= InstructionKind::GetPayload as _ {
       Ok(unsafe { transmute(byte) })
     } else {
       Err(DecodeError::UnexpectedValue)
@@ -1691,9 +3144,27 @@ pub enum ImmediateValue {
   F32(f32),
   /// 64 bit real
   F64(f64),
+  /// Unicode scalar value
+  Char(char),
+  /// 128 bit unsigned integer
+  U128(u128),
+  /// 128 bit signed integer
+  S128(i128),
+  /// The index of a String in the constant pool of a Module
+  StringRef(u32),
 }
 
 impl ImmediateValue {
+  /// The bit pattern every 32 bit NaN is encoded as, a positive quiet NaN with an empty payload
+  /// 
+  /// NaN payloads are not preserved by encoding, so round trips of NaN immediates are bit-exact
+  pub const CANONICAL_NAN_F32_BITS: u32 = 0x7fc0_0000;
+
+  /// The bit pattern every 64 bit NaN is encoded as, a positive quiet NaN with an empty payload
+  /// 
+  /// NaN payloads are not preserved by encoding, so round trips of NaN immediates are bit-exact
+  pub const CANONICAL_NAN_F64_BITS: u64 = 0x7ff8_0000_0000_0000;
+
   /// Get the IntrinsicType of an ImmediateValue
   pub fn get_intrinsic_type (&self) -> IntrinsicType {
     match self {
@@ -1709,6 +3180,10 @@ impl ImmediateValue {
       Self::S64(_)  => IntrinsicType::S64,
       Self::F32(_)  => IntrinsicType::F32,
       Self::F64(_)  => IntrinsicType::F64,
+      Self::Char(_) => IntrinsicType::Char,
+      Self::U128(_) => IntrinsicType::U128,
+      Self::S128(_) => IntrinsicType::S128,
+      Self::StringRef(_) => IntrinsicType::String,
     }
   }
 }
@@ -1732,25 +3207,33 @@ impl Encode for ImmediateValue {
       S64(x)   => x.encode(buff),
       F32(x)   => x.encode(buff),
       F64(x)   => x.encode(buff),
+      Char(x)  => x.encode(buff),
+      U128(x)  => x.encode(buff),
+      S128(x)  => x.encode(buff),
+      StringRef(x) => x.encode(buff),
     }
   }
 }
 
 impl Decode for ImmediateValue {
-  fn decode (buff: &mut &[u8]) -> Result<ImmediateValue, DecodeError> {
-    Ok(match IntrinsicType::decode(buff)? {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<ImmediateValue, DecodeError> {
+    Ok(match IntrinsicType::decode_from(buff)? {
       IntrinsicType::Null => ImmediateValue::Null,
-      IntrinsicType::Bool => bool::decode(buff)?.into(),
-      IntrinsicType::U8   =>   u8::decode(buff)?.into(),
-      IntrinsicType::U16  =>  u16::decode(buff)?.into(),
-      IntrinsicType::U32  =>  u32::decode(buff)?.into(),
-      IntrinsicType::U64  =>  u64::decode(buff)?.into(),
-      IntrinsicType::S8   =>   i8::decode(buff)?.into(),
-      IntrinsicType::S16  =>  i16::decode(buff)?.into(),
-      IntrinsicType::S32  =>  i32::decode(buff)?.into(),
-      IntrinsicType::S64  =>  i64::decode(buff)?.into(),
-      IntrinsicType::F32  =>  f32::decode(buff)?.into(),
-      IntrinsicType::F64  =>  f64::decode(buff)?.into(),
+      IntrinsicType::Bool => bool::decode_from(buff)?.into(),
+      IntrinsicType::U8   =>   u8::decode_from(buff)?.into(),
+      IntrinsicType::U16  =>  u16::decode_from(buff)?.into(),
+      IntrinsicType::U32  =>  u32::decode_from(buff)?.into(),
+      IntrinsicType::U64  =>  u64::decode_from(buff)?.into(),
+      IntrinsicType::S8   =>   i8::decode_from(buff)?.into(),
+      IntrinsicType::S16  =>  i16::decode_from(buff)?.into(),
+      IntrinsicType::S32  =>  i32::decode_from(buff)?.into(),
+      IntrinsicType::S64  =>  i64::decode_from(buff)?.into(),
+      IntrinsicType::F32  =>  f32::decode_from(buff)?.into(),
+      IntrinsicType::F64  =>  f64::decode_from(buff)?.into(),
+      IntrinsicType::Char => char::decode_from(buff)?.into(),
+      IntrinsicType::U128 => u128::decode_from(buff)?.into(),
+      IntrinsicType::S128 => i128::decode_from(buff)?.into(),
+      IntrinsicType::String => ImmediateValue::StringRef(u32::decode_from(buff)?),
       
       IntrinsicType::Void => return Err(DecodeError::UnexpectedValue),
     })
@@ -1776,6 +3259,10 @@ impl HierarchicalDisplay for ImmediateValue {
       S64(x)  => write!(f, " {}", x)?,
       F32(x)  => write!(f, " {}", x)?,
       F64(x)  => write!(f, " {}", x)?,
+      Char(x) => write!(f, " {:?}", x)?,
+      U128(x) => write!(f, " {}", x)?,
+      S128(x) => write!(f, " {}", x)?,
+      StringRef(x) => write!(f, " {}", x)?,
     }
 
     write!(f, ")")
@@ -1799,38 +3286,193 @@ impl From<i64> for ImmediateValue { fn from (s: i64) -> Self { Self::S64 (s) } }
 impl From<f32> for ImmediateValue { fn from (f: f32) -> Self { Self::F32 (f) } }
 impl From<f64> for ImmediateValue { fn from (f: f64) -> Self { Self::F64 (f) } }
 
+impl From<char> for ImmediateValue { fn from (c: char) -> Self { Self::Char (c) } }
+impl From<u128> for ImmediateValue { fn from (u: u128) -> Self { Self::U128 (u) } }
+impl From<i128> for ImmediateValue { fn from (s: i128) -> Self { Self::S128 (s) } }
+
 impl From<usize> for ImmediateValue { fn from (u: usize) -> Self { Self::U64 (u as _) } }
 impl From<isize> for ImmediateValue { fn from (s: isize) -> Self { Self::S64 (s as _) } }
 
 
 
+/// A runtime value as seen by a host inspecting an interpreter
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+  /// A value of an intrinsic type
+  Immediate(ImmediateValue),
+  /// The address of a value of another type
+  Pointer(u64),
+  /// An aggregate containing a list of values of other types
+  Struct(Vec<Value>),
+}
+
+impl From<ImmediateValue> for Value { fn from (i: ImmediateValue) -> Self { Self::Immediate(i) } }
+
+
+/// Options controlling how `format_value_with` renders a `Value`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueFormat {
+  /// Append the intrinsic type name to numeric values, e.g. `1u8` instead of `1`
+  pub type_suffixes: bool,
+  /// The number of nested structs to render before eliding their fields as `{ .. }`
+  pub max_depth: usize,
+}
+
+impl Default for ValueFormat {
+  fn default () -> Self {
+    Self {
+      type_suffixes: false,
+      max_depth: 8,
+    }
+  }
+}
+
+/// Render a `Value` for debugging output, using the `Type` it has in a `Module` to structure the result
+/// 
+/// See `format_value_with` for configurable output
+pub fn format_value (value: &Value, ty: TypeID, module: &Module) -> String {
+  format_value_with(value, ty, module, ValueFormat::default())
+}
+
+/// Render a `Value` for debugging output, using the `Type` it has in a `Module` to structure the result
+/// 
+/// Values which do not match their given type are rendered as `<invalid ...>` rather than causing an error,
+/// as this is intended for inspecting possibly-incorrect interpreter state
+pub fn format_value_with (value: &Value, ty: TypeID, module: &Module, format: ValueFormat) -> String {
+  fn format_inner (out: &mut String, value: &Value, ty: TypeID, module: &Module, format: ValueFormat, depth: usize) {
+    use std::fmt::Write;
+
+    let type_data = if let Some(ty) = module.get_type(ty) { &ty.data } else {
+      write!(out, "<invalid {}>", ty).unwrap();
+      return
+    };
+
+    match (value, type_data) {
+      (Value::Immediate(ImmediateValue::Null), TypeData::Intrinsic(IntrinsicType::Null) | TypeData::Pointer(_)) => {
+        out.push_str("null")
+      },
+
+      (Value::Immediate(immediate), TypeData::Intrinsic(ity)) if immediate.get_intrinsic_type() == *ity => {
+        use ImmediateValue::*;
+
+        match immediate {
+          Null    => out.push_str("null"),
+          Bool(x) => write!(out, "{}", x).unwrap(),
+          U8(x)   => write!(out, "{}", x).unwrap(),
+          U16(x)  => write!(out, "{}", x).unwrap(),
+          U32(x)  => write!(out, "{}", x).unwrap(),
+          U64(x)  => write!(out, "{}", x).unwrap(),
+          S8(x)   => write!(out, "{}", x).unwrap(),
+          S16(x)  => write!(out, "{}", x).unwrap(),
+          S32(x)  => write!(out, "{}", x).unwrap(),
+          S64(x)  => write!(out, "{}", x).unwrap(),
+          F32(x)  => write!(out, "{:?}", x).unwrap(),
+          F64(x)  => write!(out, "{:?}", x).unwrap(),
+          Char(x) => write!(out, "{:?}", x).unwrap(),
+          U128(x) => write!(out, "{}", x).unwrap(),
+          S128(x) => write!(out, "{}", x).unwrap(),
+          &StringRef(x) => match module.get_string(x) {
+            Some(string) => write!(out, "{:?}", string).unwrap(),
+            None => write!(out, "<invalid string {}>", x).unwrap(),
+          },
+        }
+
+        if format.type_suffixes && !matches!(immediate, Null | Bool(_) | Char(_) | StringRef(_)) {
+          out.push_str(ity.name())
+        }
+      },
+
+      (&Value::Pointer(address), TypeData::Pointer(_)) => write!(out, "0x{:016x}", address).unwrap(),
+
+      (Value::Struct(values), TypeData::Struct { fields: field_types, names }) if values.len() == field_types.len() => {
+        if values.is_empty() {
+          out.push_str("{ }")
+        } else if depth >= format.max_depth {
+          out.push_str("{ .. }")
+        } else {
+          out.push_str("{ ");
+
+          for (i, (value, &field_type)) in values.iter().zip(field_types.iter()).enumerate() {
+            if i != 0 { out.push_str(", ") }
+            match names.get(i) {
+              Some(name) => write!(out, "{}: ", name).unwrap(),
+              None => write!(out, "{}: ", i).unwrap(),
+            }
+            format_inner(out, value, field_type, module, format, depth + 1);
+          }
+
+          out.push_str(" }")
+        }
+      },
+
+      (_, type_data) => write!(out, "<invalid {}>", type_data.get_kind().name()).unwrap(),
+    }
+  }
+
+  let mut out = String::new();
+  format_inner(&mut out, value, ty, module, format, 0);
+  out
+}
+
+
+
 impl Encode for str {
   fn encode (&self, buff: &mut Vec<u8>) {
     self.len().encode(buff);
     self.as_bytes().encode(buff);
   }
+
+  fn encode_to<W: Write> (&self, w: &mut W) -> IOResult<()> {
+    self.len().encode_to(w)?;
+    w.write_all(self.as_bytes())
+  }
 }
 
-impl Encode for bool { fn encode (&self, buff: &mut Vec<u8>) { buff.push(*self as _); } }
-impl Encode for u8   { fn encode (&self, buff: &mut Vec<u8>) { buff.push(*self as _); } }
-impl Encode for i8   { fn encode (&self, buff: &mut Vec<u8>) { buff.push(*self as _); } }
+impl Encode for String {
+  fn encode (&self, buff: &mut Vec<u8>) { self.as_str().encode(buff) }
 
-impl Encode for u16 { fn encode (&self, buff: &mut Vec<u8>) { self.to_le_bytes().encode(buff) } }
-impl Encode for u32 { fn encode (&self, buff: &mut Vec<u8>) { self.to_le_bytes().encode(buff) } }
-impl Encode for u64 { fn encode (&self, buff: &mut Vec<u8>) { self.to_le_bytes().encode(buff) } }
-impl Encode for i16 { fn encode (&self, buff: &mut Vec<u8>) { self.to_le_bytes().encode(buff) } }
-impl Encode for i32 { fn encode (&self, buff: &mut Vec<u8>) { self.to_le_bytes().encode(buff) } }
-impl Encode for i64 { fn encode (&self, buff: &mut Vec<u8>) { self.to_le_bytes().encode(buff) } }
-impl Encode for f32 { fn encode (&self, buff: &mut Vec<u8>) { self.to_le_bytes().encode(buff) } }
-impl Encode for f64 { fn encode (&self, buff: &mut Vec<u8>) { self.to_le_bytes().encode(buff) } }
+  fn encode_to<W: Write> (&self, w: &mut W) -> IOResult<()> { self.as_str().encode_to(w) }
+}
+
+/// Implements `Encode` for a primitive by converting it to its little endian bytes
+macro_rules! encode_le_bytes {
+  ($($ty:ty => |$value:ident| $bytes:expr;)*) => { $(
+    impl Encode for $ty {
+      fn encode (&self, buff: &mut Vec<u8>) { let $value = *self; buff.extend_from_slice(&$bytes) }
+      fn encode_to<W: Write> (&self, w: &mut W) -> IOResult<()> { let $value = *self; w.write_all(&$bytes) }
+    }
+  )* };
+}
 
-impl Encode for usize { fn encode (&self, buff: &mut Vec<u8>) { (*self as u64).encode(buff) } }
-impl Encode for isize { fn encode (&self, buff: &mut Vec<u8>) { (*self as i64).encode(buff) } }
+encode_le_bytes! {
+  bool => |v| [ v as u8 ];
+  u8   => |v| [ v ];
+  i8   => |v| [ v as u8 ];
+
+  u16 => |v| v.to_le_bytes();
+  u32 => |v| v.to_le_bytes();
+  u64 => |v| v.to_le_bytes();
+  i16 => |v| v.to_le_bytes();
+  i32 => |v| v.to_le_bytes();
+  i64 => |v| v.to_le_bytes();
+  u128 => |v| v.to_le_bytes();
+  i128 => |v| v.to_le_bytes();
+  char => |v| (v as u32).to_le_bytes();
+  f32 => |v| if v.is_nan() { ImmediateValue::CANONICAL_NAN_F32_BITS.to_le_bytes() } else { v.to_le_bytes() };
+  f64 => |v| if v.is_nan() { ImmediateValue::CANONICAL_NAN_F64_BITS.to_le_bytes() } else { v.to_le_bytes() };
+
+  usize => |v| (v as u64).to_le_bytes();
+  isize => |v| (v as i64).to_le_bytes();
+}
 
 impl<E: Encode> Encode for [E] {
   fn encode (&self, buff: &mut Vec<u8>) {
     self.iter().for_each(|e| e.encode(buff))
   }
+
+  fn encode_to<W: Write> (&self, w: &mut W) -> IOResult<()> {
+    self.iter().try_for_each(|e| e.encode_to(w))
+  }
 }
 
 impl<E: Encode> Encode for Vec<E> {
@@ -1838,6 +3480,11 @@ impl<E: Encode> Encode for Vec<E> {
     self.len().encode(buff);
     self.iter().for_each(|e| e.encode(buff))
   }
+
+  fn encode_to<W: Write> (&self, w: &mut W) -> IOResult<()> {
+    self.len().encode_to(w)?;
+    self.iter().try_for_each(|e| e.encode_to(w))
+  }
 }
 
 impl<E: Encode> Encode for Option<E> {
@@ -1850,106 +3497,130 @@ impl<E: Encode> Encode for Option<E> {
       None => false.encode(buff)
     }
   }
-}
 
-
-impl Decode for u8 {
-  fn decode (buff: &mut &[u8]) -> Result<u8, DecodeError> {
-    match buff.get(0) {
-      Some(&b) => { *buff = &buff[1..]; Ok(b) },
-      None =>  Err(DecodeError::EOF)
+  fn encode_to<W: Write> (&self, w: &mut W) -> IOResult<()> {
+    match self {
+      Some(e) => {
+        true.encode_to(w)?;
+        e.encode_to(w)
+      },
+      None => false.encode_to(w)
     }
   }
 }
 
+impl<A: Encode, B: Encode> Encode for (A, B) {
+  fn encode (&self, buff: &mut Vec<u8>) {
+    self.0.encode(buff);
+    self.1.encode(buff);
+  }
 
-impl Decode for bool { fn decode (buff: &mut &[u8]) -> Result<bool, DecodeError> { Ok(u8::decode(buff)? == 1 ) } }
-impl Decode for i8   { fn decode (buff: &mut &[u8]) -> Result<i8,   DecodeError> { Ok(u8::decode(buff)? as i8) } }
+  fn encode_to<W: Write> (&self, w: &mut W) -> IOResult<()> {
+    self.0.encode_to(w)?;
+    self.1.encode_to(w)
+  }
+}
 
-type BytePair  = [u8; 2];
+impl<A: Encode, B: Encode, C: Encode> Encode for (A, B, C) {
+  fn encode (&self, buff: &mut Vec<u8>) {
+    self.0.encode(buff);
+    self.1.encode(buff);
+    self.2.encode(buff);
+  }
 
-impl Decode for BytePair  {
-  fn decode (buff: &mut &[u8]) -> Result<BytePair,  DecodeError> {
-    if buff.len() >= 2 {
-      let arr = unsafe { * (buff.as_ptr() as *const BytePair) };
-      *buff = &buff[2..];
-      Ok(arr)
-    } else {
-      Err(DecodeError::EOF)
-    }
+  fn encode_to<W: Write> (&self, w: &mut W) -> IOResult<()> {
+    self.0.encode_to(w)?;
+    self.1.encode_to(w)?;
+    self.2.encode_to(w)
   }
 }
 
-type ByteQuad  = [u8; 4];
 
-impl Decode for ByteQuad  {
-  fn decode (buff: &mut &[u8]) -> Result<ByteQuad,  DecodeError> {
-    if buff.len() >= 4 {
-      let arr = unsafe { * (buff.as_ptr() as *const ByteQuad) };
-      *buff = &buff[4..];
-      Ok(arr)
-    } else {
-      Err(DecodeError::EOF)
-    }
+impl Decode for u8 {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<u8, DecodeError> {
+    Ok(Byte::decode_from(buff)?[0])
   }
 }
 
-type ByteOctet = [u8; 8];
 
-impl Decode for ByteOctet  {
-  fn decode (buff: &mut &[u8]) -> Result<ByteOctet,  DecodeError> {
-    if buff.len() >= 8 {
-      let arr = unsafe { *(buff.as_ptr() as *const ByteOctet) };
-      *buff = &buff[8..];
-      Ok(arr)
-    } else {
-      Err(DecodeError::EOF)
+impl Decode for bool { fn decode_from<R: Read> (buff: &mut R) -> Result<bool, DecodeError> { Ok(u8::decode_from(buff)? == 1 ) } }
+impl Decode for i8   { fn decode_from<R: Read> (buff: &mut R) -> Result<i8,   DecodeError> { Ok(u8::decode_from(buff)? as i8) } }
+
+/// Implements `Decode` for a fixed size byte array by reading exactly its length from the source
+macro_rules! decode_byte_array {
+  ($($name:ident = $length:literal;)*) => { $(
+    type $name = [u8; $length];
+
+    impl Decode for $name {
+      fn decode_from<R: Read> (buff: &mut R) -> Result<$name, DecodeError> {
+        let mut arr = [0u8; $length];
+        buff.read_exact(&mut arr)?;
+        Ok(arr)
+      }
     }
-  }
+  )* };
+}
+
+decode_byte_array! {
+  Byte      = 1;
+  BytePair  = 2;
+  ByteQuad  = 4;
+  ByteOctet = 8;
+  ByteHex   = 16;
 }
 
-impl Decode for u16 { fn decode (buff: &mut &[u8]) -> Result<u16, DecodeError> { Ok(u16::from_le_bytes( BytePair::decode(buff)?)) }}
-impl Decode for u32 { fn decode (buff: &mut &[u8]) -> Result<u32, DecodeError> { Ok(u32::from_le_bytes( ByteQuad::decode(buff)?)) }}
-impl Decode for u64 { fn decode (buff: &mut &[u8]) -> Result<u64, DecodeError> { Ok(u64::from_le_bytes(ByteOctet::decode(buff)?)) }}
+impl Decode for u16 { fn decode_from<R: Read> (buff: &mut R) -> Result<u16, DecodeError> { Ok(u16::from_le_bytes( BytePair::decode_from(buff)?)) }}
+impl Decode for u32 { fn decode_from<R: Read> (buff: &mut R) -> Result<u32, DecodeError> { Ok(u32::from_le_bytes( ByteQuad::decode_from(buff)?)) }}
+impl Decode for u64 { fn decode_from<R: Read> (buff: &mut R) -> Result<u64, DecodeError> { Ok(u64::from_le_bytes(ByteOctet::decode_from(buff)?)) }}
+
+impl Decode for i16 { fn decode_from<R: Read> (buff: &mut R) -> Result<i16, DecodeError> { Ok(i16::from_le_bytes( BytePair::decode_from(buff)?)) }}
+impl Decode for i32 { fn decode_from<R: Read> (buff: &mut R) -> Result<i32, DecodeError> { Ok(i32::from_le_bytes( ByteQuad::decode_from(buff)?)) }}
+impl Decode for i64 { fn decode_from<R: Read> (buff: &mut R) -> Result<i64, DecodeError> { Ok(i64::from_le_bytes(ByteOctet::decode_from(buff)?)) }}
 
-impl Decode for i16 { fn decode (buff: &mut &[u8]) -> Result<i16, DecodeError> { Ok(i16::from_le_bytes( BytePair::decode(buff)?)) }}
-impl Decode for i32 { fn decode (buff: &mut &[u8]) -> Result<i32, DecodeError> { Ok(i32::from_le_bytes( ByteQuad::decode(buff)?)) }}
-impl Decode for i64 { fn decode (buff: &mut &[u8]) -> Result<i64, DecodeError> { Ok(i64::from_le_bytes(ByteOctet::decode(buff)?)) }}
+impl Decode for u128 { fn decode_from<R: Read> (buff: &mut R) -> Result<u128, DecodeError> { Ok(u128::from_le_bytes(ByteHex::decode_from(buff)?)) }}
+impl Decode for i128 { fn decode_from<R: Read> (buff: &mut R) -> Result<i128, DecodeError> { Ok(i128::from_le_bytes(ByteHex::decode_from(buff)?)) }}
+
+impl Decode for char {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<char, DecodeError> {
+    std::char::from_u32(u32::decode_from(buff)?).ok_or(DecodeError::UnexpectedValue)
+  }
+}
 
-impl Decode for f32 { fn decode (buff: &mut &[u8]) -> Result<f32, DecodeError> { Ok(f32::from_le_bytes( ByteQuad::decode(buff)?)) }}
-impl Decode for f64 { fn decode (buff: &mut &[u8]) -> Result<f64, DecodeError> { Ok(f64::from_le_bytes(ByteOctet::decode(buff)?)) }}
+impl Decode for f32 { fn decode_from<R: Read> (buff: &mut R) -> Result<f32, DecodeError> { Ok(f32::from_le_bytes( ByteQuad::decode_from(buff)?)) }}
+impl Decode for f64 { fn decode_from<R: Read> (buff: &mut R) -> Result<f64, DecodeError> { Ok(f64::from_le_bytes(ByteOctet::decode_from(buff)?)) }}
 
-impl Decode for usize { fn decode (buff: &mut &[u8]) -> Result<usize, DecodeError> { Ok(u64::decode(buff)? as _) } }
-impl Decode for isize { fn decode (buff: &mut &[u8]) -> Result<isize, DecodeError> { Ok(i64::decode(buff)? as _) } }
+impl Decode for usize { fn decode_from<R: Read> (buff: &mut R) -> Result<usize, DecodeError> { Ok(u64::decode_from(buff)? as _) } }
+impl Decode for isize { fn decode_from<R: Read> (buff: &mut R) -> Result<isize, DecodeError> { Ok(i64::decode_from(buff)? as _) } }
 
 
 impl Decode for String {
-  fn decode (buff: &mut &[u8]) -> Result<String, DecodeError> {
-    let length = u64::decode(buff)? as usize;
+  fn decode_from<R: Read> (buff: &mut R) -> Result<String, DecodeError> {
+    let length = u64::decode_from(buff)?;
 
-    if buff.len() < length { return Err(DecodeError::EOF) }
+    // Reading through `take` grows the buffer as data arrives,
+    // so a length prefix longer than the remaining data cannot force a large allocation
+    let mut bytes = Vec::new();
+    buff.by_ref().take(length).read_to_end(&mut bytes)?;
 
-    match std::str::from_utf8(&buff[..length]) {
-      Ok(str) => {
-        let string = str.to_string();
+    if (bytes.len() as u64) < length { return Err(DecodeError::EOF) }
 
-        *buff = &buff[length..];
-        
-        Ok(string)
-      },
-      Err(_) => Err(DecodeError::InvalidString)
-    }
+    String::from_utf8(bytes).map_err(|_| DecodeError::InvalidString)
   }
 }
 
+/// The maximum number of elements `Decode for Vec` will allocate space for before any are read
+const MAX_PREALLOCATED_ELEMENTS: usize = 1024;
+
 impl<D: Decode> Decode for Vec<D> {
-  fn decode (buff: &mut &[u8]) -> Result<Vec<D>, DecodeError> {
-    let length = u64::decode(buff)? as usize;
+  fn decode_from<R: Read> (buff: &mut R) -> Result<Vec<D>, DecodeError> {
+    let length = u64::decode_from(buff)? as usize;
 
-    let mut vec = Vec::with_capacity(length);
+    // The remaining length of a reader is unknown, so the length prefix is only trusted up to a limit;
+    // a longer vector still decodes, growing as elements are read, and a false length ends in EOF
+    let mut vec = Vec::with_capacity(length.min(MAX_PREALLOCATED_ELEMENTS));
 
     for _ in 0..length {
-      vec.push(D::decode(buff)?)
+      vec.push(D::decode_from(buff)?)
     }
 
     Ok(vec)
@@ -1957,16 +3628,28 @@ impl<D: Decode> Decode for Vec<D> {
 }
 
 impl<D: Decode> Decode for Option<D> {
-  fn decode (buff: &mut &[u8]) -> Result<Option<D>, DecodeError> {
-    let is_some = bool::decode(buff)?;
+  fn decode_from<R: Read> (buff: &mut R) -> Result<Option<D>, DecodeError> {
+    let is_some = bool::decode_from(buff)?;
     if is_some {
-      Ok(Some(D::decode(buff)?))
+      Ok(Some(D::decode_from(buff)?))
     } else {
       Ok(None)
     }
   }
 }
 
+impl<A: Decode, B: Decode> Decode for (A, B) {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<(A, B), DecodeError> {
+    Ok((A::decode_from(buff)?, B::decode_from(buff)?))
+  }
+}
+
+impl<A: Decode, B: Decode, C: Decode> Decode for (A, B, C) {
+  fn decode_from<R: Read> (buff: &mut R) -> Result<(A, B, C), DecodeError> {
+    Ok((A::decode_from(buff)?, B::decode_from(buff)?, C::decode_from(buff)?))
+  }
+}
+
 
 
 #[cfg(test)]
@@ -1981,10 +3664,15 @@ mod test {
         Type::new(0.into(), TypeData::Intrinsic(IntrinsicType::S64)),
         Type::new(1.into(), TypeData::Function { parameters: vec! [ 0.into(), 0.into() ], result: Some(0.into()) }),
         Type::new(2.into(), TypeData::Function { parameters: vec! [ ], result: Some(0.into()) }),
-        Type::new(3.into(), TypeData::Struct(vec! [
-          0.into(),
-          0.into(),
-        ]))
+        Type::new(3.into(), TypeData::Struct {
+          fields: vec! [ 0.into(), 0.into() ],
+          names: vec! [ "x".to_owned(), "y".to_owned() ],
+        }),
+        Type::new(4.into(), TypeData::Intrinsic(IntrinsicType::Void)),
+        Type::new(5.into(), TypeData::Enum { variants: vec! [
+          (0, 4.into()),
+          (1, 3.into()),
+        ] }),
       ],
       imports: vec! [
         ImportModule {
@@ -2043,7 +3731,8 @@ mod test {
         ])),
         Export::new("test_export_global".to_owned(), ExportData::Global(1.into())),
         Export::new("test_reexport".to_owned(), ExportData::Global(0.into())),
-      ]
+      ],
+      strings: vec! [ ],
     }
   }
 
@@ -2060,6 +3749,627 @@ mod test {
     assert_eq!(module, decoded)
   }
 
+  #[test]
+  fn test_string_pool_encode_decode () {
+    let mut module = make_test_module();
+
+    let strings = [ "hello", "", "naïve café", "日本語のテキスト", "emoji 🦀 and \"quotes\"\n" ];
+    let indices: Vec<u32> = strings.iter().map(|string| module.add_string(string)).collect();
+
+    assert_eq!(indices, vec! [ 0, 1, 2, 3, 4 ]);
+    assert_eq!(module.add_string("naïve café"), 2);
+    assert_eq!(module.strings.len(), strings.len());
+
+    module.globals[0].initializer = vec! [ Instruction::ImmediateValue(ImmediateValue::StringRef(3)) ];
+    assert_eq!(module.verify(), Ok(()));
+
+    for &format in [ FormatVersion::Standard, FormatVersion::Compact ].iter() {
+      let mut encoded = Vec::default();
+      module.encode_with(format, &mut encoded);
+
+      let mut decoder = encoded.as_slice();
+      let decoded = Module::decode(&mut decoder).expect("Failed to decode module with strings");
+
+      assert_eq!(decoded, module);
+      assert!(decoder.is_empty());
+
+      for (index, &string) in strings.iter().enumerate() {
+        assert_eq!(decoded.get_string(index as u32), Some(string));
+      }
+    }
+
+    module.globals[0].initializer = vec! [ Instruction::ImmediateValue(ImmediateValue::StringRef(5)) ];
+    assert_eq!(module.verify(), Err(vec! [
+      VerifyError { location: VerifyLocation::Global(1.into()), reference: VerifyReference::String(5) },
+    ]));
+  }
+
+  #[test]
+  fn test_recursive_functions () {
+    let mut module = Module::empty("test_module".to_owned(), Version::new(0, 0, 1));
+
+    let function = |id: u64, callees: &[u64]| Function {
+      id: id.into(),
+      ty: 0.into(),
+      body: callees.iter().map(|&callee| Instruction::IfBlock(vec! [ Instruction::CallDirect(callee.into()) ], vec! [ ])).collect(),
+    };
+
+    module.functions = vec! [
+      // self recursive
+      function(0, &[ 0 ]),
+      // mutually recursive, through a chain of three
+      function(1, &[ 2 ]),
+      function(2, &[ 3, 4 ]),
+      function(3, &[ 1 ]),
+      // calls into a cycle without being part of it
+      function(4, &[ ]),
+      function(5, &[ 1, 4, 9 ]),
+    ];
+
+    let recursive = module.recursive_functions();
+
+    assert_eq!(recursive, [ 0, 1, 2, 3 ].iter().map(|&id| FunctionID::from(id)).collect());
+  }
+
+  #[test]
+  fn test_module_builder_interns_types () {
+    let mut builder = ModuleBuilder::new("test_module".to_owned(), Version::new(0, 0, 1));
+
+    let s32 = builder.intern_type(TypeData::Intrinsic(IntrinsicType::S32));
+    let f64_ty = builder.intern_type(TypeData::Intrinsic(IntrinsicType::F64));
+    let binary = builder.intern_type(TypeData::Function { parameters: vec! [ s32, s32 ], result: Some(s32) });
+    let unary = builder.intern_type(TypeData::Function { parameters: vec! [ f64_ty ], result: Some(s32) });
+
+    assert_eq!(builder.intern_type(TypeData::Intrinsic(IntrinsicType::S32)), s32);
+    assert_eq!(builder.intern_type(TypeData::Function { parameters: vec! [ s32, s32 ], result: Some(s32) }), binary);
+    assert_eq!(builder.intern_type(TypeData::Function { parameters: vec! [ f64_ty ], result: Some(s32) }), unary);
+
+    let s32_pointer = builder.intern_type(TypeData::Pointer(s32));
+    assert_eq!(builder.intern_type(TypeData::Pointer(s32)), s32_pointer);
+
+    builder.add_function(Function { id: 0.into(), ty: binary, body: vec! [ ] });
+    let duplicate = builder.intern_type(TypeData::Function { parameters: vec! [ s32, s32 ], result: Some(s32) });
+    builder.add_function(Function { id: 1.into(), ty: duplicate, body: vec! [ ] });
+
+    let module = builder.finish();
+
+    assert_eq!(module.types.len(), 5);
+    assert!(module.types.iter().enumerate().all(|(index, ty)| ty.id == TypeID(index as u64)));
+    assert_eq!(module.functions[0].ty, module.functions[1].ty);
+    assert_eq!(module.verify(), Ok(()));
+  }
+
+  #[test]
+  fn test_diverging_functions () {
+    use Instruction::*;
+
+    let mut module = Module::empty("test_module".to_owned(), Version::new(0, 0, 1));
+
+    let bodies = vec! [
+      // loop { }
+      vec! [ LoopBlock(vec! [ NoOp ]) ],
+      // calls a diverging function, so never reaches its Return
+      vec! [ CallDirect(0.into()), Return ],
+      // diverges on both branches
+      vec! [ ImmediateValue(true.into()), IfBlock(vec! [ CallDirect(1.into()) ], vec! [ LoopBlock(vec! [ ]) ]) ],
+      // a Break in a nested loop does not leave the outer loop
+      vec! [ LoopBlock(vec! [ LoopBlock(vec! [ Break ]) ]) ],
+      // conditionally breaks out of the loop
+      vec! [ LoopBlock(vec! [ ImmediateValue(true.into()), IfBlock(vec! [ Break ], vec! [ ]) ]) ],
+      // returns from inside the loop
+      vec! [ LoopBlock(vec! [ LoopBlock(vec! [ Return ]) ]) ],
+      // only one branch diverges
+      vec! [ ImmediateValue(true.into()), IfBlock(vec! [ CallDirect(0.into()) ], vec! [ ]), Return ],
+      // returns before reaching the loop
+      vec! [ Return, LoopBlock(vec! [ ]) ],
+    ];
+
+    module.functions = bodies.into_iter().enumerate().map(|(id, body)| Function { id: (id as u64).into(), ty: 0.into(), body }).collect();
+
+    let diverging = module.diverging_functions();
+
+    assert_eq!(diverging, [ 0, 1, 2, 3 ].iter().map(|&id| FunctionID::from(id)).collect());
+  }
+
+  #[test]
+  fn test_call_graph () {
+    let mut module = Module::empty("test_module".to_owned(), Version::new(0, 0, 1));
+
+    module.imports = vec! [
+      ImportModule {
+        name: "test_import_module".to_owned(),
+        version: Version::new(1, 0, 0),
+        items: vec! [ Import::new("imported".to_owned(), ImportData::Function(9.into(), 0.into())) ],
+      }
+    ];
+
+    module.functions = vec! [
+      Function { id: 0.into(), ty: 0.into(), body: vec! [
+        Instruction::CallDirect(1.into()),
+        Instruction::IfBlock(vec! [ Instruction::CallDirect(2.into()) ], vec! [ Instruction::CallDirect(1.into()) ]),
+      ] },
+      Function { id: 1.into(), ty: 0.into(), body: vec! [
+        Instruction::LoopBlock(vec! [ Instruction::CallDirect(9.into()), Instruction::Break ]),
+      ] },
+      Function { id: 2.into(), ty: 0.into(), body: vec! [
+        Instruction::FunctionAddress(1.into()),
+        Instruction::CallIndirect,
+        Instruction::CallDirect(1.into()),
+      ] },
+      Function { id: 3.into(), ty: 0.into(), body: vec! [ ] },
+    ];
+
+    let graph = module.call_graph();
+
+    let ids = |ids: &[u64]| ids.iter().map(|&id| FunctionID::from(id)).collect::<Vec<_>>();
+
+    assert_eq!(graph.callees_of(0.into()), ids(&[ 1, 2 ]).as_slice());
+    assert_eq!(graph.callees_of(1.into()), ids(&[ 9 ]).as_slice());
+    assert_eq!(graph.callees_of(2.into()), ids(&[ 1 ]).as_slice());
+    assert_eq!(graph.callees_of(3.into()), ids(&[ ]).as_slice());
+
+    assert_eq!(graph.callers_of(0.into()), ids(&[ ]).as_slice());
+    assert_eq!(graph.callers_of(1.into()), ids(&[ 0, 2 ]).as_slice());
+    assert_eq!(graph.callers_of(2.into()), ids(&[ 0 ]).as_slice());
+    assert_eq!(graph.callers_of(9.into()), ids(&[ 1 ]).as_slice());
+
+    assert!(graph.has_unknown_callees(2.into()));
+    assert!(!graph.has_unknown_callees(0.into()));
+
+    assert!(graph.contains(3.into()));
+    assert!(!graph.contains(9.into()));
+  }
+
+  #[test]
+  fn test_module_verify () {
+    assert_eq!(make_test_module().verify(), Ok(()));
+
+    let mut module = make_test_module();
+    module.types.push(Type::new(6.into(), TypeData::Struct { fields: vec! [ 0.into(), 9.into() ], names: vec! [ "a".to_owned(), "b".to_owned() ] }));
+    module.globals[0].ty = 7.into();
+    module.functions[0].body.insert(0, Instruction::IfBlock(vec! [ Instruction::CallDirect(8.into()) ], vec! [ ]));
+    module.exports.push(Export::new("missing".to_owned(), ExportData::Global(5.into())));
+
+    let errors = module.verify().expect_err("Verifier failed to reject unresolved references");
+
+    assert_eq!(errors, vec! [
+      VerifyError { location: VerifyLocation::Type(6.into()), reference: VerifyReference::Type(9.into()) },
+      VerifyError { location: VerifyLocation::Global(1.into()), reference: VerifyReference::Type(7.into()) },
+      VerifyError { location: VerifyLocation::Function(1.into()), reference: VerifyReference::Function(8.into()) },
+      VerifyError { location: VerifyLocation::Export("missing".to_owned()), reference: VerifyReference::Global(5.into()) },
+    ]);
+
+    assert_eq!(errors[2].to_string(), "function (fid 1) references function (fid 8), which does not exist");
+  }
+
+  #[test]
+  fn test_module_check_imports () {
+    let mut dependency = Module::empty("math".to_owned(), Version::new(0, 1, 0));
+    dependency.types = vec! [
+      Type::new(0.into(), TypeData::Intrinsic(IntrinsicType::S32)),
+      Type::new(1.into(), TypeData::Function { parameters: vec! [ 0.into(), 0.into() ], result: Some(0.into()) }),
+      Type::new(2.into(), TypeData::Intrinsic(IntrinsicType::S64)),
+    ];
+    dependency.globals = vec! [ Global::empty(0.into(), 0.into()) ];
+    dependency.functions = vec! [ Function::empty(0.into(), 1.into()) ];
+    dependency.exports = vec! [
+      Export::new("add".to_owned(), ExportData::Function(0.into())),
+      Export::new("consts".to_owned(), ExportData::Namespace(vec! [ Export::new("zero".to_owned(), ExportData::Global(0.into())) ])),
+    ];
+
+    // the importer's type table is ordered differently, so only a structural comparison can match it
+    let mut importer = Module::empty("main".to_owned(), Version::new(0, 0, 1));
+    importer.types = vec! [
+      Type::new(0.into(), TypeData::Function { parameters: vec! [ 1.into(), 1.into() ], result: Some(1.into()) }),
+      Type::new(1.into(), TypeData::Intrinsic(IntrinsicType::S32)),
+    ];
+
+    let mut import_module = ImportModule::empty("math".to_owned(), Version::new(0, 1, 0));
+    import_module.items = vec! [
+      Import::new("add".to_owned(), ImportData::Function(0.into(), 0.into())),
+      Import::new("consts".to_owned(), ImportData::Namespace(vec! [ Import::new("zero".to_owned(), ImportData::Global(0.into(), 1.into())) ])),
+    ];
+    importer.imports.push(import_module);
+
+    assert_eq!(importer.check_imports(&dependency), Ok(()));
+
+    // the signature of `add` changes in the dependency, and `zero` becomes a function
+    dependency.types[1].data = TypeData::Function { parameters: vec! [ 0.into(), 2.into() ], result: Some(0.into()) };
+    dependency.exports[1] = Export::new("consts".to_owned(), ExportData::Namespace(vec! [ Export::new("zero".to_owned(), ExportData::Function(0.into())) ]));
+    importer.imports[0].items.push(Import::new("sub".to_owned(), ImportData::Function(1.into(), 0.into())));
+
+    let errors = importer.check_imports(&dependency).expect_err("Import checker failed to reject mismatched imports");
+
+    assert_eq!(errors, vec! [
+      ImportError { module: "math".to_owned(), path: "add".to_owned(), kind: ImportErrorKind::TypeMismatch },
+      ImportError { module: "math".to_owned(), path: "consts::zero".to_owned(), kind: ImportErrorKind::KindMismatch },
+      ImportError { module: "math".to_owned(), path: "sub".to_owned(), kind: ImportErrorKind::Missing },
+    ]);
+
+    assert_eq!(errors[0].to_string(), "import \"add\" of module \"math\" does not match the type of the exported item");
+  }
+
+  #[test]
+  fn test_compact_module_encode_decode () {
+    let mut tiny = Module::empty("tiny".to_owned(), Version::new(0, 1, 0));
+    tiny.types.push(Type::new(0.into(), TypeData::Intrinsic(IntrinsicType::Bool)));
+
+    let mut standard = Vec::default();
+    tiny.encode_with(FormatVersion::Standard, &mut standard);
+
+    let mut compact = Vec::default();
+    tiny.encode_with(FormatVersion::Compact, &mut compact);
+
+    // five empty length prefixes are replaced by a single bitmap byte
+    assert_eq!(compact.len(), standard.len() - 5 * 8 + 1);
+
+    for module in [ tiny, make_test_module(), Module::empty("empty".to_owned(), Version::new(0, 0, 0)) ].iter() {
+      let mut encoded = Vec::default();
+      module.encode_with(FormatVersion::Compact, &mut encoded);
+
+      let mut decoder = encoded.as_slice();
+      assert_eq!(&Module::decode(&mut decoder).expect("Failed to decode compact module"), module);
+      assert!(decoder.is_empty());
+    }
+
+    // bits beyond the known sections are rejected
+    let mut invalid = Vec::default();
+    Module::empty("invalid".to_owned(), Version::new(0, 0, 0)).encode_with(FormatVersion::Compact, &mut invalid);
+    let bitmap = invalid.len() - 5;
+    invalid[bitmap] = 1 << FormatVersion::COMPACT_SECTION_COUNT;
+
+    let checksum_offset = invalid.len() - 4;
+    let mut crc = Crc32::new();
+    crc.update(&invalid[.. checksum_offset]);
+    invalid[checksum_offset ..].copy_from_slice(&crc.finish().to_le_bytes());
+
+    assert_eq!(Module::decode(&mut invalid.as_slice()), Err(DecodeError::UnexpectedValue));
+  }
+
+  #[test]
+  fn test_module_magic_header () {
+    let module = make_test_module();
+
+    let mut encoded = Vec::default();
+    module.encode(&mut encoded);
+
+    assert_eq!(&encoded[.. 4], b"MLBC");
+    assert_eq!(&encoded[4 .. 6], &(FormatVersion::Standard as u16).to_le_bytes());
+
+    let mut bad_magic = encoded.clone();
+    bad_magic[0] = b'X';
+    assert_eq!(Module::decode(&mut bad_magic.as_slice()), Err(DecodeError::BadMagic));
+
+    let mut bad_format = encoded.clone();
+    bad_format[4 .. 6].copy_from_slice(&0xFFFFu16.to_le_bytes());
+    assert_eq!(Module::decode(&mut bad_format.as_slice()), Err(DecodeError::UnsupportedFormatVersion));
+
+    assert_eq!(Module::decode(&mut encoded.as_slice()), Ok(module));
+  }
+
+  #[test]
+  fn test_decode_header () {
+    let module = make_test_module();
+
+    for &format in [ FormatVersion::Standard, FormatVersion::Compact ].iter() {
+      let mut encoded = Vec::default();
+      module.encode_with(format, &mut encoded);
+
+      let mut header = Vec::default();
+      header.extend_from_slice(&Module::MAGIC);
+      format.encode(&mut header);
+      module.name.encode(&mut header);
+      module.version.encode(&mut header);
+
+      assert!(encoded.starts_with(&header));
+
+      // everything after the header is garbage, and would fail a full decode
+      let mut truncated = header.clone();
+      truncated.extend_from_slice(&[ 0xFF; 8 ]);
+
+      assert_eq!(Module::decode_header(&encoded), Ok((module.name.clone(), module.version)));
+      assert_eq!(Module::decode_header(&header), Ok((module.name.clone(), module.version)));
+      assert_eq!(Module::decode_header(&truncated), Ok((module.name.clone(), module.version)));
+      assert!(Module::decode(&mut truncated.as_slice()).is_err());
+    }
+
+    assert_eq!(Module::decode_header(b"NOPE"), Err(DecodeError::BadMagic));
+    assert_eq!(Module::decode_header(&Module::MAGIC), Err(DecodeError::EOF));
+  }
+
+  #[test]
+  fn test_semantically_eq () {
+    let module = make_test_module();
+
+    let mut reordered = module.clone();
+    reordered.exports.reverse();
+    if let ExportData::Namespace(items) = &mut reordered.exports[2].data { items.reverse() }
+    reordered.imports[0].items.reverse();
+    reordered.types.reverse();
+    reordered.functions.reverse();
+
+    assert!(module != reordered);
+    assert!(module.semantically_eq(&reordered));
+    assert!(reordered.semantically_eq(&module));
+
+    let mut renamed = reordered.clone();
+    renamed.exports[0].name = "test_renamed_export".to_owned();
+    assert!(!module.semantically_eq(&renamed));
+
+    let mut retyped = reordered.clone();
+    retyped.types.iter_mut().find(|ty| ty.id == TypeID(0)).unwrap().data = TypeData::Intrinsic(IntrinsicType::U64);
+    assert!(!module.semantically_eq(&retyped));
+
+    let mut reinitialized = module.clone();
+    reinitialized.globals.reverse();
+    assert!(!module.semantically_eq(&reinitialized));
+
+    // Types may be numbered differently, as long as every reference resolves to an equivalent Type
+    let mut numbered = Module::empty("numbered".to_owned(), Version::new(0, 0, 1));
+    numbered.types = vec! [
+      Type::new(0.into(), TypeData::Intrinsic(IntrinsicType::S32)),
+      Type::new(1.into(), TypeData::Pointer(0.into())),
+      Type::new(2.into(), TypeData::Function { parameters: vec! [ 0.into() ], result: Some(0.into()) }),
+    ];
+    numbered.functions = vec! [ Function { id: 0.into(), ty: 2.into(), body: vec! [
+      Instruction::CreateLocal(0.into()),
+      Instruction::IfBlock(vec! [ Instruction::Cast(1.into()) ], vec! [ ]),
+      Instruction::Return,
+    ] } ];
+
+    let mut renumbered = numbered.clone();
+    renumbered.types = vec! [
+      Type::new(5.into(), TypeData::Function { parameters: vec! [ 7.into() ], result: Some(7.into()) }),
+      Type::new(6.into(), TypeData::Pointer(7.into())),
+      Type::new(7.into(), TypeData::Intrinsic(IntrinsicType::S32)),
+    ];
+    renumbered.functions[0].ty = 5.into();
+    remap_ids(&mut renumbered.functions[0].body, &IdRemap { types: [ (0.into(), 7.into()), (1.into(), 6.into()) ].iter().copied().collect(), ..IdRemap::default() });
+
+    assert!(numbered != renumbered);
+    assert!(numbered.semantically_eq(&renumbered));
+    assert!(renumbered.semantically_eq(&numbered));
+
+    let mut miscast = renumbered.clone();
+    miscast.functions[0].body[1] = Instruction::IfBlock(vec! [ Instruction::Cast(7.into()) ], vec! [ ]);
+    assert!(!numbered.semantically_eq(&miscast));
+  }
+
+  #[test]
+  fn test_decode_depth_limit () {
+    let mut leaf = Vec::default();
+    Instruction::Return.encode(&mut leaf);
+
+    let mut block = Vec::default();
+    Instruction::LoopBlock(vec! [ Instruction::Return ]).encode(&mut block);
+    let prefix = &block[.. block.len() - leaf.len()];
+
+    let nested = |depth: usize| {
+      let mut buff = prefix.repeat(depth);
+      buff.extend_from_slice(&leaf);
+      buff
+    };
+
+    assert_eq!(max_decode_depth(), DEFAULT_MAX_DECODE_DEPTH);
+    assert!(Instruction::decode(&mut nested(DEFAULT_MAX_DECODE_DEPTH).as_slice()).is_ok());
+    assert_eq!(Instruction::decode(&mut nested(DEFAULT_MAX_DECODE_DEPTH + 1).as_slice()), Err(DecodeError::TooDeep));
+
+    // deep enough to overflow the stack without a limit
+    assert_eq!(Instruction::decode(&mut nested(1_000_000).as_slice()), Err(DecodeError::TooDeep));
+
+    set_max_decode_depth(4);
+    assert!(Instruction::decode(&mut nested(4).as_slice()).is_ok());
+    assert_eq!(Instruction::decode(&mut nested(5).as_slice()), Err(DecodeError::TooDeep));
+    set_max_decode_depth(DEFAULT_MAX_DECODE_DEPTH);
+
+    // the depth is released after an error, so later decodes are unaffected
+    assert!(Instruction::decode(&mut nested(2).as_slice()).is_ok());
+  }
+
+  #[test]
+  fn test_module_checksum () {
+    let mut crc = Crc32::new();
+    crc.update(b"123456789");
+    assert_eq!(crc.finish(), 0xCBF4_3926);
+
+    let module = make_test_module();
+
+    let mut encoded = Vec::default();
+    module.encode(&mut encoded);
+
+    let checksum = u32::from_le_bytes([ encoded[encoded.len() - 4], encoded[encoded.len() - 3], encoded[encoded.len() - 2], encoded[encoded.len() - 1] ]);
+    let mut crc = Crc32::new();
+    crc.update(&encoded[.. encoded.len() - 4]);
+    assert_eq!(checksum, crc.finish());
+
+    // a byte in the middle of a string keeps the module structurally valid, so only the checksum can catch it
+    let name_offset = 4 + 2 + 8;
+    let mut corrupted = encoded.clone();
+    corrupted[name_offset + 2] ^= 0x01;
+    assert_eq!(Module::decode(&mut corrupted.as_slice()), Err(DecodeError::ChecksumMismatch));
+
+    let mut corrupted = encoded.clone();
+    let middle = corrupted.len() / 2;
+    corrupted[middle] ^= 0xFF;
+    assert_eq!(Module::decode(&mut corrupted.as_slice()), Err(DecodeError::ChecksumMismatch));
+
+    let truncated = &encoded[.. encoded.len() - 2];
+    assert_eq!(Module::decode(&mut &truncated[..]), Err(DecodeError::ChecksumMismatch));
+
+    // a truncated reader cannot be checked ahead of time, so it runs out of data instead
+    assert_eq!(Module::decode_from(&mut &truncated[..]), Err(DecodeError::EOF));
+
+    assert_eq!(Module::decode(&mut encoded.as_slice()), Ok(module));
+  }
+
+  #[test]
+  fn test_module_encode_to_writer () {
+    let module = make_test_module();
+
+    let mut encoded = Vec::default();
+    module.encode(&mut encoded);
+
+    let mut written = Vec::default();
+    module.encode_to(&mut written).expect("Failed to write module");
+
+    assert_eq!(encoded, written);
+  }
+
+  #[test]
+  fn test_module_decode_from_reader () {
+    let module = make_test_module();
+
+    let mut encoded = Vec::default();
+    module.encode(&mut encoded);
+
+    let mut cursor = std::io::Cursor::new(encoded.as_slice());
+    assert_eq!(Module::decode_from(&mut cursor), Ok(module));
+    assert_eq!(cursor.position() as usize, encoded.len());
+
+    let mut truncated = std::io::Cursor::new(&encoded[.. encoded.len() - 1]);
+    assert_eq!(Module::decode_from(&mut truncated), Err(DecodeError::EOF));
+  }
+
+  #[test]
+  fn test_vec_oversized_length () {
+    let mut encoded = vec! [ 0xff; 8 ];
+    encoded.push(0);
+
+    assert_eq!(Vec::<u8>::decode(&mut encoded.as_slice()), Err(DecodeError::EOF));
+    assert_eq!(Vec::<Module>::decode(&mut encoded.as_slice()), Err(DecodeError::EOF));
+  }
+
+  #[test]
+  fn test_enum_type_encode_decode () {
+    let enum_type = Type::new(7.into(), TypeData::Enum { variants: vec! [ (0, 4.into()), (1, 0.into()), (5, 3.into()) ] });
+
+    let mut encoded = Vec::default();
+    enum_type.encode(&mut encoded);
+
+    let mut decoder = encoded.as_slice();
+    assert_eq!(Type::decode(&mut decoder).expect("Failed to decode enum type"), enum_type);
+    assert!(decoder.is_empty());
+
+    let mut decoder = &encoded[.. encoded.len() - 1];
+    Type::decode(&mut decoder).expect_err("Enum type decoder failed to reject truncated variant list");
+
+    assert!(enum_type.to_string().contains("(variant 5 (tid 3))"));
+  }
+
+  #[test]
+  fn test_array_type_encode_decode () {
+    let types = vec! [
+      Type::new(0.into(), TypeData::Intrinsic(IntrinsicType::F32)),
+      Type::new(1.into(), TypeData::Struct { fields: vec! [ 0.into(), 0.into() ], names: vec! [ "x".to_owned(), "y".to_owned() ] }),
+      Type::new(2.into(), TypeData::Array { element: 1.into(), length: 4 }),
+      Type::new(3.into(), TypeData::Array { element: 2.into(), length: 16 }),
+      Type::new(4.into(), TypeData::Struct { fields: vec! [ 3.into() ], names: vec! [ "grid".to_owned() ] }),
+    ];
+
+    let mut encoded = Vec::default();
+    types.encode(&mut encoded);
+
+    let mut decoder = encoded.as_slice();
+    assert_eq!(Vec::<Type>::decode(&mut decoder).expect("Failed to decode array types"), types);
+    assert!(decoder.is_empty());
+
+    let mut decoder = &encoded[.. encoded.len() - 1];
+    Vec::<Type>::decode(&mut decoder).expect_err("Array type decoder failed to reject truncated data");
+
+    assert_eq!(types[3].data.to_string(), "(array (tid 2) (length 16))");
+
+    let mut module = Module::empty("arrays".to_owned(), Version::new(0, 0, 1));
+    module.types = types;
+    assert_eq!(module.verify(), Ok(()));
+
+    module.types[2].data = TypeData::Array { element: 9.into(), length: 4 };
+    assert_eq!(module.verify(), Err(vec! [ VerifyError { location: VerifyLocation::Type(2.into()), reference: VerifyReference::Type(9.into()) } ]));
+  }
+
+  #[test]
+  fn test_struct_field_names () {
+    let mut module = Module::empty("structs".to_owned(), Version::new(0, 0, 1));
+    module.types = vec! [
+      Type::new(0.into(), TypeData::Intrinsic(IntrinsicType::S32)),
+      Type::new(1.into(), TypeData::Struct { fields: vec! [ 0.into(), 0.into() ], names: vec! [ "x".to_owned(), "y".to_owned() ] }),
+      Type::new(2.into(), TypeData::Struct { fields: vec! [ 0.into(), 0.into() ], names: vec! [ "x".to_owned() ] }),
+      Type::new(3.into(), TypeData::Struct { fields: vec! [ 0.into() ], names: vec! [ "x".to_owned(), "y".to_owned() ] }),
+    ];
+
+    assert_eq!(module.verify(), Err(vec! [
+      VerifyError { location: VerifyLocation::Type(2.into()), reference: VerifyReference::FieldName(1.into()) },
+      VerifyError { location: VerifyLocation::Type(3.into()), reference: VerifyReference::Field(1.into()) },
+    ]));
+
+    assert_eq!(module.verify().unwrap_err()[0].to_string(), "type (tid 2) references the name of field (eid 1), which does not exist");
+
+    for ty in module.types.iter() {
+      let mut encoded = Vec::default();
+      ty.encode(&mut encoded);
+
+      let decoded = Type::decode(&mut encoded.as_slice());
+
+      match &ty.data {
+        TypeData::Struct { fields, names } if fields.len() != names.len() => assert_eq!(decoded, Err(DecodeError::UnexpectedValue)),
+        _ => assert_eq!(decoded.as_ref(), Ok(ty)),
+      }
+    }
+  }
+
+  #[test]
+  fn test_module_content_hash () {
+    let module = make_test_module();
+
+    assert_eq!(module.content_hash(), make_test_module().content_hash());
+
+    let mut changed = make_test_module();
+    changed.globals[0].initializer[0] = Instruction::ImmediateValue(ImmediateValue::S64(98));
+
+    assert_ne!(module.content_hash(), changed.content_hash());
+  }
+
+  #[test]
+  fn test_nan_encodes_canonically () {
+    let f32_nans = [ std::f32::NAN, -std::f32::NAN, f32::from_bits(0x7f80_0001), f32::from_bits(0xffc0_1234) ];
+    let f64_nans = [ std::f64::NAN, -std::f64::NAN, f64::from_bits(0x7ff0_0000_0000_0001), f64::from_bits(0xfff8_dead_beef_0000) ];
+
+    for &nan in f32_nans.iter() {
+      assert!(nan.is_nan());
+
+      let mut encoded = Vec::new();
+      ImmediateValue::F32(nan).encode(&mut encoded);
+
+      let mut expected = Vec::new();
+      ImmediateValue::F32(f32::from_bits(ImmediateValue::CANONICAL_NAN_F32_BITS)).encode(&mut expected);
+      assert_eq!(encoded, expected);
+
+      match ImmediateValue::decode(&mut encoded.as_slice()).unwrap() {
+        ImmediateValue::F32(decoded) => assert_eq!(decoded.to_bits(), ImmediateValue::CANONICAL_NAN_F32_BITS),
+        other => panic!("expected f32, got {:?}", other)
+      }
+    }
+
+    for &nan in f64_nans.iter() {
+      assert!(nan.is_nan());
+
+      let mut encoded = Vec::new();
+      ImmediateValue::F64(nan).encode(&mut encoded);
+
+      match ImmediateValue::decode(&mut encoded.as_slice()).unwrap() {
+        ImmediateValue::F64(decoded) => assert_eq!(decoded.to_bits(), ImmediateValue::CANONICAL_NAN_F64_BITS),
+        other => panic!("expected f64, got {:?}", other)
+      }
+    }
+
+    let mut encoded = Vec::new();
+    ImmediateValue::F64(-0.0).encode(&mut encoded);
+
+    match ImmediateValue::decode(&mut encoded.as_slice()).unwrap() {
+      ImmediateValue::F64(decoded) => assert_eq!(decoded.to_bits(), (-0.0f64).to_bits()),
+      other => panic!("expected f64, got {:?}", other)
+    }
+  }
+
   #[test]
   fn test_all_instruction_encode_decode () {
     use Instruction::*;
@@ -2076,6 +4386,9 @@ mod test {
       FunctionAddress(14.into()),
       
       GetElement(55.into()),
+      GetIndex,
+      GetTag,
+      GetPayload(3),
 
       Cast(11.into()),
       
@@ -2205,6 +4518,8 @@ mod test {
     }
 
     let type_data_kinds = test_kinds!(TypeDataKind [
+      Array,
+      Enum,
       Function,
       Intrinsic,
       Pointer,
@@ -2237,6 +4552,10 @@ mod test {
       S64,
       F32,
       F64,
+      Char,
+      U128,
+      S128,
+      String,
     ]);
 
     let mut encoded = Vec::default();
@@ -2307,6 +4626,9 @@ mod test {
       Break,
       Continue,
       Return,
+      GetIndex,
+      GetTag,
+      GetPayload,
     ]);
 
     let mut encoded = Vec::default();
@@ -2357,4 +4679,155 @@ mod test {
 
     println!("{}", Module::empty("empty".to_owned(), Version::default()));
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn test_instruction_operands () {
+    assert_eq!(Instruction::Cast(3.into()).type_operand(), Some(TypeID(3)));
+    assert_eq!(Instruction::CreateLocal(1.into()).type_operand(), Some(TypeID(1)));
+    assert_eq!(Instruction::CallDirect(2.into()).type_operand(), None);
+
+    assert_eq!(Instruction::CallDirect(2.into()).function_operand(), Some(FunctionID(2)));
+    assert_eq!(Instruction::FunctionAddress(4.into()).function_operand(), Some(FunctionID(4)));
+    assert_eq!(Instruction::CallIndirect.function_operand(), None);
+
+    assert_eq!(Instruction::GlobalAddress(5.into()).global_operand(), Some(GlobalID(5)));
+    assert_eq!(Instruction::LocalAddress(6.into()).local_operand(), Some(LocalID(6)));
+    assert_eq!(Instruction::GetElement(7.into()).element_operand(), Some(ElementID(7)));
+    assert_eq!(Instruction::ImmediateValue(ImmediateValue::S32(8)).immediate_operand(), Some(ImmediateValue::S32(8)));
+
+    let if_block = Instruction::IfBlock(vec! [ Instruction::NoOp ], vec! [ Instruction::Break, Instruction::Return ]);
+    assert_eq!(if_block.block_arms(), Some((&[ Instruction::NoOp ][..], &[ Instruction::Break, Instruction::Return ][..])));
+    assert_eq!(if_block.loop_body(), None);
+
+    let loop_block = Instruction::LoopBlock(vec! [ Instruction::Continue ]);
+    assert_eq!(loop_block.loop_body(), Some(&[ Instruction::Continue ][..]));
+    assert_eq!(loop_block.block_arms(), None);
+  }
+
+  #[test]
+  fn test_instruction_walk () {
+    let mut body = vec! [
+      Instruction::LocalAddress(0.into()),
+      Instruction::LoopBlock(vec! [
+        Instruction::ImmediateValue(ImmediateValue::Bool(true)),
+        Instruction::IfBlock(
+          vec! [ Instruction::Break ],
+          vec! [ Instruction::NoOp, Instruction::Continue ],
+        ),
+      ]),
+      Instruction::Return,
+    ];
+
+    let kinds: Vec<_> = InstructionWalker::new(&body).map(Instruction::get_kind).collect();
+
+    assert_eq!(kinds, [
+      InstructionKind::LocalAddress,
+      InstructionKind::LoopBlock,
+      InstructionKind::ImmediateValue,
+      InstructionKind::IfBlock,
+      InstructionKind::Break,
+      InstructionKind::NoOp,
+      InstructionKind::Continue,
+      InstructionKind::Return,
+    ]);
+
+    assert_eq!(body[1].walk().count(), 6);
+    assert_eq!(body[0].walk().count(), 1);
+
+    body[1].walk_mut(|instruction| if *instruction == Instruction::NoOp { *instruction = Instruction::Discard });
+
+    assert_eq!(body[1].walk().filter(|&instruction| *instruction == Instruction::Discard).count(), 1);
+    assert_eq!(body[1].walk().filter(|&instruction| *instruction == Instruction::NoOp).count(), 0);
+  }
+
+  #[test]
+  fn test_remap_ids () {
+    let mut body = vec! [
+      Instruction::CreateLocal(0.into()),
+      Instruction::GlobalAddress(1.into()),
+      Instruction::Load,
+      Instruction::IfBlock(
+        vec! [ Instruction::CallDirect(2.into()), Instruction::Cast(0.into()) ],
+        vec! [ Instruction::LoopBlock(vec! [ Instruction::FunctionAddress(2.into()), Instruction::CallIndirect, Instruction::Break ]) ],
+      ),
+      Instruction::CallDirect(3.into()),
+      Instruction::Return,
+    ];
+
+    let mut map = IdRemap::default();
+    map.types.insert(0.into(), 10.into());
+    map.globals.insert(1.into(), 11.into());
+    map.functions.insert(2.into(), 12.into());
+
+    remap_ids(&mut body, &map);
+
+    assert_eq!(body, vec! [
+      Instruction::CreateLocal(10.into()),
+      Instruction::GlobalAddress(11.into()),
+      Instruction::Load,
+      Instruction::IfBlock(
+        vec! [ Instruction::CallDirect(12.into()), Instruction::Cast(10.into()) ],
+        vec! [ Instruction::LoopBlock(vec! [ Instruction::FunctionAddress(12.into()), Instruction::CallIndirect, Instruction::Break ]) ],
+      ),
+      Instruction::CallDirect(3.into()),
+      Instruction::Return,
+    ]);
+  }
+
+  #[test]
+  fn test_tuple_encode_decode () {
+    let pair = (7u32, "pair".to_owned());
+    let triple = (-3i64, "triple".to_owned(), vec! [ 1u16, 2, 3 ]);
+
+    let mut buff = Vec::default();
+    pair.encode(&mut buff);
+    triple.encode(&mut buff);
+
+    let mut written = Vec::default();
+    pair.encode_to(&mut written).unwrap();
+    triple.encode_to(&mut written).unwrap();
+    assert_eq!(written, buff);
+
+    let mut slice = buff.as_slice();
+    assert_eq!(<(u32, String)>::decode(&mut slice), Ok(pair));
+    assert_eq!(<(i64, String, Vec<u16>)>::decode(&mut slice), Ok(triple));
+    assert!(slice.is_empty());
+
+    assert_eq!(<(u8, u8, u8)>::decode(&mut [ 1u8, 2 ].as_ref()), Err(DecodeError::EOF));
+  }
+
+  #[test]
+  fn test_format_value () {
+    let mut module = Module::empty("test_module".to_owned(), Version::new(0, 0, 1));
+    module.types = vec! [
+      Type::new(0.into(), TypeData::Intrinsic(IntrinsicType::Bool)),
+      Type::new(1.into(), TypeData::Intrinsic(IntrinsicType::S32)),
+      Type::new(2.into(), TypeData::Intrinsic(IntrinsicType::F64)),
+      Type::new(3.into(), TypeData::Struct { fields: vec! [ 1.into(), 0.into() ], names: vec! [ "count".to_owned(), "done".to_owned() ] }),
+      Type::new(4.into(), TypeData::Pointer(3.into())),
+      Type::new(5.into(), TypeData::Struct { fields: vec! [ 3.into(), 4.into() ], names: vec! [ ] }),
+    ];
+
+    assert_eq!(module.types[3].data.element_id("done"), Some(ElementID(1)));
+    assert_eq!(module.types[3].data.element_id("missing"), None);
+
+    assert_eq!(format_value(&ImmediateValue::Bool(true).into(), 0.into(), &module), "true");
+    assert_eq!(format_value(&ImmediateValue::S32(-12).into(), 1.into(), &module), "-12");
+    assert_eq!(format_value(&ImmediateValue::F64(2.0).into(), 2.into(), &module), "2.0");
+    assert_eq!(format_value(&Value::Pointer(0x10), 4.into(), &module), "0x0000000000000010");
+    assert_eq!(format_value(&ImmediateValue::Null.into(), 4.into(), &module), "null");
+
+    let pair = Value::Struct(vec! [ ImmediateValue::S32(1).into(), ImmediateValue::Bool(false).into() ]);
+    assert_eq!(format_value(&pair, 3.into(), &module), "{ count: 1, done: false }");
+
+    let nested = Value::Struct(vec! [ pair.clone(), Value::Pointer(0) ]);
+    assert_eq!(format_value(&nested, 5.into(), &module), "{ 0: { count: 1, done: false }, 1: 0x0000000000000000 }");
+
+    let format = ValueFormat { type_suffixes: true, max_depth: 1 };
+    assert_eq!(format_value_with(&pair, 3.into(), &module, format), "{ count: 1s32, done: false }");
+    assert_eq!(format_value_with(&nested, 5.into(), &module, format), "{ 0: { .. }, 1: 0x0000000000000000 }");
+
+    assert_eq!(format_value(&ImmediateValue::U8(1).into(), 1.into(), &module), "<invalid intrinsic>");
+    assert_eq!(format_value(&pair, 9.into(), &module), "<invalid (tid 9)>");
+  }
+}
@@ -0,0 +1,1172 @@
+//! Optimization passes over the instructions of a bytecode `Module`
+
+use std::{
+  collections::{ HashSet, HashMap, },
+};
+
+use super::{ Module, Instruction, ImmediateValue, ExportData, Export, FunctionID, TypeID, LocalID, TypeData, Import, ImportData, Function, };
+
+
+/// Controls which optimization passes are run by `optimize`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OptLevel {
+  /// No optimization, the Module is left unchanged
+  O0,
+  /// Cheap local cleanup: peephole simplification and removal of unreachable instructions
+  O1,
+  /// All passes, including inlining of small Functions, constant folding,
+  /// removal of code following calls to Functions which never return and removal of unused Functions,
+  /// repeated until they no longer make changes
+  O2,
+}
+
+impl Default for OptLevel { fn default () -> Self { Self::O1 } }
+
+
+/// The maximum number of times `optimize` will repeat its pass pipeline at `OptLevel::O2`
+pub const MAX_OPTIMIZER_ITERATIONS: usize = 16;
+
+/// The maximum number of Instructions in the body of a Function for `inline_functions` to inline calls to it
+pub const INLINE_THRESHOLD: usize = 16;
+
+
+/// Run the optimization passes appropriate for an `OptLevel` over a `Module`
+///
+/// Returns true if the Module was changed
+pub fn optimize (module: &mut Module, level: OptLevel) -> bool {
+  match level {
+    OptLevel::O0 => false,
+
+    OptLevel::O1 => {
+      let mut changed = false;
+      changed |= for_each_body(module, eliminate_dead_code);
+      changed |= for_each_body(module, peephole);
+      changed
+    },
+
+    OptLevel::O2 => {
+      let mut changed = inline_functions(module);
+      changed |= fold_intrinsics(module);
+      changed |= truncate_diverging_calls(module);
+      changed |= run_to_fixpoint(module, &[ fold_constants, eliminate_dead_code, peephole ], MAX_OPTIMIZER_ITERATIONS);
+      changed |= collect_garbage(module);
+      changed
+    },
+  }
+}
+
+
+/// A sequence-level optimization pass, returning true if it changed the sequence
+pub type Pass = fn (&mut Vec<Instruction>) -> bool;
+
+/// Repeatedly run a list of passes over every Instruction sequence in a `Module`,
+/// until an iteration makes no changes or `max_iterations` is reached
+/// 
+/// An iteration which changes the Module without reducing its total instruction count is considered to have made no progress,
+/// and also ends the loop; this prevents passes which undo each other from looping until the cap
+///
+/// Returns true if the Module was changed
+pub fn run_to_fixpoint (module: &mut Module, passes: &[Pass], max_iterations: usize) -> bool {
+  let mut changed = false;
+  let mut count = instruction_count(module);
+
+  for _ in 0 .. max_iterations {
+    let mut iteration_changed = false;
+
+    for &pass in passes.iter() {
+      iteration_changed |= for_each_body(module, pass);
+    }
+
+    if !iteration_changed { break }
+
+    changed = true;
+
+    let new_count = instruction_count(module);
+    if new_count >= count { break }
+    count = new_count;
+  }
+
+  changed
+}
+
+/// Count all Instructions in the Global initializers and Function bodies of a `Module`,
+/// including those nested in IfBlocks and LoopBlocks
+pub fn instruction_count (module: &Module) -> usize {
+  let globals = module.globals.iter().map(|global| global.initializer.iter().flat_map(Instruction::walk).count());
+  let functions = module.functions.iter().map(|function| function.body.iter().flat_map(Instruction::walk).count());
+
+  globals.chain(functions).sum()
+}
+
+
+/// Apply a sequence-level pass to every Global initializer and Function body in a `Module`,
+/// including all Instruction sequences nested in IfBlocks and LoopBlocks
+///
+/// Returns true if any sequence was changed
+pub fn for_each_body (module: &mut Module, mut pass: impl FnMut (&mut Vec<Instruction>) -> bool) -> bool {
+  let mut changed = false;
+
+  for global in module.globals.iter_mut() {
+    changed |= for_each_block(&mut global.initializer, &mut pass);
+  }
+
+  for function in module.functions.iter_mut() {
+    changed |= for_each_block(&mut function.body, &mut pass);
+  }
+
+  changed
+}
+
+/// Apply a sequence-level pass to a sequence of Instructions and all sequences nested in its IfBlocks and LoopBlocks,
+/// innermost sequences first
+///
+/// Returns true if any sequence was changed
+pub fn for_each_block (instructions: &mut Vec<Instruction>, pass: &mut dyn FnMut (&mut Vec<Instruction>) -> bool) -> bool {
+  let mut changed = false;
+
+  for instruction in instructions.iter_mut() {
+    match instruction {
+      Instruction::IfBlock(then_branch, else_branch) => {
+        changed |= for_each_block(then_branch, pass);
+        changed |= for_each_block(else_branch, pass);
+      },
+
+      Instruction::LoopBlock(body) => changed |= for_each_block(body, pass),
+
+      _ => { }
+    }
+  }
+
+  changed | pass(instructions)
+}
+
+
+/// Replace arithmetic, bitwise, and comparison Instructions operating on ImmediateValues with their result
+///
+/// Operations which would fail at runtime, such as integer division by zero, are left in place
+///
+/// Returns true if the sequence was changed
+pub fn fold_constants (instructions: &mut Vec<Instruction>) -> bool {
+  let mut changed = false;
+  let mut i = 0;
+
+  while i < instructions.len() {
+    let folded = match &instructions[i .. ] {
+      [ Instruction::ImmediateValue(a), Instruction::ImmediateValue(b), op, .. ] => fold_binary(op, a, b).map(|value| (3, value)),
+      [ Instruction::ImmediateValue(a), op, .. ] => fold_unary(op, a).map(|value| (2, value)),
+      _ => None
+    };
+
+    if let Some((length, value)) = folded {
+      instructions.splice(i .. i + length, Some(Instruction::ImmediateValue(value)));
+      changed = true;
+
+      // the new value may be an operand to a previous immediate
+      i = i.saturating_sub(1);
+    } else {
+      i += 1;
+    }
+  }
+
+  changed
+}
+
+pub(crate) fn fold_unary (op: &Instruction, a: &ImmediateValue) -> Option<ImmediateValue> {
+  use ImmediateValue::*;
+
+  Some(match (op, *a) {
+    (Instruction::Not, Bool(a)) => Bool(!a),
+    (Instruction::Not, U8(a))   => U8(!a),
+    (Instruction::Not, U16(a))  => U16(!a),
+    (Instruction::Not, U32(a))  => U32(!a),
+    (Instruction::Not, U64(a))  => U64(!a),
+    (Instruction::Not, S8(a))   => S8(!a),
+    (Instruction::Not, S16(a))  => S16(!a),
+    (Instruction::Not, S32(a))  => S32(!a),
+    (Instruction::Not, S64(a))  => S64(!a),
+    (Instruction::Not, U128(a)) => U128(!a),
+    (Instruction::Not, S128(a)) => S128(!a),
+
+    (Instruction::Neg, S8(a))   => S8(a.wrapping_neg()),
+    (Instruction::Neg, S16(a))  => S16(a.wrapping_neg()),
+    (Instruction::Neg, S32(a))  => S32(a.wrapping_neg()),
+    (Instruction::Neg, S64(a))  => S64(a.wrapping_neg()),
+    (Instruction::Neg, S128(a)) => S128(a.wrapping_neg()),
+    (Instruction::Neg, F32(a))  => F32(-a),
+    (Instruction::Neg, F64(a))  => F64(-a),
+
+    _ => return None
+  })
+}
+
+pub(crate) fn fold_binary (op: &Instruction, a: &ImmediateValue, b: &ImmediateValue) -> Option<ImmediateValue> {
+  use ImmediateValue::*;
+
+  macro_rules! fold_integers {
+    ($($variant:ident),*) => {
+      match (*a, *b) {
+        $( ($variant(a), $variant(b)) => return match op {
+          Instruction::Add => Some(a.wrapping_add(b).into()),
+          Instruction::Sub => Some(a.wrapping_sub(b).into()),
+          Instruction::Mul => Some(a.wrapping_mul(b).into()),
+          Instruction::Div => a.checked_div(b).map(Into::into),
+          Instruction::Rem => a.checked_rem(b).map(Into::into),
+          Instruction::And => Some((a & b).into()),
+          Instruction::Or  => Some((a | b).into()),
+          Instruction::Xor => Some((a ^ b).into()),
+          Instruction::EQ  => Some(Bool(a == b)),
+          Instruction::NEQ => Some(Bool(a != b)),
+          Instruction::LT  => Some(Bool(a < b)),
+          Instruction::GT  => Some(Bool(a > b)),
+          Instruction::LEQ => Some(Bool(a <= b)),
+          Instruction::GEQ => Some(Bool(a >= b)),
+          _ => None
+        }, )*
+        _ => { }
+      }
+    };
+  }
+
+  macro_rules! fold_floats {
+    ($($variant:ident),*) => {
+      match (*a, *b) {
+        $( ($variant(a), $variant(b)) => return match op {
+          Instruction::Add => Some((a + b).into()),
+          Instruction::Sub => Some((a - b).into()),
+          Instruction::Mul => Some((a * b).into()),
+          Instruction::Div => Some((a / b).into()),
+          Instruction::Rem => Some((a % b).into()),
+          Instruction::EQ  => Some(Bool(a == b)),
+          Instruction::NEQ => Some(Bool(a != b)),
+          Instruction::LT  => Some(Bool(a < b)),
+          Instruction::GT  => Some(Bool(a > b)),
+          Instruction::LEQ => Some(Bool(a <= b)),
+          Instruction::GEQ => Some(Bool(a >= b)),
+          _ => None
+        }, )*
+        _ => { }
+      }
+    };
+  }
+
+  fold_integers!(U8, U16, U32, U64, U128, S8, S16, S32, S64, S128);
+  fold_floats!(F32, F64);
+
+  if let (Bool(a), Bool(b)) = (*a, *b) {
+    return match op {
+      Instruction::And => Some(Bool(a & b)),
+      Instruction::Or  => Some(Bool(a | b)),
+      Instruction::Xor => Some(Bool(a ^ b)),
+      Instruction::EQ  => Some(Bool(a == b)),
+      Instruction::NEQ => Some(Bool(a != b)),
+      _ => None
+    }
+  }
+
+  if let (Char(a), Char(b)) = (*a, *b) {
+    return match op {
+      Instruction::EQ  => Some(Bool(a == b)),
+      Instruction::NEQ => Some(Bool(a != b)),
+      Instruction::LT  => Some(Bool(a < b)),
+      Instruction::GT  => Some(Bool(a > b)),
+      Instruction::LEQ => Some(Bool(a <= b)),
+      Instruction::GEQ => Some(Bool(a >= b)),
+      _ => None
+    }
+  }
+
+  None
+}
+
+
+/// The name of the `ImportModule` providing the intrinsic Functions used by the compiler,
+/// such as integer exponentiation
+pub const INTRINSIC_MODULE_NAME: &str = "core";
+
+/// Get the FunctionIDs of the integer exponentiation intrinsics (`pow_s32` and so on) imported by a `Module`
+///
+/// These are the top level Function imports of the `INTRINSIC_MODULE_NAME` module whose names start with `pow_`
+pub fn pow_intrinsics (module: &Module) -> HashSet<FunctionID> {
+  module.imports.iter()
+    .filter(|import_module| import_module.name == INTRINSIC_MODULE_NAME)
+    .flat_map(|import_module| import_module.items.iter())
+    .filter_map(|item| match item.data {
+      ImportData::Function(id, _) if item.name.starts_with("pow_") => Some(id),
+      _ => None
+    })
+    .collect()
+}
+
+/// Replace calls to integer exponentiation intrinsics whose arguments are ImmediateValues with their result
+///
+/// Returns true if the Module was changed
+pub fn fold_intrinsics (module: &mut Module) -> bool {
+  let intrinsics = pow_intrinsics(module);
+
+  if intrinsics.is_empty() { return false }
+
+  for_each_body(module, |instructions| {
+    let mut changed = false;
+    let mut i = 0;
+
+    while i < instructions.len() {
+      let folded = match &instructions[i .. ] {
+        [ Instruction::ImmediateValue(base), Instruction::ImmediateValue(exponent), Instruction::CallDirect(id), .. ]
+        if intrinsics.contains(id)
+        => fold_pow(base, exponent),
+        _ => None
+      };
+
+      if let Some(value) = folded {
+        instructions.splice(i .. i + 3, Some(Instruction::ImmediateValue(value)));
+        changed = true;
+
+        // the new value may be an argument to a previous call
+        i = i.saturating_sub(1);
+      } else {
+        i += 1;
+      }
+    }
+
+    changed
+  })
+}
+
+/// Remove Instructions following a direct call to a Function which never returns, as found by `Module::diverging_functions`
+///
+/// Returns true if the Module was changed
+pub fn truncate_diverging_calls (module: &mut Module) -> bool {
+  let diverging = module.diverging_functions();
+
+  if diverging.is_empty() { return false }
+
+  for_each_body(module, |instructions| {
+    let call = instructions.iter().position(|instruction| matches!(instruction, Instruction::CallDirect(id) if diverging.contains(id)));
+
+    match call {
+      Some(index) if index + 1 < instructions.len() => {
+        instructions.truncate(index + 1);
+        true
+      },
+
+      _ => false
+    }
+  })
+}
+
+/// Evaluate an integer exponentiation intrinsic on a base and exponent of the same integer type
+///
+/// Results wrap on overflow, as if by repeated wrapping multiplication;
+/// a negative exponent gives `0`, unless the base is `1` or `-1`
+pub(crate) fn fold_pow (base: &ImmediateValue, exponent: &ImmediateValue) -> Option<ImmediateValue> {
+  use ImmediateValue::*;
+
+  macro_rules! wrapping_pow {
+    ($ty:ty, $base:expr, $exponent:expr) => {{
+      let (mut base, mut exponent, mut result): ($ty, u128, $ty) = ($base, $exponent as u128, 1);
+
+      while exponent > 0 {
+        if exponent & 1 == 1 { result = result.wrapping_mul(base) }
+        base = base.wrapping_mul(base);
+        exponent >>= 1;
+      }
+
+      result
+    }};
+  }
+
+  macro_rules! signed_pow {
+    ($ty:ty, $base:expr, $exponent:expr) => {
+      if $exponent >= 0 {
+        wrapping_pow!($ty, $base, $exponent)
+      } else {
+        match $base {
+          1 => 1,
+          -1 => if $exponent % 2 == 0 { 1 } else { -1 },
+          _ => 0
+        }
+      }
+    };
+  }
+
+  Some(match (*base, *exponent) {
+    (U8(b), U8(e))     => U8(wrapping_pow!(u8, b, e)),
+    (U16(b), U16(e))   => U16(wrapping_pow!(u16, b, e)),
+    (U32(b), U32(e))   => U32(wrapping_pow!(u32, b, e)),
+    (U64(b), U64(e))   => U64(wrapping_pow!(u64, b, e)),
+    (U128(b), U128(e)) => U128(wrapping_pow!(u128, b, e)),
+    (S8(b), S8(e))     => S8(signed_pow!(i8, b, e)),
+    (S16(b), S16(e))   => S16(signed_pow!(i16, b, e)),
+    (S32(b), S32(e))   => S32(signed_pow!(i32, b, e)),
+    (S64(b), S64(e))   => S64(signed_pow!(i64, b, e)),
+    (S128(b), S128(e)) => S128(signed_pow!(i128, b, e)),
+
+    _ => return None
+  })
+}
+
+
+/// Remove Instructions which can never be executed
+///
+/// This includes Instructions following a `Return`, `Break`, or `Continue`,
+/// or an IfBlock whose branches both end in one of those,
+/// as well as the untaken branch of an IfBlock with an immediate boolean predicate
+///
+/// Returns true if the sequence was changed
+pub fn eliminate_dead_code (instructions: &mut Vec<Instruction>) -> bool {
+  let mut changed = false;
+  let mut i = 0;
+
+  while i < instructions.len() {
+    if let [ Instruction::ImmediateValue(ImmediateValue::Bool(predicate)), Instruction::IfBlock(then_branch, else_branch), .. ] = &mut instructions[i .. ] {
+      let taken = std::mem::take(if *predicate { then_branch } else { else_branch });
+      instructions.splice(i .. i + 2, taken);
+      changed = true;
+      continue
+    }
+
+    if is_terminator(&instructions[i]) && i + 1 < instructions.len() {
+      instructions.truncate(i + 1);
+      changed = true;
+      break
+    }
+
+    i += 1;
+  }
+
+  changed
+}
+
+/// Determine if control flow can never proceed past an Instruction to the next one in its sequence
+pub fn is_terminator (instruction: &Instruction) -> bool {
+  match instruction {
+    Instruction::Return | Instruction::Break | Instruction::Continue => true,
+
+    Instruction::IfBlock(then_branch, else_branch)
+    => then_branch.last().map(is_terminator).unwrap_or(false)
+    && else_branch.last().map(is_terminator).unwrap_or(false),
+
+    _ => false
+  }
+}
+
+
+/// Simplify short sequences of Instructions which have no effect
+///
+/// This removes `NoOp`s, values which are pushed and then immediately discarded,
+/// and pairs of `Not` or `Neg` which cancel each other out
+///
+/// Returns true if the sequence was changed
+pub fn peephole (instructions: &mut Vec<Instruction>) -> bool {
+  let original_length = instructions.len();
+
+  instructions.retain(|instruction| *instruction != Instruction::NoOp);
+
+  let mut i = 0;
+
+  while i < instructions.len() {
+    let redundant = matches!(&instructions[i .. ],
+      | [ Instruction::ImmediateValue(_), Instruction::Discard, .. ]
+      | [ Instruction::LocalAddress(_), Instruction::Discard, .. ]
+      | [ Instruction::GlobalAddress(_), Instruction::Discard, .. ]
+      | [ Instruction::FunctionAddress(_), Instruction::Discard, .. ]
+      | [ Instruction::Duplicate, Instruction::Discard, .. ]
+      | [ Instruction::Not, Instruction::Not, .. ]
+      | [ Instruction::Neg, Instruction::Neg, .. ]
+    );
+
+    if redundant {
+      instructions.drain(i .. i + 2);
+
+      // removing a pair may expose another before it
+      i = i.saturating_sub(1);
+    } else {
+      i += 1;
+    }
+  }
+
+  instructions.len() != original_length
+}
+
+
+/// Remove Functions which are not reachable from any Export or Global initializer of a `Module`
+///
+/// Returns true if any Function was removed
+pub fn collect_garbage (module: &mut Module) -> bool {
+  fn export_roots (exports: &[Export], roots: &mut Vec<FunctionID>) {
+    for export in exports.iter() {
+      match &export.data {
+        ExportData::Namespace(exports) => export_roots(exports, roots),
+        &ExportData::Function(id) => roots.push(id),
+        ExportData::Global(_) => { }
+      }
+    }
+  }
+
+  fn instruction_roots (instructions: &[Instruction], roots: &mut Vec<FunctionID>) {
+    for instruction in instructions.iter().flat_map(Instruction::walk) {
+      if let Some(id) = instruction.function_operand() { roots.push(id) }
+    }
+  }
+
+  let mut stack = Vec::new();
+
+  export_roots(&module.exports, &mut stack);
+
+  for global in module.globals.iter() {
+    instruction_roots(&global.initializer, &mut stack);
+  }
+
+  let mut reachable = HashSet::new();
+
+  while let Some(id) = stack.pop() {
+    if !reachable.insert(id) { continue }
+
+    if let Some(function) = module.functions.iter().find(|function| function.id == id) {
+      instruction_roots(&function.body, &mut stack);
+    }
+  }
+
+  let original_length = module.functions.len();
+
+  module.functions.retain(|function| reachable.contains(&function.id));
+
+  module.functions.len() != original_length
+}
+
+
+
+/// The body of a Function which can be substituted for calls to it by `inline_functions`
+struct InlineCandidate {
+  /// The number of parameters of the Function
+  parameters: usize,
+  /// The Types of the Function's locals; its parameters, followed by one for each `CreateLocal` in its body
+  locals: Vec<TypeID>,
+  /// The body of the Function, without its `CreateLocal`s or trailing `Return`
+  body: Vec<Instruction>,
+}
+
+/// Replace `CallDirect`s to small non-recursive Functions with the bodies of the Functions
+///
+/// A Function is inlined if its body has at most `INLINE_THRESHOLD` Instructions,
+/// it is not recursive, makes no indirect calls, contains no loops, and can only return at its end.
+/// The arguments of an inlined call are stored to fresh locals of the caller, which replace the callee's locals;
+/// these are created at the start of the caller, and the caller's existing locals are renumbered after them
+///
+/// Calls are only inlined where the instructions producing each argument can be identified
+///
+/// Returns true if any call was inlined
+pub fn inline_functions (module: &mut Module) -> bool {
+  let signatures = function_signatures(module);
+  let recursive = module.recursive_functions();
+
+  let candidates: HashMap<FunctionID, InlineCandidate> = module.functions.iter().filter(|function| !recursive.contains(&function.id)).filter_map(|function| {
+    inline_candidate(module, &signatures, function).map(|candidate| (function.id, candidate))
+  }).collect();
+
+  if candidates.is_empty() { return false }
+
+  let mut changed = false;
+
+  for function in module.functions.iter_mut() {
+    let parameters = match signatures.get(&function.id) { Some(&(parameters, _)) => parameters, None => continue };
+    changed |= inline_calls(&mut function.body, parameters, &candidates, &signatures);
+  }
+
+  changed
+}
+
+/// Get the parameter count and whether there is a result for every Function defined or imported by a Module
+fn function_signatures (module: &Module) -> HashMap<FunctionID, (usize, bool)> {
+  fn import_signatures (items: &[Import], types: &mut Vec<(FunctionID, TypeID)>) {
+    for item in items.iter() {
+      match &item.data {
+        ImportData::Namespace(items) => import_signatures(items, types),
+        &ImportData::Function(id, ty) => types.push((id, ty)),
+        ImportData::Global(_, _) => { }
+      }
+    }
+  }
+
+  let mut types: Vec<(FunctionID, TypeID)> = module.functions.iter().map(|function| (function.id, function.ty)).collect();
+
+  for import_module in module.imports.iter() {
+    import_signatures(&import_module.items, &mut types);
+  }
+
+  types.into_iter().filter_map(|(id, ty)| match module.get_type(ty).map(|ty| &ty.data) {
+    Some(TypeData::Function { parameters, result }) => Some((id, (parameters.len(), result.is_some()))),
+    _ => None
+  }).collect()
+}
+
+/// Determine whether a Function can be inlined by `inline_functions`, and if so prepare its body for substitution
+fn inline_candidate (module: &Module, signatures: &HashMap<FunctionID, (usize, bool)>, function: &Function) -> Option<InlineCandidate> {
+  let (parameter_types, has_result) = match module.get_type(function.ty).map(|ty| &ty.data) {
+    Some(TypeData::Function { parameters, result }) => (parameters.clone(), result.is_some()),
+    _ => return None
+  };
+
+  let parameters = parameter_types.len();
+  let body = function.body.as_slice();
+
+  if body.iter().flat_map(Instruction::walk).count() > INLINE_THRESHOLD { return None }
+
+  let body = match body {
+    [ body @ .., Instruction::Return ] => body,
+    body => body,
+  };
+
+  // the body must leave exactly its result on the stack,
+  // which also rules out any indirect call or any other Return, Break, or Continue
+  if stack_effect(body, signatures)? != (0, has_result as usize) { return None }
+
+  let mut locals = parameter_types;
+  locals.extend(body.iter().flat_map(Instruction::walk).filter_map(|instruction| match instruction {
+    &Instruction::CreateLocal(ty) => Some(ty),
+    _ => None
+  }));
+
+  let mut body = body.to_vec();
+  for_each_block(&mut body, &mut |instructions| {
+    let length = instructions.len();
+    instructions.retain(|instruction| !matches!(instruction, Instruction::CreateLocal(_)));
+    instructions.len() != length
+  });
+
+  Some(InlineCandidate { parameters, locals, body })
+}
+
+/// Inline all calls to candidates in a Function body
+fn inline_calls (
+  body: &mut Vec<Instruction>,
+  parameters: usize,
+  candidates: &HashMap<FunctionID, InlineCandidate>,
+  signatures: &HashMap<FunctionID, (usize, bool)>,
+) -> bool {
+  let existing_locals = body.iter().flat_map(Instruction::walk).filter(|instruction| matches!(instruction, Instruction::CreateLocal(_))).count();
+
+  // fresh locals are numbered after all existing locals until the body is renumbered
+  let fresh_base = (parameters + existing_locals) as u64;
+  let mut fresh_locals = Vec::new();
+
+  let changed = for_each_block(body, &mut |instructions| inline_sequence(instructions, fresh_base, &mut fresh_locals, candidates, signatures));
+
+  if !changed { return false }
+
+  let fresh_count = fresh_locals.len() as u64;
+
+  for instruction in body.iter_mut() {
+    instruction.walk_mut(|nested| if let Instruction::LocalAddress(LocalID(id)) = nested {
+      if *id >= fresh_base {
+        *id -= existing_locals as u64
+      } else if *id >= parameters as u64 {
+        *id += fresh_count
+      }
+    })
+  }
+
+  body.splice(0 .. 0, fresh_locals.into_iter().map(Instruction::CreateLocal));
+
+  true
+}
+
+fn inline_sequence (
+  instructions: &mut Vec<Instruction>,
+  fresh_base: u64,
+  fresh_locals: &mut Vec<TypeID>,
+  candidates: &HashMap<FunctionID, InlineCandidate>,
+  signatures: &HashMap<FunctionID, (usize, bool)>,
+) -> bool {
+  let mut changed = false;
+  let mut i = 0;
+
+  while i < instructions.len() {
+    let candidate = match &instructions[i] {
+      Instruction::CallDirect(id) => candidates.get(id),
+      _ => None
+    };
+
+    let (candidate, arguments) = match candidate.and_then(|candidate| Some((candidate, split_arguments(&instructions[.. i], candidate.parameters, signatures)?))) {
+      Some(found) => found,
+      None => { i += 1; continue }
+    };
+
+    let local_base = fresh_base + fresh_locals.len() as u64;
+    fresh_locals.extend(candidate.locals.iter().copied());
+
+    let start = arguments.first().copied().unwrap_or(i);
+    let mut inlined = Vec::new();
+
+    // each argument is stored to the fresh local replacing its parameter, in evaluation order
+    for (parameter, &argument_start) in arguments.iter().enumerate() {
+      let argument_end = arguments.get(parameter + 1).copied().unwrap_or(i);
+
+      inlined.push(Instruction::LocalAddress(LocalID(local_base + parameter as u64)));
+      inlined.extend(instructions[argument_start .. argument_end].iter().cloned());
+      inlined.push(Instruction::Store);
+    }
+
+    let body_start = inlined.len();
+    inlined.extend(candidate.body.iter().cloned());
+
+    for instruction in inlined[body_start ..].iter_mut() {
+      instruction.walk_mut(|nested| if let Instruction::LocalAddress(LocalID(id)) = nested { *id += local_base })
+    }
+
+    let length = inlined.len();
+    instructions.splice(start ..= i, inlined);
+
+    i = start + length;
+    changed = true;
+  }
+
+  changed
+}
+
+/// Find the start index of the Instructions producing each of the arguments of a call at the end of a sequence
+fn split_arguments (instructions: &[Instruction], count: usize, signatures: &HashMap<FunctionID, (usize, bool)>) -> Option<Vec<usize>> {
+  let mut starts = vec! [ 0; count ];
+  let mut end = instructions.len();
+
+  for argument in (0 .. count).rev() {
+    let mut needed = 1usize;
+    let mut start = end;
+
+    while needed > 0 {
+      start = start.checked_sub(1)?;
+
+      let (pops, pushes) = stack_effect(std::slice::from_ref(&instructions[start]), signatures)?;
+
+      // an Instruction producing values for more than one argument cannot be split
+      needed = needed.checked_sub(pushes)? + pops;
+    }
+
+    starts[argument] = start;
+    end = start;
+  }
+
+  Some(starts)
+}
+
+/// Get the number of values a sequence of Instructions pops off the stack, and the number it pushes back on
+///
+/// Returns None if the effect cannot be determined statically,
+/// such as for sequences containing loops, indirect calls, or control flow leaving the sequence
+fn stack_effect (instructions: &[Instruction], signatures: &HashMap<FunctionID, (usize, bool)>) -> Option<(usize, usize)> {
+  let mut depth = 0isize;
+  let mut lowest = 0isize;
+
+  for instruction in instructions.iter() {
+    let (pops, pushes) = match instruction {
+      | Instruction::NoOp
+      | Instruction::CreateLocal(_)
+      => (0, 0),
+
+      | Instruction::ImmediateValue(_)
+      | Instruction::LocalAddress(_)
+      | Instruction::GlobalAddress(_)
+      | Instruction::FunctionAddress(_)
+      => (0, 1),
+
+      | Instruction::GetElement(_)
+      | Instruction::GetTag
+      | Instruction::GetPayload(_)
+      | Instruction::Cast(_)
+      | Instruction::Load
+      | Instruction::Neg
+      | Instruction::Not
+      => (1, 1),
+
+      Instruction::Store => (2, 0),
+      Instruction::Duplicate => (1, 2),
+      Instruction::Discard => (1, 0),
+
+      | Instruction::GetIndex
+      | Instruction::Add | Instruction::Sub | Instruction::Mul | Instruction::Div | Instruction::Rem
+      | Instruction::And | Instruction::Or | Instruction::Xor | Instruction::LShift | Instruction::RShift
+      | Instruction::EQ | Instruction::NEQ | Instruction::LT | Instruction::GT | Instruction::LEQ | Instruction::GEQ
+      => (2, 1),
+
+      Instruction::CallDirect(id) => {
+        let &(parameters, has_result) = signatures.get(id)?;
+        (parameters, has_result as usize)
+      },
+
+      Instruction::IfBlock(then_branch, else_branch) => {
+        let (then_pops, then_pushes) = stack_effect(then_branch, signatures)?;
+        let (else_pops, else_pushes) = stack_effect(else_branch, signatures)?;
+
+        if then_pushes as isize - then_pops as isize != else_pushes as isize - else_pops as isize { return None }
+
+        // the predicate is popped before either branch runs
+        let pops = then_pops.max(else_pops);
+        (pops + 1, pops + then_pushes - then_pops)
+      },
+
+      | Instruction::CallIndirect
+      | Instruction::LoopBlock(_)
+      | Instruction::Break
+      | Instruction::Continue
+      | Instruction::Return
+      => return None,
+    };
+
+    depth -= pops as isize;
+    lowest = lowest.min(depth);
+    depth += pushes as isize;
+  }
+
+  Some((-lowest as usize, (depth - lowest) as usize))
+}
+
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{ Encode, Type, TypeData, IntrinsicType, Function, Global, };
+  use mod_common::Version;
+
+  #[test]
+  fn test_optimize_o2 () {
+    let mut module = Module::empty("test_module".to_owned(), Version::new(0, 0, 1));
+
+    module.types = vec! [
+      Type::new(0.into(), TypeData::Intrinsic(IntrinsicType::S32)),
+      Type::new(1.into(), TypeData::Function { parameters: vec! [ ], result: Some(0.into()) }),
+    ];
+
+    module.globals = vec! [
+      Global {
+        id: 0.into(),
+        ty: 0.into(),
+        initializer: vec! [
+          Instruction::ImmediateValue(ImmediateValue::S32(2)),
+          Instruction::ImmediateValue(ImmediateValue::S32(3)),
+          Instruction::Mul,
+          Instruction::ImmediateValue(ImmediateValue::S32(1)),
+          Instruction::Add,
+        ]
+      }
+    ];
+
+    module.functions = vec! [
+      Function {
+        id: 0.into(),
+        ty: 1.into(),
+        body: vec! [
+          Instruction::NoOp,
+          Instruction::ImmediateValue(ImmediateValue::S32(4)),
+          Instruction::ImmediateValue(ImmediateValue::S32(4)),
+          Instruction::EQ,
+          Instruction::IfBlock(
+            vec! [
+              Instruction::GlobalAddress(0.into()),
+              Instruction::Load,
+              Instruction::Return,
+              Instruction::CallDirect(1.into()),
+            ],
+            vec! [
+              Instruction::CallDirect(1.into()),
+              Instruction::Return,
+            ],
+          ),
+          Instruction::ImmediateValue(ImmediateValue::S32(0)),
+          Instruction::Return,
+        ]
+      },
+      Function {
+        id: 1.into(),
+        ty: 1.into(),
+        body: vec! [
+          Instruction::ImmediateValue(ImmediateValue::S32(1)),
+          Instruction::Discard,
+          Instruction::ImmediateValue(ImmediateValue::S32(1)),
+          Instruction::Return,
+        ]
+      },
+    ];
+
+    module.exports = vec! [ Export::new("main".to_owned(), ExportData::Function(0.into())) ];
+
+    let original = module.clone();
+
+    assert!(!optimize(&mut module, OptLevel::O0));
+    assert_eq!(module, original);
+
+    assert!(optimize(&mut module, OptLevel::O2));
+
+    assert_eq!(module.globals[0].initializer, vec! [ Instruction::ImmediateValue(ImmediateValue::S32(7)) ]);
+
+    assert_eq!(module.functions, vec! [
+      Function {
+        id: 0.into(),
+        ty: 1.into(),
+        body: vec! [
+          Instruction::GlobalAddress(0.into()),
+          Instruction::Load,
+          Instruction::Return,
+        ]
+      }
+    ]);
+
+    let mut original_bytes = Vec::new();
+    original.encode(&mut original_bytes);
+
+    let mut optimized_bytes = Vec::new();
+    module.encode(&mut optimized_bytes);
+
+    assert!(optimized_bytes.len() < original_bytes.len());
+
+    assert!(!optimize(&mut module, OptLevel::O2));
+  }
+
+  #[test]
+  fn test_fold_constants_preserves_runtime_errors () {
+    let mut body = vec! [
+      Instruction::ImmediateValue(ImmediateValue::U8(1)),
+      Instruction::ImmediateValue(ImmediateValue::U8(0)),
+      Instruction::Div,
+    ];
+
+    assert!(!fold_constants(&mut body));
+    assert_eq!(body.len(), 3);
+  }
+
+  #[test]
+  fn test_fold_char_comparisons () {
+    let mut body = vec! [
+      Instruction::ImmediateValue(ImmediateValue::Char('a')),
+      Instruction::ImmediateValue(ImmediateValue::Char('b')),
+      Instruction::LT,
+    ];
+
+    assert!(fold_constants(&mut body));
+    assert_eq!(body, vec! [ Instruction::ImmediateValue(ImmediateValue::Bool(true)) ]);
+
+    // characters are not numeric
+    let mut body = vec! [
+      Instruction::ImmediateValue(ImmediateValue::Char('a')),
+      Instruction::ImmediateValue(ImmediateValue::Char('b')),
+      Instruction::Add,
+    ];
+
+    assert!(!fold_constants(&mut body));
+  }
+
+  #[test]
+  fn test_fold_pow () {
+    use ImmediateValue::*;
+
+    assert_eq!(fold_pow(&S32(-3), &S32(3)), Some(S32(-27)));
+    assert_eq!(fold_pow(&U64(7), &U64(0)), Some(U64(1)));
+    assert_eq!(fold_pow(&U8(2), &U8(9)), Some(U8(0)));
+    assert_eq!(fold_pow(&S8(3), &S8(5)), Some(S8(3i8.wrapping_pow(5))));
+
+    assert_eq!(fold_pow(&S16(2), &S16(-1)), Some(S16(0)));
+    assert_eq!(fold_pow(&S16(1), &S16(-7)), Some(S16(1)));
+    assert_eq!(fold_pow(&S16(-1), &S16(-7)), Some(S16(-1)));
+    assert_eq!(fold_pow(&S16(-1), &S16(-8)), Some(S16(1)));
+
+    assert_eq!(fold_pow(&S32(2), &U32(2)), None);
+    assert_eq!(fold_pow(&F32(2.0), &F32(2.0)), None);
+  }
+
+  #[test]
+  fn test_truncate_diverging_calls () {
+    let mut module = Module::empty("test_module".to_owned(), Version::new(0, 0, 1));
+
+    module.types = vec! [
+      Type::new(0.into(), TypeData::Intrinsic(IntrinsicType::S32)),
+      Type::new(1.into(), TypeData::Function { parameters: vec! [ ], result: Some(0.into()) }),
+    ];
+
+    module.functions = vec! [
+      Function { id: 0.into(), ty: 1.into(), body: vec! [ Instruction::LoopBlock(vec! [ Instruction::NoOp ]) ] },
+      Function { id: 1.into(), ty: 1.into(), body: vec! [
+        Instruction::IfBlock(
+          vec! [ Instruction::CallDirect(0.into()), Instruction::ImmediateValue(ImmediateValue::S32(1)), Instruction::Return ],
+          vec! [ ],
+        ),
+        Instruction::ImmediateValue(ImmediateValue::S32(2)),
+        Instruction::Return,
+      ] },
+    ];
+
+    assert!(truncate_diverging_calls(&mut module));
+
+    // only the branch which calls the diverging function is cut short
+    assert_eq!(module.functions[1].body, vec! [
+      Instruction::IfBlock(vec! [ Instruction::CallDirect(0.into()) ], vec! [ ]),
+      Instruction::ImmediateValue(ImmediateValue::S32(2)),
+      Instruction::Return,
+    ]);
+
+    assert!(!truncate_diverging_calls(&mut module));
+  }
+
+  #[test]
+  fn test_fold_intrinsics () {
+    use crate::{ ImportModule, };
+
+    let mut module = Module::empty("test_module".to_owned(), Version::new(0, 0, 1));
+
+    module.types = vec! [
+      Type::new(0.into(), TypeData::Intrinsic(IntrinsicType::S32)),
+      Type::new(1.into(), TypeData::Function { parameters: vec! [ 0.into(), 0.into() ], result: Some(0.into()) }),
+    ];
+
+    let mut core = ImportModule::empty(INTRINSIC_MODULE_NAME.to_owned(), Version::new(0, 0, 0));
+    core.items.push(Import::new("pow_s32".to_owned(), ImportData::Function(0.into(), 1.into())));
+    core.items.push(Import::new("max_s32".to_owned(), ImportData::Function(1.into(), 1.into())));
+    module.imports.push(core);
+
+    let expected: HashSet<FunctionID> = vec! [ 0.into() ].into_iter().collect();
+    assert_eq!(pow_intrinsics(&module), expected);
+
+    module.globals = vec! [
+      Global {
+        id: 0.into(),
+        ty: 0.into(),
+        initializer: vec! [
+          Instruction::ImmediateValue(ImmediateValue::S32(2)),
+          Instruction::ImmediateValue(ImmediateValue::S32(3)),
+          Instruction::ImmediateValue(ImmediateValue::S32(2)),
+          Instruction::CallDirect(0.into()),
+          Instruction::CallDirect(0.into()),
+        ]
+      },
+
+      Global {
+        id: 1.into(),
+        ty: 0.into(),
+        initializer: vec! [
+          Instruction::ImmediateValue(ImmediateValue::S32(2)),
+          Instruction::ImmediateValue(ImmediateValue::S32(3)),
+          Instruction::CallDirect(1.into()),
+        ]
+      },
+    ];
+
+    assert!(fold_intrinsics(&mut module));
+    assert_eq!(module.globals[0].initializer, vec! [ Instruction::ImmediateValue(ImmediateValue::S32(512)) ]);
+    assert_eq!(module.globals[1].initializer.len(), 3);
+  }
+
+  #[test]
+  fn test_conflicting_passes_terminate () {
+    fn add_to_sub (instructions: &mut [Instruction]) -> bool {
+      let mut changed = false;
+      for instruction in instructions.iter_mut().filter(|instruction| **instruction == Instruction::Add) {
+        *instruction = Instruction::Sub;
+        changed = true;
+      }
+      changed
+    }
+
+    fn sub_to_add (instructions: &mut [Instruction]) -> bool {
+      let mut changed = false;
+      for instruction in instructions.iter_mut().filter(|instruction| **instruction == Instruction::Sub) {
+        *instruction = Instruction::Add;
+        changed = true;
+      }
+      changed
+    }
+
+    fn grow (instructions: &mut Vec<Instruction>) -> bool {
+      instructions.push(Instruction::NoOp);
+      true
+    }
+
+    let mut module = Module::empty("test_module".to_owned(), Version::new(0, 0, 1));
+    module.functions = vec! [ Function { id: 0.into(), ty: 0.into(), body: vec! [ Instruction::Add ] } ];
+
+    assert!(run_to_fixpoint(&mut module, &[ |instructions| add_to_sub(instructions), |instructions| sub_to_add(instructions) ], usize::MAX));
+    assert_eq!(module.functions[0].body, vec! [ Instruction::Add ]);
+
+    assert!(run_to_fixpoint(&mut module, &[ grow ], usize::MAX));
+    assert_eq!(instruction_count(&module), 2);
+  }
+
+  #[test]
+  fn test_inline_functions () {
+    use crate::interpreter::Interpreter;
+
+    let mut module = Module::empty("test_module".to_owned(), Version::new(0, 0, 1));
+
+    module.types = vec! [
+      Type::new(0.into(), TypeData::Intrinsic(IntrinsicType::S32)),
+      Type::new(1.into(), TypeData::Function { parameters: vec! [ 0.into(), 0.into() ], result: Some(0.into()) }),
+    ];
+
+    module.functions = vec! [
+      Function {
+        id: 0.into(),
+        ty: 1.into(),
+        body: vec! [
+          Instruction::LocalAddress(0.into()),
+          Instruction::Load,
+          Instruction::LocalAddress(1.into()),
+          Instruction::Load,
+          Instruction::Add,
+          Instruction::Return,
+        ]
+      },
+      Function {
+        id: 1.into(),
+        ty: 1.into(),
+        body: vec! [
+          Instruction::CreateLocal(0.into()),
+          Instruction::LocalAddress(2.into()),
+          Instruction::LocalAddress(0.into()),
+          Instruction::Load,
+          Instruction::LocalAddress(1.into()),
+          Instruction::Load,
+          Instruction::CallDirect(0.into()),
+          Instruction::Store,
+          Instruction::LocalAddress(2.into()),
+          Instruction::Load,
+          Instruction::ImmediateValue(ImmediateValue::S32(3)),
+          Instruction::CallDirect(0.into()),
+          Instruction::LocalAddress(0.into()),
+          Instruction::Load,
+          Instruction::CallDirect(0.into()),
+          Instruction::Return,
+        ]
+      },
+      Function {
+        id: 2.into(),
+        ty: 1.into(),
+        body: vec! [
+          Instruction::LocalAddress(1.into()),
+          Instruction::Load,
+          Instruction::LocalAddress(0.into()),
+          Instruction::Load,
+          Instruction::CallDirect(2.into()),
+          Instruction::Return,
+        ]
+      },
+    ];
+
+    let original = module.clone();
+
+    assert!(inline_functions(&mut module));
+
+    // recursive functions are never inlined, even into themselves
+    assert_eq!(module.functions[2], original.functions[2]);
+
+    assert!(!module.functions[1].body.iter().flat_map(Instruction::walk).any(|instruction| matches!(instruction, Instruction::CallDirect(_))));
+
+    let mut original_interpreter = Interpreter::new(&original).unwrap();
+    let mut inlined_interpreter = Interpreter::new(&module).unwrap();
+
+    for &(a, b) in [ (0, 0), (1, 2), (-7, 40), (100, -3) ].iter() {
+      let arguments = vec! [ ImmediateValue::S32(a), ImmediateValue::S32(b) ];
+      let expected = Some(ImmediateValue::S32((a + b) + 3 + a));
+
+      assert_eq!(original_interpreter.call(1.into(), arguments.clone()), Ok(expected));
+      assert_eq!(inlined_interpreter.call(1.into(), arguments), Ok(expected));
+    }
+  }
+}
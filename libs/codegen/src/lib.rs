@@ -16,13 +16,23 @@ use std::{
 
 
 use mod_bytecode as bc;
-use mod_common::{ Identifier, Version, Operator, Constant, Number, FloatingPoint, };
+use bc::{ Encode, optimizer::{ optimize, OptLevel, }, };
+use mod_common::{ Identifier, Version, Operator, Constant, Number, FloatingPoint, LayoutStrategy, };
 use mod_frontend::{
+  session::{ SESSION, Message, MessageKind, },
+  source::{ SOURCE_MANAGER, SourceRegion, },
+  lexer::{ Lexer, },
+  parser::{ Parser, },
+  analyzer::{ Analyzer, },
   ctx::{ self, Context, ContextKey, ContextItem, },
   ir,
 };
 
 
+/// The name of the ImportModule external Functions of the main module are imported from
+pub const HOST_MODULE_NAME: &str = "host";
+
+
 /// An ID counter for generating Module items
 pub struct Counter<T: From<bc::ID>> {
   base: bc::ID,
@@ -55,15 +65,59 @@ impl<T: From<bc::ID>> Counter<T> {
   }
 }
 
+/// How a parameter or return value is passed between the caller and callee of a Function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassingMode {
+  /// The value is passed on the stack
+  Direct,
+  /// A parameter is passed as the address of a copy made by the caller,
+  /// while a return value is stored by the callee to an address the caller passes as a hidden first parameter
+  Indirect,
+}
+
+impl PassingMode {
+  /// Get the PassingMode used for values of the Type associated with a ContextKey
+  /// 
+  /// Structures, enums and optionals are passed indirectly, all other types are passed directly
+  pub fn of (context: &Context, ty_key: ContextKey) -> Self {
+    match context.items.get(ty_key).and_then(ContextItem::ref_type).and_then(|ty| ty.data.as_ref()) {
+      | Some(ctx::TypeData::Structure { .. })
+      | Some(ctx::TypeData::Enum { .. })
+      | Some(ctx::TypeData::Optional(_))
+      => Self::Indirect,
+      _ => Self::Direct,
+    }
+  }
+}
+
+/// Metadata for a local variable slot of a bytecoded context,
+/// created by a `CreateLocal` instruction or as a parameter
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalSlot {
+  /// The Type of the value held in a LocalSlot
+  pub ty: bc::TypeID,
+  /// The source-level name of a LocalSlot, or None for temporaries introduced during codegen
+  pub name: Option<Identifier>,
+  /// The SourceRegion responsible for the creation of a LocalSlot
+  pub region: SourceRegion,
+}
+
 /// Represents local state for a codegen session,
 /// inside a bytecoded context such as a global initializer or function body
 pub struct LocalCodegen {
   /// A counter for getting unique local variable ids for instruction generation
   pub id_counter: Counter<bc::LocalID>,
+  /// Metadata for each local variable slot created, indexed by LocalID
+  pub slots: Vec<LocalSlot>,
   /// A counter for tracking source-level local indices encountered
   pub index_counter: Counter<usize>,
   /// A local map from Context local variable indices to bytecode LocalIDs
   pub index_id_map: HashMap<usize, bc::LocalID>,
+  /// The LocalIDs and PassingModes of the parameters of the Function being generated
+  pub parameters: Vec<(bc::LocalID, PassingMode)>,
+  /// The LocalID of the hidden parameter holding the address to store the result of the Function being generated,
+  /// if its return value is passed indirectly
+  pub result_address: Option<bc::LocalID>,
 }
 
 impl Default for LocalCodegen { fn default () -> Self { Self::new() } }
@@ -73,15 +127,47 @@ impl LocalCodegen {
   pub fn new () -> Self {
     Self {
       id_counter: Counter::default(),
+      slots: Vec::default(),
       index_counter: Counter::default(),
       index_id_map: HashMap::default(),
+      parameters: Vec::default(),
+      result_address: None,
     }
   }
 
   /// Clear a LocalCodegen's state
   pub fn clear (&mut self) {
     self.id_counter.clear();
+    self.slots.clear();
+    self.index_counter.clear();
     self.index_id_map.clear();
+    self.parameters.clear();
+    self.result_address = None;
+  }
+
+  /// Get a new LocalID for a local variable slot, and record its metadata
+  pub fn create_slot (&mut self, ty: bc::TypeID, name: Option<Identifier>, region: SourceRegion) -> bc::LocalID {
+    let id = self.id_counter.get_next();
+
+    debug_assert_eq!(u64::from(id) as usize, self.slots.len(), "Internal error, LocalSlot created out of order");
+
+    self.slots.push(LocalSlot { ty, name, region });
+
+    id
+  }
+
+  /// Get the metadata for the local variable slot associated with a LocalID, if it exists
+  pub fn get_slot (&self, id: bc::LocalID) -> Option<&LocalSlot> {
+    self.slots.get(u64::from(id) as usize)
+  }
+
+  /// Get the bytecode LocalID for a Context local variable or parameter index
+  pub fn get_id (&self, is_parameter: bool, index: usize) -> bc::LocalID {
+    if is_parameter {
+      self.parameters[index].0
+    } else {
+      self.index_id_map[&(self.parameters.len() + index)]
+    }
   }
 }
 
@@ -103,6 +189,13 @@ pub struct Codegen<'a> {
   pub local: LocalCodegen,
   /// A staging area for generating module imports
   pub unresolved_imports: HashMap<ContextKey, bc::ID>,
+  /// Pointer types generated for indirectly passed values, which have no equivalent in the Context
+  pub indirect_type_ids: HashMap<ContextKey, bc::TypeID>,
+  /// Whether Global initializers and Function bodies are omitted, see `generate_interface`
+  pub interface_only: bool,
+  /// The arrangement of structure fields used by `size_of` and `field_offset`,
+  /// taken from the SESSION when the Codegen is created
+  pub layout_strategy: LayoutStrategy,
 }
 
 impl<'a> Codegen<'a> {
@@ -117,6 +210,9 @@ impl<'a> Codegen<'a> {
       function_id_counter: Counter::default(),
       local: LocalCodegen::default(),
       unresolved_imports: HashMap::default(),
+      indirect_type_ids: HashMap::default(),
+      interface_only: false,
+      layout_strategy: SESSION.layout_strategy(),
     }
   }
 
@@ -126,12 +222,80 @@ impl<'a> Codegen<'a> {
     generate_module(&mut self);
     self.module
   }
+
+  /// Fill in the Module of a Codegen with only the exports of its Context and the types they reference,
+  /// consuming the Codegen and returning the Module
+  /// 
+  /// Exported Globals have no initializer and exported Functions have no body,
+  /// so the resulting interface Module describes what a Module provides to its importers without its implementation
+  pub fn generate_interface (mut self) -> bc::Module {
+    self.interface_only = true;
+    self.generate()
+  }
+
+
+  /// Get the size in bytes of a type from the Context, generating a typedef for it if one does not already exist
+  /// 
+  /// Returns None if the layout of the type cannot be determined, see `bc::layout::layout_of`
+  pub fn size_of (&mut self, ty_key: ContextKey) -> Option<u64> {
+    let id = generate_type_def(self, ty_key);
+    bc::layout::size_of(&self.module, id, self.layout_strategy)
+  }
+
+  /// Get the offset in bytes of a named field of a structure type from the Context,
+  /// generating a typedef for the structure if one does not already exist
+  /// 
+  /// Returns None if the type is not a structure, it has no such field, or its layout cannot be determined
+  pub fn field_offset (&mut self, ty_key: ContextKey, field_name: &str) -> Option<u64> {
+    let id = generate_type_def(self, ty_key);
+    let element = self.module.get_type(id)?.data.element_id(field_name)?;
+    bc::layout::field_offset(&self.module, id, element, self.layout_strategy)
+  }
+}
+
+
+
+
+/// Lex, parse, analyze, generate, optimize and encode a source string as a bytecode Module in one step
+/// 
+/// Returns the encoded Module, or every Message created while compiling if any of them are Errors.
+/// The SESSION and SOURCE_MANAGER must already be initialized,
+/// and the source is added to the SOURCE_MANAGER as `name` with an `.ms` extension
+pub fn compile_to_bytecode (source: &str, name: &str, version: Version) -> Result<Vec<u8>, Vec<Message>> {
+  let message_base = SESSION.messages().len();
+
+  let collect_errors = || {
+    let messages = &SESSION.messages()[message_base..];
+
+    if messages.iter().any(|message| message.kind == MessageKind::Error) { Err(messages.to_vec()) }
+    else { Ok(()) }
+  };
+
+  let source = SOURCE_MANAGER.add_source(format!("{}.ms", name), source);
+
+  let tokens = Lexer::new(source).lex_stream();
+  let ast = Parser::new(&tokens).parse_ast();
+  let (context, _) = Analyzer::new().analyze(ast);
+
+  collect_errors()?;
+
+  let mut module = Codegen::new(&context, name.to_owned(), version).generate();
+  optimize(&mut module, OptLevel::default());
+
+  let mut buff = Vec::new();
+  module.encode(&mut buff);
+
+  Ok(buff)
 }
 
 
 
 
 fn generate_module (cg: &mut Codegen) {
+  if !cg.interface_only {
+    cg.module.strings = cg.context.strings.clone();
+  }
+
   let exports = generate_namespace_export_body(
     cg,
     cg.context.items
@@ -156,7 +320,13 @@ fn generate_imports(cg: &mut Codegen) {
   std::mem::swap(&mut unresolved_imports, &mut cg.unresolved_imports);
 
   for (&imp_key, &imp_id) in unresolved_imports.iter() {
-    let (chain, key_chain) = make_path(cg.context, imp_key);
+    let (chain, key_chain) = if cg.context.get_item_module(imp_key) == Some(cg.context.main_mod) {
+      // external items of the main module are provided by the host environment
+      let canonical_name = cg.context.get_item_canonical_name(imp_key).unwrap().clone();
+      (vec! [ Identifier::from(HOST_MODULE_NAME), canonical_name ], vec! [ cg.context.main_mod, imp_key ])
+    } else {
+      make_path(cg.context, imp_key)
+    };
     
     let mut iter = chain.iter().zip(key_chain.iter()).peekable();
 
@@ -255,7 +425,7 @@ fn generate_namespace_export_body (cg: &mut Codegen, ns: &ctx::Namespace) -> Vec
       },
 
       ContextItem::Function(func) => {
-        if func.parent_module == cg.context.main_mod {
+        if func.parent_module == cg.context.main_mod && !func.is_external {
           bc::ExportData::Function(generate_function(cg, func, export_key))
         } else { // no aliasing
           continue
@@ -284,10 +454,12 @@ fn generate_global (cg: &mut Codegen, global_ctx: &ctx::Global, key: ContextKey)
     // now we can create the global with an empty initializer, a mutable reference to which we will pass into the instruction codegen
     let mut global_bc = bc::Global::empty(id, ty);
 
-    // then we can evaluate the initializer code if there is any, and recursively generate dependencies
-    if let Some(initializer) = global_ctx.initializer.as_ref() {
-      cg.local.clear();
+    // then we can evaluate the initializer code if there is any (and this is not an interface), and recursively generate dependencies
+    if let Some(initializer) = global_ctx.initializer.as_ref().filter(|_| !cg.interface_only) {
+      // this may be reached while generating another body, so its local state is set aside
+      let outer_local = std::mem::take(&mut cg.local);
       generate_expression(cg, initializer, &mut global_bc.initializer);
+      cg.local = outer_local;
     }
 
     // finally we register the new global
@@ -308,7 +480,7 @@ fn generate_function (cg: &mut Codegen, function_ctx: &ctx::Function, key: Conte
   if let Some(existing_id) = cg.key_id_map.get(&key) {
     // if we've already registered this function we can just return
     (*existing_id).into()
-  } else if function_ctx.parent_module == cg.context.main_mod {
+  } else if function_ctx.parent_module == cg.context.main_mod && !function_ctx.is_external {
     // first we need to register the new function we will be creating
     let id: bc::FunctionID = cg.function_id_counter.get_next();
     cg.key_id_map.insert(key, id.into()).unwrap_none();
@@ -319,15 +491,47 @@ fn generate_function (cg: &mut Codegen, function_ctx: &ctx::Function, key: Conte
     // now we can create the function with an empty body, a mutable reference to which we will pass into the instruction codegen
     let mut function_bc = bc::Function::empty(id, ty);
 
-    // then we can evaluate the body code if there is any, and recursively generate dependencies
-    if let Some(body) = function_ctx.body.as_ref() {
-      cg.local.clear();
-      for _ in function_ctx.params.iter() {
-        let p_id = cg.local.id_counter.get_next();
+    // then we can evaluate the body code if there is any (and this is not an interface), and recursively generate dependencies
+    if let Some(body) = function_ctx.body.as_ref().filter(|_| !cg.interface_only) {
+      // this may be reached while generating another body, so its local state is set aside
+      let outer_local = std::mem::take(&mut cg.local);
+
+      if let Some(return_ty) = function_ctx.return_ty {
+        if PassingMode::of(cg.context, return_ty) == PassingMode::Indirect {
+          let ptr_ty = generate_indirect_type_def(cg, return_ty);
+          cg.local.result_address = Some(cg.local.create_slot(ptr_ty, None, function_ctx.origin));
+        }
+      }
+
+      for (param_name, param_ty, param_origin) in function_ctx.params.iter() {
+        let param_ty = *param_ty;
+
+        let slot_ty = match PassingMode::of(cg.context, param_ty) {
+          PassingMode::Direct => generate_type_def(cg, param_ty),
+          PassingMode::Indirect => generate_indirect_type_def(cg, param_ty),
+        };
+
+        let p_id = cg.local.create_slot(slot_ty, Some(param_name.clone()), *param_origin);
         let index = cg.local.index_counter.get_next();
         cg.local.index_id_map.insert(index, p_id);
+        cg.local.parameters.push((p_id, PassingMode::of(cg.context, param_ty)));
       }
+
       generate_block(cg, body, &mut function_bc.body);
+
+      if let (Some(result_address), Some(_)) = (cg.local.result_address, body.trailing_expression.as_ref()) {
+        generate_indirect_result_store(result_address, &mut function_bc.body);
+      }
+
+      // functions without a result on the stack, including those returning through a result address,
+      // may fall off the end of their body, so they are given an implicit return
+      let has_result = matches!(cg.module.get_type(ty).map(|ty| &ty.data), Some(bc::TypeData::Function { result: Some(_), .. }));
+
+      if !has_result && function_bc.body.last() != Some(&bc::Instruction::Return) {
+        function_bc.body.push(bc::Instruction::Return);
+      }
+
+      cg.local = outer_local;
     }
 
     // finally we register the new function
@@ -359,6 +563,8 @@ fn generate_type_def (cg: &mut Codegen, key: ContextKey) -> bc::TypeID {
         bc::TypeData::Intrinsic(match prim_type {
           ctx::PrimitiveType::Void => bc::IntrinsicType::Void,
           ctx::PrimitiveType::Bool => bc::IntrinsicType::Bool,
+          ctx::PrimitiveType::Char => bc::IntrinsicType::Char,
+          ctx::PrimitiveType::String => bc::IntrinsicType::String,
           ctx::PrimitiveType::Integer { signed, bit_size } => {
             match (signed, bit_size) {
               (true,  8) => bc::IntrinsicType::S8,
@@ -387,11 +593,40 @@ fn generate_type_def (cg: &mut Codegen, key: ContextKey) -> bc::TypeID {
 
       ctx::TypeData::Pointer(val_key) => bc::TypeData::Pointer(generate_type_def(cg, *val_key)),
 
-      ctx::TypeData::Structure { field_types, .. } => bc::TypeData::Struct(field_types.iter().map(|&field_key| generate_type_def(cg, field_key)).collect()),
+      ctx::TypeData::Structure { field_names, field_types } => bc::TypeData::Struct {
+        fields: field_types.iter().map(|&field_key| generate_type_def(cg, field_key)).collect(),
+        names: field_names.iter().map(ToString::to_string).collect(),
+      },
+
+      // Variants without a payload are given the void type
+      ctx::TypeData::Enum { variant_types, .. } => bc::TypeData::Enum {
+        variants: variant_types.iter().enumerate().map(|(tag, &payload_key)| {
+          (tag as u32, generate_type_def(cg, payload_key.unwrap_or(cg.context.void_ty)))
+        }).collect()
+      },
+
+      // Optionals are laid out as an enum of `Some` and `None`
+      &ctx::TypeData::Optional(payload_key) => bc::TypeData::Enum {
+        variants: vec! [ (0, generate_type_def(cg, payload_key)), (1, generate_type_def(cg, cg.context.void_ty)) ]
+      },
 
       ctx::TypeData::Function { parameter_types, return_type } => {
-        let parameters = parameter_types.iter().map(|&param_key| generate_type_def(cg, param_key)).collect();
-        let result = return_type.map(|ret_key| generate_type_def(cg, ret_key));
+        let mut parameters: Vec<bc::TypeID> = parameter_types.iter().map(|&param_key| match PassingMode::of(cg.context, param_key) {
+          PassingMode::Direct => generate_type_def(cg, param_key),
+          PassingMode::Indirect => generate_indirect_type_def(cg, param_key),
+        }).collect();
+
+        // an explicit `void` return type produces no result, the same as an omitted one
+        let return_type = return_type.filter(|&ret_key| ret_key != cg.context.void_ty);
+
+        let result = match return_type.map(|ret_key| (ret_key, PassingMode::of(cg.context, ret_key))) {
+          Some((ret_key, PassingMode::Direct)) => Some(generate_type_def(cg, ret_key)),
+          Some((ret_key, PassingMode::Indirect)) => {
+            parameters.insert(0, generate_indirect_type_def(cg, ret_key));
+            None
+          },
+          None => None,
+        };
 
         bc::TypeData::Function { parameters, result }
       },
@@ -406,6 +641,29 @@ fn generate_type_def (cg: &mut Codegen, key: ContextKey) -> bc::TypeID {
 }
 
 
+/// Get the TypeID of a pointer to the Type associated with a ContextKey, used to pass values of that Type indirectly
+fn generate_indirect_type_def (cg: &mut Codegen, key: ContextKey) -> bc::TypeID {
+  if let Some(&ptr_key) = cg.context.anon_types.get(&ctx::TypeData::Pointer(key)) {
+    generate_type_def(cg, ptr_key)
+  } else if let Some(&existing_id) = cg.indirect_type_ids.get(&key) {
+    existing_id
+  } else {
+    let value_id = generate_type_def(cg, key);
+    let id: bc::TypeID = cg.type_id_counter.get_next();
+    cg.module.types.push(bc::Type::new(id, bc::TypeData::Pointer(value_id)));
+    cg.indirect_type_ids.insert(key, id);
+    id
+  }
+}
+
+/// Store the value on top of the stack to the address held in the hidden result parameter of a Function
+fn generate_indirect_result_store (result_address: bc::LocalID, code: &mut Vec<bc::Instruction>) {
+  code.push(bc::Instruction::LocalAddress(result_address));
+  code.push(bc::Instruction::Load);
+  code.push(bc::Instruction::Store);
+}
+
+
 fn generate_conditional (cg: &mut Codegen, conditional_ir: &ir::Conditional, code: &mut Vec<bc::Instruction>) {
   generate_expression(cg, &conditional_ir.if_branch.condition, code);
 
@@ -454,16 +712,16 @@ fn generate_block (cg: &mut Codegen, block_ir: &ir::Block, code: &mut Vec<bc::In
 
 fn generate_statement (cg: &mut Codegen, statement_ir: &ir::Statement, code: &mut Vec<bc::Instruction>) {
   match &statement_ir.data {
-    ir::StatementData::Expression(expression) => {
+    ir::StatementData::Expression { expression, discard } => {
       generate_expression(cg, expression, code);
-      code.push(bc::Instruction::Discard);
+      if *discard { code.push(bc::Instruction::Discard); }
     },
 
-    ir::StatementData::Declaration { ty, initializer } => {
+    ir::StatementData::Declaration { name, ty, initializer } => {
       let t_id = generate_type_def(cg, *ty);
 
       let index = cg.local.index_counter.get_next();
-      let l_id = cg.local.id_counter.get_next();
+      let l_id = cg.local.create_slot(t_id, name.clone(), statement_ir.origin);
 
       cg.local.index_id_map.insert(index, l_id).unwrap_none();
 
@@ -494,6 +752,10 @@ fn generate_statement (cg: &mut Codegen, statement_ir: &ir::Statement, code: &mu
     ir::StatementData::Return(expression) => {
       if let Some(expression) = expression {
         generate_expression(cg, expression, code);
+
+        if let Some(result_address) = cg.local.result_address {
+          generate_indirect_result_store(result_address, code);
+        }
       }
       code.push(bc::Instruction::Return);
     },
@@ -506,10 +768,31 @@ fn generate_statement (cg: &mut Codegen, statement_ir: &ir::Statement, code: &mu
 
 
 fn generate_lvalue (cg: &mut Codegen, expression_ir: &ir::Expression, code: &mut Vec<bc::Instruction>) {
-  if let ir::ExpressionData::Unary { operand, operator: Operator::Dereference } = &expression_ir.data {
-    generate_expression(cg, operand, code)
-  } else {
-    generate_expression(cg, expression_ir, code)
+  match &expression_ir.data {
+    ir::ExpressionData::Unary { operand, operator: Operator::Dereference } => generate_expression(cg, operand, code),
+    ir::ExpressionData::Reference(reference) => generate_reference_address(cg, reference, code),
+    _ => generate_expression(cg, expression_ir, code)
+  }
+}
+
+/// Push the address of the value of a Reference
+fn generate_reference_address (cg: &mut Codegen, reference: &ir::Reference, code: &mut Vec<bc::Instruction>) {
+  match reference {
+    &ir::Reference::Local { is_parameter, index } => {
+      code.push(bc::Instruction::LocalAddress(cg.local.get_id(is_parameter, index)));
+
+      // indirect parameters hold the address of their value
+      if is_parameter && cg.local.parameters[index].1 == PassingMode::Indirect {
+        code.push(bc::Instruction::Load);
+      }
+    },
+    &ir::Reference::Global(ctx_key) => {
+      match cg.context.items.get(ctx_key).unwrap() {
+        ContextItem::Global(glo) => code.push(bc::Instruction::GlobalAddress(generate_global(cg, glo, ctx_key))),
+        ContextItem::Function(func) => code.push(bc::Instruction::FunctionAddress(generate_function(cg, func, ctx_key))),
+        _ => unreachable!()
+      }
+    }
   }
 }
 
@@ -518,21 +801,11 @@ fn generate_expression (cg: &mut Codegen, expression_ir: &ir::Expression, code:
     ir::ExpressionData::Coerce(sub_expression) => generate_cast(cg, expression_ir.ty, sub_expression, code),
 
     ir::ExpressionData::Reference(reference) => {
-      match reference {
-        ir::Reference::Local { index, .. } => {
-          code.push(bc::Instruction::LocalAddress((*index as u64).into()));
-          code.push(bc::Instruction::Load);
-        },
-        &ir::Reference::Global(ctx_key) => {
-          match cg.context.items.get(ctx_key).unwrap() {
-            ContextItem::Global(glo) => {
-              code.push(bc::Instruction::GlobalAddress(generate_global(cg, glo, ctx_key)));
-              code.push(bc::Instruction::Load);
-            },
-            ContextItem::Function(func) => code.push(bc::Instruction::FunctionAddress(generate_function(cg, func, ctx_key))),
-            _ => unreachable!()
-          }
-        }
+      generate_reference_address(cg, reference, code);
+
+      // functions are referenced by address
+      if !matches!(reference, &ir::Reference::Global(ctx_key) if cg.context.items.get(ctx_key).unwrap().ref_function().is_some()) {
+        code.push(bc::Instruction::Load);
       }
     },
 
@@ -544,7 +817,8 @@ fn generate_expression (cg: &mut Codegen, expression_ir: &ir::Expression, code:
         Constant::Number(Number::FloatingPoint(FloatingPoint::Norm(float))) => bc::ImmediateValue::F32(float as _),
         Constant::Number(Number::FloatingPoint(FloatingPoint::Inf)) => bc::ImmediateValue::F32(f32::INFINITY),
         Constant::Number(Number::FloatingPoint(FloatingPoint::NaN)) => bc::ImmediateValue::F32(f32::NAN),
-        Constant::String(ref _s) => unimplemented!("strings are not yet implemented"),
+        Constant::String(ref string) => generate_string_ref(cg, string),
+        Constant::Char(ch) => bc::ImmediateValue::Char(ch),
       }));
     },
 
@@ -553,24 +827,15 @@ fn generate_expression (cg: &mut Codegen, expression_ir: &ir::Expression, code:
         if let box ir::Expression { data: ir::ExpressionData::Reference(reference), .. } = operand {
           // &local is just local_address, while if we did it naively via recursion to generate_expression,
           // we would generate a load and then a spill of the variable into a new temp variable
-          match reference {
-            ir::Reference::Local { index, .. } => code.push(bc::Instruction::LocalAddress((*index as u64).into())),
-            &ir::Reference::Global(ctx_key) => {
-              match cg.context.items.get(ctx_key).unwrap() {
-                ContextItem::Global(glo) => code.push(bc::Instruction::GlobalAddress(generate_global(cg, glo, ctx_key))),
-                ContextItem::Function(func) => code.push(bc::Instruction::FunctionAddress(generate_function(cg, func, ctx_key))),
-                _ => unreachable!()
-              }
-            }
-          }
+          generate_reference_address(cg, reference, code);
         } else if let box ir::Expression { data: ir::ExpressionData::Unary { operand, operator: Operator::Dereference }, .. } = operand {
           // TODO this should be handled in IR generation, and produce a warning
           // elide `&*x` to just `x`
           generate_expression(cg, operand, code);
         } else {
           // Generate a spill of operand stack data
-          let id = cg.local.id_counter.get_next();
           let ty = generate_type_def(cg, operand.ty);
+          let id = cg.local.create_slot(ty, None, operand.origin);
 
           code.push(bc::Instruction::CreateLocal(ty));
 
@@ -584,6 +849,21 @@ fn generate_expression (cg: &mut Codegen, expression_ir: &ir::Expression, code:
         // TODO this should be handled in IR generation, and produce a warning
         // elide `*&x` to just `x`
         generate_expression(cg, operand, code);
+      } else if operator == Operator::Sub {
+        let mut operand_code = Vec::new();
+
+        generate_expression(cg, operand, &mut operand_code);
+
+        // Fold negated literals into a single immediate
+        if let [ bc::Instruction::ImmediateValue(value) ] = operand_code.as_slice() {
+          if let Some(negated) = negate_immediate(value) {
+            code.push(bc::Instruction::ImmediateValue(negated));
+            return
+          }
+        }
+
+        code.extend(operand_code);
+        code.push(bc::Instruction::Neg);
       } else {
         generate_expression(cg, operand, code);
         code.push(bc::Instruction::from_operator(operator).unwrap());
@@ -597,33 +877,165 @@ fn generate_expression (cg: &mut Codegen, expression_ir: &ir::Expression, code:
     },
 
     ir::ExpressionData::Call { callee, arguments } => {
+      // an indirect result is stored to a temporary owned by the caller, whose address is passed before the other arguments
+      let result_temp = if PassingMode::of(cg.context, expression_ir.ty) == PassingMode::Indirect {
+        let ty = generate_type_def(cg, expression_ir.ty);
+        let id = cg.local.create_slot(ty, None, expression_ir.origin);
+        code.push(bc::Instruction::CreateLocal(ty));
+        code.push(bc::Instruction::LocalAddress(id));
+        Some(id)
+      } else {
+        None
+      };
+
       for arg in arguments.iter() {
-        generate_expression(cg, arg, code);
+        match PassingMode::of(cg.context, arg.ty) {
+          PassingMode::Direct => generate_expression(cg, arg, code),
+          PassingMode::Indirect => {
+            // indirect arguments are copied to a temporary owned by the caller, and its address is passed
+            let ty = generate_type_def(cg, arg.ty);
+            let id = cg.local.create_slot(ty, None, arg.origin);
+            code.push(bc::Instruction::CreateLocal(ty));
+            generate_expression(cg, arg, code);
+            code.push(bc::Instruction::LocalAddress(id));
+            code.push(bc::Instruction::Store);
+            code.push(bc::Instruction::LocalAddress(id));
+          }
+        }
       }
 
-      if let ir::ExpressionData::Reference(ir::Reference::Global(ctx_key)) = callee.data {
-        if let Some(function) = cg.context.items.get(ctx_key).unwrap().ref_function() {
-          let direct_ref = generate_function(cg, function, ctx_key);
-          code.push(bc::Instruction::CallDirect(direct_ref));
-          return
-        }
+      let direct_ref = if let ir::ExpressionData::Reference(ir::Reference::Global(ctx_key)) = callee.data {
+        cg.context.items.get(ctx_key).unwrap().ref_function().map(|function| generate_function(cg, function, ctx_key))
+      } else {
+        None
+      };
+
+      if let Some(direct_ref) = direct_ref {
+        code.push(bc::Instruction::CallDirect(direct_ref));
+      } else {
+        generate_expression(cg, callee, code);
+        code.push(bc::Instruction::CallIndirect);
       }
 
-      generate_expression(cg, callee, code);
-      code.push(bc::Instruction::CallIndirect);
+      if let Some(id) = result_temp {
+        code.push(bc::Instruction::LocalAddress(id));
+        code.push(bc::Instruction::Load);
+      }
     },
 
     ir::ExpressionData::Conditional(conditional) => generate_conditional(cg, conditional, code),
 
     ir::ExpressionData::Block(block) => generate_block(cg, block, code),
+
+    &ir::ExpressionData::Variant { tag, ref payload } => {
+      let payload = payload.as_deref().map(|payload| (payload.ty, payload));
+      generate_variant(cg, expression_ir.ty, tag, payload, expression_ir.origin, code)
+    },
+
+    &ir::ExpressionData::IsVariant { ref operand, tag } => {
+      generate_value_address(cg, operand, code);
+      code.push(bc::Instruction::GetTag);
+      code.push(bc::Instruction::Load);
+      code.push(bc::Instruction::ImmediateValue(bc::ImmediateValue::U32(tag as _)));
+      code.push(bc::Instruction::EQ);
+    },
+
+    &ir::ExpressionData::Payload { ref operand, tag } => {
+      generate_value_address(cg, operand, code);
+      code.push(bc::Instruction::GetPayload(tag as _));
+      code.push(bc::Instruction::Load);
+    },
+  }
+}
+
+/// Push the address of the value of an Expression,
+/// spilling it to a temporary if it is not already held by a variable
+fn generate_value_address (cg: &mut Codegen, expression_ir: &ir::Expression, code: &mut Vec<bc::Instruction>) {
+  if let ir::ExpressionData::Reference(reference) = &expression_ir.data {
+    generate_reference_address(cg, reference, code);
+  } else {
+    let ty = generate_type_def(cg, expression_ir.ty);
+    let id = cg.local.create_slot(ty, None, expression_ir.origin);
+
+    code.push(bc::Instruction::CreateLocal(ty));
+    code.push(bc::Instruction::LocalAddress(id));
+    generate_expression(cg, expression_ir, code);
+    code.push(bc::Instruction::Store);
+    code.push(bc::Instruction::LocalAddress(id));
   }
 }
 
+/// Build a variant of an enum in a temporary by storing its payload and tag,
+/// then push the whole value on the stack
+/// 
+/// The payload is given with the Type it is stored as, and is cast to it if necessary
+fn generate_variant (cg: &mut Codegen, ty_key: ContextKey, tag: usize, payload: Option<(ContextKey, &ir::Expression)>, origin: SourceRegion, code: &mut Vec<bc::Instruction>) {
+  let ty = generate_type_def(cg, ty_key);
+  let id = cg.local.create_slot(ty, None, origin);
+
+  code.push(bc::Instruction::CreateLocal(ty));
+
+  if let Some((payload_key, payload)) = payload {
+    code.push(bc::Instruction::LocalAddress(id));
+    code.push(bc::Instruction::GetPayload(tag as _));
+
+    if payload.ty == payload_key {
+      generate_expression(cg, payload, code);
+    } else {
+      generate_cast(cg, payload_key, payload, code);
+    }
+
+    code.push(bc::Instruction::Store);
+  }
+
+  code.push(bc::Instruction::LocalAddress(id));
+  code.push(bc::Instruction::GetTag);
+  code.push(bc::Instruction::ImmediateValue(bc::ImmediateValue::U32(tag as _)));
+  code.push(bc::Instruction::Store);
+
+  code.push(bc::Instruction::LocalAddress(id));
+  code.push(bc::Instruction::Load);
+}
+
+/// Negates signed and floating point immediates,
+/// wrapping so that the most negative signed values can be formed from their magnitude
+fn negate_immediate (value: &bc::ImmediateValue) -> Option<bc::ImmediateValue> {
+  Some(match *value {
+    bc::ImmediateValue::S8(x)  => bc::ImmediateValue::S8(x.wrapping_neg()),
+    bc::ImmediateValue::S16(x) => bc::ImmediateValue::S16(x.wrapping_neg()),
+    bc::ImmediateValue::S32(x) => bc::ImmediateValue::S32(x.wrapping_neg()),
+    bc::ImmediateValue::S64(x) => bc::ImmediateValue::S64(x.wrapping_neg()),
+    bc::ImmediateValue::F32(x) => bc::ImmediateValue::F32(-x),
+    bc::ImmediateValue::F64(x) => bc::ImmediateValue::F64(-x),
+    _ => return None
+  })
+}
+
+/// Get a reference to a string literal in the constant pool of the Module,
+/// which is seeded with the string pool of the Context so interned literals keep their index
+fn generate_string_ref (cg: &mut Codegen, string: &str) -> bc::ImmediateValue {
+  bc::ImmediateValue::StringRef(match cg.context.string_indices.get(string) {
+    Some(&index) => index as u32,
+    None => cg.module.add_string(string),
+  })
+}
+
 fn generate_cast (cg: &mut Codegen, ty_key: ContextKey, expression_ir: &ir::Expression, code: &mut Vec<bc::Instruction>) {
   let type_id = generate_type_def(cg, ty_key);
 
   let type_ctx: &ctx::Type = cg.context.items.get(ty_key).unwrap().ref_type().unwrap();
 
+  // Void pointers such as `null` become `None`, and any other value becomes the payload of `Some`
+  if let Some(ctx::TypeData::Optional(payload_key)) = type_ctx.data {
+    let from_ctx: &ctx::Type = cg.context.items.get(expression_ir.ty).unwrap().ref_type().unwrap();
+
+    return if from_ctx.data == Some(ctx::TypeData::Pointer(cg.context.void_ty)) {
+      generate_variant(cg, ty_key, 1, None, expression_ir.origin, code)
+    } else {
+      generate_variant(cg, ty_key, 0, Some((payload_key, expression_ir)), expression_ir.origin, code)
+    }
+  }
+
   match &expression_ir.data {
     ir::ExpressionData::Coerce(sub_expression_ir) => generate_cast(cg, ty_key, sub_expression_ir, code),
 
@@ -664,7 +1076,7 @@ fn generate_cast (cg: &mut Codegen, ty_key: ContextKey, expression_ir: &ir::Expr
         (ctx::TypeData::Primitive(ctx::PrimitiveType::FloatingPoint { bit_size: 64 }), Constant::Number(Number::FloatingPoint(FloatingPoint::NaN)))
         => bc::ImmediateValue::F64(f64::NAN),
         
-        (_, Constant::String(ref _s)) => unimplemented!("strings are not yet implemented"),
+        (_, Constant::String(string)) => generate_string_ref(cg, string),
 
         _ => {
           generate_expression(cg, expression_ir, code);
@@ -680,6 +1092,9 @@ fn generate_cast (cg: &mut Codegen, ty_key: ContextKey, expression_ir: &ir::Expr
     | ir::ExpressionData::Call        { .. }
     | ir::ExpressionData::Block       { .. }
     | ir::ExpressionData::Conditional { .. }
+    | ir::ExpressionData::Variant     { .. }
+    | ir::ExpressionData::IsVariant   { .. }
+    | ir::ExpressionData::Payload     { .. }
     => {
       generate_expression(cg, expression_ir, code);
       code.push(bc::Instruction::Cast(type_id));
@@ -771,4 +1186,476 @@ fn make_path (context: &Context, ns_key: ContextKey) -> (Vec<Identifier>, Vec<Co
   key_chain.insert(0, module_key);
 
   (chain, key_chain)
-}
\ No newline at end of file
+}
+
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use mod_frontend::{ analyzer::Analyzer, test_support::{ analyze_str, analyze_str_with, lock_singletons, }, };
+  use bc::{ Decode, };
+
+  #[test]
+  fn external_function_import () {
+    let output = analyze_str("external_function.ms", r#"
+      import fn host_print (value: s32);
+
+      export fn print_twice (value: s32) {
+        host_print(value);
+        host_print(value);
+      }
+    "#);
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let module = Codegen::new(&output.context, "external_function".to_owned(), (0, 0, 0).into()).generate();
+
+    assert_eq!(module.functions.len(), 1);
+
+    assert_eq!(module.imports.len(), 1);
+    assert_eq!(module.imports[0].name, HOST_MODULE_NAME);
+    assert_eq!(module.imports[0].items.len(), 1);
+    assert_eq!(module.imports[0].items[0].name, "host_print");
+    assert!(matches!(module.imports[0].items[0].data, bc::ImportData::Function(_, _)));
+  }
+
+  #[test]
+  fn compile_to_bytes () {
+    let _guard = lock_singletons();
+
+    let bytes = compile_to_bytecode(r#"
+      fn helper (value: s32) -> s32 { value * 2 }
+
+      export fn double (value: s32) -> s32 { helper(value) }
+    "#, "compile_to_bytes", (1, 2, 3).into()).unwrap();
+
+    let module = bc::Module::decode(&mut bytes.as_slice()).unwrap();
+
+    assert_eq!(module.name, "compile_to_bytes");
+    assert_eq!(module.version, (1, 2, 3).into());
+    assert_eq!(module.verify(), Ok(()));
+    assert_eq!(module.exports.len(), 1);
+    assert_eq!(module.exports[0].name, "double");
+
+    let messages = compile_to_bytecode("export fn broken () -> s32 { missing }", "compile_to_bytes_error", (0, 0, 0).into()).unwrap_err();
+    assert!(messages.iter().any(|message| message.kind == MessageKind::Error));
+  }
+
+  #[test]
+  fn char_literals () {
+    let _guard = lock_singletons();
+
+    let bytes = compile_to_bytecode("export fn letter () -> char { 'a' }", "char_literals", (0, 0, 0).into()).unwrap();
+
+    let module = bc::Module::decode(&mut bytes.as_slice()).unwrap();
+
+    assert_eq!(module.verify(), Ok(()));
+    assert!(module.types.iter().any(|ty| ty.data == bc::TypeData::Intrinsic(bc::IntrinsicType::Char)));
+    assert!(module.functions[0].body.contains(&bc::Instruction::ImmediateValue(bc::ImmediateValue::Char('a'))), "{:?}", module.functions);
+  }
+
+  #[test]
+  fn string_literals () {
+    let _guard = lock_singletons();
+
+    let bytes = compile_to_bytecode(r#"export fn greeting () -> string { "héllo" }"#, "string_literals", (0, 0, 0).into()).unwrap();
+
+    let module = bc::Module::decode(&mut bytes.as_slice()).unwrap();
+
+    assert_eq!(module.verify(), Ok(()));
+    assert_eq!(module.strings, [ "héllo" ]);
+    assert!(module.types.iter().any(|ty| ty.data == bc::TypeData::Intrinsic(bc::IntrinsicType::String)));
+    assert!(module.functions[0].body.contains(&bc::Instruction::ImmediateValue(bc::ImmediateValue::StringRef(0))), "{:?}", module.functions);
+  }
+
+  #[test]
+  fn repeated_string_literals () {
+    let _guard = lock_singletons();
+
+    let bytes = compile_to_bytecode(r#"
+      export global greeting: string = "hello";
+      export fn first () -> string { "hello" }
+      export fn second () -> string { let other = "world"; "hello" }
+    "#, "repeated_string_literals", (0, 0, 0).into()).unwrap();
+
+    let module = bc::Module::decode(&mut bytes.as_slice()).unwrap();
+
+    assert_eq!(module.verify(), Ok(()));
+    assert_eq!(module.strings, [ "hello", "world" ]);
+
+    let hello = bc::Instruction::ImmediateValue(bc::ImmediateValue::StringRef(0));
+    assert!(module.globals[0].initializer.contains(&hello), "{:?}", module.globals);
+    assert!(module.functions.iter().all(|function| function.body.contains(&hello)), "{:?}", module.functions);
+  }
+
+  #[test]
+  fn enum_construction_and_matching () {
+    let _guard = lock_singletons();
+
+    let bytes = compile_to_bytecode(r#"
+      export enum Shape { Circle(s32), Empty }
+      export fn make (r: s32) -> Shape { Shape::Circle(r) }
+      export fn empty () -> Shape { Shape::Empty }
+      export fn radius (s: Shape) -> s32 { match s { Circle(r) => r, Empty => 0 } }
+    "#, "enum_construction_and_matching", (0, 0, 0).into()).unwrap();
+
+    let module = bc::Module::decode(&mut bytes.as_slice()).unwrap();
+
+    assert_eq!(module.verify(), Ok(()));
+
+    let contains = |function: &bc::Function, instruction: &bc::Instruction| function.body.iter().flat_map(bc::Instruction::walk).any(|i| i == instruction);
+
+    // construction stores the payload and tag of the variant to a temporary
+    let [ make, empty, radius ] = [ &module.functions[0], &module.functions[1], &module.functions[2] ];
+    assert!(contains(make, &bc::Instruction::GetPayload(0)) && contains(make, &bc::Instruction::GetTag), "{:#?}", make.body);
+    assert!(contains(make, &bc::Instruction::ImmediateValue(bc::ImmediateValue::U32(0))), "{:#?}", make.body);
+    assert!(!contains(empty, &bc::Instruction::GetPayload(1)) && contains(empty, &bc::Instruction::GetTag), "{:#?}", empty.body);
+    assert!(contains(empty, &bc::Instruction::ImmediateValue(bc::ImmediateValue::U32(1))), "{:#?}", empty.body);
+
+    // matching compares the tag and loads the payload of the matched variant
+    assert!(contains(radius, &bc::Instruction::GetTag) && contains(radius, &bc::Instruction::EQ), "{:#?}", radius.body);
+    assert!(contains(radius, &bc::Instruction::GetPayload(0)), "{:#?}", radius.body);
+  }
+
+  #[test]
+  fn optional_wrapping_and_checking () {
+    let _guard = lock_singletons();
+
+    let bytes = compile_to_bytecode(r#"
+      export fn wrapped (v: s32) -> ?s32 { let x: ?s32 = v; x }
+      export fn widened () -> ?s64 { let x: ?s64 = 5; x }
+      export fn absent () -> ?s32 { let x: ?s32 = null; x }
+      export fn checked (x: ?s32) -> s32 { match x { Some(v) => v + 1, None => 0 } }
+    "#, "optional_wrapping_and_checking", (0, 0, 0).into()).unwrap();
+
+    let module = bc::Module::decode(&mut bytes.as_slice()).unwrap();
+
+    assert_eq!(module.verify(), Ok(()));
+
+    let contains = |function: &bc::Function, instruction: &bc::Instruction| function.body.iter().flat_map(bc::Instruction::walk).any(|i| i == instruction);
+
+    // values are stored as the payload of `Some`, cast to the payload type if necessary
+    let [ wrapped, widened, absent, checked ] = [ &module.functions[0], &module.functions[1], &module.functions[2], &module.functions[3] ];
+    assert!(contains(wrapped, &bc::Instruction::GetPayload(0)) && contains(wrapped, &bc::Instruction::ImmediateValue(bc::ImmediateValue::U32(0))), "{:#?}", wrapped.body);
+    assert!(contains(widened, &bc::Instruction::ImmediateValue(bc::ImmediateValue::S64(5))), "{:#?}", widened.body);
+
+    // `null` is `None`, which has no payload
+    assert!(!contains(absent, &bc::Instruction::GetPayload(0)) && contains(absent, &bc::Instruction::ImmediateValue(bc::ImmediateValue::U32(1))), "{:#?}", absent.body);
+    assert!(!contains(absent, &bc::Instruction::ImmediateValue(bc::ImmediateValue::Null)), "{:#?}", absent.body);
+
+    assert!(contains(checked, &bc::Instruction::GetTag) && contains(checked, &bc::Instruction::GetPayload(0)), "{:#?}", checked.body);
+  }
+
+  #[test]
+  fn implicit_void_return () {
+    let output = analyze_str("implicit_void_return.ms", r#"
+      import fn host_print (value: s32);
+
+      export fn print_sum (a: s32, b: s32) {
+        let sum = a + b;
+        host_print(sum);
+      }
+
+      export fn double (value: s32) -> s32 { value * 2 }
+
+      export fn print_double (value: s32) -> void {
+        host_print(double(value));
+      }
+
+      struct Pair {
+        a: s32,
+        b: s32,
+      }
+
+      export fn make_pair (pair: Pair) -> Pair { pair }
+    "#);
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let module = Codegen::new(&output.context, "implicit_void_return".to_owned(), (0, 0, 0).into()).generate();
+
+    let exported = |name: &str| {
+      let id = match module.exports.iter().find(|export| export.name == name).unwrap().data {
+        bc::ExportData::Function(id) => id,
+        _ => unreachable!(),
+      };
+
+      module.functions.iter().find(|function| function.id == id).unwrap()
+    };
+
+    let print_sum = exported("print_sum");
+
+    assert!(print_sum.body.len() > 1);
+    assert_eq!(print_sum.body.last(), Some(&bc::Instruction::Return));
+    assert_eq!(print_sum.body.iter().filter(|&instruction| *instruction == bc::Instruction::Return).count(), 1);
+
+    let print_double = exported("print_double");
+    assert!(matches!(module.get_type(print_double.ty).map(|ty| &ty.data), Some(bc::TypeData::Function { result: None, .. })));
+    assert_eq!(print_double.body.last(), Some(&bc::Instruction::Return));
+
+    // functions with a result leave it on the stack at the end of their body instead
+    let double = exported("double");
+    assert_ne!(double.body.last(), Some(&bc::Instruction::Return));
+
+    // while functions returning through a result address store it and then return, like void functions
+    let make_pair = exported("make_pair");
+    assert!(matches!(module.get_type(make_pair.ty).map(|ty| &ty.data), Some(bc::TypeData::Function { result: None, .. })));
+    assert!(make_pair.body.ends_with(&[ bc::Instruction::Store, bc::Instruction::Return ]), "{:#?}", make_pair.body);
+  }
+
+  #[test]
+  fn interface_module () {
+    let output = analyze_str("interface_module.ms", r#"
+      import fn host_print (value: s32);
+
+      fn helper (value: s32) -> s32 { value * 2 }
+
+      export global LIMIT: s32 = helper(21);
+
+      export fn print_limit () {
+        host_print(LIMIT);
+      }
+    "#);
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let module = Codegen::new(&output.context, "interface_module".to_owned(), (0, 0, 0).into()).generate();
+    let interface = Codegen::new(&output.context, "interface_module".to_owned(), (0, 0, 0).into()).generate_interface();
+
+    assert_eq!(interface.verify(), Ok(()));
+
+    let export_names = |module: &bc::Module| module.exports.iter().map(|export| export.name.clone()).collect::<Vec<_>>();
+    assert_eq!(export_names(&interface), export_names(&module));
+
+    // only the exported items are present, and none of their implementations or dependencies
+    assert_eq!(interface.globals.len(), 1);
+    assert!(interface.globals[0].initializer.is_empty());
+
+    assert_eq!(interface.functions.len(), 1);
+    assert!(interface.functions[0].body.is_empty());
+
+    assert!(interface.imports.is_empty());
+    assert_eq!(module.functions.len(), 2);
+    assert_eq!(module.imports.len(), 1);
+
+    for export in interface.exports.iter() {
+      match export.data {
+        bc::ExportData::Global(id) => assert!(interface.globals.iter().any(|global| global.id == id)),
+        bc::ExportData::Function(id) => assert!(interface.functions.iter().any(|function| function.id == id)),
+        bc::ExportData::Namespace(_) => unreachable!(),
+      }
+    }
+
+    // a dependent Module's imports resolve against the interface just as they do against the full Module
+    let mut importer = bc::Module::empty("importer".to_owned(), (0, 0, 0).into());
+
+    importer.types = vec! [
+      bc::Type::new(0.into(), bc::TypeData::Intrinsic(bc::IntrinsicType::S32)),
+      bc::Type::new(1.into(), bc::TypeData::Function { parameters: vec! [ ], result: None }),
+    ];
+
+    let mut imported = bc::ImportModule::empty("interface_module".to_owned(), (0, 0, 0).into());
+    imported.items.push(bc::Import::new("LIMIT".to_owned(), bc::ImportData::Global(0.into(), 0.into())));
+    imported.items.push(bc::Import::new("print_limit".to_owned(), bc::ImportData::Function(0.into(), 1.into())));
+    importer.imports.push(imported);
+
+    assert_eq!(importer.verify(), Ok(()));
+    assert_eq!(importer.check_imports(&interface), Ok(()));
+    assert_eq!(importer.check_imports(&module), Ok(()));
+
+    // and a mismatched import is caught by the interface alone
+    importer.imports[0].items[1].data = bc::ImportData::Function(0.into(), 0.into());
+
+    assert_eq!(
+      importer.check_imports(&interface).unwrap_err().iter().map(|error| (error.path.as_str(), error.kind)).collect::<Vec<_>>(),
+      [ ("print_limit", bc::ImportErrorKind::TypeMismatch) ]
+    );
+  }
+
+  #[test]
+  fn negative_literal_min () {
+    let output = analyze_str("negative_literal_min.ms", r#"
+      export global MIN: s64 = -9223372036854775808;
+      export global SMALL: s8 = -128;
+    "#);
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let module = Codegen::new(&output.context, "negative_literal_min".to_owned(), (0, 0, 0).into()).generate();
+
+    let initializers: Vec<_> = module.globals.iter().map(|global| global.initializer.as_slice()).collect();
+
+    assert!(initializers.contains(&&[ bc::Instruction::ImmediateValue(bc::ImmediateValue::S64(i64::MIN)) ][..]), "{:#?}", initializers);
+    assert!(initializers.contains(&&[ bc::Instruction::ImmediateValue(bc::ImmediateValue::S8(i8::MIN)) ][..]), "{:#?}", initializers);
+  }
+
+  #[test]
+  fn builtin_function_import () {
+    let mut analyzer = Analyzer::new();
+
+    let f32_ty = analyzer.context.core_bs.get_entry("f32").unwrap();
+    analyzer.context.register_builtin_function("sqrt", &[ ("value", f32_ty) ], Some(f32_ty));
+
+    let output = analyze_str_with(analyzer, "builtin_function_import.ms", "export fn root (x: f32) -> f32 { sqrt(x) }");
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let module = Codegen::new(&output.context, "builtin_function_import".to_owned(), (0, 0, 0).into()).generate();
+
+    assert_eq!(module.functions.len(), 1);
+
+    assert_eq!(module.imports.len(), 1);
+    assert_eq!(module.imports[0].name, "core");
+    assert_eq!(module.imports[0].items.len(), 1);
+    assert_eq!(module.imports[0].items[0].name, "sqrt");
+    assert!(matches!(module.imports[0].items[0].data, bc::ImportData::Function(_, _)));
+  }
+
+  #[test]
+  fn expression_statement_discard () {
+    let output = analyze_str("expression_statement_discard.ms", r#"
+      fn value () -> s32 { 1 }
+      fn nothing () { }
+
+      export fn f () {
+        value();
+        nothing();
+      }
+    "#);
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let module = Codegen::new(&output.context, "expression_statement_discard".to_owned(), (0, 0, 0).into()).generate();
+
+    let f = module.functions.iter().find(|function| function.body.iter().filter(|instruction| matches!(instruction, bc::Instruction::CallDirect(_))).count() == 2).unwrap();
+
+    assert!(matches!(f.body[.. 3], [ bc::Instruction::CallDirect(_), bc::Instruction::Discard, bc::Instruction::CallDirect(_) ]), "{:#?}", f.body);
+    assert!(!matches!(f.body.get(3), Some(bc::Instruction::Discard)), "{:#?}", f.body);
+  }
+
+  #[test]
+  fn session_layout_strategy () {
+    let output = analyze_str("session_layout_strategy.ms", r#"
+      struct Padded {
+        a: s8,
+        b: s64,
+        c: s8,
+      }
+    "#);
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let padded_key = output.context.items.get(output.context.main_ns).unwrap().ref_namespace().unwrap().local_bindings.get_entry("Padded").unwrap();
+
+    let layout_with = |strategy: LayoutStrategy| {
+      let _guard = lock_singletons();
+
+      SESSION.set_layout_strategy(strategy);
+      let mut cg = Codegen::new(&output.context, "session_layout_strategy".to_owned(), (0, 0, 0).into());
+      SESSION.set_layout_strategy(LayoutStrategy::default());
+
+      assert_eq!(cg.layout_strategy, strategy);
+
+      (cg.size_of(padded_key), [ "a", "b", "c" ].iter().map(|&field| cg.field_offset(padded_key, field).unwrap()).collect::<Vec<_>>())
+    };
+
+    assert_eq!(layout_with(LayoutStrategy::COrder), (Some(24), vec! [ 0, 8, 16 ]));
+    assert_eq!(layout_with(LayoutStrategy::Optimized), (Some(16), vec! [ 8, 0, 9 ]));
+  }
+
+  #[test]
+  fn struct_passing () {
+    let output = analyze_str("struct_passing.ms", r#"
+      struct Pair {
+        a: s32,
+        b: s32,
+      }
+
+      fn identity (pair: Pair) -> Pair {
+        pair
+      }
+
+      export fn call_identity (pair: Pair) -> Pair {
+        identity(pair)
+      }
+    "#);
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let pair_key = output.context.items.get(output.context.main_ns).unwrap().ref_namespace().unwrap().local_bindings.get_entry("Pair").unwrap();
+    assert_eq!(PassingMode::of(&output.context, pair_key), PassingMode::Indirect);
+    assert_eq!(PassingMode::of(&output.context, output.context.bool_ty), PassingMode::Direct);
+
+    let module = Codegen::new(&output.context, "struct_passing".to_owned(), (0, 0, 0).into()).generate();
+
+    let get_type = |id: bc::TypeID| &module.types.iter().find(|ty| ty.id == id).unwrap().data;
+
+    let pair_id = module.types.iter().find(|ty| matches!(ty.data, bc::TypeData::Struct { .. })).unwrap().id;
+
+    for function in module.functions.iter() {
+      // the result address is passed before the struct parameter, and both are pointers to the struct
+      match get_type(function.ty) {
+        bc::TypeData::Function { parameters, result: None } => {
+          assert_eq!(parameters.len(), 2);
+
+          for &parameter in parameters.iter() {
+            assert_eq!(get_type(parameter), &bc::TypeData::Pointer(pair_id));
+          }
+        },
+        other => panic!("expected function type with no result, got {:?}", other)
+      }
+
+      // the result is stored through the hidden result parameter
+      assert!(function.body.ends_with(&[ bc::Instruction::LocalAddress(0.into()), bc::Instruction::Load, bc::Instruction::Store, bc::Instruction::Return ]), "{:#?}", function.body);
+    }
+
+    let caller = module.functions.iter().find(|function| function.body.iter().any(|instruction| matches!(instruction, bc::Instruction::CallDirect(_)))).unwrap();
+
+    // the caller allocates the result temporary and a copy of the argument, and loads the result after the call
+    assert_eq!(caller.body.iter().filter(|&instruction| *instruction == bc::Instruction::CreateLocal(pair_id)).count(), 2);
+
+    let call_index = caller.body.iter().position(|instruction| matches!(instruction, bc::Instruction::CallDirect(_))).unwrap();
+    assert!(matches!(caller.body[call_index + 1 .. call_index + 3], [ bc::Instruction::LocalAddress(_), bc::Instruction::Load ]), "{:#?}", caller.body);
+  }
+
+
+  #[test]
+  fn local_slots () {
+    let output = analyze_str("local_slots.ms", r#"
+      export fn locals () -> s32 {
+        let wide: s64 = 1;
+        let flag: bool = true;
+        0
+      }
+    "#);
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let context = &output.context;
+    let function = context.items.get(context.get_key_from_path(&[ "locals" ]).unwrap()).unwrap().ref_function().unwrap();
+
+    let mut cg = Codegen::new(context, "local_slots".to_owned(), (0, 0, 0).into());
+    let mut code = Vec::new();
+
+    generate_block(&mut cg, function.body.as_ref().unwrap(), &mut code);
+
+    assert_eq!(cg.local.slots.len(), 2);
+
+    // slots are created in declaration order, and are described by the same types as their CreateLocal instructions
+    let created: Vec<bc::TypeID> = code.iter().filter_map(|instruction| if let bc::Instruction::CreateLocal(ty) = instruction { Some(*ty) } else { None }).collect();
+    assert_eq!(cg.local.slots.iter().map(|slot| slot.ty).collect::<Vec<_>>(), created);
+
+    let wide = cg.local.get_slot(0.into()).unwrap();
+    assert_eq!(wide.name, Some(Identifier::from("wide")));
+    assert_eq!(cg.module.types.iter().find(|ty| ty.id == wide.ty).unwrap().data, bc::TypeData::Intrinsic(bc::IntrinsicType::S64));
+
+    let flag = cg.local.get_slot(1.into()).unwrap();
+    assert_eq!(flag.name, Some(Identifier::from("flag")));
+
+    let flag_ty = flag.ty;
+    assert_eq!(flag_ty, generate_type_def(&mut cg, context.bool_ty));
+
+    assert!(cg.local.get_slot(2.into()).is_none());
+  }
+}
@@ -9,7 +9,7 @@
 
 use std::{
   fmt::{ Display, Debug, Formatter, Result as FMTResult, },
-  str::{ from_utf8_unchecked as str_from_utf8_unchecked, },
+  str::{ FromStr, from_utf8_unchecked as str_from_utf8_unchecked, },
   slice::{ Iter as SliceIter, },
   cmp::{ Ordering, },
   hash::{ Hash, Hasher, },
@@ -67,6 +67,71 @@ impl Display for Version { fn fmt (&self, f: &mut Formatter) -> FMTResult { self
 
 
 
+/// Determines how the fields of a structure are arranged in memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum LayoutStrategy {
+  /// Fields are placed in declaration order, with padding inserted to align each one
+  #[default]
+  COrder,
+  /// Fields are placed largest alignment first to minimize padding,
+  /// the order of fields in memory no longer matches their declaration order
+  Optimized,
+}
+
+impl LayoutStrategy {
+  /// Get a LayoutStrategy in str form
+  pub fn get_name (self) -> &'static str {
+    match self {
+      LayoutStrategy::COrder => "c_order",
+      LayoutStrategy::Optimized => "optimized",
+    }
+  }
+}
+
+impl Display for LayoutStrategy {
+  fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    write!(f, "{}", self.get_name())
+  }
+}
+
+impl FromStr for LayoutStrategy {
+  type Err = String;
+
+  fn from_str (s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "c_order" => Ok(LayoutStrategy::COrder),
+      "optimized" => Ok(LayoutStrategy::Optimized),
+      _ => Err(format!("Invalid layout strategy `{}`, expected c_order or optimized", s))
+    }
+  }
+}
+
+
+
+/// The reason a string is not a valid Identifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentifierError {
+  /// The string contains no characters
+  Empty,
+  /// The string is longer than `Identifier::MAX_LENGTH` bytes
+  TooLong,
+  /// The string contains a non-ASCII character
+  NonAscii,
+  /// The string does not start with an ASCII letter or `_`
+  InvalidStart,
+}
+
+impl Display for IdentifierError {
+  fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    match self {
+      IdentifierError::Empty => write!(f, "Identifier is empty"),
+      IdentifierError::TooLong => write!(f, "Identifier is longer than the maximum of {} bytes", Identifier::MAX_LENGTH),
+      IdentifierError::NonAscii => write!(f, "Identifier contains non-ASCII characters"),
+      IdentifierError::InvalidStart => write!(f, "Identifier must start with an ASCII letter or `_`"),
+    }
+  }
+}
+
 /// A value identifying a particular language variable or type
 #[derive(Clone)]
 pub struct Identifier {
@@ -148,15 +213,49 @@ impl Identifier {
     self.len() == 0
   }
 
+  /// Create a new Identifier from a string, giving the reason if it is not valid
+  /// 
+  /// Unlike `From<&str>`, which produces an empty Identifier from invalid input,
+  /// this is suitable for names that are not known ahead of time to be valid
+  /// 
+  /// (`TryFrom<&str>` cannot be implemented alongside `From<&str>`)
+  pub fn try_new (s: &str) -> Result<Self, IdentifierError> {
+    Self::is_valid(s)?;
+
+    let mut i = Self::new();
+    i.set(&s);
+    Ok(i)
+  }
+
+  /// Determine if a string is a valid Identifier without constructing one,
+  /// giving the reason if it is not
+  pub fn is_valid (s: &str) -> Result<(), IdentifierError> {
+    if s.is_empty() { return Err(IdentifierError::Empty) }
+
+    if s.len() > Self::MAX_LENGTH { return Err(IdentifierError::TooLong) }
+
+    if !s.is_ascii() { return Err(IdentifierError::NonAscii) }
+
+    if !Self::is_valid_start(s.as_bytes()[0] as char) { return Err(IdentifierError::InvalidStart) }
+
+    Ok(())
+  }
+
+  /// Determine if a char is allowed as the first char of an Identifier,
+  /// which must be an ASCII letter or `_`, matching what the lexer accepts
+  #[inline]
+  pub fn is_valid_start (c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+  }
+
   /// Set the value of an Identifier
+  /// 
+  /// Returns false and leaves the Identifier unchanged if the value is not valid,
+  /// see `is_valid`
   pub fn set<S: AsRef<str>> (&mut self, s: &S) -> bool {
     let s = s.as_ref();
 
-    if s.len() > Self::MAX_LENGTH { return false }
-
-    for ch in s.chars() {
-      if !ch.is_ascii() { return false }
-    }
+    if Self::is_valid(s).is_err() { return false }
 
     self.vec.clear();
 
@@ -167,9 +266,10 @@ impl Identifier {
     true
   }
 
-  /// Append a char to the end of an Identifier if it will fit and is ASCII
+  /// Append a char to the end of an Identifier if it will fit and is ASCII,
+  /// and is a valid start char if the Identifier is empty
   pub fn append (&mut self, c: char) -> bool {
-    if self.len() < Self::MAX_LENGTH && c.is_ascii() {
+    if self.len() < Self::MAX_LENGTH && c.is_ascii() && (!self.is_empty() || Self::is_valid_start(c)) {
       self.vec.push(c as u8);
       
       true
@@ -225,16 +325,43 @@ impl Borrow<str> for Identifier {
 }
 
 /// An enum representing a floating point constant
+/// 
+/// Every finite value is stored exactly as `Norm`, including subnormals and `-0.0`;
+/// nothing is flushed to zero or rounded.
+/// Equality follows IEEE 754, so `Norm(-0.0) == Norm(0.0)`,
+/// use `to_bits` where the sign of zero must be preserved, such as when deduplicating constants
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub enum FloatingPoint {
-  /// Not a Number
+  /// Not a Number, the payload of the original value is not preserved
   NaN,
-  /// A normal number value
+  /// Any finite number value, including zero and subnormals
   Norm(f64),
   /// Infinity
   Inf,
 }
 
+impl FloatingPoint {
+  /// Get the f64 value represented by a FloatingPoint
+  pub fn value (self) -> f64 {
+    match self {
+      FloatingPoint::NaN => std::f64::NAN,
+      FloatingPoint::Norm(norm) => norm,
+      FloatingPoint::Inf => std::f64::INFINITY,
+    }
+  }
+
+  /// Get the raw bits of the f64 value represented by a FloatingPoint,
+  /// which unlike equality distinguishes `-0.0` from `0.0`
+  pub fn to_bits (self) -> u64 {
+    self.value().to_bits()
+  }
+
+  /// Determine if a FloatingPoint is a nonzero value too small to be represented in normal form
+  pub fn is_subnormal (self) -> bool {
+    if let FloatingPoint::Norm(norm) = self { norm.classify() == std::num::FpCategory::Subnormal } else { false }
+  }
+}
+
 impl Display for FloatingPoint {
   fn fmt (&self, f: &mut Formatter) -> FMTResult {
     match self {
@@ -300,6 +427,8 @@ pub enum Constant {
   Bool(bool),
   /// A string literal
   String(String),
+  /// A character literal
+  Char(char),
 }
 
 impl Display for Constant {
@@ -309,6 +438,7 @@ impl Display for Constant {
       Constant::Number(number) => Display::fmt(number, f),
       Constant::Bool(bool) => Display::fmt(bool, f),
       Constant::String(string) => write!(f, "\"{}\"", string),
+      Constant::Char(ch) => write!(f, "'{}'", ch.escape_debug()),
     }
   }
 }
@@ -325,6 +455,10 @@ impl From<String> for Constant {
   fn from (string: String) -> Constant { Constant::String(string) }
 }
 
+impl From<char> for Constant {
+  fn from (ch: char) -> Constant { Constant::Char(ch) }
+}
+
 
 /// An enum representing a language control word such as `fn` or `let`
 #[repr(u8)]
@@ -337,11 +471,16 @@ pub enum Keyword {
   Namespace,
   Global,
   Struct,
+  Enum,
   Type,
   Function,
   If,
   Else,
+  Match,
   Let,
+  Mut,
+  TypeOf,
+  Include,
 }
 
 impl Keyword {
@@ -355,19 +494,24 @@ impl Keyword {
       Export    => "export",
       Namespace => "ns",
       Struct    => "struct",
+      Enum      => "enum",
       Type      => "type",
       Global    => "global",
       Function  => "fn",
       If        => "if",
       Else      => "else",
+      Match     => "match",
       Let       => "let",
+      Mut       => "mut",
+      TypeOf    => "typeof",
+      Include   => "include",
     }
   }
 }
 
 /// An enum representing a language operator symbol such as `+` or `-`
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[allow(missing_docs)]
 pub enum Operator {
   Not,
@@ -378,6 +522,7 @@ pub enum Operator {
 
   DoubleColon,
   RightArrow,
+  FatArrow,
 
   AssignAdd,
   AssignSub,
@@ -399,9 +544,15 @@ pub enum Operator {
   Mul,
   Div,
   Rem,
+  Pow,
+
+  ShiftLeft,
+  ShiftRight,
+  BitNot,
 
   AddressOf,
   Dereference,
+  Question,
 
   Comma,
   Colon,
@@ -428,6 +579,7 @@ impl Operator {
 
       DoubleColon => "::",
       RightArrow => "->",
+      FatArrow => "=>",
     
       AssignAdd => "+=",
       AssignSub => "-=",
@@ -449,9 +601,15 @@ impl Operator {
       Mul => "*", 
       Div => "/", 
       Rem => "%", 
+      Pow => "**",
+
+      ShiftLeft => "<<",
+      ShiftRight => ">>",
+      BitNot => "~",
 
       AddressOf => "^",
       Dereference => "@",
+      Question => "?",
   
       Comma => ",", 
       Colon => ":", 
@@ -480,19 +638,24 @@ pub enum IdentifierValue {
 /// Note that values are stored in order of longest to shortest in order to facilitate the lexer's matching system
 pub const IDENTIFIER_VALUES: &[(&str, IdentifierValue)] = {
   &[
+    ("include", IdentifierValue::Keyword(Keyword::Include)),
     ("import", IdentifierValue::Keyword(Keyword::Import)),
     ("export", IdentifierValue::Keyword(Keyword::Export)),
     ("global", IdentifierValue::Keyword(Keyword::Global)),
     ("struct", IdentifierValue::Keyword(Keyword::Struct)),
+    ("typeof", IdentifierValue::Keyword(Keyword::TypeOf)),
     ("alias",  IdentifierValue::Keyword(Keyword::Alias)),
+    ("match",  IdentifierValue::Keyword(Keyword::Match)),
     ("false",  IdentifierValue::Constant(Constant::Bool(false))),
     ("true",   IdentifierValue::Constant(Constant::Bool(true))),
     ("type",   IdentifierValue::Keyword(Keyword::Type)),
+    ("enum",   IdentifierValue::Keyword(Keyword::Enum)),
     ("else",   IdentifierValue::Keyword(Keyword::Else)),
     ("null",   IdentifierValue::Constant(Constant::NullPointer)),
     ("nan",    IdentifierValue::Constant(Constant::Number(Number::FloatingPoint(FloatingPoint::NaN)))),
     ("inf",    IdentifierValue::Constant(Constant::Number(Number::FloatingPoint(FloatingPoint::Inf)))),
     ("let",    IdentifierValue::Keyword(Keyword::Let)),
+    ("mut",    IdentifierValue::Keyword(Keyword::Mut)),
     ("not",    IdentifierValue::Operator(Operator::Not)),
     ("and",    IdentifierValue::Operator(Operator::And)),
     ("xor",    IdentifierValue::Operator(Operator::Xor)),
@@ -514,6 +677,7 @@ pub const SYM_OPERATOR_VALUES: &[(&str, Operator)] = {
   &[
     ("::", DoubleColon),
     ("->", RightArrow),
+    ("=>", FatArrow),
     
     ("+=", AssignAdd),
     ("-=", AssignSub),
@@ -525,6 +689,13 @@ pub const SYM_OPERATOR_VALUES: &[(&str, Operator)] = {
     ("!=", NotEqual),
     (">=", GreaterOrEqual),
     ("<=", LesserOrEqual),
+
+    ("**", Pow),
+
+    // Must come before `<` and `>`, so `a << b` is not lexed as two comparisons
+    ("<<", ShiftLeft),
+    (">>", ShiftRight),
+
     (">", Greater),
     ("<", Lesser),
 
@@ -536,8 +707,11 @@ pub const SYM_OPERATOR_VALUES: &[(&str, Operator)] = {
     ("/",  Div),
     ("%",  Rem),
     
+    ("~", BitNot),
+
     ("^", AddressOf),
     ("@", Dereference),
+    ("?", Question),
 
     (",",  Comma),
     (":",  Colon),
@@ -559,6 +733,7 @@ pub const STATEMENT_KEYWORDS: &[Keyword] = {
   &[
     Let,
     If,
+    Match,
   ]
 };
 
@@ -572,6 +747,7 @@ pub const ITEM_KEYWORDS: &[Keyword] = {
     Alias,
     Export,
     Struct,
+    Enum,
     Type,
     Global,
     Function,
@@ -581,6 +757,9 @@ pub const ITEM_KEYWORDS: &[Keyword] = {
 /// The operator precedence used by all unary operators consuming an unbounded subexpression
 pub const UNARY_PRECEDENCE: usize = 70;
 
+/// The operator precedence used by unary `-`, which binds looser than `**` so that `-a ** b` is `-(a ** b)`
+pub const NEGATION_PRECEDENCE: usize = 64;
+
 /// A lookup table for Pratt operator precedences of binary operators
 pub const BINARY_PRECEDENCES: &[(Operator, usize)] = {
   use Operator::*;
@@ -597,6 +776,9 @@ pub const BINARY_PRECEDENCES: &[(Operator, usize)] = {
     (LesserOrEqual, 30),
     (GreaterOrEqual, 30),
 
+    (ShiftLeft, 40),
+    (ShiftRight, 40),
+
     (Add, 50),
     (Sub, 50),
     
@@ -604,10 +786,18 @@ pub const BINARY_PRECEDENCES: &[(Operator, usize)] = {
     (Div, 60),
     (Rem, 60),
 
+    // Lower than LeftParen so `a ** f(x)` calls `f`, and higher than NEGATION_PRECEDENCE so `-a ** b` is `-(a ** b)`
+    (Pow, 65),
+
     (LeftParen, 70),
   ]
 };
 
+/// Determine if a binary operator groups from the right, such that `a ** b ** c` is `a ** (b ** c)`
+pub const fn is_right_associative (operator: Operator) -> bool {
+  matches!(operator, Operator::Pow)
+}
+
 /// Use a lookup table to get the Pratt operator precedence of a binary operator
 pub const fn get_binary_precedence (operator: Operator) -> usize {
   let mut i = 0;
@@ -619,4 +809,112 @@ pub const fn get_binary_precedence (operator: Operator) -> usize {
 
     i += 1;
   }
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn pow_operator () {
+    assert_eq!(Operator::Pow.value(), "**");
+    assert_eq!(SYM_OPERATOR_VALUES.iter().find(|(substr, _)| "**".starts_with(substr)).map(|&(_, op)| op), Some(Operator::Pow));
+    assert!(get_binary_precedence(Operator::Pow) > get_binary_precedence(Operator::Mul));
+    assert!(get_binary_precedence(Operator::Pow) < get_binary_precedence(Operator::LeftParen));
+    assert!(is_right_associative(Operator::Pow));
+    assert!(!is_right_associative(Operator::Sub));
+  }
+
+  #[test]
+  fn shift_operators () {
+    let lookup = |source: &str| SYM_OPERATOR_VALUES.iter().find(|(substr, _)| source.starts_with(substr)).map(|&(_, op)| op);
+
+    assert_eq!(lookup("<< b"), Some(Operator::ShiftLeft));
+    assert_eq!(lookup(">> b"), Some(Operator::ShiftRight));
+    assert_eq!(lookup("<= b"), Some(Operator::LesserOrEqual));
+    assert_eq!(lookup(">= b"), Some(Operator::GreaterOrEqual));
+    assert_eq!(lookup("< b"), Some(Operator::Lesser));
+    assert_eq!(lookup("> b"), Some(Operator::Greater));
+    assert_eq!(lookup("~a"), Some(Operator::BitNot));
+
+    assert!(get_binary_precedence(Operator::ShiftLeft) > get_binary_precedence(Operator::Lesser));
+    assert!(get_binary_precedence(Operator::ShiftLeft) < get_binary_precedence(Operator::Add));
+    assert_eq!(get_binary_precedence(Operator::ShiftLeft), get_binary_precedence(Operator::ShiftRight));
+  }
+
+  #[test]
+  fn identifier_is_valid () {
+    assert_eq!(Identifier::is_valid("valid_name"), Ok(()));
+    assert_eq!(Identifier::is_valid("_0"), Ok(()));
+    assert_eq!(Identifier::is_valid(&"x".repeat(Identifier::MAX_LENGTH)), Ok(()));
+
+    assert_eq!(Identifier::is_valid(""), Err(IdentifierError::Empty));
+    assert_eq!(Identifier::is_valid(&"x".repeat(Identifier::MAX_LENGTH + 1)), Err(IdentifierError::TooLong));
+    assert_eq!(Identifier::is_valid("caf\u{e9}"), Err(IdentifierError::NonAscii));
+    assert_eq!(Identifier::is_valid("9lives"), Err(IdentifierError::InvalidStart));
+  }
+
+  #[test]
+  fn identifier_start_char () {
+    let mut ident = Identifier::new();
+
+    assert!(!ident.set(&"9abc"));
+    assert!(ident.is_empty());
+    assert!(!ident.append('9'));
+    assert!(ident.is_empty());
+
+    assert!(ident.set(&"abc9"));
+    assert_eq!(ident.as_ref(), "abc9");
+    assert!(ident.append('9'));
+    assert_eq!(ident.as_ref(), "abc99");
+
+    let mut ident = Identifier::new();
+    assert!(ident.append('_'));
+    assert!(ident.append('0'));
+    assert_eq!(ident.as_ref(), "_0");
+
+    assert_eq!(Identifier::from("9abc").as_ref(), "");
+  }
+
+  #[test]
+  fn identifier_try_new () {
+    assert_eq!(Identifier::try_new("valid_name"), Ok(Identifier::from("valid_name")));
+
+    assert_eq!(Identifier::try_new(""), Err(IdentifierError::Empty));
+    assert_eq!(Identifier::try_new("x".repeat(Identifier::MAX_LENGTH + 1).as_str()), Err(IdentifierError::TooLong));
+    assert_eq!(Identifier::try_new("caf\u{e9}"), Err(IdentifierError::NonAscii));
+    assert_eq!(Identifier::try_new("9lives"), Err(IdentifierError::InvalidStart));
+  }
+
+  #[test]
+  fn char_constant_display () {
+    assert_eq!(Constant::from('a').to_string(), "'a'");
+    assert_eq!(Constant::from('\n').to_string(), "'\\n'");
+    assert_eq!(Constant::from('\'').to_string(), "'\\''");
+    assert_eq!(Constant::from('\u{1F600}').to_string(), "'\u{1F600}'");
+  }
+
+  #[test]
+  fn float_negative_zero () {
+    let neg = FloatingPoint::from(-0.0);
+    let pos = FloatingPoint::from(0.0);
+
+    assert_eq!(neg, FloatingPoint::Norm(-0.0));
+    assert_eq!(neg, pos);
+    assert_ne!(neg.to_bits(), pos.to_bits());
+    assert!(neg.value().is_sign_negative());
+    assert!(!neg.is_subnormal());
+  }
+
+  #[test]
+  fn float_subnormal_round_trip () {
+    let value = std::f64::MIN_POSITIVE / 4.0;
+    let float = FloatingPoint::from(value);
+
+    assert!(float.is_subnormal());
+    assert_eq!(float.to_bits(), value.to_bits());
+    assert_eq!(float.value(), value);
+    assert!(!FloatingPoint::from(std::f64::MIN_POSITIVE).is_subnormal());
+  }
 }
\ No newline at end of file
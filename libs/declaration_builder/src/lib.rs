@@ -171,15 +171,26 @@ fn decl_ns (ctx: &Context, exported_items: &HashSet<ContextKey>, ns_name: Identi
 fn decl_ty (ctx: &Context, base_key: ContextKey, ty_name: Identifier, ty_key: ContextKey) -> ast::Item {
   let ty = ctx.items.get(ty_key).unwrap().ref_type().unwrap();
 
-  if let Some(TypeData::Structure { field_names, field_types }) = ty.data.as_ref() {
-    let fields =
-      field_names.iter().zip(field_types.iter())
-        .map(|(field_name, &field_type)| ast::LocalDeclaration::no_src(field_name.clone(), make_texpr(ctx, base_key, field_type)))
-        .collect();
-
-    ast::Item::no_src(ast::ItemData::Struct { identifier: ty_name, fields, terminal: true })
-  } else {
-    unreachable!()
+  match ty.data.as_ref() {
+    Some(TypeData::Structure { field_names, field_types }) => {
+      let fields =
+        field_names.iter().zip(field_types.iter())
+          .map(|(field_name, &field_type)| ast::LocalDeclaration::no_src(field_name.clone(), make_texpr(ctx, base_key, field_type)))
+          .collect();
+
+      ast::Item::no_src(ast::ItemData::Struct { identifier: ty_name, fields, terminal: true })
+    },
+
+    Some(TypeData::Enum { variant_names, variant_types }) => {
+      let variants =
+        variant_names.iter().zip(variant_types.iter())
+          .map(|(variant_name, &variant_type)| ast::EnumVariant::no_src(variant_name.clone(), variant_type.map(|ty| make_texpr(ctx, base_key, ty))))
+          .collect();
+
+      ast::Item::no_src(ast::ItemData::Enum { identifier: ty_name, variants })
+    },
+
+    _ => unreachable!()
   }
 }
 
@@ -201,7 +212,8 @@ fn decl_global (ctx: &Context, base_key: ContextKey, global_name: Identifier, gl
     ast::ItemData::Global {
       identifier: global_name,
       explicit_type: make_texpr(ctx, base_key, global.ty.unwrap()),
-      initializer: None
+      initializer: None,
+      mutable: global.is_mutable,
     }
   )
 }
@@ -220,7 +232,8 @@ fn decl_function (ctx: &Context, base_key: ContextKey, function_name: Identifier
       identifier: function_name,
       parameters,
       return_type: function.return_ty.map(|ty| make_texpr(ctx, base_key, ty)),
-      body: None
+      body: None,
+      external: false
     }
   )
 }
@@ -247,10 +260,13 @@ fn make_texpr (ctx: &Context, base_key: ContextKey, ty_key: ContextKey) -> ast::
 
   let texpr_data = match ty.data.as_ref().unwrap() {
     | TypeData::Structure { .. }
+    | TypeData::Enum      { .. }
     | TypeData::Primitive { .. }
     => ast::TypeExpressionData::Path(make_path(ctx, base_key, ty_key)),
 
     &TypeData::Pointer(value_ty) => ast::TypeExpressionData::Pointer(box make_texpr(ctx, base_key, value_ty)),
+
+    &TypeData::Optional(payload_ty) => ast::TypeExpressionData::Optional(box make_texpr(ctx, base_key, payload_ty)),
     
     TypeData::Function { parameter_types, return_type } => {
       let parameter_types = parameter_types.iter().map(|&ty| make_texpr(ctx, base_key, ty)).collect();
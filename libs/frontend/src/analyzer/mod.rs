@@ -4,16 +4,21 @@ use mod_utils::{ UnwrapUnchecked, };
 use mod_common::{ Identifier, };
 
 use crate::{
-  session::{ SESSION, MessageKind, Message, },
+  session::{ SESSION, MessageKind, Message, DEFAULT_INT_TYPE, DEFAULT_FLOAT_TYPE, },
   source::{ SourceRegion, },
   ast::{ Item, },
-  ctx::{ Context, Module, Namespace, ContextItem, ContextItemKind, ContextKey, LocalContext, },
+  ctx::{ Context, Module, Namespace, ContextItem, ContextItemKind, ContextKey, LocalContext, TypeData, PrimitiveType, },
 };
 
 
 pub mod passes;
 
 
+/// A custom lint function run by an Analyzer on each Item of the main module after all other passes,
+/// which may emit Messages through the Analyzer
+pub type Lint = fn (&Analyzer, &Item);
+
+
 /// The core interface structure for semantic analysis
 pub struct Analyzer {
   /// All contextual information used by a semantic analyzer
@@ -24,6 +29,14 @@ pub struct Analyzer {
   pub local_context: Option<LocalContext>,
   /// A counter used to track initialization order of Globals
   pub global_rank_counter: usize,
+  /// The nesting depth of the expression currently being analyzed
+  pub expression_depth: usize,
+  /// Whether the outermost expression currently being analyzed has already exceeded `MAX_EXPRESSION_DEPTH`
+  pub expression_depth_exceeded: bool,
+  /// The identifier of the Function required to be exported as an executable entry point, if entry point checking is enabled
+  pub entry_point: Option<Identifier>,
+  /// Custom lints run on every Item of the main module once analysis is complete
+  pub lints: Vec<Lint>,
 }
 
 
@@ -33,6 +46,15 @@ impl Default for Analyzer {
 
 
 impl Analyzer {
+  /// The maximum nesting depth of expressions an Analyzer will descend into before producing an error
+  /// 
+  /// Expressions are analyzed recursively, with several stack frames per level of nesting,
+  /// so this is kept low enough that an expression at the limit fits in the default 2 MiB thread stack of a debug build
+  pub const MAX_EXPRESSION_DEPTH: usize = 128;
+
+  /// The identifier used for an entry point when none is specified
+  pub const DEFAULT_ENTRY_POINT: &'static str = "main";
+
   /// Create a new semantic analyzer
   pub fn new () -> Self {
     let context = Context::default();
@@ -43,12 +65,77 @@ impl Analyzer {
       active_mod_and_ns,
       local_context: None,
       global_rank_counter: 0,
+      expression_depth: 0,
+      expression_depth_exceeded: false,
+      entry_point: None,
+      lints: Vec::new(),
+    }
+  }
+
+  /// Set the canonical name of the main Module being analyzed
+  /// 
+  /// The default is `main`
+  pub fn with_main_module_name<I: Into<Identifier>> (mut self, identifier: I) -> Self {
+    self.context.set_main_module_name(identifier.into());
+    self
+  }
+
+  fn find_core_primitive (&self, identifier: &str, predicate: fn (&PrimitiveType) -> bool) -> Option<ContextKey> {
+    self.context.core_bs.get_entry(identifier)
+      .filter(|&key| matches!(
+        self.context.items.get(key).and_then(ContextItem::ref_type).and_then(|ty| ty.data.as_ref()),
+        Some(TypeData::Primitive(prim)) if predicate(prim)
+      ))
+  }
+
+  /// Apply the default integer and floating point types configured on the SESSION,
+  /// reporting an Error and keeping `DEFAULT_INT_TYPE` or `DEFAULT_FLOAT_TYPE` if either is not a core type of the right kind
+  fn apply_session_defaults (&mut self) {
+    if let Some(key) = self.find_default_primitive(SESSION.default_int_type(), DEFAULT_INT_TYPE, "integer", |prim| matches!(prim, PrimitiveType::Integer { .. })) {
+      self.context.concrete_int_ty = key;
+    }
+
+    if let Some(key) = self.find_default_primitive(SESSION.default_float_type(), DEFAULT_FLOAT_TYPE, "floating point", |prim| matches!(prim, PrimitiveType::FloatingPoint { .. })) {
+      self.context.concrete_float_ty = key;
     }
   }
 
+  fn find_default_primitive (&self, identifier: &str, fallback: &str, kind: &str, predicate: fn (&PrimitiveType) -> bool) -> Option<ContextKey> {
+    let key = self.find_core_primitive(identifier, predicate);
+
+    if key.is_none() {
+      let article = if kind.starts_with(|ch| "aeiou".contains(ch)) { "an" } else { "a" };
+
+      SESSION.error(SourceRegion::ANONYMOUS, format!(
+        "`{}` is not {} {} type in the core namespace, and cannot be the default {} type; `{}` will be used instead",
+        identifier, article, kind, kind, fallback
+      ));
+    }
+
+    key
+  }
+
+  /// Enable entry point checking for an Analyzer,
+  /// requiring the main module to export a Function with the given identifier
+  /// (or `main` if none is given) that takes no parameters and returns `void` or an integer
+  pub fn with_entry_point (mut self, identifier: Option<Identifier>) -> Self {
+    self.entry_point.replace(identifier.unwrap_or_else(|| Self::DEFAULT_ENTRY_POINT.into()));
+    self
+  }
+
+  /// Register a custom Lint to be run by an Analyzer
+  /// 
+  /// Lints are run in registration order, on each Item including those nested in Namespaces
+  pub fn with_lint (mut self, lint: Lint) -> Self {
+    self.lints.push(lint);
+    self
+  }
+
   /// Run a semantic analyzer on its ast,
   /// consuming the analyzer in the process
   pub fn analyze (mut self, mut ast: Vec<Item>) -> (Context, Vec<Item>) {
+    self.apply_session_defaults();
+
     self.run_passes(&mut ast);
 
     (self.context, ast)
@@ -197,9 +284,10 @@ impl Analyzer {
       let shadowed_location = self.get_active_namespace().local_bindings.get_bind_location(shadowed_key).expect("Internal error, shadowed item has no bind location");
 
       self.error(origin, format!(
-        "{} `{}` shadows existing {} in `{}`, defined at [{}]",
-        new_item.kind(), identifier, shadowed_kind, self.get_active_namespace().canonical_name, shadowed_location
-      ));
+        "{} `{}` shadows existing {} in `{}`",
+        new_item.kind(), identifier, shadowed_kind, self.get_active_namespace().canonical_name
+      ))
+      .relate(shadowed_location, format!("previous definition of `{}` here", identifier));
     }
 
     let key = (|| {
@@ -83,6 +83,7 @@ pub fn bind_top_level (analyzer: &mut Analyzer, items: &[Item], pseudonyms: &mut
       | ItemData::Import    { .. }
       | ItemData::Namespace { .. }
       | ItemData::Struct    { .. }
+      | ItemData::Enum      { .. }
       | ItemData::Global    { .. }
       | ItemData::Function  { .. }
       => {
@@ -115,6 +116,19 @@ fn bind_item<'a> (analyzer: &mut Analyzer, item: &'a Item, pseudonyms: &mut Vec<
   match &item.data {
     ItemData::Import { identifier, new_name, ast_key } => {
       let module_key = if let Some(&module_key) = analyzer.context.modules.get(identifier) {
+        // Each Module is only bound once, so cycles cannot loop, but they are still reported
+        // as they usually indicate a dependency structure that should be split up
+        if let Some(cycle_start) = analyzer.active_mod_and_ns.iter().position(|&(active_key, _)| active_key == module_key) {
+          let cycle: Vec<String> =
+            analyzer.active_mod_and_ns[cycle_start..].iter()
+              .map(|&(active_key, _)| active_key)
+              .chain(std::iter::once(module_key))
+              .map(|key| format!("`{}`", analyzer.context.get_item_canonical_name(key).unwrap()))
+              .collect();
+
+          analyzer.warning(item.origin, format!("Import cycle detected: {}", cycle.join(" -> ")));
+        }
+
         module_key
       } else {
         let module_key = analyzer.create_module(identifier.clone(), item.origin);
@@ -130,6 +144,10 @@ fn bind_item<'a> (analyzer: &mut Analyzer, item: &'a Item, pseudonyms: &mut Vec<
         module_key
       };
 
+      let imports = &mut analyzer.get_active_module_mut().imports;
+
+      if !imports.contains(&module_key) { imports.push(module_key) }
+
       let new_name = if let Some(new_name) = new_name { new_name } else { identifier };
 
       let ns = analyzer.get_active_namespace_mut();
@@ -140,6 +158,10 @@ fn bind_item<'a> (analyzer: &mut Analyzer, item: &'a Item, pseudonyms: &mut Vec<
     },
 
     ItemData::Namespace { identifier, items, .. } => {
+      if items.is_empty() {
+        analyzer.notice(item.origin, format!("Namespace `{}` is empty", identifier));
+      }
+
       let new_ns = analyzer.create_item(
         identifier.to_owned(),
         Namespace::new(
@@ -160,27 +182,35 @@ fn bind_item<'a> (analyzer: &mut Analyzer, item: &'a Item, pseudonyms: &mut Vec<
       (identifier, new_ns)
     },
 
-    ItemData::Struct { identifier, .. } => (identifier, analyzer.create_item(
-      identifier.to_owned(),
-      Type::new(
-        Some(analyzer.get_active_module_key()),
-        Some(analyzer.get_active_namespace_key()),
-        Some(identifier.to_owned()),
-        item.origin,
-        None
-      ),
-      item.origin
-    )),
+    ItemData::Struct { identifier, fields, .. } => {
+      if fields.is_empty() {
+        analyzer.notice(item.origin, format!("Struct `{}` has no fields, it will have a size of 0", identifier));
+      }
 
-    ItemData::Global { identifier, .. } => {
-      let rank =  analyzer.get_global_rank();
       (identifier, analyzer.create_item(
         identifier.to_owned(),
-        Global::new(
-          analyzer.get_active_module_key(),
-          analyzer.get_active_namespace_key(),
-          rank,
-          identifier.to_owned(),
+        Type::new(
+          Some(analyzer.get_active_module_key()),
+          Some(analyzer.get_active_namespace_key()),
+          Some(identifier.to_owned()),
+          item.origin,
+          None
+        ),
+        item.origin
+      ))
+    },
+
+    ItemData::Enum { identifier, variants } => {
+      if variants.is_empty() {
+        analyzer.notice(item.origin, format!("Enum `{}` has no variants, it cannot be constructed", identifier));
+      }
+
+      (identifier, analyzer.create_item(
+        identifier.to_owned(),
+        Type::new(
+          Some(analyzer.get_active_module_key()),
+          Some(analyzer.get_active_namespace_key()),
+          Some(identifier.to_owned()),
           item.origin,
           None
         ),
@@ -188,17 +218,44 @@ fn bind_item<'a> (analyzer: &mut Analyzer, item: &'a Item, pseudonyms: &mut Vec<
       ))
     },
 
-    ItemData::Function { identifier, .. } => (identifier, analyzer.create_item(
-      identifier.to_owned(),
-      Function::new(
+    &ItemData::Global { ref identifier, mutable, .. } => {
+      let rank =  analyzer.get_global_rank();
+
+      let mut global = Global::new(
+        analyzer.get_active_module_key(),
+        analyzer.get_active_namespace_key(),
+        rank,
+        identifier.to_owned(),
+        item.origin,
+        None
+      );
+
+      global.is_mutable = mutable;
+
+      (identifier, analyzer.create_item(
+        identifier.to_owned(),
+        global,
+        item.origin
+      ))
+    },
+
+    &ItemData::Function { ref identifier, external, .. } => {
+      let mut function = Function::new(
         analyzer.get_active_module_key(),
         analyzer.get_active_namespace_key(),
         identifier.to_owned(),
         item.origin,
         None
-      ),
-      item.origin
-    )),
+      );
+
+      function.is_external = external;
+
+      (identifier, analyzer.create_item(
+        identifier.to_owned(),
+        function,
+        item.origin
+      ))
+    },
 
     | ItemData::Alias { .. }
     | ItemData::Export { .. }
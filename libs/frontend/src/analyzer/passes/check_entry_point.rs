@@ -0,0 +1,64 @@
+use crate::{
+  source::{ SourceRegion, },
+  ctx::{ ContextItem, Function, TypeData, PrimitiveType, TypeDisplay, },
+};
+
+use super::{
+  Analyzer,
+};
+
+
+
+/// Verifies that the main module exports a Function usable as an executable entry point,
+/// if an entry point identifier has been configured for the Analyzer
+pub fn check_entry_point (analyzer: &mut Analyzer) {
+  let entry_ident = if let Some(entry_ident) = analyzer.entry_point.as_ref() { entry_ident.clone() } else { return };
+
+  let main_ns = analyzer.context.items.get(analyzer.context.main_ns).unwrap().ref_namespace().unwrap();
+
+  let entry_key = if let Some(entry_key) = main_ns.export_bindings.get_entry(&entry_ident) {
+    entry_key
+  } else {
+    if let Some(local_key) = main_ns.local_bindings.get_entry(&entry_ident) {
+      let location = main_ns.local_bindings.get_bind_location(local_key).unwrap_or(SourceRegion::ANONYMOUS);
+
+      analyzer.error(location, format!("Entry point `{}` must be exported from the main module", entry_ident));
+    } else {
+      analyzer.error(SourceRegion::ANONYMOUS, format!("Missing entry point, expected the main module to export a function `{}`", entry_ident));
+    }
+
+    return
+  };
+
+  let function: &Function = if let ContextItem::Function(function) = analyzer.context.items.get(entry_key).unwrap() {
+    function
+  } else {
+    let location = main_ns.export_bindings.get_bind_location(entry_key).unwrap_or(SourceRegion::ANONYMOUS);
+
+    analyzer.error(location, format!("Entry point `{}` must be a function", entry_ident));
+
+    return
+  };
+
+  // If the type is missing there was already an error linking the signature
+  let fn_ty = if let Some(fn_ty) = function.ty { fn_ty } else { return };
+
+  let valid_params = function.params.is_empty();
+
+  let valid_return = match function.return_ty {
+    None => true,
+    Some(return_ty) => matches!(
+      analyzer.context.items.get(return_ty).unwrap().ref_type().and_then(|ty| ty.data.as_ref()),
+      Some(TypeData::Primitive(PrimitiveType::Void | PrimitiveType::Integer { .. }))
+    )
+  };
+
+  if !(valid_params && valid_return) {
+    analyzer.error(function.origin, format!(
+      "Entry point `{}` has type `{}`, \
+       but an entry point must take no parameters and return either `void` or an integer",
+      entry_ident,
+      TypeDisplay { ty_key: fn_ty, context: &analyzer.context }
+    ));
+  }
+}
@@ -1,6 +1,10 @@
+use std::{
+  collections::{ HashMap, HashSet, },
+};
+
 use crate::{
   source::{ SourceRegion, },
-  ctx::{ Context, ContextItem, Global, ContextKey, },
+  ctx::{ Context, ContextItem, ContextKey, },
   ir::{ Expression, ExpressionData, Statement, StatementData, Reference, Conditional, ConditionalBranch, Block, },
 };
 
@@ -10,190 +14,321 @@ use super::{
 
 
 
-#[repr(u8)]
-#[derive(PartialEq)]
-enum GlobalInitError {
-  Circlular(SourceRegion),
-  Rank { reference_location: SourceRegion, referenced_global: ContextKey },
-  External { reference_location: SourceRegion, referenced_global: ContextKey },
+/// A global referenced by an initializer, either directly or through the body of a function it calls
+struct GlobalReference {
+  global: ContextKey,
+  location: SourceRegion,
 }
 
+/// Collects the globals referenced by an initializer,
+/// following the bodies of any functions referenced along the way
+struct ReferenceCollector<'c> {
+  ctx: &'c Context,
+  references: Vec<GlobalReference>,
+  visited_functions: HashSet<ContextKey>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+  InProgress,
+  Done,
+}
 
-/// Performs analysis at the global, block, statement & expression levels, providing typechecking and IR generation
+
+/// Orders the globals of the main module so that each is initialized after the globals its initializer depends on,
+/// and checks initializers for circular dependencies and references to external module data
+///
+/// Global ranks are reassigned to match the new order, in which the main module globals of `Context::globals` are also stored
 pub fn check_global_initializers (analyzer: &mut Analyzer) {
+  let mut dependencies = HashMap::new();
+  let mut main_globals = Vec::new();
+
   for &global_key in analyzer.context.globals.iter() {
-    let global: &Global = analyzer.context.items.get(global_key).unwrap().ref_global().unwrap();
-    
+    let global = analyzer.context.items.get(global_key).unwrap().ref_global().unwrap();
+
     if global.parent_module != analyzer.context.main_mod { continue }
 
+    let mut collector = ReferenceCollector { ctx: &analyzer.context, references: Vec::new(), visited_functions: HashSet::new() };
+
     if let Some(initializer_ir) = &global.initializer {
-      if let Some(error) = check_expression_ir(&analyzer.context, global.rank, global_key, initializer_ir) {
-        match error {
-          GlobalInitError::Circlular(reference_location) => {
-            analyzer.error(global.origin, "Circular reference found in global initializer".to_owned())
-            .append(reference_location, "This expression references the global being initialized".to_owned());
-          },
-
-          GlobalInitError::Rank { reference_location, referenced_global } => {
-            let referenced_global = analyzer.context.items.get(referenced_global).unwrap().ref_global().unwrap();
-
-            analyzer.error(global.origin, "Global initializer references uninitialized data".to_owned())
-            .append(reference_location, format!(
-              "This expression references a global `{}` which is initialized later",
-              referenced_global.canonical_name
-            ))
-            .append(referenced_global.origin, "The referenced global is later initialized here".to_owned());
-          },
-
-          GlobalInitError::External { reference_location, referenced_global } => {
-            let referenced_global = analyzer.context.items.get(referenced_global).unwrap().ref_global().unwrap();
-            let parent_module = analyzer.context.items.get(referenced_global.parent_module).unwrap().ref_module().unwrap();
-            
-            analyzer.warning(global.origin, "Global initializer references external module data. Module initialization order cannot be guaranteed by the compiler".to_owned())
-            .append(reference_location, format!(
-              "This expression references a global `{}` which is imported from external module `{}`",
-              referenced_global.canonical_name,
-              parent_module.canonical_name
-            ))
-            .append(parent_module.origin.unwrap(), "External module originally imported here".to_owned());
-          },
-        }
+      collect_expression_refs(&mut collector, initializer_ir);
+    }
+
+    let (local_refs, external_refs): (Vec<_>, Vec<_>) = collector.references.into_iter().partition(|reference| {
+      analyzer.context.items.get(reference.global).unwrap().ref_global().unwrap().parent_module == analyzer.context.main_mod
+    });
+
+    if let Some(GlobalReference { global: referenced_global, location }) = external_refs.first() {
+      let referenced_global = analyzer.context.items.get(*referenced_global).unwrap().ref_global().unwrap();
+      let parent_module = analyzer.context.items.get(referenced_global.parent_module).unwrap().ref_module().unwrap();
+
+      analyzer.warning(global.origin, "Global initializer references external module data. Module initialization order cannot be guaranteed by the compiler".to_owned())
+      .append(*location, format!(
+        "This expression references a global `{}` which is imported from external module `{}`",
+        referenced_global.canonical_name,
+        parent_module.canonical_name
+      ))
+      .append(parent_module.origin.unwrap(), "External module originally imported here".to_owned());
+    }
+
+    dependencies.insert(global_key, local_refs);
+    main_globals.push(global_key);
+  }
+
+  let mut visits = HashMap::new();
+  let mut path = Vec::new();
+  let mut order = Vec::with_capacity(main_globals.len());
+  let mut cyclic = HashSet::new();
+
+  // Globals are visited in source order, so independent globals keep their relative order
+  for &global_key in main_globals.iter() {
+    order_global(analyzer, &dependencies, &mut visits, &mut path, &mut order, &mut cyclic, global_key);
+  }
+
+  for (rank, &global_key) in order.iter().enumerate() {
+    analyzer.context.items.get_mut(global_key).unwrap().mut_global().unwrap().rank = rank;
+  }
+
+  check_initialization_order(analyzer, &main_globals, &dependencies, &cyclic);
+
+  let mut ordered = order.into_iter();
+
+  for global_key in analyzer.context.globals.iter_mut() {
+    if dependencies.contains_key(&*global_key) {
+      *global_key = ordered.next().unwrap();
+    }
+  }
+}
+
+
+/// Depth first traversal of the dependencies of a global initializer,
+/// adding a global to the order once all of its dependencies have been added,
+/// and adding the globals of any cycle reported along the way to `cyclic`
+fn order_global (
+  analyzer: &Analyzer,
+  dependencies: &HashMap<ContextKey, Vec<GlobalReference>>,
+  visits: &mut HashMap<ContextKey, Visit>,
+  path: &mut Vec<(ContextKey, SourceRegion)>,
+  order: &mut Vec<ContextKey>,
+  cyclic: &mut HashSet<ContextKey>,
+  global_key: ContextKey,
+) {
+  if visits.contains_key(&global_key) { return }
+
+  visits.insert(global_key, Visit::InProgress);
+
+  for reference in dependencies[&global_key].iter() {
+    match visits.get(&reference.global) {
+      Some(Visit::Done) => { },
+
+      Some(Visit::InProgress) => report_cycle(analyzer, path, cyclic, global_key, reference),
+
+      None => {
+        path.push((global_key, reference.location));
+        order_global(analyzer, dependencies, visits, path, order, cyclic, reference.global);
+        path.pop();
+      }
+    }
+  }
+
+  visits.insert(global_key, Visit::Done);
+  order.push(global_key);
+}
+
+
+/// Verify that no initializer reads a global which is initialized after it
+///
+/// The ordering of globals only leaves a global initialized before one it reads when both are part of a cycle,
+/// so the `cyclic` globals, whose cycles have already been reported, are skipped
+fn check_initialization_order (
+  analyzer: &Analyzer,
+  main_globals: &[ContextKey],
+  dependencies: &HashMap<ContextKey, Vec<GlobalReference>>,
+  cyclic: &HashSet<ContextKey>,
+) {
+  let get_global = |key: ContextKey| analyzer.context.items.get(key).unwrap().ref_global().unwrap();
+
+  for &global_key in main_globals.iter() {
+    if cyclic.contains(&global_key) { continue }
+
+    let global = get_global(global_key);
+
+    for reference in dependencies[&global_key].iter() {
+      let referenced_global = get_global(reference.global);
+
+      if referenced_global.rank > global.rank {
+        analyzer.error(global.origin, "Global initializer reads uninitialized data".to_owned())
+        .append(reference.location, format!(
+          "This expression reads the global `{}` before it is initialized",
+          referenced_global.canonical_name
+        ))
+        .append(referenced_global.origin, "The referenced global is initialized later here".to_owned());
       }
     }
   }
 }
 
 
-fn check_expression_ir (ctx: &Context, global_rank: usize, global_key: ContextKey, ir: &Expression) -> Option<GlobalInitError> {
+fn report_cycle (
+  analyzer: &Analyzer,
+  path: &[(ContextKey, SourceRegion)],
+  cyclic: &mut HashSet<ContextKey>,
+  global_key: ContextKey,
+  reference: &GlobalReference,
+) {
+  let get_global = |key: ContextKey| analyzer.context.items.get(key).unwrap().ref_global().unwrap();
+
+  cyclic.insert(global_key);
+
+  if reference.global == global_key {
+    analyzer.error(get_global(global_key).origin, "Circular reference found in global initializer".to_owned())
+    .append(reference.location, "This expression references the global being initialized".to_owned());
+
+    return
+  }
+
+  // The cycle begins where the referenced global was entered in the current path
+  let start = path.iter().position(|&(key, _)| key == reference.global).expect("Internal error, global initializer cycle does not begin in the current path");
+
+  let mut links = path[start ..].to_vec();
+  links.push((global_key, reference.location));
+
+  cyclic.extend(links.iter().map(|&(key, _)| key));
+
+  let first = get_global(reference.global);
+
+  let message = analyzer.error(first.origin, "Circular dependency found between global initializers".to_owned());
+
+  for (index, &(key, location)) in links.iter().enumerate() {
+    let next_key = links.get(index + 1).map(|&(next_key, _)| next_key).unwrap_or(reference.global);
+
+    message.append(location, format!(
+      "The initializer of `{}` depends on `{}` here",
+      get_global(key).canonical_name,
+      get_global(next_key).canonical_name,
+    ));
+  }
+}
+
+
+fn collect_expression_refs (rc: &mut ReferenceCollector, ir: &Expression) {
   match &ir.data {
     &ExpressionData::Reference(Reference::Global(context_key)) => {
-      if context_key == global_key {
-        Some(GlobalInitError::Circlular(ir.origin))
-      } else {
-        match ctx.items.get(context_key).unwrap() {
-          ContextItem::Global(referenced_global) => if referenced_global.parent_module != ctx.main_mod {
-            Some(GlobalInitError::External { reference_location: ir.origin, referenced_global: context_key })
-          } else if referenced_global.rank > global_rank {
-            Some(GlobalInitError::Rank { reference_location: ir.origin, referenced_global: context_key })
-          } else {
-            None
-          },
-
-
-          ContextItem::Function(function) => if let Some(body) = &function.body {
-            check_block_ir(ctx, global_rank, global_key, body)
-          } else {
-            None
-          },
-
-
-          // Cannot contain a global reference
-          | ContextItem::Module(_)
-          | ContextItem::Namespace(_)
-          | ContextItem::Type(_)
-          => None
-        }
+      let ctx = rc.ctx;
+
+      match ctx.items.get(context_key).unwrap() {
+        ContextItem::Global(_) => if !rc.references.iter().any(|reference| reference.global == context_key) {
+          rc.references.push(GlobalReference { global: context_key, location: ir.origin });
+        },
+
+
+        ContextItem::Function(function) => if rc.visited_functions.insert(context_key) {
+          if let Some(body) = &function.body {
+            collect_block_refs(rc, body)
+          }
+        },
+
+
+        // Cannot contain a global reference
+        | ContextItem::Module(_)
+        | ContextItem::Namespace(_)
+        | ContextItem::Type(_)
+        => { }
       }
     },
 
 
     | ExpressionData::Coerce(inner)
     | ExpressionData::Unary { operand: inner, .. }
-    => check_expression_ir(ctx, global_rank, global_key, inner),
+    | ExpressionData::Variant { payload: Some(inner), .. }
+    | ExpressionData::IsVariant { operand: inner, .. }
+    | ExpressionData::Payload { operand: inner, .. }
+    => collect_expression_refs(rc, inner),
 
 
-    ExpressionData::Binary { left, right, .. }
-    => check_expression_ir(ctx, global_rank, global_key, left).or_else(|| check_expression_ir(ctx, global_rank, global_key, right)),
+    ExpressionData::Binary { left, right, .. } => {
+      collect_expression_refs(rc, left);
+      collect_expression_refs(rc, right);
+    },
 
 
     ExpressionData::Call { callee, arguments } => {
       for arg in arguments.iter() {
-        let arg_res = check_expression_ir(ctx, global_rank, global_key, arg);
-
-        if arg_res.is_some() { return arg_res }
+        collect_expression_refs(rc, arg);
       }
 
-      check_expression_ir(ctx, global_rank, global_key, callee)
+      collect_expression_refs(rc, callee);
     },
 
-    
-    ExpressionData::Conditional(conditional) => check_conditional_ir(ctx, global_rank, global_key, conditional),
-    ExpressionData::Block(block) => check_block_ir(ctx, global_rank, global_key, block),
+
+    ExpressionData::Conditional(conditional) => collect_conditional_refs(rc, conditional),
+    ExpressionData::Block(block) => collect_block_refs(rc, block),
 
 
     // cannot contain a reference to a global
     | ExpressionData::Reference(Reference::Local { .. })
     | ExpressionData::Constant(_)
-    => None
+    | ExpressionData::Variant { payload: None, .. }
+    => { }
   }
 }
 
 
-fn check_conditional_ir (ctx: &Context, global_rank: usize, global_key: ContextKey, ir: &Conditional) -> Option<GlobalInitError> {
-  let if_res = check_conditional_branch_ir(ctx, global_rank, global_key, &ir.if_branch);
-
-  if if_res.is_some() { return if_res }
+fn collect_conditional_refs (rc: &mut ReferenceCollector, ir: &Conditional) {
+  collect_conditional_branch_refs(rc, &ir.if_branch);
 
   for branch in ir.else_if_branches.iter() {
-    let branch_res = check_conditional_branch_ir(ctx, global_rank, global_key, branch);
-
-    if branch_res.is_some() { return branch_res }
+    collect_conditional_branch_refs(rc, branch);
   }
 
   if let Some(else_block) = &ir.else_block {
-    check_block_ir(ctx, global_rank, global_key, else_block)
-  } else {
-    None
+    collect_block_refs(rc, else_block);
   }
 }
 
 
-fn check_conditional_branch_ir (ctx: &Context, global_rank: usize, global_key: ContextKey, ir: &ConditionalBranch) -> Option<GlobalInitError> {
-  let condition_res = check_expression_ir(ctx, global_rank, global_key, &ir.condition);
-
-  if condition_res.is_some() { return condition_res }
-
-  check_block_ir(ctx, global_rank, global_key, &ir.body)
+fn collect_conditional_branch_refs (rc: &mut ReferenceCollector, ir: &ConditionalBranch) {
+  collect_expression_refs(rc, &ir.condition);
+  collect_block_refs(rc, &ir.body);
 }
 
 
-fn check_block_ir (ctx: &Context, global_rank: usize, global_key: ContextKey, ir: &Block) -> Option<GlobalInitError> {
+fn collect_block_refs (rc: &mut ReferenceCollector, ir: &Block) {
   for statement in ir.statements.iter() {
-    let statement_res = check_statement_ir(ctx, global_rank, global_key, statement);
-
-    if statement_res.is_some() { return statement_res }
+    collect_statement_refs(rc, statement);
   }
 
   if let Some(trailing_expression) = &ir.trailing_expression {
-    check_expression_ir(ctx, global_rank, global_key, trailing_expression)
-  } else {
-    None
+    collect_expression_refs(rc, trailing_expression);
   }
 }
 
 
-fn check_statement_ir (ctx: &Context, global_rank: usize, global_key: ContextKey, ir: &Statement) -> Option<GlobalInitError> {
+fn collect_statement_refs (rc: &mut ReferenceCollector, ir: &Statement) {
   match &ir.data {
-    | StatementData::Expression(expression)
+    | StatementData::Expression { expression, .. }
     | StatementData::Return(Some(expression))
     | StatementData::Declaration { initializer: Some(expression), .. }
-    => check_expression_ir(ctx, global_rank, global_key, expression),
+    => collect_expression_refs(rc, expression),
 
 
     | StatementData::Assignment { target, value }
     | StatementData::ModAssignment { target, value, ..}
-    => check_expression_ir(ctx, global_rank, global_key, target).or_else(|| check_expression_ir(ctx, global_rank, global_key, value)),
+    => {
+      collect_expression_refs(rc, target);
+      collect_expression_refs(rc, value);
+    },
+
 
-    
-    StatementData::Conditional(conditional) => check_conditional_ir(ctx, global_rank, global_key, conditional),
+    StatementData::Conditional(conditional) => collect_conditional_refs(rc, conditional),
 
 
-    StatementData::Block(block) => check_block_ir(ctx, global_rank, global_key, block),
-  
+    StatementData::Block(block) => collect_block_refs(rc, block),
+
 
     // Cannot contain a reference to a global
     | StatementData::Return(None)
     | StatementData::Declaration { initializer: None, .. }
-    => None
+    => { }
   }
-}
\ No newline at end of file
+}
@@ -10,7 +10,8 @@ use crate::{
 
 use super::{
   Analyzer,
-  ty_helpers::{ ty_from_anon_data },
+  ty_helpers::{ ty_from_anon_data, ty_finalize_coercible, },
+  generate_bodies::{ generate_expr, },
 };
 
 
@@ -51,8 +52,33 @@ pub fn eval_local_ident (analyzer: &mut Analyzer, identifier: &Identifier, origi
 }
 
 
+/// Render a Path as it was written in the source, for use in diagnostics
+/// 
+/// Relative Paths also name the Namespace they were resolved from
+pub fn path_spelling (analyzer: &Analyzer, path: &Path, relative_to: ContextKey) -> String {
+  if path.absolute {
+    format!("`{}`", path)
+  } else {
+    let namespace =
+      analyzer.context.items
+        .get(relative_to)
+        .and_then(ContextItem::ref_namespace)
+        .expect("Internal error, path is relative to an invalid namespace key");
+
+    format!("`{}` (relative to `{}`)", path, namespace.canonical_name)
+  }
+}
+
+
 /// Get the ContextKey associated with a Path in the current Context
 pub fn eval_path (analyzer: &mut Analyzer, path: &Path, origin: SourceRegion) -> Option<ContextKey> {
+  eval_path_prefix(analyzer, path, path.len(), origin)
+}
+
+/// Get the ContextKey associated with the first `length` identifiers of a Path in the current Context
+/// 
+/// Errors still render the full Path, so they match the source even when only a prefix is evaluated
+fn eval_path_prefix (analyzer: &mut Analyzer, path: &Path, length: usize, origin: SourceRegion) -> Option<ContextKey> {
   let mut base_name = Identifier::default();
                 
   let base_key = if path.absolute {
@@ -63,7 +89,7 @@ pub fn eval_path (analyzer: &mut Analyzer, path: &Path, origin: SourceRegion) ->
 
   let mut resolved_key = base_key;
   
-  for ident in path.iter() {
+  for ident in path.iter().take(length) {
     let base = analyzer.context.items.get(resolved_key).expect("Internal error, invalid lowered key during path resolution");
 
     let (ns_key, namespace) = match base {
@@ -84,13 +110,15 @@ pub fn eval_path (analyzer: &mut Analyzer, path: &Path, origin: SourceRegion) ->
       } else if let Some(core) = analyzer.context.core_bs.get_entry(ident) {
         core
       } else {
-        analyzer.error(origin, format!("Namespace `{}` does not have access to an item named `{}`", base_name, ident));
+        let spelling = path_spelling(analyzer, path, base_key);
+        analyzer.error(origin, format!("Cannot resolve path {}: Namespace `{}` does not have access to an item named `{}`", spelling, base_name, ident));
         return None
       }
     } else if let Some(exported_key) = namespace.export_bindings.get_entry(ident) {
       exported_key
     } else {
-      analyzer.error(origin, format!("Namespace `{}` does not export an item named `{}`", base_name, ident));
+      let spelling = path_spelling(analyzer, path, base_key);
+      analyzer.error(origin, format!("Cannot resolve path {}: Namespace `{}` does not export an item named `{}`", spelling, base_name, ident));
       return None
     };
   }
@@ -99,6 +127,41 @@ pub fn eval_path (analyzer: &mut Analyzer, path: &Path, origin: SourceRegion) ->
 }
 
 
+/// Determine if a Path refers to a variant of an enum type, such as `Shape::Circle`,
+/// and if so get the ContextKey of the enum type and the tag of the variant
+/// 
+/// Returns Some(None) if the Path does not refer to an enum variant, and None if an error was issued
+pub fn eval_variant_path (analyzer: &mut Analyzer, path: &Path, origin: SourceRegion) -> Option<Option<(ContextKey, usize)>> {
+  if path.len() < 2 { return Some(None) }
+
+  let mut enum_path = path.clone();
+  let variant_name = enum_path.pop();
+
+  let enum_key = eval_path_prefix(analyzer, path, enum_path.len(), origin)?;
+
+  let variant_names = match analyzer.context.items.get(enum_key).unwrap().ref_type() {
+    Some(ty) => match &ty.data {
+      Some(TypeData::Enum { variant_names, .. }) => variant_names,
+
+      // The enum type failed to link, an error has already been issued
+      None => return None,
+
+      _ => return Some(None)
+    },
+
+    None => return Some(None)
+  };
+
+  if let Some(tag) = variant_names.iter().position(|name| name == &variant_name) {
+    Some(Some((enum_key, tag)))
+  } else {
+    analyzer.error(origin, format!("Enum `{}` has no variant named `{}`", enum_path, variant_name));
+
+    None
+  }
+}
+
+
 /// Evaluate a TypeExpression in the active context and get a ContextKey representing the type referenced by it
 pub fn eval_texpr (analyzer: &mut Analyzer, texpr: &TypeExpression) -> Option<ContextKey> {
   match &texpr.data {
@@ -136,6 +199,12 @@ pub fn eval_texpr (analyzer: &mut Analyzer, texpr: &TypeExpression) -> Option<Co
       Some(ty_from_anon_data(analyzer, TypeData::Pointer(value_key), texpr.origin))
     },
 
+    TypeExpressionData::Optional(payload_texpr) => {
+      let payload_key = eval_texpr(analyzer, payload_texpr)?;
+
+      Some(ty_from_anon_data(analyzer, TypeData::Optional(payload_key), texpr.origin))
+    },
+
     TypeExpressionData::Function { parameter_types: parameter_texprs, return_type: return_texpr } => {
       let mut parameter_types = Vec::with_capacity(parameter_texprs.len());
 
@@ -149,5 +218,20 @@ pub fn eval_texpr (analyzer: &mut Analyzer, texpr: &TypeExpression) -> Option<Co
 
       Some(ty_from_anon_data(analyzer, fn_td, texpr.origin))
     },
+
+    TypeExpressionData::TypeOf(expr) => {
+      if analyzer.local_context.is_none() {
+        analyzer.error(texpr.origin, "`typeof` can only be used inside a function body".to_owned());
+
+        return None
+      }
+
+      // The expression is only inspected for its type, the ir generated for it is discarded
+      let mut expr_ir = generate_expr(analyzer, expr)?;
+
+      ty_finalize_coercible(analyzer, &mut expr_ir);
+
+      Some(expr_ir.ty)
+    },
   }
 }
\ No newline at end of file
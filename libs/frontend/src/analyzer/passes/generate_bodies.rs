@@ -1,17 +1,18 @@
 use mod_utils::{ some, };
-use mod_common::{ Operator, };
+use mod_common::{ Operator, Constant, };
 
 use crate::{
+  source::{ SourceRegion, },
   ast::{ self, Item, ItemData, ExportData, },
-  ctx::{ ContextItem, Type, TypeData, LocalItem,  MultiKey, TypeDisplay, },
+  ctx::{ ContextKey, ContextItem, Type, TypeData, PrimitiveType, LocalItem,  MultiKey, TypeDisplay, },
   ir,
 };
 
 use super::{
   Analyzer,
   support_structures::{ Expect, TyMeetResult, },
-  ty_helpers::{ ty_from_global_item, ty_from_unary, ty_from_binary, ty_meet, ty_will_coerce, ty_meet_n, ty_handle_coercion, ty_finalize_coercible, ty_of_constant, },
-  eval_helpers::{ eval_path, eval_local_ident, eval_texpr, },
+  ty_helpers::{ ty_from_global_item, ty_from_unary, ty_from_binary, ty_meet, ty_is_int_float_mix, ty_will_coerce, ty_meet_n, ty_handle_coercion, ty_finalize_coercible, ty_of_constant, ty_optional_hint, },
+  eval_helpers::{ eval_path, eval_variant_path, eval_local_ident, eval_texpr, },
 };
 
 
@@ -24,6 +25,7 @@ pub fn generate_bodies (analyzer: &mut Analyzer, items: &mut Vec<Item>) {
       | ItemData::Alias  { .. }
       | ItemData::Type   { .. }
       | ItemData::Struct { .. }
+      | ItemData::Enum   { .. }
       | ItemData::Export { data: ExportData::List { .. }, .. }
       => continue,
       
@@ -69,14 +71,15 @@ fn generate_item (analyzer: &mut Analyzer, item: &mut Item) {
 
         if initializer_ir.ty != explicit_ty {
           if ty_will_coerce(analyzer, false, initializer_ir.ty, explicit_ty) {
-            ty_handle_coercion(explicit_ty, &mut initializer_ir);
+            ty_handle_coercion(analyzer, explicit_ty, &mut initializer_ir);
           } else {
             analyzer.error(initializer_ir.origin, format!(
               "The type of this expression (`{}`) \
                is not the same as the explicit type given for this declaration (`{}`), \
-               and will not automatically coerce to it",
+               and will not automatically coerce to it{}",
               TypeDisplay { ty_key: initializer_ir.ty, context: &analyzer.context },
-              TypeDisplay { ty_key: explicit_ty, context: &analyzer.context }
+              TypeDisplay { ty_key: explicit_ty, context: &analyzer.context },
+              ty_optional_hint(analyzer, initializer_ir.ty)
             ));
 
             return
@@ -89,7 +92,7 @@ fn generate_item (analyzer: &mut Analyzer, item: &mut Item) {
       }
     },
 
-    ItemData::Function { identifier, body, .. } => {
+    &mut ItemData::Function { ref identifier, ref mut body, external, .. } => {
       let function_key = analyzer.get_active_namespace().local_bindings.get_entry(identifier).unwrap();
 
       if let Some(body_block) = body {
@@ -107,7 +110,7 @@ fn generate_item (analyzer: &mut Analyzer, item: &mut Item) {
         let local_ctx = analyzer.create_local_context();
 
         for (param_name, param_type, param_origin) in params.into_iter() {
-          local_ctx.create_variable(param_name, param_type, true, param_origin);
+          local_ctx.create_variable(param_name, param_type, true, true, param_origin);
         }
 
         let body_ir = generate_block(analyzer, Expect::Allow, body_block);
@@ -117,7 +120,7 @@ fn generate_item (analyzer: &mut Analyzer, item: &mut Item) {
         unsafe { analyzer.context.items.get_unchecked_mut(function_key).mut_function_unchecked() }
           .body.replace(some!(body_ir));
           // .expect_none("Internal error, function body IR replaced"); there was an error, yes, but this is ok; our codegen is dead anyways
-      } else if analyzer.get_active_module().is_main {
+      } else if analyzer.get_active_module().is_main && !external {
         analyzer.error(item.origin, "Function definitions inside a source module must have a body".to_owned());
       }
     },
@@ -125,6 +128,7 @@ fn generate_item (analyzer: &mut Analyzer, item: &mut Item) {
     // Handled in previous pass
     | ItemData::Import { .. }
     | ItemData::Struct { .. }
+    | ItemData::Enum   { .. }
     | ItemData::Type   { .. }
     => { },
 
@@ -173,11 +177,11 @@ fn generate_conditional (analyzer: &mut Analyzer, expect_expression: Expect, con
 
       match meet_result {
         TyMeetResult::Ok(coerce_ty) => {
-          ty_handle_coercion(coerce_ty, if_trail);
+          ty_handle_coercion(analyzer, coerce_ty, if_trail);
 
-          else_if_branches.iter_mut().for_each(|branch| ty_handle_coercion(coerce_ty, branch.body.trailing_expression.as_mut().unwrap()));
+          else_if_branches.iter_mut().for_each(|branch| ty_handle_coercion(analyzer, coerce_ty, branch.body.trailing_expression.as_mut().unwrap()));
 
-          if let Some(else_block) = &mut else_block { ty_handle_coercion(coerce_ty, else_block.trailing_expression.as_mut().unwrap()) };
+          if let Some(else_block) = &mut else_block { ty_handle_coercion(analyzer, coerce_ty, else_block.trailing_expression.as_mut().unwrap()) };
         },
 
         TyMeetResult::None => {
@@ -238,6 +242,337 @@ fn generate_conditional_branch (analyzer: &mut Analyzer, expect_expression: Expe
   }
 }
 
+fn generate_match (analyzer: &mut Analyzer, match_expr: &ast::Match) -> Option<ir::Expression> {
+  // Scrutinees other than simple references and constants are evaluated once, into a temporary;
+  // the temporary is created before the scrutinee is generated, because codegen indexes locals in declaration order
+  let temporary = if matches!(match_expr.scrutinee.data, ast::ExpressionData::Identifier(_) | ast::ExpressionData::Path(_) | ast::ExpressionData::Constant(_) | ast::ExpressionData::SuffixedNumber { .. }) {
+    None
+  } else {
+    let err_ty = analyzer.context.err_ty;
+    Some(analyzer.get_local_context_mut().create_anonymous_variable(err_ty))
+  };
+
+  let mut scrutinee_ir = generate_expr(analyzer, &match_expr.scrutinee)?;
+
+  ty_finalize_coercible(analyzer, &mut scrutinee_ir);
+
+  let scrutinee_ty = scrutinee_ir.ty;
+
+  let scrutinee_td = analyzer.context.items.get(scrutinee_ty).unwrap().ref_type().unwrap().data.as_ref();
+
+  if !matches!(scrutinee_td, Some(TypeData::Primitive(PrimitiveType::Bool | PrimitiveType::Integer { .. } | PrimitiveType::FloatingPoint { .. }) | TypeData::Pointer(_) | TypeData::Enum { .. } | TypeData::Optional(_))) {
+    if !matches!(scrutinee_td, Some(TypeData::Error)) {
+      analyzer.error(match_expr.scrutinee.origin, format!(
+        "The type of this match scrutinee (`{}`) cannot be compared against constant or variant patterns",
+        TypeDisplay { ty_key: scrutinee_ty, context: &analyzer.context }
+      ));
+    }
+
+    return None
+  }
+
+  let (scrutinee_ref, declaration) = if let Some(local_key) = temporary {
+    let local = analyzer.get_local_context_mut().variables.get_mut(local_key).unwrap();
+
+    local.ty = scrutinee_ty;
+
+    let reference = ir::Expression::new(ir::ExpressionData::Reference((&*local).into()), scrutinee_ty, match_expr.scrutinee.origin);
+    let declaration = ir::Statement::new(ir::StatementData::Declaration { name: None, ty: scrutinee_ty, initializer: Some(scrutinee_ir) }, match_expr.scrutinee.origin);
+
+    (reference, Some(declaration))
+  } else {
+    (scrutinee_ir, None)
+  };
+
+  let mut branches = Some(Vec::new());
+  let mut else_block = None;
+
+  for arm in match_expr.arms.iter() {
+    if else_block.is_some() {
+      analyzer.warning(arm.origin, "This match arm is unreachable, it follows a wildcard arm".to_owned());
+      continue
+    }
+
+    match &arm.pattern {
+      ast::MatchPattern::Wildcard => {
+        if let Some(body_ir) = generate_expr(analyzer, &arm.body) {
+          else_block = Some(Ok(ir::Block::new(vec! [], Some(body_ir), arm.origin)));
+        } else {
+          else_block = Some(Err(()));
+        }
+      },
+
+      ast::MatchPattern::Constant(constant) => {
+        let body_ir = generate_expr(analyzer, &arm.body);
+
+        let constant_ty = ty_of_constant(analyzer, constant, arm.origin);
+        let mut pattern_ir = ir::Expression::new(ir::ExpressionData::Constant(constant.clone()), constant_ty, arm.origin);
+
+        if ty_will_coerce(analyzer, false, pattern_ir.ty, scrutinee_ty) {
+          ty_handle_coercion(analyzer, scrutinee_ty, &mut pattern_ir);
+        } else {
+          analyzer.error(arm.origin, format!(
+            "The type of this match arm's pattern (`{}`) \
+             is not the same as the type of the match scrutinee (`{}`), \
+             and will not automatically coerce to it",
+            TypeDisplay { ty_key: pattern_ir.ty, context: &analyzer.context },
+            TypeDisplay { ty_key: scrutinee_ty, context: &analyzer.context },
+          ));
+
+          branches.take();
+          continue
+        }
+
+        if let (Some(body_ir), Some(branches)) = (body_ir, &mut branches) {
+          let condition = ir::Expression::new(
+            ir::ExpressionData::Binary { left: box scrutinee_ref.clone(), right: box pattern_ir, operator: Operator::Equal },
+            analyzer.context.bool_ty,
+            arm.origin
+          );
+
+          branches.push(ir::ConditionalBranch::new(condition, ir::Block::new(vec! [], Some(body_ir), arm.origin), arm.origin));
+        } else {
+          branches.take();
+        }
+      },
+
+      ast::MatchPattern::Variant { identifier, binding } => {
+        let variants = analyzer.context.items.get(scrutinee_ty).unwrap().ref_type().unwrap().data.as_ref().and_then(TypeData::variants);
+
+        let variant = if let Some(variants) = variants {
+          if let Some(tag) = variants.iter().position(|&(name, _)| name == identifier.as_ref()) {
+            Ok((tag, variants[tag].1))
+          } else {
+            Err(format!(
+              "The type of the match scrutinee (`{}`) has no variant named `{}`",
+              TypeDisplay { ty_key: scrutinee_ty, context: &analyzer.context },
+              identifier
+            ))
+          }
+        } else {
+          Err(format!(
+            "The type of the match scrutinee (`{}`) is not an enum or optional, and cannot be matched against the variant pattern `{}`",
+            TypeDisplay { ty_key: scrutinee_ty, context: &analyzer.context },
+            identifier
+          ))
+        };
+
+        let (tag, payload_ty) = match variant {
+          Ok(variant) => variant,
+          Err(message) => {
+            analyzer.error(arm.origin, message);
+
+            branches.take();
+            continue
+          }
+        };
+
+        // The payload binding is only visible within the body of its arm
+        let declaration = match (binding, payload_ty) {
+          (Some(binding), Some(payload_ty)) => {
+            analyzer.get_local_context_mut().push_stack_frame();
+            analyzer.get_local_context_mut().create_variable(binding.clone(), payload_ty, false, false, arm.origin);
+
+            Some(ir::Statement::new(
+              ir::StatementData::Declaration {
+                name: Some(binding.clone()),
+                ty: payload_ty,
+                initializer: Some(ir::Expression::new(ir::ExpressionData::Payload { operand: box scrutinee_ref.clone(), tag }, payload_ty, arm.origin))
+              },
+              arm.origin
+            ))
+          },
+
+          (Some(_), None) => {
+            analyzer.error(arm.origin, format!("Enum variant `{}` does not carry a value, and cannot bind one", identifier));
+
+            branches.take();
+            continue
+          },
+
+          (None, _) => None,
+        };
+
+        let body_ir = generate_expr(analyzer, &arm.body);
+
+        if declaration.is_some() {
+          analyzer.get_local_context_mut().pop_stack_frame();
+        }
+
+        if let (Some(body_ir), Some(branches)) = (body_ir, &mut branches) {
+          let condition = ir::Expression::new(
+            ir::ExpressionData::IsVariant { operand: box scrutinee_ref.clone(), tag },
+            analyzer.context.bool_ty,
+            arm.origin
+          );
+
+          branches.push(ir::ConditionalBranch::new(condition, ir::Block::new(declaration.into_iter().collect(), Some(body_ir), arm.origin), arm.origin));
+        } else {
+          branches.take();
+        }
+      },
+    }
+  }
+
+  let mut branches = branches?;
+
+  let mut else_block = match else_block {
+    Some(else_block) => else_block.ok()?,
+
+    // Without a wildcard, only a match covering every value of a finite domain is exhaustive, in which case the final arm can become the else
+    None => match match_missing_values(analyzer, scrutinee_ty, &match_expr.arms) {
+      Some(missing) if missing.is_empty() => branches.pop().unwrap().body,
+
+      Some(missing) => {
+        analyzer.error(match_expr.origin, format!(
+          "This match over `{}` is not exhaustive, it does not cover {}; \
+           add an arm for each missing value, or a wildcard `_` arm",
+          TypeDisplay { ty_key: scrutinee_ty, context: &analyzer.context },
+          missing.iter().map(|value| format!("`{}`", value)).collect::<Vec<_>>().join(", ")
+        ));
+
+        return None
+      },
+
+      None => {
+        analyzer.error(match_expr.origin, "This match is not exhaustive, add a wildcard `_` arm to handle the remaining values".to_owned());
+
+        return None
+      }
+    }
+  };
+
+  let mut trail_tys: Vec<_> = branches.iter().map(|branch| branch.body.trailing_expression.as_ref().unwrap().ty).collect();
+
+  trail_tys.push(else_block.trailing_expression.as_ref().unwrap().ty);
+
+  let ty = match ty_meet_n(analyzer, false, trail_tys.as_slice()) {
+    TyMeetResult::Ok(coerce_ty) => {
+      branches.iter_mut().for_each(|branch| ty_handle_coercion(analyzer, coerce_ty, branch.body.trailing_expression.as_mut().unwrap()));
+
+      ty_handle_coercion(analyzer, coerce_ty, else_block.trailing_expression.as_mut().unwrap());
+
+      coerce_ty
+    },
+
+    TyMeetResult::None => {
+      analyzer.error(
+        match_expr.origin,
+        "This match expression has multiple arms that evaluate to different types, \
+         with no possible coercion that works for all arms; \
+         The final type is unresolvable".to_owned()
+      );
+
+      return None
+    },
+
+    TyMeetResult::Unresolvable => {
+      analyzer.error(
+        match_expr.origin,
+        "This match expression has multiple arms that evaluate to different types, \
+         with multiple possible coercion outcomes; \
+         The final type is unresolvable".to_owned()
+      );
+
+      return None
+    },
+  };
+
+  let mut branches = branches.into_iter();
+
+  let result = if let Some(if_branch) = branches.next() {
+    ir::Expression::new(
+      ir::ExpressionData::Conditional(box ir::Conditional::new(if_branch, branches.collect(), Some(else_block), match_expr.origin)),
+      ty,
+      match_expr.origin
+    )
+  } else {
+    // A lone wildcard arm always matches
+    else_block.trailing_expression.unwrap()
+  };
+
+  if let Some(declaration) = declaration {
+    Some(ir::Expression::new(
+      ir::ExpressionData::Block(box ir::Block::new(vec! [ declaration ], Some(result), match_expr.origin)),
+      ty,
+      match_expr.origin
+    ))
+  } else {
+    Some(result)
+  }
+}
+
+/// Get the values of a scrutinee type which are not covered by the constant or variant patterns of a set of MatchArms
+/// 
+/// Returns None if the type does not have a finite domain, in which case only a wildcard arm makes a match exhaustive
+fn match_missing_values (analyzer: &Analyzer, scrutinee_ty: ContextKey, arms: &[ast::MatchArm]) -> Option<Vec<String>> {
+  if scrutinee_ty == analyzer.context.bool_ty {
+    return Some([ true, false ].iter()
+      .filter(|&&value| !arms.iter().any(|arm| matches!(arm.pattern, ast::MatchPattern::Constant(Constant::Bool(covered)) if covered == value)))
+      .map(|value| value.to_string())
+      .collect())
+  }
+
+  let variants = analyzer.context.items.get(scrutinee_ty).unwrap().ref_type().unwrap().data.as_ref().and_then(TypeData::variants)?;
+
+  Some(variants.into_iter()
+    .filter(|&(name, _)| !arms.iter().any(|arm| matches!(&arm.pattern, ast::MatchPattern::Variant { identifier, .. } if identifier.as_ref() == name)))
+    .map(|(name, _)| name.to_owned())
+    .collect())
+}
+
+fn generate_variant (analyzer: &mut Analyzer, enum_ty: ContextKey, tag: usize, arguments: Option<&[ast::Expression]>, origin: SourceRegion) -> Option<ir::Expression> {
+  let (variant_name, payload_ty) = if let Some(TypeData::Enum { variant_names, variant_types }) = &analyzer.context.items.get(enum_ty).unwrap().ref_type().unwrap().data {
+    (variant_names[tag].clone(), variant_types[tag])
+  } else {
+    unreachable!("Internal error, enum variant resolved on non-enum type")
+  };
+
+  let payload_ir = match (payload_ty, arguments) {
+    (Some(payload_ty), Some([ argument ])) => {
+      let mut argument_ir = generate_expr(analyzer, argument)?;
+
+      if argument_ir.ty != payload_ty {
+        if ty_will_coerce(analyzer, false, argument_ir.ty, payload_ty) {
+          ty_handle_coercion(analyzer, payload_ty, &mut argument_ir);
+        } else {
+          analyzer.error(argument.origin, format!(
+            "The type of this enum variant's payload (`{}`) \
+             is not the same as the type given in the variant declaration (`{}`), \
+             and will not automatically coerce to it",
+            TypeDisplay { ty_key: argument_ir.ty, context: &analyzer.context },
+            TypeDisplay { ty_key: payload_ty, context: &analyzer.context },
+          ));
+
+          return None
+        }
+      }
+
+      Some(box argument_ir)
+    },
+
+    (None, None) => None,
+
+    (Some(payload_ty), _) => {
+      analyzer.error(origin, format!(
+        "Enum variant `{}` carries a value of type `{}`, and must be constructed with exactly one argument",
+        variant_name,
+        TypeDisplay { ty_key: payload_ty, context: &analyzer.context }
+      ));
+
+      return None
+    },
+
+    (None, Some(_)) => {
+      analyzer.error(origin, format!("Enum variant `{}` does not carry a value, and cannot be called", variant_name));
+
+      return None
+    },
+  };
+
+  Some(ir::Expression::new(ir::ExpressionData::Variant { tag, payload: payload_ir }, enum_ty, origin))
+}
+
 fn generate_block (analyzer: &mut Analyzer, expect_expression: Expect, block: &ast::Block) -> Option<ir::Block> {
   analyzer.get_local_context_mut().push_stack_frame();
 
@@ -281,7 +616,7 @@ fn generate_block (analyzer: &mut Analyzer, expect_expression: Expect, block: &a
 
 fn generate_stmt (analyzer: &mut Analyzer, stmt: &ast::Statement) -> Option<ir::Statement> {
   match &stmt.data {
-    ast::StatementData::Declaration { identifier, explicit_type, initializer } => {
+    ast::StatementData::Declaration { identifier, explicit_type, initializer, mutable } => {
       let explicit_tk = if let Some(texpr) = explicit_type { Some(eval_texpr(analyzer, texpr)) } else { None };
 
       let initializer_ir = if let Some(expr) = initializer { Some(generate_expr(analyzer, expr)) } else { None };
@@ -290,18 +625,20 @@ fn generate_stmt (analyzer: &mut Analyzer, stmt: &ast::Statement) -> Option<ir::
         (Some(Some(e_tk)), Some(Some(mut i_ir))) => {
           if i_ir.ty != e_tk {
             if ty_will_coerce(analyzer, false, i_ir.ty, e_tk) {
-              ty_handle_coercion(e_tk, &mut i_ir);
+              ty_handle_coercion(analyzer, e_tk, &mut i_ir);
               (e_tk, Some(i_ir))
             } else {
               analyzer.error(i_ir.origin, format!(
                 "The type of this expression (`{}`) \
                  is not the same as the explicit type given for this declaration (`{}`), \
-                 and will not automatically coerce to it",
+                 and will not automatically coerce to it{}",
                 TypeDisplay { ty_key: i_ir.ty, context: &analyzer.context },
                 TypeDisplay { ty_key: e_tk, context: &analyzer.context },
+                ty_optional_hint(analyzer, i_ir.ty)
               ));
 
-              return None
+              // the variable is still bound with its explicit type, to avoid follow-on errors where it is used
+              (e_tk, None)
             }
           } else {
             (e_tk, Some(i_ir))
@@ -313,7 +650,9 @@ fn generate_stmt (analyzer: &mut Analyzer, stmt: &ast::Statement) -> Option<ir::
           (i_ir.ty, Some(i_ir))
         },
 
-        (Some(Some(e_tk)), None) => (e_tk, None),
+        | (Some(Some(e_tk)), None)
+        | (Some(Some(e_tk)), Some(None))
+        => (e_tk, None),
 
         (None, None) => {
           analyzer.error(
@@ -329,10 +668,10 @@ fn generate_stmt (analyzer: &mut Analyzer, stmt: &ast::Statement) -> Option<ir::
         => return None
       };
 
-      analyzer.get_local_context_mut().create_variable(identifier.clone(), ty, false, stmt.origin);
+      analyzer.get_local_context_mut().create_variable(identifier.clone(), ty, false, *mutable, stmt.origin);
 
       Some(ir::Statement::new(
-        ir::StatementData::Declaration { ty, initializer },
+        ir::StatementData::Declaration { name: Some(identifier.clone()), ty, initializer },
         stmt.origin
       ))
     },
@@ -367,10 +706,17 @@ fn generate_stmt (analyzer: &mut Analyzer, stmt: &ast::Statement) -> Option<ir::
     )),
 
 
-    ast::StatementData::Expression(expr) => Some(ir::Statement::new(
-      ir::StatementData::Expression(generate_expr(analyzer, expr)?),
-      stmt.origin
-    )),
+    ast::StatementData::Expression(expr) => {
+      let expression = generate_expr(analyzer, expr)?;
+
+      // the result of an expression statement is unused, so any value it produces must be discarded to keep the stack balanced
+      let discard = expression.ty != analyzer.context.void_ty;
+
+      Some(ir::Statement::new(
+        ir::StatementData::Expression { expression, discard },
+        stmt.origin
+      ))
+    },
 
     ast::StatementData::Conditional(conditional) => Some(ir::Statement::new(
       ir::StatementData::Conditional(box generate_conditional(analyzer, Expect::Deny, conditional)?),
@@ -393,12 +739,54 @@ fn generate_lvalue (analyzer: &mut Analyzer, expr: &ast::Expression) -> Option<i
     if *operator == Operator::Dereference
     => Some(ir),
 
-    ir::ExpressionData::Reference(ir::Reference::Local { .. })
-    => Some(ir),
+    &ir::ExpressionData::Reference(ir::Reference::Local { is_parameter, index }) => {
+      let local = analyzer.get_local_context().variables
+        .values().iter()
+        .find(|local| local.is_parameter == is_parameter && local.index == index)
+        .unwrap();
+
+      if local.is_mutable {
+        Some(ir)
+      } else {
+        analyzer.error(expr.origin, format!(
+          "Cannot assign to immutable local variable `{}`, \
+           it must be declared with `let mut` to be assigned to",
+          local.canonical_name
+        ));
+
+        None
+      }
+    },
     
-    ir::ExpressionData::Reference(ir::Reference::Global(key))
-    if analyzer.context.items.get(*key).unwrap().ref_global().is_some()
-    => Some(ir),
+    &ir::ExpressionData::Reference(ir::Reference::Global(key))
+    if analyzer.context.items.get(key).unwrap().ref_global().is_some()
+    => {
+      let global = analyzer.context.items.get(key).unwrap().ref_global().unwrap();
+
+      if global.parent_module != analyzer.context.main_mod {
+        let parent_module = analyzer.context.items.get(global.parent_module).unwrap().ref_module().unwrap();
+
+        analyzer.error(expr.origin, format!(
+          "Cannot assign to global `{}`, it is imported from external module `{}`",
+          global.canonical_name,
+          parent_module.canonical_name
+        ))
+        .append(global.origin, "The global is defined here".to_owned());
+
+        None
+      } else if !global.is_mutable {
+        analyzer.error(expr.origin, format!(
+          "Cannot assign to immutable global `{}`, \
+           it must be declared with `global mut` to be assigned to",
+          global.canonical_name
+        ))
+        .append(global.origin, "The global is defined here".to_owned());
+
+        None
+      } else {
+        Some(ir)
+      }
+    },
 
     _ => {
       analyzer.error(expr.origin, "This expression is not a valid L-value, it cannot be assigned to".to_owned());
@@ -408,9 +796,40 @@ fn generate_lvalue (analyzer: &mut Analyzer, expr: &ast::Expression) -> Option<i
 }
 
 
-fn generate_expr (analyzer: &mut Analyzer, expr: &ast::Expression) -> Option<ir::Expression> {
+pub(super) fn generate_expr (analyzer: &mut Analyzer, expr: &ast::Expression) -> Option<ir::Expression> {
+  if analyzer.expression_depth >= Analyzer::MAX_EXPRESSION_DEPTH {
+    // sibling subexpressions can each reach the limit, but the outermost expression is only reported once
+    if !analyzer.expression_depth_exceeded {
+      analyzer.error(expr.origin, format!(
+        "Expression exceeds the maximum nesting depth of {}, \
+         consider splitting it into multiple statements",
+        Analyzer::MAX_EXPRESSION_DEPTH
+      ));
+
+      analyzer.expression_depth_exceeded = true;
+    }
+
+    return None
+  }
+
+  analyzer.expression_depth += 1;
+
+  let result = generate_expr_data(analyzer, expr);
+
+  analyzer.expression_depth -= 1;
+
+  if analyzer.expression_depth == 0 { analyzer.expression_depth_exceeded = false }
+
+  result
+}
+
+fn generate_expr_data (analyzer: &mut Analyzer, expr: &ast::Expression) -> Option<ir::Expression> {
   match &expr.data {
     ast::ExpressionData::Path(path) => {
+      if let Some((enum_ty, tag)) = eval_variant_path(analyzer, path, expr.origin)? {
+        return generate_variant(analyzer, enum_ty, tag, None, expr.origin)
+      }
+
       let key = eval_path(analyzer, path, expr.origin)?;
 
       let item = analyzer.context.items.get(key).unwrap();
@@ -457,11 +876,28 @@ fn generate_expr (analyzer: &mut Analyzer, expr: &ast::Expression) -> Option<ir:
       ty_of_constant(analyzer, constant, expr.origin),
       expr.origin
     )),
+
+    &ast::ExpressionData::SuffixedNumber { number, ref suffix } => {
+      // The lexer only accepts suffixes naming numeric primitives, which are always in the core namespace
+      let suffix_ty = analyzer.context.core_bs.get_entry(suffix).expect("Internal error, number suffix does not name a core type");
+
+      let constant = Constant::Number(number);
+      let constant_ty = ty_of_constant(analyzer, &constant, expr.origin);
+
+      let mut constant_ir = ir::Expression::new(ir::ExpressionData::Constant(constant), constant_ty, expr.origin);
+      ty_handle_coercion(analyzer, suffix_ty, &mut constant_ir);
+
+      Some(constant_ir)
+    },
   
     &ast::ExpressionData::Unary { box ref operand, operator } => {
       let mut operand_ir = generate_expr(analyzer, operand)?;
 
-      ty_finalize_coercible(analyzer, &mut operand_ir);
+      // Negated literals keep their coercible type so they can be typed from context,
+      // e.g. `-9223372036854775808` is only in range once it is known to be an s64
+      if !(operator == Operator::Sub && matches!(operand_ir.data, ir::ExpressionData::Constant(_))) {
+        ty_finalize_coercible(analyzer, &mut operand_ir);
+      }
 
       let result_ty = ty_from_unary(analyzer, operand_ir.ty, operator, expr.origin)?;
 
@@ -477,12 +913,34 @@ fn generate_expr (analyzer: &mut Analyzer, expr: &ast::Expression) -> Option<ir:
 
       let (mut left_ir, mut right_ir) = (irs.0?, irs.1?);
 
-      ty_finalize_coercible(analyzer, &mut left_ir);
-      ty_finalize_coercible(analyzer, &mut right_ir);
+      // A coercible literal takes on the type of the other operand, such as `u32` in `1 + y`,
+      // and is only finalized to its concrete type if both operands are coercible or the types do not meet
+      let operand_tk = match ty_meet(analyzer, true, left_ir.ty, right_ir.ty) {
+        Some(tk) if tk == analyzer.context.int_ty => Some(analyzer.context.concrete_int_ty),
+        Some(tk) if tk == analyzer.context.float_ty => Some(analyzer.context.concrete_float_ty),
+        operand_tk => {
+          if operand_tk.is_none() {
+            ty_finalize_coercible(analyzer, &mut left_ir);
+            ty_finalize_coercible(analyzer, &mut right_ir);
+          }
+
+          operand_tk
+        }
+      };
 
       let operand_tk =
-        if let Some(tk) = ty_meet(analyzer, true, left_ir.ty, right_ir.ty) { tk }
-        else {
+        if let Some(tk) = operand_tk { tk }
+        else if ty_is_int_float_mix(analyzer, left_ir.ty, right_ir.ty) {
+          analyzer.error(expr.origin, format!(
+            "The subexpressions for this binary operator mix integer and floating point types \
+             (left: `{}`, right: `{}`), \
+             which is not done implicitly; one of the operands must be explicitly converted",
+            TypeDisplay { ty_key: left_ir.ty,  context: &analyzer.context },
+            TypeDisplay { ty_key: right_ir.ty, context: &analyzer.context },
+          ));
+
+          return None
+        } else {
           analyzer.error(expr.origin, format!(
             "The types of the subexpressions for this binary operator \
              (left: `{}`, right: `{}`), \
@@ -494,8 +952,33 @@ fn generate_expr (analyzer: &mut Analyzer, expr: &ast::Expression) -> Option<ir:
           return None
         };
 
-      ty_handle_coercion(operand_tk, &mut left_ir);
-      ty_handle_coercion(operand_tk, &mut right_ir);
+      ty_handle_coercion(analyzer, operand_tk, &mut left_ir);
+      ty_handle_coercion(analyzer, operand_tk, &mut right_ir);
+
+      // There is no exponentiation instruction, so it is lowered to a call to an intrinsic for the operand type
+      if operator == Operator::Pow {
+        let operand_td = analyzer.context.items.get(operand_tk).unwrap().ref_type().unwrap().data.as_ref();
+
+        if !matches!(operand_td, Some(TypeData::Primitive(PrimitiveType::Integer { .. }))) {
+          if !matches!(operand_td, Some(TypeData::Error)) {
+            analyzer.error(expr.origin, format!(
+              "The operand type of this binary expression (`{}`) does not support the operator `{}`, \
+               exponentiation is only available for integer types",
+              TypeDisplay { ty_key: operand_tk, context: &analyzer.context },
+              operator.value()
+            ));
+          }
+
+          return None
+        }
+
+        let pow_key = analyzer.context.get_pow_intrinsic(operand_tk);
+        let pow_ty = analyzer.context.items.get(pow_key).unwrap().ref_function().unwrap().ty.unwrap();
+
+        let callee = ir::Expression::new(ir::ExpressionData::Reference(pow_key.into()), pow_ty, expr.origin);
+
+        return Some(ir::Expression::new(ir::ExpressionData::Call { callee: box callee, arguments: vec! [ left_ir, right_ir ] }, operand_tk, expr.origin))
+      }
 
       let result_tk = ty_from_binary(analyzer, operand_tk, operator, expr.origin)?;
 
@@ -503,10 +986,32 @@ fn generate_expr (analyzer: &mut Analyzer, expr: &ast::Expression) -> Option<ir:
     },
 
     ast::ExpressionData::Call { box callee, arguments } => {
-      let argument_irs: Vec<_> = arguments.iter().map(|arg| (generate_expr(analyzer, arg), arg.origin)).collect();
-      
+      if let ast::ExpressionData::Path(path) = &callee.data {
+        if let Some((enum_ty, tag)) = eval_variant_path(analyzer, path, callee.origin)? {
+          return generate_variant(analyzer, enum_ty, tag, Some(arguments.as_slice()), expr.origin)
+        }
+      }
+
       let callee_ir = generate_expr(analyzer, callee)?;
       
+      let callee_ty: &Type = analyzer.context.items.get(callee_ir.ty).unwrap().ref_type().unwrap();
+
+      // Verify the callee is callable before checking arguments, so the error refers to the actual problem
+      if !matches!(callee_ty.data, Some(TypeData::Function { .. })) {
+        // An error type has already been reported elsewhere
+        if matches!(callee_ty.data, Some(TypeData::Error)) { return None }
+
+        analyzer.error(callee.origin, format!(
+          "This expression is not callable, it has type `{}`, \
+           only functions can be used as the callee of a call expression",
+          TypeDisplay { ty_key: callee_ir.ty, context: &analyzer.context }
+        ));
+
+        return None
+      }
+
+      let argument_irs: Vec<_> = arguments.iter().map(|arg| (generate_expr(analyzer, arg), arg.origin)).collect();
+      
       let callee_ty: &Type = analyzer.context.items.get(callee_ir.ty).unwrap().ref_type().unwrap();
       
       let callee_ty_data = callee_ty.data.as_ref().unwrap();
@@ -527,7 +1032,7 @@ fn generate_expr (analyzer: &mut Analyzer, expr: &ast::Expression) -> Option<ir:
 
                 if arg_ir.ty != param_ty {
                   if ty_will_coerce(analyzer, false, arg_ir.ty, param_ty) {
-                    ty_handle_coercion(param_ty, &mut arg_ir);
+                    ty_handle_coercion(analyzer, param_ty, &mut arg_ir);
                     
                     if let Some(argument_irs) = &mut argument_irs {
                       argument_irs.push(arg_ir);
@@ -536,10 +1041,11 @@ fn generate_expr (analyzer: &mut Analyzer, expr: &ast::Expression) -> Option<ir:
                     analyzer.error(arg_origin, format!(
                       "The type of argument {} (`{}`) \
                        is not the same as the explicit type given for the parameter declaration (`{}`), \
-                       and will not automatically coerce to it",
+                       and will not automatically coerce to it{}",
                       i,
                       TypeDisplay { ty_key: arg_ir.ty, context: &analyzer.context },
                       TypeDisplay { ty_key: param_ty, context: &analyzer.context },
+                      ty_optional_hint(analyzer, arg_ir.ty)
                     ));
 
                     argument_irs = None;
@@ -569,13 +1075,7 @@ fn generate_expr (analyzer: &mut Analyzer, expr: &ast::Expression) -> Option<ir:
           }
         },
 
-        _ => {
-          analyzer.error(
-            callee.origin,
-            "Subexpression does not evaluate to a function, \
-             and cannot be used as the callee of a call expression".to_owned()
-          );
-        }
+        _ => unreachable!("Internal error, callee type was already verified to be a function")
       }
 
       None
@@ -604,5 +1104,7 @@ fn generate_expr (analyzer: &mut Analyzer, expr: &ast::Expression) -> Option<ir:
         expr.origin
       ))
     }
+
+    ast::ExpressionData::Match(box match_expr) => generate_match(analyzer, match_expr),
   }
 }
\ No newline at end of file
@@ -32,10 +32,19 @@ pub use generate_bodies::*;
 mod check_global_initializers;
 pub use check_global_initializers::*;
 
+mod check_entry_point;
+pub use check_entry_point::*;
+
+mod run_lints;
+pub use run_lints::*;
+
 
 
 impl Analyzer {
   /// Runs each pass of analysis session in sequence
+  /// 
+  /// All Global and Function signatures are linked to their types before any bodies are generated,
+  /// so bodies may freely reference items defined later in the source, including mutually recursive Functions
   pub fn run_passes (&mut self, ast: &mut Vec<Item>) {
     {
       let mut pseudonyms = Vec::new();
@@ -53,6 +62,920 @@ impl Analyzer {
 
     check_global_initializers(self);
 
+    check_entry_point(self);
+
+    run_lints(self, ast);
+
     assert!(self.get_active_namespace_key() == self.context.main_ns, "Internal error, a pass did not pop an active namespace");
   }
+}
+
+
+#[cfg(test)]
+mod test {
+  use crate::{
+    ir,
+    ast,
+    ctx,
+    analyzer::Analyzer,
+    session::{ SESSION, MessageKind, DEFAULT_INT_TYPE, DEFAULT_FLOAT_TYPE, },
+    source::{ SOURCE_MANAGER, },
+    test_support::{ TestOutput, analyze_str, analyze_str_with, analyze_str_locked, lock_singletons, },
+  };
+
+  #[test]
+  fn mutual_recursion () {
+    let output = analyze_str("mutual_recursion.ms", r#"
+      export fn is_even (n: s32) -> bool {
+        if n == 0 { true } else { is_odd(n - 1) }
+      }
+
+      export fn is_odd (n: s32) -> bool {
+        if n == 0 { false } else { is_even(n - 1) }
+      }
+    "#);
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let is_even = output.context.get_key_from_path(&["is_even"]).expect("is_even is bound");
+    let is_odd = output.context.get_key_from_path(&["is_odd"]).expect("is_odd is bound");
+
+    for &(key, callee) in &[ (is_even, is_odd), (is_odd, is_even) ] {
+      let function = output.context.items.get(key).unwrap().ref_function().unwrap();
+
+      assert!(function.ty.is_some());
+
+      let body = function.body.as_ref().expect("function body is generated");
+      let conditional = match &body.trailing_expression.as_ref().expect("body has a trailing expression").data {
+        ir::ExpressionData::Conditional(conditional) => conditional,
+        other => panic!("expected conditional, got {:?}", other)
+      };
+
+      let call = conditional.else_block.as_ref().unwrap().trailing_expression.as_ref().unwrap();
+
+      match &call.data {
+        ir::ExpressionData::Call { callee: box ir::Expression { data: ir::ExpressionData::Reference(ir::Reference::Global(callee_key)), .. }, .. }
+        => assert_eq!(*callee_key, callee),
+        other => panic!("expected call, got {:?}", other)
+      }
+    }
+  }
+
+  #[test]
+  fn deep_expression () {
+    let depth = 5000;
+    let chain = " + 1".repeat(depth);
+
+    // both operands of `wide` exceed the limit, but each expression is only reported once
+    let source = format!("global DEEP: s32 = 1{chain};\nfn wide () -> s32 {{ (1{chain}) * (1{chain}) }}", chain = chain);
+
+    let output = analyze_str("deep_expression.ms", &source);
+
+    assert_eq!(output.error_count(), 2, "{:#?}", output.messages);
+    assert!(output.messages.iter().all(|message| message.items[0].content.contains(&Analyzer::MAX_EXPRESSION_DEPTH.to_string())));
+    assert_ne!(output.messages[0].items[0].origin, output.messages[1].items[0].origin);
+
+    let printed = format!("{}", ast::Displayer(&output.ast));
+    assert_eq!(printed.matches('+').count(), depth * 3);
+  }
+
+  fn analyze_entry (name: &str, source: &str) -> Vec<String> {
+    let output = analyze_str_with(Analyzer::new().with_entry_point(None), name, source);
+
+    output.messages.iter().map(|message| message.items[0].content.clone()).collect()
+  }
+
+  #[test]
+  fn entry_point_valid () {
+    let messages = analyze_entry("entry_valid.ms", "export fn main () -> s32 { 0 }");
+
+    assert!(messages.is_empty(), "{:#?}", messages);
+  }
+
+  #[test]
+  fn entry_point_missing () {
+    let messages = analyze_entry("entry_missing.ms", "export fn start () { }");
+
+    assert_eq!(messages.len(), 1, "{:#?}", messages);
+    assert!(messages[0].starts_with("Missing entry point"));
+  }
+
+  #[test]
+  fn entry_point_wrong_type () {
+    let messages = analyze_entry("entry_wrong_type.ms", "export fn main (argc: s32) -> bool { argc == 0 }");
+
+    assert_eq!(messages.len(), 1, "{:#?}", messages);
+    assert!(messages[0].starts_with("Entry point `main` has type"));
+  }
+
+  #[test]
+  fn call_non_function () {
+    let output = analyze_str("call_non_function.ms", r#"
+      global X: s32 = 10;
+
+      fn call_x () -> s32 {
+        X(1)
+      }
+    "#);
+
+    assert_eq!(output.error_count(), 1, "{:#?}", output.messages);
+    assert!(output.messages[0].items[0].content.starts_with("This expression is not callable, it has type `s32`"));
+  }
+
+  #[test]
+  fn negated_literal_to_unsigned () {
+    let output = analyze_str("negated_literal_to_unsigned.ms", r#"
+      fn f () {
+        let x: s8 = -1;
+        let y: u8 = -1;
+      }
+    "#);
+
+    assert_eq!(output.error_count(), 1, "{:#?}", output.messages);
+    assert!(output.messages[0].items[0].content.starts_with("The negated literal `-1` cannot be represented by the unsigned type"));
+  }
+
+  #[test]
+  fn negated_literal_range () {
+    let output = analyze_str("negated_literal_range.ms", r#"
+      fn f () {
+        let a: s8 = -128;
+        let b: s8 = -129;
+        let c: s64 = -9223372036854775808;
+        let d = -2147483648;
+        let e = -2147483649;
+      }
+    "#);
+
+    assert_eq!(output.error_count(), 2, "{:#?}", output.messages);
+    assert!(output.messages[0].items[0].content.starts_with("The negated literal `-129` is out of range"));
+    assert!(output.messages[1].items[0].content.starts_with("The negated literal `-2147483649` is out of range"));
+  }
+
+  #[test]
+  fn char_literals () {
+    let output = analyze_str("char_literals.ms", r#"
+      export fn letter () -> char { 'a' }
+      export fn is_newline (c: char) -> bool { c == '\n' }
+    "#);
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let context = &output.context;
+    let letter = context.items.get(context.get_key_from_path(&[ "letter" ]).unwrap()).unwrap().ref_function().unwrap();
+    let trailing_expression = letter.body.as_ref().unwrap().trailing_expression.as_ref().unwrap();
+
+    assert_eq!(trailing_expression.ty, context.char_ty);
+    assert_eq!(trailing_expression.data, ir::ExpressionData::Constant(mod_common::Constant::Char('a')));
+  }
+
+  #[test]
+  fn string_literals () {
+    let output = analyze_str("string_literals.ms", r#"
+      export fn greeting () -> string { "hello" }
+      export fn mismatched () { let s: s32 = "text"; }
+    "#);
+
+    assert_eq!(output.error_count(), 1, "{:#?}", output.messages);
+    assert!(output.messages[0].items[0].content.contains("`string`"), "{:#?}", output.messages);
+
+    let context = &output.context;
+    let greeting = context.items.get(context.get_key_from_path(&[ "greeting" ]).unwrap()).unwrap().ref_function().unwrap();
+    let trailing_expression = greeting.body.as_ref().unwrap().trailing_expression.as_ref().unwrap();
+
+    assert_eq!(trailing_expression.ty, context.string_ty);
+    assert_eq!(trailing_expression.data, ir::ExpressionData::Constant(mod_common::Constant::String("hello".to_owned())));
+  }
+
+  fn default_int_declaration_ty (default_int: &'static str, name: &str) -> (ctx::ContextKey, TestOutput) {
+    let output = {
+      let _guard = lock_singletons();
+
+      SESSION.set_default_int_type(default_int);
+      let output = analyze_str_locked(Analyzer::new(), name, "fn f () { let x = 1; }");
+      SESSION.set_default_int_type(DEFAULT_INT_TYPE);
+
+      output
+    };
+
+    let function = output.context.items.get(output.context.functions[0]).unwrap().ref_function().unwrap();
+
+    let ty = match function.body.as_ref().unwrap().statements[0].data {
+      ir::StatementData::Declaration { ty, .. } => ty,
+      ref other => panic!("expected declaration, got {:?}", other)
+    };
+
+    (ty, output)
+  }
+
+  #[test]
+  fn default_int_type () {
+    let (ty, output) = default_int_declaration_ty(DEFAULT_INT_TYPE, "default_int.ms");
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+    assert_eq!(Some(ty), output.context.core_bs.get_entry("s32"));
+  }
+
+  #[test]
+  fn overridden_int_type () {
+    let (ty, output) = default_int_declaration_ty("s64", "overridden_int.ms");
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+    assert_eq!(Some(ty), output.context.core_bs.get_entry("s64"));
+  }
+
+  #[test]
+  fn invalid_default_int_type () {
+    let (ty, output) = default_int_declaration_ty("f64", "invalid_default_int.ms");
+
+    assert_eq!(output.error_count(), 1, "{:#?}", output.messages);
+    assert!(output.messages[0].items[0].content.starts_with("`f64` is not an integer type"));
+    assert_eq!(Some(ty), output.context.core_bs.get_entry("s32"));
+  }
+
+  fn default_float_declaration_ty (default_float: &'static str, name: &str) -> (ctx::ContextKey, TestOutput) {
+    let output = {
+      let _guard = lock_singletons();
+
+      SESSION.set_default_float_type(default_float);
+      let output = analyze_str_locked(Analyzer::new(), name, "fn f () { let x = 1.0; }");
+      SESSION.set_default_float_type(DEFAULT_FLOAT_TYPE);
+
+      output
+    };
+
+    let function = output.context.items.get(output.context.functions[0]).unwrap().ref_function().unwrap();
+
+    let ty = match function.body.as_ref().unwrap().statements[0].data {
+      ir::StatementData::Declaration { ty, .. } => ty,
+      ref other => panic!("expected declaration, got {:?}", other)
+    };
+
+    (ty, output)
+  }
+
+  #[test]
+  fn overridden_float_type () {
+    let (ty, output) = default_float_declaration_ty("f64", "overridden_float.ms");
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+    assert_eq!(Some(ty), output.context.core_bs.get_entry("f64"));
+  }
+
+  #[test]
+  fn invalid_default_float_type () {
+    let (ty, output) = default_float_declaration_ty("s64", "invalid_default_float.ms");
+
+    assert_eq!(output.error_count(), 1, "{:#?}", output.messages);
+    assert!(output.messages[0].items[0].content.starts_with("`s64` is not a floating point type"));
+    assert_eq!(Some(ty), output.context.core_bs.get_entry("f32"));
+  }
+
+  #[test]
+  fn hand_built_ast () {
+    use mod_common::Operator;
+    use crate::{ session::SESSION, test_support::lock_singletons, };
+
+    let ast = vec! [
+      ast::Item::export(ast::Item::function(
+        "add_twice",
+        vec! [
+          ast::LocalDeclaration::no_src("a".into(), "s32".into()),
+          ast::LocalDeclaration::no_src("b".into(), "s32".into()),
+        ],
+        Some("s32".into()),
+        ast::Block::no_src(
+          vec! [ ast::Statement::declaration("c", None, Some(ast::Expression::binary(Operator::Add, "a", "b"))) ],
+          Some(ast::Expression::binary(Operator::Add, "c", "c"))
+        )
+      )),
+    ];
+
+    let _guard = lock_singletons();
+    let message_base = SESSION.messages().len();
+
+    let (context, _) = Analyzer::new().analyze(ast);
+
+    let messages = &SESSION.messages()[message_base..];
+    assert!(messages.is_empty(), "{:#?}", messages);
+
+    let main_ns = context.items.get(context.main_ns).unwrap().ref_namespace().unwrap();
+    let function = context.items.get(main_ns.export_bindings.get_entry("add_twice").unwrap()).unwrap().ref_function().unwrap();
+
+    assert_eq!(function.params.len(), 2);
+    assert_eq!(function.return_ty, context.core_bs.get_entry("s32"));
+    assert!(function.body.is_some());
+  }
+
+  #[test]
+  fn empty_struct_and_namespace () {
+    use crate::session::MessageKind;
+
+    let output = analyze_str("empty_struct_and_namespace.ms", r#"
+      struct Empty { }
+      ns nothing { }
+    "#);
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let notices: Vec<_> = output.of_kind(MessageKind::Notice).map(|message| message.items[0].content.as_str()).collect();
+
+    assert_eq!(notices, [
+      "Struct `Empty` has no fields, it will have a size of 0",
+      "Namespace `nothing` is empty",
+    ]);
+  }
+
+  #[test]
+  fn typeof_declaration () {
+    let output = analyze_str("typeof_declaration.ms", "fn f () { let x: typeof(1 + 2u32) = 0; let y: u8 = 2; let z: typeof(y * 3) = 0; }");
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let function = output.context.items.get(output.context.functions[0]).unwrap().ref_function().unwrap();
+
+    let declaration_ty = |index: usize| match function.body.as_ref().unwrap().statements[index].data {
+      ir::StatementData::Declaration { ty, .. } => ty,
+      ref other => panic!("expected declaration, got {:?}", other)
+    };
+
+    assert_eq!(Some(declaration_ty(0)), output.context.core_bs.get_entry("u32"));
+    assert_eq!(Some(declaration_ty(2)), output.context.core_bs.get_entry("u8"));
+  }
+
+  #[test]
+  fn typeof_outside_body () {
+    let output = analyze_str("typeof_outside_body.ms", "global X: typeof(1) = 1;");
+
+    assert!(output.error_count() >= 1, "{:#?}", output.messages);
+    assert_eq!(output.messages[0].items[0].content, "`typeof` can only be used inside a function body");
+  }
+
+  #[test]
+  fn int_float_mix () {
+    let output = analyze_str("int_float_mix.ms", "fn f () { let x = 1 + 2.0; }");
+
+    assert_eq!(output.error_count(), 1, "{:#?}", output.messages);
+    assert!(output.messages[0].items[0].content.starts_with(
+      "The subexpressions for this binary operator mix integer and floating point types (left: `s32`, right: `f32`)"
+    ));
+  }
+
+  #[test]
+  fn float_float () {
+    let output = analyze_str("float_float.ms", "fn f () { let x = 1.0 + 2.0; }");
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+  }
+
+  fn import_closure_names (output: &crate::test_support::TestOutput) -> Vec<String> {
+    output.context.import_closure(output.context.main_mod).into_iter()
+      .map(|key| output.context.get_item_canonical_name(key).unwrap().to_string())
+      .collect()
+  }
+
+  #[test]
+  fn import_closure_chain () {
+    // CodegenTests imports GlobalDef
+    let output = analyze_str("import_closure_chain.ms", "import CodegenTests;");
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+    assert_eq!(import_closure_names(&output), [ "CodegenTests", "GlobalDef" ]);
+  }
+
+  #[test]
+  fn import_closure_cycle () {
+    // Circle and Declaration import each other
+    let output = analyze_str("import_closure_cycle.ms", "import Circle;");
+
+    assert_eq!(import_closure_names(&output), [ "Circle", "Declaration" ]);
+  }
+
+  #[test]
+  fn import_cycle () {
+    use crate::session::MessageKind;
+
+    // Circle and Declaration import each other
+    let output = analyze_str("import_cycle.ms", "import Circle;");
+
+    let warnings: Vec<_> = output.of_kind(MessageKind::Warning).map(|message| message.items[0].content.as_str()).collect();
+
+    assert_eq!(warnings, [ "Import cycle detected: `Circle` -> `Declaration` -> `Circle`" ]);
+  }
+
+  #[test]
+  fn custom_main_module_name () {
+    let output = analyze_str_with(Analyzer::new().with_main_module_name("app"), "custom_main_module_name.ms", "export fn f () { }");
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let context = &output.context;
+
+    assert_eq!(context.modules.get("app"), Some(&context.main_mod));
+    assert_eq!(context.modules.get("main"), None);
+    assert_eq!(context.get_item_canonical_name(context.main_mod).unwrap().as_ref(), "app");
+
+    let function_key = context.get_key_from_path(&[ "f" ]).unwrap();
+
+    assert_eq!(context.get_item_module(function_key), Some(context.main_mod));
+  }
+
+  #[test]
+  fn builtin_function () {
+    let mut analyzer = Analyzer::new();
+
+    let f32_ty = analyzer.context.core_bs.get_entry("f32").unwrap();
+    let sqrt = analyzer.context.register_builtin_function("sqrt", &[ ("value", f32_ty) ], Some(f32_ty));
+
+    let output = analyze_str_with(analyzer, "builtin_function.ms", "export fn length (x: f32, y: f32) -> f32 { sqrt(x * x + y * y) }");
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let context = &output.context;
+
+    let builtin = context.items.get(sqrt).unwrap().ref_function().unwrap();
+    assert!(builtin.is_intrinsic);
+    assert_eq!(context.get_item_module(sqrt), Some(context.core_mod));
+
+    let length = context.items.get(context.get_key_from_path(&[ "length" ]).unwrap()).unwrap().ref_function().unwrap();
+    assert!(!length.is_intrinsic);
+
+    match &length.body.as_ref().unwrap().trailing_expression.as_ref().expect("body has a trailing expression").data {
+      ir::ExpressionData::Call { callee: box ir::Expression { data: ir::ExpressionData::Reference(ir::Reference::Global(callee_key)), .. }, .. }
+      => assert_eq!(*callee_key, sqrt),
+      other => panic!("expected call, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn assignment_mutability () {
+    let output = analyze_str("assignment_mutability.ms", "\
+      global X: s32 = 1;
+      global mut Y: s32 = 2;
+
+      fn f (p: s32) {
+        let a: s32 = 1;
+        let mut b: s32 = 2;
+
+        a = 3;
+        b = 4;
+        p = 5;
+        X = 6;
+        Y = 7;
+      }
+    ");
+
+    let errors: Vec<_> = output.of_kind(MessageKind::Error).map(|message| message.items[0].content.as_str()).collect();
+
+    assert_eq!(errors.len(), 2, "{:#?}", output.messages);
+    assert!(errors[0].contains("immutable local variable `a`"), "{:#?}", errors);
+    assert!(errors[1].contains("immutable global `X`"), "{:#?}", errors);
+  }
+
+  #[test]
+  fn assignment_to_import () {
+    let output = analyze_str("assignment_to_import.ms", "\
+      import GlobalDef;
+
+      fn f () {
+        GlobalDef::X = 1;
+      }
+    ");
+
+    let errors: Vec<_> = output.of_kind(MessageKind::Error).collect();
+
+    assert_eq!(errors.len(), 1, "{:#?}", output.messages);
+    assert!(errors[0].items[0].content.contains("imported from external module `GlobalDef`"), "{:#?}", errors);
+
+    let context = &output.context;
+
+    let global = context.globals.iter()
+      .map(|&key| context.items.get(key).unwrap().ref_global().unwrap())
+      .find(|global| global.parent_module != context.main_mod)
+      .unwrap();
+
+    assert_eq!(errors[0].items[1].origin, global.origin);
+  }
+
+  #[test]
+  fn shadowed_item_related_location () {
+    let output = analyze_str("shadowed_item_related_location.ms", "global value: s32 = 1;\nglobal value: s32 = 2;\n");
+
+    let errors: Vec<_> = output.of_kind(MessageKind::Error).filter(|message| message.items[0].content.contains("shadows existing Global")).collect();
+
+    assert_eq!(errors.len(), 1, "{:#?}", output.messages);
+    assert_eq!(errors[0].items[0].origin.start.line, 1);
+
+    let (previous, label) = &errors[0].related[0];
+
+    assert_eq!(previous.start.line, 0);
+    assert_eq!(label, "previous definition of `value` here");
+    assert!(errors[0].to_string().contains("note: previous definition of `value` here"), "{}", errors[0]);
+  }
+
+  fn lint_todo (analyzer: &Analyzer, item: &ast::Item) {
+    if let ast::ItemData::Function { identifier, .. } = &item.data {
+      if identifier.as_ref() == "todo" {
+        analyzer.warning(item.origin, "Function `todo` should be implemented before release".to_owned());
+      }
+    }
+  }
+
+  #[test]
+  fn custom_lint () {
+    let output = analyze_str_with(Analyzer::new().with_lint(lint_todo), "custom_lint.ms", "\
+      fn todo () { }
+      fn done () { }
+
+      ns nested {
+        fn todo () { }
+      }
+    ");
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let warnings: Vec<_> = output.of_kind(MessageKind::Warning).map(|message| message.items[0].content.as_str()).collect();
+
+    assert_eq!(warnings.iter().filter(|warning| warning.contains("`todo`")).count(), 2, "{:#?}", warnings);
+  }
+
+  #[test]
+  fn pow_lowering () {
+    let output = analyze_str("pow_lowering.ms", "\
+      export fn cube (x: s64) -> s64 { x ** 3 }
+      export fn float_pow (x: f32) -> f32 { x ** x }
+    ");
+
+    let errors: Vec<_> = output.of_kind(MessageKind::Error).map(|message| message.items[0].content.as_str()).collect();
+
+    assert_eq!(errors.len(), 1, "{:#?}", output.messages);
+    assert!(errors[0].contains("only available for integer types"), "{:#?}", errors);
+
+    let context = &output.context;
+
+    let cube = context.items.get(context.get_key_from_path(&[ "cube" ]).unwrap()).unwrap().ref_function().unwrap();
+
+    match &cube.body.as_ref().unwrap().trailing_expression.as_ref().expect("body has a trailing expression").data {
+      ir::ExpressionData::Call { callee: box ir::Expression { data: ir::ExpressionData::Reference(ir::Reference::Global(callee_key)), .. }, arguments } => {
+        let intrinsic = context.items.get(*callee_key).unwrap().ref_function().unwrap();
+
+        assert!(intrinsic.is_intrinsic);
+        assert_eq!(intrinsic.canonical_name.as_ref(), "pow_s64");
+        assert_eq!(intrinsic.return_ty, cube.return_ty);
+        assert_eq!(arguments.len(), 2);
+        assert!(arguments.iter().all(|argument| Some(argument.ty) == cube.return_ty));
+      },
+      other => panic!("expected call, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn match_lowering () {
+    use mod_common::{ Operator, Constant, };
+
+    let output = analyze_str("match_lowering.ms", "\
+      export fn pick (x: s32) -> s32 { match x { 1 => 10, _ => 20 } }
+      export fn pick_sum (x: s32) -> s32 { match x + 1 { 1 => 10, _ => 20 } }
+      export fn pick_bool (b: bool) -> s32 { match b { true => 1, false => 0 } }
+      export fn partial (x: s32) -> s32 { match x { 1 => 10 } }
+    ");
+
+    let errors: Vec<_> = output.of_kind(MessageKind::Error).map(|message| message.items[0].content.as_str()).collect();
+
+    assert_eq!(errors.len(), 1, "{:#?}", output.messages);
+    assert!(errors[0].contains("not exhaustive"), "{:#?}", errors);
+
+    let context = &output.context;
+    let body_of = |name: &str| context.items.get(context.get_key_from_path(&[ name ]).unwrap()).unwrap().ref_function().unwrap().body.as_ref().unwrap();
+
+    let conditional = match &body_of("pick").trailing_expression.as_ref().unwrap().data {
+      ir::ExpressionData::Conditional(conditional) => conditional,
+      other => panic!("expected conditional, got {:?}", other)
+    };
+
+    match &conditional.if_branch.condition.data {
+      ir::ExpressionData::Binary { left, right, operator: Operator::Equal } => {
+        assert_eq!(left.data, ir::ExpressionData::Reference(ir::Reference::Local { is_parameter: true, index: 0 }));
+        assert!(matches!(&right.data, ir::ExpressionData::Coerce(box ir::Expression { data: ir::ExpressionData::Constant(constant), .. }) if *constant == Constant::Number(1u64.into())));
+      },
+      other => panic!("expected equality comparison, got {:?}", other)
+    }
+
+    assert!(conditional.else_if_branches.is_empty());
+    assert!(conditional.else_block.as_ref().unwrap().trailing_expression.is_some());
+
+    // A scrutinee which is not a simple reference is evaluated once, into a temporary
+    match &body_of("pick_sum").trailing_expression.as_ref().unwrap().data {
+      ir::ExpressionData::Block(box ir::Block { statements, trailing_expression: Some(ir::Expression { data: ir::ExpressionData::Conditional(conditional), .. }), .. }) => {
+        assert!(matches!(statements.as_slice(), [ ir::Statement { data: ir::StatementData::Declaration { initializer: Some(_), .. }, .. } ]));
+        assert!(matches!(
+          &conditional.if_branch.condition.data,
+          ir::ExpressionData::Binary { left: box ir::Expression { data: ir::ExpressionData::Reference(ir::Reference::Local { is_parameter: false, index: 0 }), .. }, .. }
+        ));
+      },
+      other => panic!("expected block binding the scrutinee, got {:?}", other)
+    }
+
+    // Covering both boolean values is exhaustive without a wildcard
+    match &body_of("pick_bool").trailing_expression.as_ref().unwrap().data {
+      ir::ExpressionData::Conditional(conditional) => {
+        assert!(conditional.else_if_branches.is_empty());
+        assert!(conditional.else_block.is_some());
+      },
+      other => panic!("expected conditional, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn match_bool_exhaustiveness () {
+    let output = analyze_str("match_bool_exhaustiveness.ms", "\
+      export fn exhaustive (b: bool) -> s32 { match b { false => 0, true => 1 } }
+      export fn only_true (b: bool) -> s32 { match b { true => 1 } }
+      export fn repeated (b: bool) -> s32 { match b { false => 0, false => 1 } }
+      export fn wildcard (b: bool) -> s32 { match b { true => 1, _ => 0 } }
+    ");
+
+    let errors: Vec<_> = output.of_kind(MessageKind::Error).map(|message| message.items[0].content.as_str()).collect();
+
+    assert_eq!(errors.len(), 2, "{:#?}", output.messages);
+    assert!(errors[0].contains("does not cover `false`"), "{:#?}", errors);
+    assert!(errors[1].contains("does not cover `true`"), "{:#?}", errors);
+
+    let context = &output.context;
+
+    for &name in &[ "exhaustive", "wildcard" ] {
+      let function = context.items.get(context.get_key_from_path(&[ name ]).unwrap()).unwrap().ref_function().unwrap();
+
+      match &function.body.as_ref().unwrap().trailing_expression.as_ref().unwrap().data {
+        ir::ExpressionData::Conditional(conditional) => assert!(conditional.else_if_branches.is_empty() && conditional.else_block.is_some()),
+        other => panic!("expected conditional, got {:?}", other)
+      }
+    }
+  }
+
+  #[test]
+  fn enum_construction_and_matching () {
+    let output = analyze_str("enum_construction_and_matching.ms", "\
+      export enum Shape { Circle(s32), Empty }
+      export fn make (r: s32) -> Shape { Shape::Circle(r) }
+      export fn empty () -> Shape { Shape::Empty }
+      export fn radius (s: Shape) -> s32 { match s { Circle(r) => r, Empty => 0 } }
+      export fn partial (s: Shape) -> s32 { match s { Circle(r) => r } }
+      export fn missing_payload () -> Shape { Shape::Circle }
+    ");
+
+    let errors: Vec<_> = output.of_kind(MessageKind::Error).map(|message| message.items[0].content.as_str()).collect();
+
+    assert_eq!(errors.len(), 2, "{:#?}", output.messages);
+    assert!(errors[0].contains("does not cover `Empty`"), "{:#?}", errors);
+    assert!(errors[1].contains("must be constructed with exactly one argument"), "{:#?}", errors);
+
+    let context = &output.context;
+    let shape_ty = context.get_key_from_path(&[ "Shape" ]).unwrap();
+    let body_of = |name: &str| context.items.get(context.get_key_from_path(&[ name ]).unwrap()).unwrap().ref_function().unwrap().body.as_ref().unwrap();
+
+    let make = body_of("make").trailing_expression.as_ref().unwrap();
+
+    assert_eq!(make.ty, shape_ty);
+    assert!(matches!(
+      &make.data,
+      ir::ExpressionData::Variant { tag: 0, payload: Some(box ir::Expression { data: ir::ExpressionData::Reference(ir::Reference::Local { is_parameter: true, index: 0 }), .. }) }
+    ), "{:?}", make);
+
+    assert_eq!(body_of("empty").trailing_expression.as_ref().unwrap().data, ir::ExpressionData::Variant { tag: 1, payload: None });
+
+    match &body_of("radius").trailing_expression.as_ref().unwrap().data {
+      ir::ExpressionData::Conditional(conditional) => {
+        assert!(matches!(
+          &conditional.if_branch.condition.data,
+          ir::ExpressionData::IsVariant { operand: box ir::Expression { data: ir::ExpressionData::Reference(ir::Reference::Local { is_parameter: true, index: 0 }), .. }, tag: 0 }
+        ));
+
+        // The payload is bound to a local declared at the start of the arm body
+        let body = &conditional.if_branch.body;
+
+        assert!(matches!(
+          body.statements.as_slice(),
+          [ ir::Statement { data: ir::StatementData::Declaration { initializer: Some(ir::Expression { data: ir::ExpressionData::Payload { tag: 0, .. }, .. }), .. }, .. } ]
+        ));
+        assert_eq!(body.trailing_expression.as_ref().unwrap().data, ir::ExpressionData::Reference(ir::Reference::Local { is_parameter: false, index: 0 }));
+
+        assert!(conditional.else_if_branches.is_empty());
+        assert!(conditional.else_block.is_some());
+      },
+      other => panic!("expected conditional, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn optional_checking () {
+    let output = analyze_str("optional_checking.ms", "\
+      export fn unchecked (x: ?s32) -> s32 { let y: s32 = x; y }
+      export fn unchecked_arithmetic (x: ?s32) -> s32 { let y: s32 = x + 1; y }
+      export fn checked (x: ?s32) -> s32 { match x { Some(v) => v + 1, None => 0 } }
+      export fn wrapped (v: s32) -> ?s32 { let x: ?s32 = v; x }
+      export fn absent () -> ?s32 { let x: ?s32 = null; x }
+    ");
+
+    let errors: Vec<_> = output.of_kind(MessageKind::Error).map(|message| message.items[0].content.as_str()).collect();
+
+    // Only the unchecked uses are errors, and both explain that the value must be checked
+    assert_eq!(errors.len(), 2, "{:#?}", output.messages);
+    assert!(errors[0].contains("`?s32`") && errors[0].contains("will not automatically coerce"), "{:#?}", errors);
+    assert!(errors[1].contains("`?s32`") && errors[1].contains("does not support the operator `+`"), "{:#?}", errors);
+    assert!(errors.iter().all(|error| error.contains("must be checked with a `match`")), "{:#?}", errors);
+
+    let context = &output.context;
+    let body_of = |name: &str| context.items.get(context.get_key_from_path(&[ name ]).unwrap()).unwrap().ref_function().unwrap().body.as_ref().unwrap();
+
+    match &body_of("checked").trailing_expression.as_ref().unwrap().data {
+      ir::ExpressionData::Conditional(conditional) => {
+        assert!(matches!(&conditional.if_branch.condition.data, ir::ExpressionData::IsVariant { tag: 0, .. }));
+        assert!(matches!(
+          conditional.if_branch.body.statements.as_slice(),
+          [ ir::Statement { data: ir::StatementData::Declaration { initializer: Some(ir::Expression { data: ir::ExpressionData::Payload { tag: 0, .. }, .. }), .. }, .. } ]
+        ));
+        assert!(conditional.else_block.is_some());
+      },
+      other => panic!("expected conditional, got {:?}", other)
+    }
+
+    // Values and `null` coerce into optionals implicitly
+    for &name in &[ "wrapped", "absent" ] {
+      match &body_of(name).statements[0].data {
+        ir::StatementData::Declaration { initializer: Some(initializer), ty, .. } => {
+          assert!(matches!(initializer.data, ir::ExpressionData::Coerce(_)), "{:?}", initializer);
+          assert!(matches!(context.items.get(*ty).unwrap().ref_type().unwrap().data, Some(ctx::TypeData::Optional(_))));
+        },
+        other => panic!("expected declaration, got {:?}", other)
+      }
+    }
+  }
+
+
+  #[test]
+  fn unresolved_path_spelling () {
+    let output = analyze_str("unresolved_path_spelling.ms", "\
+      ns inner { }
+
+      alias inner::aliased;
+
+      fn relative () -> s32 { inner::missing }
+      fn absolute () -> s32 { ::inner::missing }
+    ");
+
+    let errors: Vec<_> = output.of_kind(MessageKind::Error).map(|message| message.items[0].content.as_str()).collect();
+
+    assert_eq!(errors.len(), 3, "{:#?}", output.messages);
+    assert!(errors.iter().any(|error| error.contains("`inner::aliased` (relative to")), "{:#?}", errors);
+    assert!(errors.iter().any(|error| error.contains("`inner::missing` (relative to")), "{:#?}", errors);
+    assert!(errors.iter().any(|error| error.contains("`::inner::missing`") && !error.contains("relative to")), "{:#?}", errors);
+    assert!(errors.iter().all(|error| !error.contains("`::inner`")), "{:#?}", errors);
+  }
+
+  #[test]
+  fn global_initializer_order () {
+    let output = analyze_str("global_initializer_order.ms", "\
+      global A: s32 = get_b() + 1;
+      global B: s32 = C * 2;
+      global C: s32 = 3;
+      global D: s32 = 4;
+
+      fn get_b () -> s32 { B }
+    ");
+
+    assert_eq!(output.error_count(), 0, "{:#?}", output.messages);
+
+    let context = &output.context;
+    let global = |key: ctx::ContextKey| context.items.get(key).unwrap().ref_global().unwrap();
+
+    let order: Vec<&str> = context.globals.iter().map(|&key| global(key)).filter(|global| global.parent_module == context.main_mod).map(|global| global.canonical_name.as_ref()).collect();
+    assert_eq!(order, [ "C", "B", "A", "D" ]);
+
+    for (rank, &key) in context.globals.iter().filter(|&&key| global(key).parent_module == context.main_mod).enumerate() {
+      assert_eq!(global(key).rank, rank);
+    }
+  }
+
+  #[test]
+  fn global_initializer_cycle () {
+    let output = analyze_str("global_initializer_cycle.ms", "\
+      global X: s32 = get_y();
+      global Y: s32 = X + 1;
+      global Z: s32 = 2;
+
+      fn get_y () -> s32 { Y }
+    ");
+
+    let errors: Vec<_> = output.of_kind(MessageKind::Error).collect();
+
+    assert_eq!(errors.len(), 1, "{:#?}", output.messages);
+    assert!(errors[0].items[0].content.contains("Circular dependency"), "{:#?}", errors);
+    assert_eq!(errors[0].items.len(), 3, "{:#?}", errors);
+    assert!(errors[0].items[1].content.contains("`X` depends on `Y`"), "{:#?}", errors);
+    assert!(errors[0].items[2].content.contains("`Y` depends on `X`"), "{:#?}", errors);
+  }
+
+  #[test]
+  fn global_read_before_init () {
+    let output = analyze_str("global_read_before_init.ms", "\
+      global FIRST: s32 = SECOND + 1;
+      global SECOND: s32 = read_first();
+
+      fn read_first () -> s32 { FIRST }
+    ");
+
+    let errors: Vec<_> = output.of_kind(MessageKind::Error).collect();
+
+    // the read of `FIRST` before it is initialized is only possible through a cycle, so it is reported once, as the cycle
+    assert_eq!(errors.len(), 1, "{:#?}", output.messages);
+    assert!(errors[0].items[0].content.contains("Circular dependency"), "{:#?}", errors);
+    assert!(errors[0].items.iter().any(|item| item.content.contains("`SECOND` depends on `FIRST`")), "{:#?}", errors);
+  }
+
+  #[test]
+  fn include_directive () {
+    let included = {
+      let _guard = lock_singletons();
+
+      SOURCE_MANAGER.add_source("include_directive/shapes.ms", "\
+        global AREA: s32 = 4;
+        global FLAG: bool = AREA;
+      ")
+    };
+
+    let output = analyze_str("include_directive/main.ms", "\
+      include \"shapes.ms\";
+      global DOUBLE: s32 = AREA * 2;
+    ");
+
+    let context = &output.context;
+    let get_global = |name: &str| context.globals.iter().map(|&key| context.items.get(key).unwrap().ref_global().unwrap()).find(|global| global.canonical_name.as_ref() == name).unwrap();
+
+    assert_eq!(get_global("AREA").origin.source, included);
+    assert_eq!(get_global("FLAG").origin.source, included);
+    assert_eq!(get_global("DOUBLE").origin.source, output.source);
+
+    // The type error in the included file is attributed to it
+    assert!(output.error_count() > 0);
+    assert!(output.of_kind(MessageKind::Error).all(|error| error.items[0].origin.source == included), "{:#?}", output.messages);
+  }
+
+  #[test]
+  fn include_cycle () {
+    let included = {
+      let _guard = lock_singletons();
+
+      SOURCE_MANAGER.add_source("include_cycle/b.ms", "include \"a.ms\"; global B: s32 = 2;")
+    };
+
+    let output = analyze_str("include_cycle/a.ms", "include \"b.ms\"; global A: s32 = B;");
+
+    let errors: Vec<_> = output.of_kind(MessageKind::Error).collect();
+
+    assert_eq!(errors.len(), 1, "{:#?}", output.messages);
+    assert!(errors[0].items[0].content.contains("Circular include"));
+    assert_eq!(errors[0].items[0].origin.source, included);
+  }
+
+  #[test]
+  fn conditional_compilation () {
+    let source = "\
+      global ALWAYS: s32 = 1;
+
+      #if feature \"extra\"
+        global EXTRA: s32 = ALWAYS + 1;
+
+        #if feature \"unknown\"
+          global NEVER: s32 = 0;
+        #endif
+      #else
+        global FALLBACK: s32 = ALWAYS - 1;
+      #endif
+    ";
+
+    let global_names = |context: &ctx::Context| -> Vec<String> {
+      context.globals.iter().map(|&key| context.items.get(key).unwrap().ref_global().unwrap().canonical_name.to_string()).collect()
+    };
+
+    let without = analyze_str("conditional_compilation_off.ms", source);
+
+    {
+      let _guard = lock_singletons();
+      SESSION.enable_feature("extra");
+    }
+
+    let with = analyze_str("conditional_compilation_on.ms", source);
+
+    {
+      let _guard = lock_singletons();
+      assert!(SESSION.disable_feature("extra"));
+    }
+
+    assert_eq!(without.error_count(), 0, "{:#?}", without.messages);
+    assert_eq!(with.error_count(), 0, "{:#?}", with.messages);
+
+    assert_eq!(global_names(&without.context), vec! [ "ALWAYS", "FALLBACK" ]);
+    assert_eq!(global_names(&with.context), vec! [ "ALWAYS", "EXTRA" ]);
+  }
 }
\ No newline at end of file
@@ -9,6 +9,7 @@ use super::{
   Analyzer,
   support_structures::{ Pseudonym, PseudonymKind, PseudonymPayload, },
   ty_helpers::{ ty_from_anon_data, },
+  eval_helpers::{ path_spelling, },
 };
 
 
@@ -119,7 +120,8 @@ fn resolve_path (analyzer: &mut Analyzer, pseudonyms: &mut Vec<Pseudonym>, relat
       } else if let Some(core) = analyzer.context.core_bs.get_entry(ident) {
         core
       } else {
-        analyzer.error(path.origin, format!("Namespace `{}` does not have access to an item named `{}`", base_name, ident));
+        let spelling = path_spelling(analyzer, path, relative_to);
+        analyzer.error(path.origin, format!("Cannot resolve path {}: Namespace `{}` does not have access to an item named `{}`", spelling, base_name, ident));
         return None
       }
     } else if let Some(exported_key) = namespace.export_bindings.get_entry(ident) {
@@ -129,7 +131,8 @@ fn resolve_path (analyzer: &mut Analyzer, pseudonyms: &mut Vec<Pseudonym>, relat
       // TODO should unresolved pseudonyms link an error item? (probably)
       resolve_pseudonym(analyzer, pseudonyms, pseudonym)?
     } else {
-      analyzer.error(path.origin, format!("Namespace `{}` does not export an item named `{}`", base_name, ident));
+      let spelling = path_spelling(analyzer, path, relative_to);
+      analyzer.error(path.origin, format!("Cannot resolve path {}: Namespace `{}` does not export an item named `{}`", spelling, base_name, ident));
       return None
     };
     
@@ -148,6 +151,11 @@ fn resolve_texpr (analyzer: &mut Analyzer, pseudonyms: &mut Vec<Pseudonym>, rela
 
       Some(ty_from_anon_data(analyzer, TypeData::Pointer(sub_key), texpr.origin))
     },
+    TypeExpressionData::Optional(box sub_texpr) => {
+      let sub_key = resolve_texpr(analyzer, pseudonyms, relative_to, sub_texpr)?;
+
+      Some(ty_from_anon_data(analyzer, TypeData::Optional(sub_key), texpr.origin))
+    },
     TypeExpressionData::Function { parameter_types, return_type } => {
       let mut param_keys = Vec::new();
 
@@ -158,6 +166,11 @@ fn resolve_texpr (analyzer: &mut Analyzer, pseudonyms: &mut Vec<Pseudonym>, rela
       let ret_key = if let box Some(ret_texpr) = return_type { Some(resolve_texpr(analyzer, pseudonyms, relative_to, ret_texpr)?) } else { None };
 
       Some(ty_from_anon_data(analyzer, TypeData::Function { parameter_types: param_keys, return_type: ret_key }, texpr.origin))
-    }
+    },
+    TypeExpressionData::TypeOf(_) => {
+      analyzer.error(texpr.origin, "`typeof` can only be used inside a function body".to_owned());
+
+      None
+    },
   }
 }
\ No newline at end of file
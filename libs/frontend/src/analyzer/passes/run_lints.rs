@@ -0,0 +1,32 @@
+use crate::{
+  ast::{ Item, ItemData, },
+};
+
+use super::{
+  Analyzer,
+};
+
+
+
+/// Runs each custom Lint registered with the Analyzer on every Item in an ast,
+/// descending into the Items of inline and file Namespaces
+pub fn run_lints (analyzer: &mut Analyzer, ast: &[Item]) {
+  if analyzer.lints.is_empty() { return }
+
+  for item in ast.iter() {
+    lint_item(analyzer, item);
+  }
+}
+
+
+fn lint_item (analyzer: &Analyzer, item: &Item) {
+  for lint in analyzer.lints.iter() {
+    lint(analyzer, item);
+  }
+
+  if let ItemData::Namespace { items, .. } = &item.data {
+    for sub_item in items.iter() {
+      lint_item(analyzer, sub_item);
+    }
+  }
+}
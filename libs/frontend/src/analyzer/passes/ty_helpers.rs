@@ -9,7 +9,7 @@ use mod_common::{ Operator, Constant, Number, };
 
 use crate::{
   source::{ SourceRegion, },
-  ctx::{ Type, Global, Function, ContextItem, ContextKey, TypeData, PrimitiveType, CoercibleType, TypeDisplay, },
+  ctx::{ Global, Function, ContextItem, ContextKey, TypeData, PrimitiveType, CoercibleType, TypeDisplay, },
   ir,
 };
 
@@ -33,6 +33,7 @@ pub fn ty_from_unary (analyzer: &mut Analyzer, operand_tk: ContextKey, operator:
     | TypeData::Coercible(CoercibleType::Integer)
     | TypeData::Coercible(CoercibleType::FloatingPoint)
     | TypeData::Primitive(PrimitiveType::Bool)
+    | TypeData::Primitive(PrimitiveType::Char)
     | TypeData::Primitive(PrimitiveType::Integer { .. })
     | TypeData::Primitive(PrimitiveType::FloatingPoint { .. })
 
@@ -48,6 +49,13 @@ pub fn ty_from_unary (analyzer: &mut Analyzer, operand_tk: ContextKey, operator:
     => operand_tk,
 
 
+    | TypeData::Coercible(CoercibleType::Integer)
+    | TypeData::Primitive(PrimitiveType::Integer { .. })
+
+    if matches!(operator, Operator::BitNot)
+    => operand_tk,
+
+
     | TypeData::Coercible(CoercibleType::Integer)
     | TypeData::Coercible(CoercibleType::FloatingPoint)
     | TypeData::Primitive(PrimitiveType::Integer { .. })
@@ -61,9 +69,10 @@ pub fn ty_from_unary (analyzer: &mut Analyzer, operand_tk: ContextKey, operator:
 
     _ => {
       analyzer.error(origin, format!(
-        "This expression type (`{}`) is not compatible with unary operator `{}`",
+        "This expression type (`{}`) is not compatible with unary operator `{}`{}",
         TypeDisplay { ty_key: operand_tk, context: &analyzer.context },
-        operator.value()
+        operator.value(),
+        ty_optional_hint(analyzer, operand_tk)
       ));
       return None
     }
@@ -122,6 +131,22 @@ pub fn ty_will_coerce (analyzer: &Analyzer, allow_int_to_ptr: bool, from_tk: Con
       => true,
 
 
+      // Void pointers such as `null` coerce into any optional as `None`,
+      // this takes precedence over wrapping them as the payload of an optional pointer
+      | (&TypeData::Pointer(from_p)
+      ,  &TypeData::Optional(_))
+
+      if from_p == analyzer.context.void_ty
+      => true,
+
+
+      // Any other value coerces into an optional as `Some`, if it will coerce into the payload type
+      | (_
+      ,  &TypeData::Optional(payload_tk))
+
+      => ty_will_coerce(analyzer, allow_int_to_ptr, from_tk, payload_tk),
+
+
       _ => false
     }
   } else {
@@ -130,6 +155,21 @@ pub fn ty_will_coerce (analyzer: &Analyzer, allow_int_to_ptr: bool, from_tk: Con
 }
 
 
+/// Determine if one of a pair of types is an integer and the other is a floating point number
+/// 
+/// Integers and floating point numbers never coerce into one another,
+/// conversion between them must be explicit
+pub fn ty_is_int_float_mix (analyzer: &Analyzer, a_tk: ContextKey, b_tk: ContextKey) -> bool {
+  let is_int_or_float = |tk| match analyzer.context.items.get(tk).unwrap().ref_type().unwrap().data {
+    Some(TypeData::Primitive(PrimitiveType::Integer { .. }) | TypeData::Coercible(CoercibleType::Integer)) => Some(true),
+    Some(TypeData::Primitive(PrimitiveType::FloatingPoint { .. }) | TypeData::Coercible(CoercibleType::FloatingPoint)) => Some(false),
+    _ => None
+  };
+
+  matches!((is_int_or_float(a_tk), is_int_or_float(b_tk)), (Some(a), Some(b)) if a != b)
+}
+
+
 /// Get the type coerced union of two types, if one is available
 /// 
 /// Allows control of conversion from integers to pointers via `allow_int_to_ptr`
@@ -175,9 +215,20 @@ pub fn ty_meet_n (analyzer: &mut Analyzer, allow_int_to_ptr: bool, tks: &[Contex
 
 
 /// Handles type coercion, wrapping an IR node into a new IR Coerce node if necessary
-pub fn ty_handle_coercion (coerce_ty: ContextKey, expr_ir: &mut ir::Expression) {
+pub fn ty_handle_coercion (analyzer: &Analyzer, coerce_ty: ContextKey, expr_ir: &mut ir::Expression) {
   if expr_ir.ty == coerce_ty { return }
 
+  // Negated literals are coerced as a unit, so the literal itself takes on the concrete type
+  if let ir::ExpressionData::Unary { operand, operator: Operator::Sub } = &mut expr_ir.data {
+    if let ir::ExpressionData::Constant(constant) = &operand.data {
+      ty_check_negated_literal(analyzer, coerce_ty, constant, expr_ir.origin);
+
+      ty_handle_coercion(analyzer, coerce_ty, operand);
+      expr_ir.ty = coerce_ty;
+      return
+    }
+  }
+
   let mut new_ir = ir::Expression::new(
     ir::ExpressionData::Constant(0u64.into()), // placeholder data
     coerce_ty,
@@ -189,6 +240,31 @@ pub fn ty_handle_coercion (coerce_ty: ContextKey, expr_ir: &mut ir::Expression)
   expr_ir.data = ir::ExpressionData::Coerce(box new_ir);
 }
 
+/// Report an Error if a negated integer literal cannot be represented by the integer type it is coerced to
+fn ty_check_negated_literal (analyzer: &Analyzer, coerce_ty: ContextKey, constant: &Constant, origin: SourceRegion) {
+  let magnitude = if let &Constant::Number(Number::Integer(magnitude)) = constant { magnitude } else { return };
+
+  let (signed, bit_size) = match analyzer.context.items.get(coerce_ty).and_then(ContextItem::ref_type).and_then(|ty| ty.data.as_ref()) {
+    Some(&TypeData::Primitive(PrimitiveType::Integer { signed, bit_size })) => (signed, bit_size),
+    _ => return
+  };
+
+  if !signed {
+    analyzer.error(origin, format!(
+      "The negated literal `-{}` cannot be represented by the unsigned type `{}`",
+      magnitude,
+      TypeDisplay { ty_key: coerce_ty, context: &analyzer.context }
+    ));
+  } else if bit_size <= 64 && magnitude > 1u64 << (bit_size - 1) {
+    analyzer.error(origin, format!(
+      "The negated literal `-{}` is out of range for the type `{}`, which has a minimum value of `-{}`",
+      magnitude,
+      TypeDisplay { ty_key: coerce_ty, context: &analyzer.context },
+      1u64 << (bit_size - 1)
+    ));
+  }
+}
+
 /// Get a type key from a constant
 pub fn ty_of_constant (analyzer: &mut Analyzer, constant: &Constant, origin: SourceRegion) -> ContextKey {
   match constant {
@@ -196,7 +272,12 @@ pub fn ty_of_constant (analyzer: &mut Analyzer, constant: &Constant, origin: Sou
     Constant::Bool(_) => analyzer.context.bool_ty,
     Constant::Number(Number::Integer(_)) => analyzer.context.int_ty,
     Constant::Number(Number::FloatingPoint(_)) => analyzer.context.float_ty,
-    Constant::String(_) => unimplemented!("String types are not yet implemented"),
+    Constant::String(string) => {
+      // Identical literals share a single entry in the string pool, which codegen lowers into the module constant pool
+      analyzer.context.intern_string(string);
+      analyzer.context.string_ty
+    },
+    Constant::Char(_) => analyzer.context.char_ty,
   }
 }
 
@@ -211,7 +292,7 @@ pub fn ty_finalize_coercible (analyzer: &mut Analyzer, expr_ir: &mut ir::Express
     return
   };
 
-  ty_handle_coercion(concrete_ty, expr_ir)
+  ty_handle_coercion(analyzer, concrete_ty, expr_ir)
 }
 
 
@@ -226,15 +307,21 @@ pub fn ty_from_binary (analyzer: &mut Analyzer, operand_tk: ContextKey, operator
     ,  Operator::And | Operator::Or | Operator::Xor | Operator::Add | Operator::Sub | Operator::Mul | Operator::Div | Operator::Rem)   
 
     => operand_tk,
+
+
+    | (TypeData::Primitive(PrimitiveType::Integer { .. }) | TypeData::Coercible(CoercibleType::Integer)
+    ,  Operator::ShiftLeft | Operator::ShiftRight)
+
+    => operand_tk,
     
     
     | (TypeData::Primitive(PrimitiveType::Bool)
     ,  Operator::And | Operator::Or | Operator::Xor)
 
-    | (TypeData::Primitive(PrimitiveType::Bool | PrimitiveType::Integer { .. } | PrimitiveType::FloatingPoint { .. }) | TypeData::Pointer(_) | TypeData::Coercible(_)
+    | (TypeData::Primitive(PrimitiveType::Bool | PrimitiveType::Char | PrimitiveType::Integer { .. } | PrimitiveType::FloatingPoint { .. }) | TypeData::Pointer(_) | TypeData::Coercible(_)
     ,  Operator::Equal | Operator::NotEqual)
 
-    | (TypeData::Primitive(PrimitiveType::Integer { .. } | PrimitiveType::FloatingPoint { .. }) | TypeData::Pointer(_) | TypeData::Coercible(_)
+    | (TypeData::Primitive(PrimitiveType::Char | PrimitiveType::Integer { .. } | PrimitiveType::FloatingPoint { .. }) | TypeData::Pointer(_) | TypeData::Coercible(_)
     ,  Operator::Lesser | Operator::Greater | Operator::LesserOrEqual | Operator::GreaterOrEqual)
 
     => analyzer.context.bool_ty,
@@ -242,9 +329,10 @@ pub fn ty_from_binary (analyzer: &mut Analyzer, operand_tk: ContextKey, operator
 
     _ => {
       analyzer.error(origin, format!(
-        "The operand type of this binary expression (`{}`) does not support the operator `{}`",
+        "The operand type of this binary expression (`{}`) does not support the operator `{}`{}",
         TypeDisplay { ty_key: operand_tk,  context: &analyzer.context },
-        operator.value()
+        operator.value(),
+        ty_optional_hint(analyzer, operand_tk)
       ));
 
       return None
@@ -253,6 +341,19 @@ pub fn ty_from_binary (analyzer: &mut Analyzer, operand_tk: ContextKey, operator
 }
 
 
+/// Get a suffix for type errors caused by using an optional value in place of its payload,
+/// explaining that the value must be checked first
+/// 
+/// Returns an empty string if the type is not optional
+pub fn ty_optional_hint (analyzer: &Analyzer, tk: ContextKey) -> &'static str {
+  if matches!(analyzer.context.items.get(tk).unwrap().ref_type().unwrap().data, Some(TypeData::Optional(_))) {
+    "; optional values may be `None`, and must be checked with a `match` before their value can be used"
+  } else {
+    ""
+  }
+}
+
+
 /// Extract the Type ContextKey from a ContextItem, if it is a value item
 pub fn ty_from_global_item (context_item: &ContextItem) -> Option<ContextKey> {
   match context_item {
@@ -271,14 +372,5 @@ pub fn ty_from_global_item (context_item: &ContextItem) -> Option<ContextKey> {
 /// Get a ContextKey for an anonymous (unnamed) TypeData,
 /// either by getting an existing key or registering a new one
 pub fn ty_from_anon_data (analyzer: &mut Analyzer, type_data: TypeData, origin: SourceRegion) -> ContextKey {
-  assert!(type_data.is_anon(), "Internal error, non-anonymous TypeData passed to eval_anon_tdata");
-
-  if let Some(existing_key) = analyzer.context.anon_types.get(&type_data) {
-    *existing_key
-  } else {
-    let new_key = analyzer.context.items.insert(Type::new(None, None, None, origin, Some(type_data.clone())).into());
-    analyzer.context.anon_types.insert(type_data, new_key);
-    analyzer.context.types.push(new_key);
-    new_key
-  }
+  analyzer.context.get_anon_type(type_data, origin)
 }
\ No newline at end of file
@@ -3,10 +3,11 @@ use std::{
 };
 
 use mod_utils::{ some, };
+use mod_common::{ Identifier, };
 
 use crate::{
   source::{ SOURCE_MANAGER, ASTKey, },
-  ast::{ Item, ItemData, ExportData, LocalDeclaration, },
+  ast::{ Item, ItemData, ExportData, LocalDeclaration, EnumVariant, },
   ctx::{ TypeData, },
 };
 
@@ -31,6 +32,7 @@ pub fn type_link_top_level (analyzer: &mut Analyzer, linked_module_asts: &mut Ha
       | ItemData::Import    { .. }
       | ItemData::Namespace { .. }
       | ItemData::Struct    { .. }
+      | ItemData::Enum      { .. }
       | ItemData::Type      { .. }
       | ItemData::Global    { .. }
       | ItemData::Function  { .. }
@@ -93,6 +95,47 @@ fn type_link_item (analyzer: &mut Analyzer, linked_module_asts: &mut HashSet<AST
       }
     },
 
+    ItemData::Enum { identifier, variants } => {
+      let enum_key = analyzer.get_active_namespace().local_bindings.get_entry(identifier).unwrap();
+      
+      // its possible some shadowing error has overwritten this def and if so we just return
+      some!(analyzer.context.items.get(enum_key).unwrap().ref_type());
+
+      let mut variant_names: Vec<Identifier> = Vec::new();
+      let mut variant_types = Some(Vec::new());
+      
+      for EnumVariant { identifier, payload, origin } in variants.iter() {
+        if variant_names.contains(identifier) {
+          analyzer.error(*origin, format!("Enum variant `{}` is defined more than once", identifier));
+          variant_types = None;
+        }
+
+        variant_names.push(identifier.clone());
+
+        let variant_type = if let Some(payload) = payload {
+          if let Some(payload_type) = eval_texpr(analyzer, payload) {
+            Some(payload_type)
+          } else {
+            variant_types = None;
+            continue
+          }
+        } else {
+          None
+        };
+
+        if let Some(variant_types) = variant_types.as_mut() {
+          variant_types.push(variant_type)
+        }
+      }
+
+      if let Some(variant_types) = variant_types {
+        let enum_td = TypeData::Enum { variant_names, variant_types };
+
+        unsafe { analyzer.context.items.get_unchecked_mut(enum_key).mut_type_unchecked() }
+          .data.replace(enum_td);
+      }
+    },
+
     ItemData::Global { identifier, explicit_type, .. } => {
       let global_key = analyzer.get_active_namespace().local_bindings.get_entry(identifier).unwrap();
 
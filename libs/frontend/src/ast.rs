@@ -3,9 +3,11 @@
 use std::{
   fmt::{ Display, Debug, Formatter, Result as FMTResult, },
   ops::{ Deref, },
+  hash::{ Hash, Hasher, },
+  collections::hash_map::{ DefaultHasher, },
 };
 
-use mod_common::{ Constant, Identifier, Operator, HierarchicalDisplay, Padding, };
+use mod_common::{ Constant, Number, FloatingPoint, Identifier, Operator, HierarchicalDisplay, Padding, };
 
 use crate::{
   source::{ SourceRegion, ASTKey, },
@@ -108,13 +110,17 @@ impl Display for Path {
 
 /// A declaration of a field or function parameter
 #[allow(missing_docs)]
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct LocalDeclaration {
   pub identifier: Identifier,
   pub ty: TypeExpression,
   pub origin: SourceRegion,
 }
 
+impl PartialEq for LocalDeclaration {
+  #[inline] fn eq (&self, other: &Self) -> bool { self.identifier == other.identifier && self.ty == other.ty }
+}
+
 impl LocalDeclaration {
   /// Create a new LocalDeclaration
   pub fn new (identifier: Identifier, ty: TypeExpression, origin: SourceRegion) -> Self {
@@ -128,6 +134,32 @@ impl LocalDeclaration {
 }
 
 
+/// A declaration of a variant of an enum, with an optional payload type
+#[allow(missing_docs)]
+#[derive(Debug, Clone)]
+pub struct EnumVariant {
+  pub identifier: Identifier,
+  pub payload: Option<TypeExpression>,
+  pub origin: SourceRegion,
+}
+
+impl PartialEq for EnumVariant {
+  #[inline] fn eq (&self, other: &Self) -> bool { self.identifier == other.identifier && self.payload == other.payload }
+}
+
+impl EnumVariant {
+  /// Create a new EnumVariant
+  pub fn new (identifier: Identifier, payload: Option<TypeExpression>, origin: SourceRegion) -> Self {
+    Self { identifier, payload, origin }
+  }
+
+  /// Create a new EnumVariant with no SourceRegion origin
+  pub fn no_src (identifier: Identifier, payload: Option<TypeExpression>) -> Self {
+    Self { identifier, payload, origin: SourceRegion::ANONYMOUS }
+  }
+}
+
+
 /// An enum containing the particular variant of an expression referencing a type
 #[allow(missing_docs)]
 #[derive(Debug, Clone, PartialEq)]
@@ -135,7 +167,10 @@ pub enum TypeExpressionData {
   Identifier(Identifier),
   Path(Path),
   Pointer(Box<TypeExpression>),
+  /// `?T`, a value of type `T` which may be absent
+  Optional(Box<TypeExpression>),
   Function { parameter_types: Vec<TypeExpression>, return_type: Box<Option<TypeExpression>> },
+  TypeOf(Box<Expression>),
 }
 
 impl From<Identifier> for TypeExpressionData {
@@ -194,6 +229,21 @@ impl TypeExpression {
   pub fn no_src (data: TypeExpressionData) -> Self {
     Self { data, origin: SourceRegion::ANONYMOUS }
   }
+
+  /// Create a new pointer TypeExpression with no SourceRegion origin
+  pub fn pointer<T: Into<TypeExpression>> (target: T) -> Self {
+    Self::no_src(TypeExpressionData::Pointer(box target.into()))
+  }
+
+  /// Create a new optional TypeExpression with no SourceRegion origin
+  pub fn optional<T: Into<TypeExpression>> (payload: T) -> Self {
+    Self::no_src(TypeExpressionData::Optional(box payload.into()))
+  }
+
+  /// Create a new function TypeExpression with no SourceRegion origin
+  pub fn function (parameter_types: Vec<TypeExpression>, return_type: Option<TypeExpression>) -> Self {
+    Self::no_src(TypeExpressionData::Function { parameter_types, return_type: box return_type })
+  }
 }
 
 
@@ -205,6 +255,8 @@ pub enum ExpressionData {
   Path(Path),
 
   Constant(Constant),
+  /// A decimal literal with a type suffix naming the primitive type it has, such as `2u32`
+  SuffixedNumber { number: Number, suffix: Identifier },
 
   Unary {
     operand: Box<Expression>,
@@ -221,6 +273,7 @@ pub enum ExpressionData {
 
   Block(Box<Block>),
   Conditional(Box<Conditional>),
+  Match(Box<Match>),
 }
 
 impl From<Identifier> for ExpressionData {
@@ -244,6 +297,22 @@ impl From<u64> for ExpressionData {
   #[inline] fn from (num: u64) -> Self { Self::Constant(Constant::Number(num.into())) }
 }
 
+impl From<bool> for ExpressionData {
+  #[inline] fn from (bool: bool) -> Self { Self::Constant(Constant::Bool(bool)) }
+}
+
+impl From<Block> for ExpressionData {
+  #[inline] fn from (block: Block) -> Self { Self::Block(box block) }
+}
+
+impl From<Conditional> for ExpressionData {
+  #[inline] fn from (conditional: Conditional) -> Self { Self::Conditional(box conditional) }
+}
+
+impl From<Match> for ExpressionData {
+  #[inline] fn from (match_expr: Match) -> Self { Self::Match(box match_expr) }
+}
+
 impl From<f64> for ExpressionData {
   #[inline] fn from (num: f64) -> Self { Self::Constant(Constant::Number(num.into())) }
 }
@@ -291,6 +360,21 @@ impl Expression {
   pub fn no_src (data: ExpressionData) -> Self {
     Self { data, origin: SourceRegion::ANONYMOUS }
   }
+
+  /// Create a new unary Expression with no SourceRegion origin
+  pub fn unary<E: Into<Expression>> (operator: Operator, operand: E) -> Self {
+    Self::no_src(ExpressionData::Unary { operand: box operand.into(), operator })
+  }
+
+  /// Create a new binary Expression with no SourceRegion origin
+  pub fn binary<L: Into<Expression>, R: Into<Expression>> (operator: Operator, left: L, right: R) -> Self {
+    Self::no_src(ExpressionData::Binary { left: box left.into(), right: box right.into(), operator })
+  }
+
+  /// Create a new call Expression with no SourceRegion origin
+  pub fn call<E: Into<Expression>> (callee: E, arguments: Vec<Expression>) -> Self {
+    Self::no_src(ExpressionData::Call { callee: box callee.into(), arguments })
+  }
 }
 
 
@@ -300,7 +384,7 @@ impl Expression {
 #[derive(Debug, Clone, PartialEq)]
 pub enum StatementData {
   Expression(Expression),
-  Declaration { identifier: Identifier, explicit_type: Option<TypeExpression>, initializer: Option<Expression> },
+  Declaration { identifier: Identifier, explicit_type: Option<TypeExpression>, initializer: Option<Expression>, mutable: bool },
   Assignment { target: Expression, value: Expression },
   ModAssignment { target: Expression, value: Expression, operator: Operator },
 
@@ -382,6 +466,26 @@ impl Statement {
   pub fn no_src (data: StatementData) -> Self {
     Self { data, origin: SourceRegion::ANONYMOUS }
   }
+
+  /// Create a new immutable local variable declaration Statement with no SourceRegion origin
+  pub fn declaration<I: Into<Identifier>> (identifier: I, explicit_type: Option<TypeExpression>, initializer: Option<Expression>) -> Self {
+    Self::no_src(StatementData::Declaration { identifier: identifier.into(), explicit_type, initializer, mutable: false })
+  }
+
+  /// Create a new mutable local variable declaration Statement with no SourceRegion origin
+  pub fn mut_declaration<I: Into<Identifier>> (identifier: I, explicit_type: Option<TypeExpression>, initializer: Option<Expression>) -> Self {
+    Self::no_src(StatementData::Declaration { identifier: identifier.into(), explicit_type, initializer, mutable: true })
+  }
+
+  /// Create a new assignment Statement with no SourceRegion origin
+  pub fn assignment<T: Into<Expression>, V: Into<Expression>> (target: T, value: V) -> Self {
+    Self::no_src(StatementData::Assignment { target: target.into(), value: value.into() })
+  }
+
+  /// Create a new return Statement with no SourceRegion origin
+  pub fn ret (value: Option<Expression>) -> Self {
+    Self::no_src(StatementData::Return(value))
+  }
 }
 
 
@@ -506,15 +610,101 @@ impl Conditional {
 }
 
 
-/// Data associated with a pseudonym, either an alias or an export
+/// A value tested against the scrutinee of a Match
 #[allow(missing_docs)]
 #[derive(Debug, Clone, PartialEq)]
+pub enum MatchPattern {
+  Constant(Constant),
+  /// A variant of the enum type of the scrutinee, optionally binding its payload to a new variable
+  Variant { identifier: Identifier, binding: Option<Identifier> },
+  /// `_`, matches any value
+  Wildcard,
+}
+
+/// An individual arm of a Match, a pattern and the Expression evaluated if it matches
+#[derive(Clone)]
+#[allow(missing_docs)]
+pub struct MatchArm {
+  pub pattern: MatchPattern,
+  pub body: Expression,
+  pub origin: SourceRegion,
+}
+
+impl Debug for MatchArm {
+  #[inline] fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    f.debug_struct("MatchArm")
+      .field("pattern", &self.pattern)
+      .field("body", &self.body)
+      .finish()
+  }
+}
+
+impl PartialEq for MatchArm {
+  #[inline] fn eq (&self, other: &Self) -> bool { self.pattern == other.pattern && self.body == other.body }
+}
+
+impl MatchArm {
+  /// Create a new MatchArm
+  pub fn new (pattern: MatchPattern, body: Expression, origin: SourceRegion) -> Self {
+    Self { pattern, body, origin }
+  }
+
+  /// Create a new MatchArm with no SourceRegion
+  pub fn no_src (pattern: MatchPattern, body: Expression) -> Self {
+    Self { pattern, body, origin: SourceRegion::ANONYMOUS }
+  }
+}
+
+/// A scrutinee Expression and a sequence of MatchArms, the first of which with a matching pattern is evaluated
+/// 
+/// The analyzer lowers a Match to a Conditional chain comparing the scrutinee against each pattern
+#[derive(Clone)]
+#[allow(missing_docs)]
+pub struct Match {
+  pub scrutinee: Expression,
+  pub arms: Vec<MatchArm>,
+  pub origin: SourceRegion,
+}
+
+impl Debug for Match {
+  #[inline] fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    f.debug_struct("Match")
+      .field("scrutinee", &self.scrutinee)
+      .field("arms", &self.arms)
+      .finish()
+  }
+}
+
+impl PartialEq for Match {
+  #[inline] fn eq (&self, other: &Self) -> bool { self.scrutinee == other.scrutinee && self.arms == other.arms }
+}
+
+impl Match {
+  /// Create a new Match
+  pub fn new (scrutinee: Expression, arms: Vec<MatchArm>, origin: SourceRegion) -> Self {
+    Self { scrutinee, arms, origin }
+  }
+
+  /// Create a new Match with no SourceRegion
+  pub fn no_src (scrutinee: Expression, arms: Vec<MatchArm>) -> Self {
+    Self { scrutinee, arms, origin: SourceRegion::ANONYMOUS }
+  }
+}
+
+
+/// Data associated with a pseudonym, either an alias or an export
+#[allow(missing_docs)]
+#[derive(Debug, Clone)]
 pub struct PseudonymData {
   pub path: Path,
   pub new_name: Option<Identifier>,
   pub origin: SourceRegion,
 }
 
+impl PartialEq for PseudonymData {
+  #[inline] fn eq (&self, other: &Self) -> bool { self.path == other.path && self.new_name == other.new_name }
+}
+
 impl PseudonymData {
   /// Create a new PseudonymData
   pub fn new (path: Path, new_name: Option<Identifier>, origin: SourceRegion) -> Self {
@@ -546,10 +736,11 @@ pub enum ItemData {
   Export { data: ExportData, terminal: bool },
 
   Struct { identifier: Identifier, fields: Vec<LocalDeclaration>, terminal: bool, },
+  Enum { identifier: Identifier, variants: Vec<EnumVariant> },
   Type { identifier: Identifier, type_expression: TypeExpression },
   Namespace { identifier: Identifier, items: Vec<Item>, inline: bool },
-  Global { identifier: Identifier, explicit_type: TypeExpression, initializer: Option<Expression> },
-  Function { identifier: Identifier, parameters: Vec<LocalDeclaration>, return_type: Option<TypeExpression>, body: Option<Block> },
+  Global { identifier: Identifier, explicit_type: TypeExpression, initializer: Option<Expression>, mutable: bool },
+  Function { identifier: Identifier, parameters: Vec<LocalDeclaration>, return_type: Option<TypeExpression>, body: Option<Block>, external: bool },
 }
 
 impl ItemData {
@@ -562,6 +753,8 @@ impl ItemData {
       | ItemData::Type   { .. }
       => true,
 
+      ItemData::Enum { .. } => false,
+
       ItemData::Namespace { inline, .. } => !*inline,
       
       ItemData::Function { body, .. } => body.is_none(),
@@ -616,6 +809,294 @@ impl Item {
   pub fn no_src (data: ItemData) -> Self {
     Self { data, origin: SourceRegion::ANONYMOUS }
   }
+
+  /// Create a new function Item with a body and no SourceRegion origin
+  pub fn function<I: Into<Identifier>> (identifier: I, parameters: Vec<LocalDeclaration>, return_type: Option<TypeExpression>, body: Block) -> Self {
+    Self::no_src(ItemData::Function { identifier: identifier.into(), parameters, return_type, body: Some(body), external: false })
+  }
+
+  /// Create a new immutable global variable Item with no SourceRegion origin
+  pub fn global<I: Into<Identifier>, T: Into<TypeExpression>> (identifier: I, explicit_type: T, initializer: Option<Expression>) -> Self {
+    Self::no_src(ItemData::Global { identifier: identifier.into(), explicit_type: explicit_type.into(), initializer, mutable: false })
+  }
+
+  /// Create a new mutable global variable Item with no SourceRegion origin
+  pub fn mut_global<I: Into<Identifier>, T: Into<TypeExpression>> (identifier: I, explicit_type: T, initializer: Option<Expression>) -> Self {
+    Self::no_src(ItemData::Global { identifier: identifier.into(), explicit_type: explicit_type.into(), initializer, mutable: true })
+  }
+
+  /// Create a new Item with no SourceRegion origin, exporting an inline Item
+  pub fn export (item: Item) -> Self {
+    let terminal = !item.requires_semi();
+    Self::no_src(ItemData::Export { data: ExportData::Inline(box item), terminal })
+  }
+
+  /// Determine if two Items have the same structure,
+  /// ignoring the SourceRegions of the Items and all of their descendants
+  pub fn structural_eq (&self, other: &Self) -> bool {
+    self == other
+  }
+
+  /// Get a hash of an Item's structure,
+  /// ignoring the SourceRegions of the Item and all of its descendants
+  /// 
+  /// Items that are `structural_eq` always have the same `structural_hash`
+  pub fn structural_hash (&self) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    self.hash_structure(&mut hasher);
+    hasher.finish()
+  }
+}
+
+
+
+/// Hashes the parts of an AST node that take part in equality comparison,
+/// skipping SourceRegions
+trait StructuralHash {
+  fn hash_structure<H: Hasher> (&self, state: &mut H);
+}
+
+impl<T: StructuralHash> StructuralHash for Box<T> {
+  #[inline] fn hash_structure<H: Hasher> (&self, state: &mut H) { (**self).hash_structure(state) }
+}
+
+impl<T: StructuralHash> StructuralHash for Option<T> {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    match self {
+      Some(value) => { state.write_u8(1); value.hash_structure(state) },
+      None => state.write_u8(0),
+    }
+  }
+}
+
+impl<T: StructuralHash> StructuralHash for Vec<T> {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    state.write_usize(self.len());
+    for value in self.iter() { value.hash_structure(state) }
+  }
+}
+
+impl StructuralHash for Constant {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    std::mem::discriminant(self).hash(state);
+
+    match self {
+      Constant::NullPointer => { },
+      Constant::Bool(bool) => bool.hash(state),
+      Constant::String(string) => string.hash(state),
+      Constant::Char(ch) => ch.hash(state),
+      Constant::Number(Number::Integer(int)) => { state.write_u8(0); int.hash(state) },
+      Constant::Number(Number::FloatingPoint(float)) => {
+        state.write_u8(1);
+        std::mem::discriminant(float).hash(state);
+        // Adding zero folds -0.0 into 0.0, which compare equal
+        if let FloatingPoint::Norm(norm) = float { (norm + 0.0).to_bits().hash(state) }
+      },
+    }
+  }
+}
+
+impl StructuralHash for Path {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    self.absolute.hash(state);
+    self.chain.hash(state);
+  }
+}
+
+impl StructuralHash for LocalDeclaration {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    self.identifier.hash(state);
+    self.ty.hash_structure(state);
+  }
+}
+
+impl StructuralHash for EnumVariant {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    self.identifier.hash(state);
+    self.payload.hash_structure(state);
+  }
+}
+
+impl StructuralHash for TypeExpression {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    std::mem::discriminant(&self.data).hash(state);
+
+    match &self.data {
+      TypeExpressionData::Identifier(ident) => ident.hash(state),
+      TypeExpressionData::Path(path) => path.hash_structure(state),
+      TypeExpressionData::Pointer(sub_texpr) => sub_texpr.hash_structure(state),
+      TypeExpressionData::Optional(sub_texpr) => sub_texpr.hash_structure(state),
+      TypeExpressionData::Function { parameter_types, return_type } => {
+        parameter_types.hash_structure(state);
+        return_type.hash_structure(state);
+      },
+      TypeExpressionData::TypeOf(expr) => expr.hash_structure(state),
+    }
+  }
+}
+
+impl StructuralHash for Expression {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    std::mem::discriminant(&self.data).hash(state);
+
+    match &self.data {
+      ExpressionData::Identifier(ident) => ident.hash(state),
+      ExpressionData::Path(path) => path.hash_structure(state),
+      ExpressionData::Constant(constant) => constant.hash_structure(state),
+      ExpressionData::SuffixedNumber { number, suffix } => {
+        Constant::Number(*number).hash_structure(state);
+        suffix.hash(state);
+      },
+      ExpressionData::Unary { operand, operator } => {
+        operator.hash(state);
+        operand.hash_structure(state);
+      },
+      ExpressionData::Binary { left, right, operator } => {
+        operator.hash(state);
+        left.hash_structure(state);
+        right.hash_structure(state);
+      },
+      ExpressionData::Call { callee, arguments } => {
+        callee.hash_structure(state);
+        arguments.hash_structure(state);
+      },
+      ExpressionData::Block(block) => block.hash_structure(state),
+      ExpressionData::Conditional(conditional) => conditional.hash_structure(state),
+      ExpressionData::Match(match_expr) => match_expr.hash_structure(state),
+    }
+  }
+}
+
+impl StructuralHash for Statement {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    std::mem::discriminant(&self.data).hash(state);
+
+    match &self.data {
+      StatementData::Expression(expr) => expr.hash_structure(state),
+      StatementData::Declaration { identifier, explicit_type, initializer, mutable } => {
+        identifier.hash(state);
+        explicit_type.hash_structure(state);
+        initializer.hash_structure(state);
+        mutable.hash(state);
+      },
+      StatementData::Assignment { target, value } => {
+        target.hash_structure(state);
+        value.hash_structure(state);
+      },
+      StatementData::ModAssignment { target, value, operator } => {
+        operator.hash(state);
+        target.hash_structure(state);
+        value.hash_structure(state);
+      },
+      StatementData::Return(expr) => expr.hash_structure(state),
+      StatementData::Block(block) => block.hash_structure(state),
+      StatementData::Conditional(conditional) => conditional.hash_structure(state),
+    }
+  }
+}
+
+impl StructuralHash for Block {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    self.statements.hash_structure(state);
+    self.trailing_expression.hash_structure(state);
+  }
+}
+
+impl StructuralHash for ConditionalBranch {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    self.condition.hash_structure(state);
+    self.body.hash_structure(state);
+  }
+}
+
+impl StructuralHash for Conditional {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    self.if_branch.hash_structure(state);
+    self.else_if_branches.hash_structure(state);
+    self.else_block.hash_structure(state);
+  }
+}
+
+impl StructuralHash for MatchArm {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    std::mem::discriminant(&self.pattern).hash(state);
+    match &self.pattern {
+      MatchPattern::Constant(constant) => constant.hash_structure(state),
+      MatchPattern::Variant { identifier, binding } => { identifier.hash(state); binding.hash(state) },
+      MatchPattern::Wildcard => { },
+    }
+    self.body.hash_structure(state);
+  }
+}
+
+impl StructuralHash for Match {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    self.scrutinee.hash_structure(state);
+    self.arms.hash_structure(state);
+  }
+}
+
+impl StructuralHash for PseudonymData {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    self.path.hash_structure(state);
+    self.new_name.hash(state);
+  }
+}
+
+impl StructuralHash for Item {
+  fn hash_structure<H: Hasher> (&self, state: &mut H) {
+    std::mem::discriminant(&self.data).hash(state);
+
+    match &self.data {
+      ItemData::Import { identifier, new_name, ast_key } => {
+        identifier.hash(state);
+        new_name.hash(state);
+        ast_key.hash(state);
+      },
+      ItemData::Alias { data, terminal } => {
+        data.hash_structure(state);
+        terminal.hash(state);
+      },
+      ItemData::Export { data, terminal } => {
+        match data {
+          ExportData::List(list) => { state.write_u8(0); list.hash_structure(state) },
+          ExportData::Inline(item) => { state.write_u8(1); item.hash_structure(state) },
+        }
+        terminal.hash(state);
+      },
+      ItemData::Struct { identifier, fields, terminal } => {
+        identifier.hash(state);
+        fields.hash_structure(state);
+        terminal.hash(state);
+      },
+      ItemData::Enum { identifier, variants } => {
+        identifier.hash(state);
+        variants.hash_structure(state);
+      },
+      ItemData::Type { identifier, type_expression } => {
+        identifier.hash(state);
+        type_expression.hash_structure(state);
+      },
+      ItemData::Namespace { identifier, items, inline } => {
+        identifier.hash(state);
+        items.hash_structure(state);
+        inline.hash(state);
+      },
+      ItemData::Global { identifier, explicit_type, initializer, mutable } => {
+        identifier.hash(state);
+        explicit_type.hash_structure(state);
+        initializer.hash_structure(state);
+        mutable.hash(state);
+      },
+      ItemData::Function { identifier, parameters, return_type, body, external } => {
+        identifier.hash(state);
+        parameters.hash_structure(state);
+        return_type.hash_structure(state);
+        body.hash_structure(state);
+        external.hash(state);
+      },
+    }
+  }
 }
 
 
@@ -633,6 +1114,8 @@ impl HierarchicalDisplay for TypeExpressionData {
       TypeExpressionData::Identifier(ident) => Display::fmt(ident, f),
       TypeExpressionData::Path(path) => Display::fmt(path, f),
       TypeExpressionData::Pointer(sub_texpr) => write!(f, "^{}", sub_texpr),
+      TypeExpressionData::Optional(sub_texpr) => write!(f, "?{}", sub_texpr),
+      TypeExpressionData::TypeOf(expr) => write!(f, "typeof({})", expr),
       TypeExpressionData::Function { parameter_types, return_type } => {
         write!(f, "fn")?;
 
@@ -666,43 +1149,62 @@ impl Display for ExpressionData {
 
 impl HierarchicalDisplay for ExpressionData {
   fn fmt_hierarchical (&self, f: &mut Formatter, level: &mut usize) -> FMTResult {
-    match self {
-      ExpressionData::Identifier(ident) => Display::fmt(ident, f),
-      ExpressionData::Path(path) => Display::fmt(path, f),
-      ExpressionData::Constant(constant) => Display::fmt(constant, f),
-      ExpressionData::Conditional(conditional) => conditional.fmt_hierarchical(f, level),
-      ExpressionData::Block(block) => block.fmt_hierarchical(f, level),
-
-      ExpressionData::Unary { operand, operator } => {
-        write!(f, "{}(", operator.value())?;
-        operand.fmt_hierarchical(f, level)?;
-        write!(f, ")")
-      }
-
-      ExpressionData::Binary { left, right, operator } => {
-        write!(f, "(")?;
-        left.fmt_hierarchical(f, level)?;
-        write!(f, ") {} (", operator.value())?;
-        right.fmt_hierarchical(f, level)?;
-        write!(f, ")")
-      },
-      
-      ExpressionData::Call { callee, arguments } => {
-        write!(f, "(")?;
-        callee.fmt_hierarchical(f, level)?;
-        write!(f, ")")?;
-
-        let mut iter = arguments.iter().peekable();
+    // Operator chains can be arbitrarily deep, so they are walked with an explicit stack instead of recursion
+    enum Work<'e> {
+      Expr(&'e ExpressionData),
+      Text(&'static str),
+      Operator(Operator, &'static str),
+    }
 
-        while let Some(arg_expr) = iter.next() {
-          arg_expr.fmt_hierarchical(f, level)?;
+    let mut stack = vec! [ Work::Expr(self) ];
+
+    while let Some(work) = stack.pop() {
+      match work {
+        Work::Text(text) => write!(f, "{}", text)?,
+        Work::Operator(operator, suffix) => write!(f, "{}{}", operator.value(), suffix)?,
+
+        Work::Expr(expr) => match expr {
+          ExpressionData::Identifier(ident) => Display::fmt(ident, f)?,
+          ExpressionData::Path(path) => Display::fmt(path, f)?,
+          ExpressionData::Constant(constant) => Display::fmt(constant, f)?,
+          ExpressionData::SuffixedNumber { number, suffix } => write!(f, "{}{}", Constant::Number(*number), suffix)?,
+          ExpressionData::Conditional(conditional) => conditional.fmt_hierarchical(f, level)?,
+          ExpressionData::Match(match_expr) => match_expr.fmt_hierarchical(f, level)?,
+          ExpressionData::Block(block) => block.fmt_hierarchical(f, level)?,
+
+          ExpressionData::Unary { operand, operator } => {
+            stack.push(Work::Text(")"));
+            stack.push(Work::Expr(&operand.data));
+            stack.push(Work::Operator(*operator, "("));
+          },
+
+          ExpressionData::Binary { left, right, operator } => {
+            stack.push(Work::Text(")"));
+            stack.push(Work::Expr(&right.data));
+            stack.push(Work::Text(" ("));
+            stack.push(Work::Operator(*operator, ""));
+            stack.push(Work::Text(") "));
+            stack.push(Work::Expr(&left.data));
+            stack.push(Work::Text("("));
+          },
+
+          ExpressionData::Call { callee, arguments } => {
+            stack.push(Work::Text(")"));
+
+            for (i, arg_expr) in arguments.iter().enumerate().rev() {
+              stack.push(Work::Expr(&arg_expr.data));
+              if i != 0 { stack.push(Work::Text(", ")); }
+            }
 
-          if iter.peek().is_some() { write!(f, ", ")?; }
+            stack.push(Work::Text(")("));
+            stack.push(Work::Expr(&callee.data));
+            stack.push(Work::Text("("));
+          },
         }
-
-        write!(f, ")")
-      },
+      }
     }
+
+    Ok(())
   }
 }
 
@@ -718,8 +1220,12 @@ impl HierarchicalDisplay for StatementData {
       StatementData::Block(block) => block.fmt_hierarchical(f, level),
       StatementData::Conditional(conditional) => conditional.fmt_hierarchical(f, level),
 
-      StatementData::Declaration { identifier, explicit_type, initializer } => {
-        write!(f, "let {}", identifier)?;
+      StatementData::Declaration { identifier, explicit_type, initializer, mutable } => {
+        write!(f, "let ")?;
+
+        if *mutable { write!(f, "mut ")?; }
+
+        write!(f, "{}", identifier)?;
 
         if let Some(explicit_texpr) = explicit_type {
           write!(f, ": {}", explicit_texpr)?;
@@ -836,6 +1342,60 @@ impl HierarchicalDisplay for Conditional {
 
 
 
+impl Display for MatchPattern {
+  fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    match self {
+      MatchPattern::Constant(constant) => Display::fmt(constant, f),
+      MatchPattern::Variant { identifier, binding: Some(binding) } => write!(f, "{}({})", identifier, binding),
+      MatchPattern::Variant { identifier, binding: None } => Display::fmt(identifier, f),
+      MatchPattern::Wildcard => write!(f, "_"),
+    }
+  }
+}
+
+
+impl Display for MatchArm {
+  #[inline] fn fmt (&self, f: &mut Formatter) -> FMTResult { self.fmt_hierarchical(f, &mut 0) }
+}
+
+impl HierarchicalDisplay for MatchArm {
+  fn fmt_hierarchical (&self, f: &mut Formatter, level: &mut usize) -> FMTResult {
+    write!(f, "{} => ", self.pattern)?;
+
+    self.body.fmt_hierarchical(f, level)
+  }
+}
+
+
+impl Display for Match {
+  #[inline] fn fmt (&self, f: &mut Formatter) -> FMTResult { self.fmt_hierarchical(f, &mut 0) }
+}
+
+impl HierarchicalDisplay for Match {
+  fn fmt_hierarchical (&self, f: &mut Formatter, level: &mut usize) -> FMTResult {
+    write!(f, "match ")?;
+
+    self.scrutinee.fmt_hierarchical(f, level)?;
+
+    writeln!(f, " {{")?;
+    *level += 1;
+
+    for arm in self.arms.iter() {
+      Padding.fmt_hierarchical(f, level)?;
+
+      arm.fmt_hierarchical(f, level)?;
+
+      writeln!(f, ",")?;
+    }
+
+    *level -= 1;
+    Padding.fmt_hierarchical(f, level)?;
+    write!(f, "}}")
+  }
+}
+
+
+
 impl Display for ItemData {
   #[inline] fn fmt (&self, f: &mut Formatter) -> FMTResult { self.fmt_hierarchical(f, &mut 0) }
 }
@@ -980,8 +1540,41 @@ impl HierarchicalDisplay for ItemData {
         write!(f, "}}")
       },
 
-      ItemData::Global { identifier, explicit_type, initializer } => {
-        write!(f, "global {}: ", identifier)?;
+      ItemData::Enum { identifier, variants } => {
+        writeln!(f, "enum {} {{", identifier)?;
+        *level += 1;
+
+        let mut iter = variants.iter().peekable();
+    
+        while let Some(EnumVariant { identifier, payload, .. }) = iter.next() {
+          Padding.fmt_hierarchical(f, level)?;
+
+          write!(f, "{}", identifier)?;
+
+          if let Some(payload) = payload {
+            write!(f, "(")?;
+            payload.fmt_hierarchical(f, level)?;
+            write!(f, ")")?;
+          }
+
+          if iter.peek().is_some() {
+            write!(f, ",")?;
+          }
+
+          writeln!(f)?;
+        }
+
+        *level -= 1;
+        Padding.fmt_hierarchical(f, level)?;
+        write!(f, "}}")
+      },
+
+      ItemData::Global { identifier, explicit_type, initializer, mutable } => {
+        write!(f, "global ")?;
+
+        if *mutable { write!(f, "mut ")?; }
+
+        write!(f, "{}: ", identifier)?;
 
         explicit_type.fmt_hierarchical(f, level)?;
 
@@ -993,7 +1586,9 @@ impl HierarchicalDisplay for ItemData {
         Ok(())
       },
 
-      ItemData::Function { identifier, parameters, return_type, body } => {
+      ItemData::Function { identifier, parameters, return_type, body, external } => {
+        if *external { write!(f, "import ")?; }
+
         write!(f, "fn {}", identifier)?;
 
         if !parameters.is_empty() {
@@ -1063,4 +1658,190 @@ impl<'a> HierarchicalDisplay for Displayer<'a> {
 
     Ok(())
   }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{
+    source::{ SOURCE_MANAGER, },
+    session::{ SESSION, MessageKind, },
+    lexer::{ Lexer, },
+    parser::{ Parser, },
+    test_support::lock_singletons,
+  };
+
+  fn parse (name: &str, content: &str) -> Vec<Item> {
+    let source = SOURCE_MANAGER.add_source(name, content);
+    let tokens = Lexer::new(source).lex_stream();
+    Parser::new(&tokens).parse_ast()
+  }
+
+  #[test]
+  fn structural_eq_ignores_regions () {
+    let _guard = lock_singletons();
+
+    let a = parse("structural_a.ms", "struct P { x: s32, y: s32 } fn f (p: ^P) -> s32 { let x = 1; x + 2 }");
+    let b = parse("structural_b.ms", "\n\n  struct P {\n    x: s32,\n    y: s32\n  }\n\n  fn f (p: ^P) -> s32 {\n    let x = 1;\n    x + 2\n  }\n");
+    let c = parse("structural_c.ms", "struct P { x: s32, y: s32 } fn f (p: ^P) -> s32 { let x = 1; x + 3 }");
+
+    assert_eq!(a.len(), 2);
+    assert_eq!(a.len(), b.len());
+
+    for (a, b) in a.iter().zip(b.iter()) {
+      assert_ne!(a.origin, b.origin);
+      assert!(a.structural_eq(b));
+      assert_eq!(a.structural_hash(), b.structural_hash());
+    }
+
+    assert!(a[0].structural_eq(&c[0]));
+    assert!(!a[1].structural_eq(&c[1]));
+    assert_ne!(a[1].structural_hash(), c[1].structural_hash());
+  }
+
+  #[test]
+  fn pow_precedence_and_associativity () {
+    let _guard = lock_singletons();
+
+    let same = |a: &str, b: &str| parse("pow_a.ms", a)[0].structural_eq(&parse("pow_b.ms", b)[0]);
+
+    assert!(same("global X: s32 = 2 ** 3 ** 2;", "global X: s32 = 2 ** (3 ** 2);"));
+    assert!(!same("global X: s32 = 2 ** 3 ** 2;", "global X: s32 = (2 ** 3) ** 2;"));
+
+    assert!(same("global X: s32 = 4 * 2 ** 3 + 1;", "global X: s32 = (4 * (2 ** 3)) + 1;"));
+    assert!(same("global X: s32 = -2 ** 2;", "global X: s32 = -(2 ** 2);"));
+    assert!(same("global X: s32 = -2 * 3;", "global X: s32 = (-2) * 3;"));
+    assert!(same("global X: s32 = 2 ** f(1);", "global X: s32 = 2 ** (f(1));"));
+  }
+
+  #[test]
+  fn match_expression () {
+    let _guard = lock_singletons();
+
+    let ast = parse("match_a.ms", "global X: s32 = match Y { 1 => 10, 2 => { 20 }, _ => 0 };");
+
+    let arms = match &ast[0].data {
+      ItemData::Global { initializer: Some(Expression { data: ExpressionData::Match(box Match { scrutinee, arms, .. }), .. }), .. } => {
+        assert!(matches!(&scrutinee.data, ExpressionData::Identifier(ident) if ident.as_ref() == "Y"));
+        arms
+      },
+      other => panic!("expected global with match initializer, got {:?}", other)
+    };
+
+    assert_eq!(
+      arms.iter().map(|arm| arm.pattern.clone()).collect::<Vec<_>>(),
+      [ MatchPattern::Constant(Constant::Number(1u64.into())), MatchPattern::Constant(Constant::Number(2u64.into())), MatchPattern::Wildcard ]
+    );
+    assert!(matches!(arms[1].body.data, ExpressionData::Block(_)));
+
+    assert!(ast[0].structural_eq(&parse("match_b.ms", "global X: s32 = match Y {\n  1 => 10,\n  2 => { 20 },\n  _ => 0,\n};")[0]));
+    assert!(!ast[0].structural_eq(&parse("match_c.ms", "global X: s32 = match Y { 1 => 10, _ => { 20 }, 2 => 0 };")[0]));
+  }
+
+  #[test]
+  fn enum_item () {
+    let _guard = lock_singletons();
+
+    let ast = parse("enum_a.ms", "enum Shape { Circle(f32), Empty } global X: s32 = match Y { Circle(r) => 1, Empty => 0 };");
+
+    assert_eq!(ast[0].data, ItemData::Enum {
+      identifier: "Shape".into(),
+      variants: vec! [
+        EnumVariant::no_src("Circle".into(), Some(TypeExpression::no_src("f32".into()))),
+        EnumVariant::no_src("Empty".into(), None),
+      ]
+    });
+    assert!(!ast[0].requires_semi());
+
+    match &ast[1].data {
+      ItemData::Global { initializer: Some(Expression { data: ExpressionData::Match(box Match { arms, .. }), .. }), .. } => assert_eq!(
+        arms.iter().map(|arm| arm.pattern.clone()).collect::<Vec<_>>(),
+        [ MatchPattern::Variant { identifier: "Circle".into(), binding: Some("r".into()) }, MatchPattern::Variant { identifier: "Empty".into(), binding: None } ]
+      ),
+      other => panic!("expected global with match initializer, got {:?}", other)
+    }
+
+    assert_eq!(ast[0].to_string(), "enum Shape {\n  Circle(f32),\n  Empty\n}");
+    assert!(ast[0].structural_eq(&parse("enum_b.ms", "enum Shape {\n  Circle(f32),\n  Empty,\n}")[0]));
+  }
+
+  #[test]
+  fn trailing_commas () {
+    let _guard = lock_singletons();
+
+    let errors_before = SESSION.count_errors();
+
+    let trailing = parse("trailing_commas_a.ms", "\
+      struct P { x: s32, y: s32, }
+      fn f (a: s32, b: s32,) -> s32 { g(a, b,) }
+      alias { f, P, }
+      global H: fn (s32, s32,) -> s32 = f;
+    ");
+
+    let plain = parse("trailing_commas_b.ms", "\
+      struct P { x: s32, y: s32 }
+      fn f (a: s32, b: s32) -> s32 { g(a, b) }
+      alias { f, P }
+      global H: fn (s32, s32) -> s32 = f;
+    ");
+
+    assert_eq!(SESSION.count_errors(), errors_before);
+
+    assert_eq!(trailing.len(), 4);
+    assert_eq!(trailing.len(), plain.len());
+
+    for (a, b) in trailing.iter().zip(plain.iter()) {
+      assert!(a.structural_eq(b), "{} != {}", a, b);
+    }
+  }
+
+  #[test]
+  fn contextual_keyword_fields () {
+    let _guard = lock_singletons();
+
+    let errors_before = SESSION.count_errors();
+
+    let items = parse("contextual_keyword_fields.ms", "struct Decl { type: s32, global: bool, name: s32 }");
+
+    assert_eq!(SESSION.count_errors(), errors_before);
+    assert_eq!(items.len(), 1);
+
+    if let ItemData::Struct { fields, .. } = &items[0].data {
+      let names: Vec<&str> = fields.iter().map(|field| field.identifier.as_ref()).collect();
+
+      assert_eq!(names, vec! [ "type", "global", "name" ]);
+    } else {
+      panic!("expected struct, got {}", items[0]);
+    }
+  }
+
+  #[test]
+  fn block_semicolons () {
+    let _guard = lock_singletons();
+
+    let body = |item: &Item| if let ItemData::Function { body: Some(body), .. } = &item.data { body.clone() } else { panic!("expected function with body, got {}", item) };
+
+    let errors_before = SESSION.count_errors();
+
+    let valid = parse("block_semicolons_a.ms", "fn f () -> s32 { let a = 1; g(a); a }");
+
+    assert_eq!(SESSION.count_errors(), errors_before);
+
+    let valid_body = body(&valid[0]);
+    assert_eq!(valid_body.statements.len(), 2);
+    assert_eq!(valid_body.trailing_expression, Some(Expression::no_src("a".into())));
+
+    let message_base = SESSION.messages().len();
+
+    let missing = parse("block_semicolons_b.ms", "fn f () -> s32 { g(1) g(2); a }");
+
+    let errors: Vec<_> = SESSION.messages()[message_base ..].iter().filter(|message| message.kind == MessageKind::Error).collect();
+    assert_eq!(errors.len(), 1, "{:#?}", errors);
+    assert!(errors[0].items[0].content.contains("Expected a ; to end statement"), "{:#?}", errors);
+
+    // the unterminated expression is kept as a statement, and parsing continues normally
+    let missing_body = body(&missing[0]);
+    assert_eq!(missing_body.statements.len(), 2);
+    assert_eq!(missing_body.trailing_expression, Some(Expression::no_src("a".into())));
+  }
 }
\ No newline at end of file
@@ -6,7 +6,7 @@ use core::{
 
 use std::{
   fmt::{ Display, Debug, Formatter, Result as FMTResult, },
-  collections::{ HashMap, },
+  collections::{ HashMap, HashSet, },
 };
 
 use mod_utils::{
@@ -51,6 +51,10 @@ pub struct Context {
   pub void_ty: ContextKey,
   /// The type of logical expressions
   pub bool_ty: ContextKey,
+  /// The type of character literals
+  pub char_ty: ContextKey,
+  /// The type of string literals
+  pub string_ty: ContextKey,
 
   /// Concrete type the coercible integer type becomes without inferrence
   pub concrete_int_ty: ContextKey,
@@ -62,6 +66,11 @@ pub struct Context {
   /// Anonymous type lookup helper
   pub anon_types: HashMap<TypeData, ContextKey>,
 
+  /// The pool of unique string literal values in a context, indexed by the result of `intern_string`
+  pub strings: Vec<String>,
+  /// String literal lookup helper, mapping values to their index in `strings`
+  pub string_indices: HashMap<String, usize>,
+
   /// A list of all types in a context
   pub types: Vec<ContextKey>,
   /// A list of all namespaces in a context
@@ -77,6 +86,9 @@ impl Default for Context {
 }
 
 impl Context {
+  /// The canonical name given to the main Module when none is specified
+  pub const DEFAULT_MAIN_MODULE_NAME: &'static str = "main";
+
   /// Create a new semantic analysis context, and initialize it with the core primitives,
   /// namespace, and bindspace, module, as well as a root module
   pub fn new () -> Self {
@@ -85,6 +97,10 @@ impl Context {
       
       ("bool", TypeData::Primitive(PrimitiveType::Bool)),
 
+      ("char", TypeData::Primitive(PrimitiveType::Char)),
+
+      ("string", TypeData::Primitive(PrimitiveType::String)),
+
       ("u8",   TypeData::Primitive(PrimitiveType::Integer { signed: false, bit_size: 8 })),
       ("u16",  TypeData::Primitive(PrimitiveType::Integer { signed: false, bit_size: 16 })),
       ("u32",  TypeData::Primitive(PrimitiveType::Integer { signed: false, bit_size: 32 })),
@@ -134,6 +150,8 @@ impl Context {
 
     let void_ty = core_bs.get_entry("void").unwrap();
     let bool_ty = core_bs.get_entry("bool").unwrap();
+    let char_ty = core_bs.get_entry("char").unwrap();
+    let string_ty = core_bs.get_entry("string").unwrap();
 
     let concrete_int_ty = core_bs.get_entry("s32").unwrap();
     let concrete_float_ty = core_bs.get_entry("f32").unwrap();
@@ -143,12 +161,12 @@ impl Context {
     let main_ns = items.insert(Namespace::new(ContextKey::default(), None, "module".into(), SourceRegion::ANONYMOUS).into());
     core_bs.set_entry_bound("module", main_ns, SourceRegion::ANONYMOUS);
 
-    let main_mod = items.insert(Module::new("main".into(), true, main_ns, None).into());
+    let main_mod = items.insert(Module::new(Self::DEFAULT_MAIN_MODULE_NAME.into(), true, main_ns, None).into());
 
     unsafe { items.get_unchecked_mut(main_ns).mut_namespace_unchecked() }.parent_module = main_mod;
 
     modules.insert("core".into(), core_mod);
-    modules.insert("main".into(), main_mod);
+    modules.insert(Self::DEFAULT_MAIN_MODULE_NAME.into(), main_mod);
 
     namespaces.push(core_ns);
     namespaces.push(main_ns);
@@ -169,6 +187,8 @@ impl Context {
 
       void_ty,
       bool_ty,
+      char_ty,
+      string_ty,
 
       concrete_int_ty,
       concrete_float_ty,
@@ -176,6 +196,9 @@ impl Context {
       modules,
       anon_types,
 
+      strings: Vec::new(),
+      string_indices: HashMap::default(),
+
       namespaces,
       types,
       globals,
@@ -241,6 +264,297 @@ impl Context {
       => Some(canonical_name)
     }
   }
+
+  /// Change the canonical name of the main Module
+  /// 
+  /// Panics if another Module already uses the name
+  pub fn set_main_module_name (&mut self, identifier: Identifier) {
+    let main_mod = self.main_mod;
+    let module = self.items.get_mut(main_mod).and_then(ContextItem::mut_module).expect("Internal error, main module key does not reference a Module");
+
+    let old_identifier = std::mem::replace(&mut module.canonical_name, identifier.clone());
+
+    self.modules.remove(&old_identifier);
+
+    if let Some(existing) = self.modules.insert(identifier.clone(), main_mod) {
+      assert!(existing == main_mod, "Cannot rename the main module to `{}`, a module with that name already exists", identifier);
+    }
+  }
+
+  /// Get a ContextKey for an anonymous (unnamed) TypeData,
+  /// either by getting an existing key or registering a new one
+  pub fn get_anon_type (&mut self, type_data: TypeData, origin: SourceRegion) -> ContextKey {
+    assert!(type_data.is_anon(), "Internal error, non-anonymous TypeData passed to get_anon_type");
+
+    if let Some(existing_key) = self.anon_types.get(&type_data) {
+      *existing_key
+    } else {
+      let new_key = self.items.insert(Type::new(None, None, None, origin, Some(type_data.clone())).into());
+      self.anon_types.insert(type_data, new_key);
+      self.types.push(new_key);
+      new_key
+    }
+  }
+
+  /// Get the index of a string literal value in the string pool of a Context,
+  /// either by getting the index of an identical existing value or adding a new one
+  pub fn intern_string<S: AsRef<str>> (&mut self, string: S) -> usize {
+    let string = string.as_ref();
+
+    if let Some(&existing_index) = self.string_indices.get(string) {
+      existing_index
+    } else {
+      let new_index = self.strings.len();
+      self.strings.push(string.to_owned());
+      self.string_indices.insert(string.to_owned(), new_index);
+      new_index
+    }
+  }
+
+  /// Register a built-in Function in the core Namespace, before analysis,
+  /// making it available to all source without an import
+  /// 
+  /// The Function is marked as intrinsic, and references to it are generated as imports from the core module
+  /// 
+  /// Panics if there is an existing core item with the same identifier
+  pub fn register_builtin_function<I: Into<Identifier>> (&mut self, identifier: I, params: &[(&str, ContextKey)], return_ty: Option<ContextKey>) -> ContextKey {
+    let identifier = identifier.into();
+
+    assert!(!self.core_bs.has_entry(&identifier), "Cannot register built-in `{}`, a core item with that name already exists", identifier);
+
+    let ty = self.get_anon_type(TypeData::Function {
+      parameter_types: params.iter().map(|&(_, ty)| ty).collect(),
+      return_type: return_ty,
+    }, SourceRegion::ANONYMOUS);
+
+    let mut function = Function::new(self.core_mod, self.core_ns, identifier.clone(), SourceRegion::ANONYMOUS, Some(ty));
+    function.params = params.iter().map(|&(name, ty)| (name.into(), ty, SourceRegion::ANONYMOUS)).collect();
+    function.return_ty = return_ty;
+    function.is_intrinsic = true;
+
+    let key = self.items.insert(function.into());
+    self.functions.push(key);
+
+    self.core_bs.set_entry_bound(identifier.clone(), key, SourceRegion::ANONYMOUS);
+
+    let core_ns = self.items.get_mut(self.core_ns).and_then(ContextItem::mut_namespace).expect("Internal error, core namespace key does not reference a Namespace");
+    core_ns.local_bindings.set_entry_bound(identifier.clone(), key, SourceRegion::ANONYMOUS);
+    core_ns.export_bindings.set_entry_bound(identifier, key, SourceRegion::ANONYMOUS);
+
+    key
+  }
+
+  /// Get the key of the intrinsic Function implementing integer exponentiation (`**`) for an integer type,
+  /// registering it in the core Namespace the first time it is needed
+  /// 
+  /// The intrinsic for a type is named after its primitive, such as `pow_s32`,
+  /// and takes a base and an exponent of the same type.
+  /// Results wrap on overflow, as if by repeated wrapping multiplication;
+  /// a negative exponent gives `0` unless the base is `1` or `-1`, in which case the result is `1` or `(-1) ** -exponent`
+  /// 
+  /// Panics if the type is not a primitive integer type
+  pub fn get_pow_intrinsic (&mut self, ty_key: ContextKey) -> ContextKey {
+    let identifier = match self.items.get(ty_key).and_then(ContextItem::ref_type).and_then(|ty| ty.data.as_ref()) {
+      Some(&TypeData::Primitive(PrimitiveType::Integer { signed, bit_size })) => format!("pow_{}{}", if signed { 's' } else { 'u' }, bit_size),
+      _ => panic!("Internal error, non-integer type passed to get_pow_intrinsic")
+    };
+
+    if let Some(existing_key) = self.core_bs.get_entry(identifier.as_str()) {
+      existing_key
+    } else {
+      self.register_builtin_function(identifier.as_str(), &[ ("base", ty_key), ("exponent", ty_key) ], Some(ty_key))
+    }
+  }
+
+  /// Get the ContextKeys of all Modules imported by a Module, directly or indirectly
+  /// 
+  /// The root Module is not included, even if it is imported by one of its dependencies
+  /// 
+  /// Keys are given in the order they are first discovered in a depth-first walk of the import graph
+  pub fn import_closure (&self, root: ContextKey) -> Vec<ContextKey> {
+    let mut closure = Vec::new();
+    let mut visited = HashSet::new();
+    let mut stack = vec! [ root ];
+
+    visited.insert(root);
+
+    while let Some(module_key) = stack.pop() {
+      if module_key != root { closure.push(module_key) }
+
+      let module = self.items.get(module_key).and_then(ContextItem::ref_module).expect("Internal error, import closure walked to non-module key");
+
+      for &import_key in module.imports.iter().rev() {
+        if visited.insert(import_key) { stack.push(import_key) }
+      }
+    }
+
+    closure
+  }
+
+  /// Check the internal consistency of a Context, collecting a ContextError for each broken invariant:
+  /// + Every key bound in a Bindspace, and every key with a bind location, exists
+  /// + Every key referenced by an item or listed by the Context exists, and is the expected kind of item
+  /// + Every unnamed anonymous Type is registered in `anon_types`, and every registration matches its Type
+  /// 
+  /// This is a safety net for catching internal errors in debug builds and tests,
+  /// complementing the `_unchecked` accessors of ContextItem
+  pub fn validate (&self) -> Result<(), Vec<ContextError>> {
+    fn check_key (items: &SlotMap<ContextKey, ContextItem>, errors: &mut Vec<ContextError>, referrer: impl FnOnce () -> String, key: ContextKey, expected: Option<ContextItemKind>) {
+      match (items.get(key), expected) {
+        (None, _) => errors.push(ContextError::DanglingKey { referrer: referrer(), key }),
+        (Some(item), Some(expected)) if item.kind() != expected => errors.push(ContextError::WrongKind { referrer: referrer(), key, expected, found: item.kind() }),
+        _ => { }
+      }
+    }
+
+    fn check_bindspace (items: &SlotMap<ContextKey, ContextItem>, errors: &mut Vec<ContextError>, name: &str, bindspace: &GlobalBindspace) {
+      for (ident, &key) in bindspace.entry_iter() {
+        check_key(items, errors, || format!("{} binding `{}`", name, ident), key, None);
+      }
+
+      for (&key, _) in bindspace.bind_iter() {
+        check_key(items, errors, || format!("{} bind location", name), key, None);
+      }
+    }
+
+    use ContextItemKind::{ Module as M, Namespace as N, Type as T, Function as F, Global as G, };
+
+    let items = &self.items;
+    let mut errors = Vec::new();
+
+    check_bindspace(items, &mut errors, "core", &self.core_bs);
+
+    for &(name, key, kind) in [
+      ("core_ns", self.core_ns, N), ("core_mod", self.core_mod, M),
+      ("main_ns", self.main_ns, N), ("main_mod", self.main_mod, M),
+      ("err_ty", self.err_ty, T), ("int_ty", self.int_ty, T), ("float_ty", self.float_ty, T),
+      ("void_ty", self.void_ty, T), ("bool_ty", self.bool_ty, T), ("char_ty", self.char_ty, T),
+      ("string_ty", self.string_ty, T),
+      ("concrete_int_ty", self.concrete_int_ty, T), ("concrete_float_ty", self.concrete_float_ty, T),
+    ].iter() {
+      check_key(items, &mut errors, || format!("Context field `{}`", name), key, Some(kind));
+    }
+
+    for (name, &key) in self.modules.iter() {
+      check_key(items, &mut errors, || format!("module entry `{}`", name), key, Some(M));
+    }
+
+    for &(list, kind) in [ (&self.types, T), (&self.namespaces, N), (&self.globals, G), (&self.functions, F) ].iter() {
+      for &key in list.iter() {
+        check_key(items, &mut errors, || format!("{} list", kind), key, Some(kind));
+      }
+    }
+
+    for (&item_key, item) in items.pair_iter() {
+      let kind = item.kind();
+      let referrer = move |role: &'static str| move || format!("{} of {} {:?}", role, kind, item_key);
+
+      match item {
+        ContextItem::Module(Module { namespace, imports, .. }) => {
+          check_key(items, &mut errors, referrer("namespace"), *namespace, Some(N));
+          imports.iter().for_each(|&key| check_key(items, &mut errors, referrer("import"), key, Some(M)));
+        },
+
+        ContextItem::Namespace(Namespace { parent_module, parent_namespace, local_bindings, export_bindings, .. }) => {
+          check_key(items, &mut errors, referrer("parent module"), *parent_module, Some(M));
+          if let Some(key) = parent_namespace { check_key(items, &mut errors, referrer("parent namespace"), *key, Some(N)) }
+          check_bindspace(items, &mut errors, &format!("local {} {:?}", kind, item_key), local_bindings);
+          check_bindspace(items, &mut errors, &format!("export {} {:?}", kind, item_key), export_bindings);
+        },
+
+        ContextItem::Type(Type { parent_module, parent_namespace, canonical_name, data, .. }) => {
+          if let Some(key) = parent_module { check_key(items, &mut errors, referrer("parent module"), *key, Some(M)) }
+          if let Some(key) = parent_namespace { check_key(items, &mut errors, referrer("parent namespace"), *key, Some(N)) }
+
+          if let Some(data) = data {
+            let referenced: Vec<ContextKey> = match data {
+              TypeData::Error | TypeData::Primitive(_) | TypeData::Coercible(_) => vec! [ ],
+              &TypeData::Pointer(key) | &TypeData::Optional(key) => vec! [ key ],
+              TypeData::Function { parameter_types, return_type } => parameter_types.iter().chain(return_type.iter()).copied().collect(),
+              TypeData::Structure { field_types, .. } => field_types.clone(),
+              TypeData::Enum { variant_types, .. } => variant_types.iter().flatten().copied().collect(),
+            };
+
+            referenced.into_iter().for_each(|key| check_key(items, &mut errors, referrer("type data"), key, Some(T)));
+
+            if data.is_anon() && canonical_name.is_none() && self.anon_types.get(data) != Some(&item_key) {
+              errors.push(ContextError::UnregisteredAnonType { key: item_key });
+            }
+          }
+        },
+
+        ContextItem::Global(Global { parent_module, parent_namespace, ty, .. }) => {
+          check_key(items, &mut errors, referrer("parent module"), *parent_module, Some(M));
+          check_key(items, &mut errors, referrer("parent namespace"), *parent_namespace, Some(N));
+          if let Some(key) = ty { check_key(items, &mut errors, referrer("type"), *key, Some(T)) }
+        },
+
+        ContextItem::Function(Function { parent_module, parent_namespace, params, return_ty, ty, .. }) => {
+          check_key(items, &mut errors, referrer("parent module"), *parent_module, Some(M));
+          check_key(items, &mut errors, referrer("parent namespace"), *parent_namespace, Some(N));
+          params.iter().for_each(|(_, key, _)| check_key(items, &mut errors, referrer("parameter type"), *key, Some(T)));
+          if let Some(key) = return_ty { check_key(items, &mut errors, referrer("return type"), *key, Some(T)) }
+          if let Some(key) = ty { check_key(items, &mut errors, referrer("type"), *key, Some(T)) }
+        },
+      }
+    }
+
+    for (data, &key) in self.anon_types.iter() {
+      match items.get(key) {
+        Some(ContextItem::Type(Type { data: Some(existing), .. })) if existing == data => { },
+        None => errors.push(ContextError::DanglingKey { referrer: "anonymous type entry".to_owned(), key }),
+        Some(_) => errors.push(ContextError::MismatchedAnonType { key }),
+      }
+    }
+
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+  }
+}
+
+
+/// An inconsistency in a Context found by `Context::validate`,
+/// indicating an internal error in a semantic analyzer rather than an error in source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContextError {
+  /// A key referenced somewhere in a Context does not exist in its items
+  DanglingKey {
+    /// A description of where the key is referenced, such as the name of the item holding it
+    referrer: String,
+    /// The key that does not exist
+    key: ContextKey,
+  },
+  /// A key referenced somewhere in a Context exists, but is not the expected kind of item
+  WrongKind {
+    /// A description of where the key is referenced, such as the name of the item holding it
+    referrer: String,
+    /// The key referencing the wrong kind of item
+    key: ContextKey,
+    /// The kind of item the referrer requires
+    expected: ContextItemKind,
+    /// The kind of item the key actually references
+    found: ContextItemKind,
+  },
+  /// An unnamed Type with anonymous TypeData is not registered in `anon_types`
+  UnregisteredAnonType {
+    /// The unregistered Type
+    key: ContextKey,
+  },
+  /// An entry in `anon_types` references a Type whose data does not match the entry
+  MismatchedAnonType {
+    /// The Type referenced by the mismatched entry
+    key: ContextKey,
+  },
+}
+
+impl Display for ContextError {
+  fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    match self {
+      Self::DanglingKey { referrer, key } => write!(f, "Dangling key {:?} referenced by {}", key, referrer),
+      Self::WrongKind { referrer, key, expected, found } => write!(f, "Key {:?} referenced by {} should be a {}, found a {}", key, referrer, expected, found),
+      Self::UnregisteredAnonType { key } => write!(f, "Anonymous Type {:?} is not registered", key),
+      Self::MismatchedAnonType { key } => write!(f, "Anonymous type entry for {:?} does not match the Type's data", key),
+    }
+  }
 }
 
 
@@ -423,6 +737,8 @@ pub struct Module {
   pub namespace: ContextKey,
   /// The location a Module was first imported (if it is an import)
   pub origin: Option<SourceRegion>,
+  /// The Modules imported directly by a Module
+  pub imports: Vec<ContextKey>,
 }
 
 impl Module {
@@ -433,6 +749,7 @@ impl Module {
       is_main,
       namespace,
       origin,
+      imports: Vec::new(),
     }
   }
 }
@@ -479,6 +796,10 @@ pub enum PrimitiveType {
   Void,
   /// A binary state, True or False
   Bool,
+  /// A single Unicode scalar value
+  Char,
+  /// An immutable sequence of Unicode text, such as a string literal
+  String,
   /// An integer numeric value
   Integer {
     /// Determines whether an integer type has a sign bit
@@ -514,6 +835,9 @@ pub enum TypeData {
   Coercible(CoercibleType),
   /// A pointer to another type
   Pointer(ContextKey),
+  /// A value of another type which may be absent,
+  /// treated as an enum with the variants `Some`, holding the value, and `None`
+  Optional(ContextKey),
   /// A function pointer
   Function {
     /// The type(s) of any parameters accepted by a function
@@ -528,6 +852,13 @@ pub enum TypeData {
     /// Types for each field of a structural type
     field_types: Vec<ContextKey>,
   },
+  /// A tagged union type, holding a value of exactly one of its variants
+  Enum {
+    /// Names for each variant of an enum type, the index of a name is its tag
+    variant_names: Vec<Identifier>,
+    /// Payload types for each variant of an enum type, if the variant carries a value
+    variant_types: Vec<Option<ContextKey>>,
+  },
 }
 
 impl TypeData {
@@ -536,15 +867,28 @@ impl TypeData {
     match self {
       | TypeData::Error    { .. }
       | TypeData::Pointer  { .. }
+      | TypeData::Optional { .. }
       | TypeData::Function { .. }
       => true,
       
       | TypeData::Primitive { .. }
       | TypeData::Coercible { .. }
       | TypeData::Structure { .. }
+      | TypeData::Enum      { .. }
       => false,
     }
   }
+
+  /// Get the name and payload type of each variant of an enum or optional Type, in tag order
+  /// 
+  /// Returns None if a Type is not an enum or optional Type
+  pub fn variants (&self) -> Option<Vec<(&str, Option<ContextKey>)>> {
+    match self {
+      TypeData::Enum { variant_names, variant_types } => Some(variant_names.iter().map(AsRef::as_ref).zip(variant_types.iter().copied()).collect()),
+      &TypeData::Optional(payload_ty) => Some(vec! [ ("Some", Some(payload_ty)), ("None", None) ]),
+      _ => None
+    }
+  }
 }
 
 
@@ -623,12 +967,15 @@ impl<'a> Display for TypeDisplay<'a> {
     } else if let Some(data) = &ty.data {
        match data {
         TypeData::Pointer(ty_key) => { write!(f, "^{}", self.descend(*ty_key))?; },
+        TypeData::Optional(ty_key) => { write!(f, "?{}", self.descend(*ty_key))?; },
         TypeData::Error => { write!(f, "err ty")?; },
         TypeData::Coercible(CoercibleType::Integer) => { write!(f, "int")?; },
         TypeData::Coercible(CoercibleType::FloatingPoint) => { write!(f, "float")?; },
         TypeData::Primitive(prim) => match prim {
           PrimitiveType::Void => { write!(f, "void")?; },
           PrimitiveType::Bool => { write!(f, "bool")?; },
+          PrimitiveType::Char => { write!(f, "char")?; },
+          PrimitiveType::String => { write!(f, "string")?; },
           PrimitiveType::Integer { bit_size, signed } => { write!(f, "{}{}", if *signed { "s" } else { "u" }, bit_size)?; },
           PrimitiveType::FloatingPoint { bit_size } => { write!(f, "f{}", bit_size)?; },
         },
@@ -660,6 +1007,21 @@ impl<'a> Display for TypeDisplay<'a> {
             if iter.peek().is_some() { write!(f, ", " )?; }
           }
 
+          write!(f, " }}")?;
+        },
+        TypeData::Enum { variant_names, variant_types } => {
+          write!(f, "enum {{")?;
+
+          let mut iter = variant_names.iter().zip(variant_types.iter()).peekable();
+
+          while let Some((variant_name, variant_type)) = iter.next() {
+            write!(f, " {}", variant_name)?;
+
+            if let &Some(variant_type) = variant_type { write!(f, "({})", self.descend(variant_type))?; }
+
+            if iter.peek().is_some() { write!(f, ",")?; }
+          }
+
           write!(f, " }}")?;
         },
       }
@@ -690,6 +1052,8 @@ pub struct Global {
   pub initializer: Option<ir::Expression>,
   /// The initialization order of a Global
   pub rank: usize,
+  /// Whether a Global was declared with `mut` and may be assigned to after initialization
+  pub is_mutable: bool,
 }
 
 impl Global {
@@ -703,6 +1067,7 @@ impl Global {
       origin,
       initializer: None,
       rank,
+      is_mutable: false,
     }
   }
 }
@@ -725,7 +1090,11 @@ pub struct Function {
   /// The SourceRegion at which a Function was defined
   pub origin: SourceRegion,
   /// The IR associated with a Function's body, if it has one
-  pub body: Option<ir::Block>
+  pub body: Option<ir::Block>,
+  /// Whether a Function is provided by the host environment rather than defined in source
+  pub is_external: bool,
+  /// Whether a Function is a built-in provided by the compiler, registered in the core Namespace
+  pub is_intrinsic: bool,
 }
 
 impl Function {
@@ -739,7 +1108,9 @@ impl Function {
       return_ty: None,
       ty,
       origin,
-      body: None
+      body: None,
+      is_external: false,
+      is_intrinsic: false,
     }
   }
 }
@@ -765,6 +1136,7 @@ pub struct LocalItem {
   pub canonical_name: Identifier,
   pub ty: ContextKey,
   pub is_parameter: bool,
+  pub is_mutable: bool,
   pub index: usize,
 }
 
@@ -864,6 +1236,7 @@ impl LocalContext {
     canonical_name: Identifier,
     ty: ContextKey,
     is_parameter: bool,
+    is_mutable: bool,
     origin: SourceRegion,
   ) -> LocalKey {
     let count = if is_parameter { &mut self.parameter_count } else { &mut self.local_count };
@@ -876,6 +1249,7 @@ impl LocalContext {
       canonical_name: canonical_name.clone(),
       ty,
       is_parameter,
+      is_mutable,
       index
     });
 
@@ -884,6 +1258,22 @@ impl LocalContext {
     key
   }
 
+  /// Create a local variable in a LocalContext which is not bound to any identifier,
+  /// for use by temporaries introduced during analysis
+  pub fn create_anonymous_variable (&mut self, ty: ContextKey) -> LocalKey {
+    let index = self.local_count;
+
+    self.local_count += 1;
+
+    self.variables.insert(LocalItem {
+      canonical_name: Identifier::default(),
+      ty,
+      is_parameter: false,
+      is_mutable: false,
+      index
+    })
+  }
+
   /// Set the value of a local variable in the current stack frame of a LocalContext
   pub fn set_variable<I: Into<Identifier> + AsRef<str>> (&mut self, ident: I, value: MultiKey, origin: SourceRegion) {
     self.stack_frames.last_mut().unwrap().set_entry_bound(ident, value, origin)
@@ -1052,4 +1442,47 @@ impl Display for ContextItemKind {
 make_key_type! {
   /// A key referring to a top-level item in a semantic analyzer
   pub struct ContextKey;
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn intern_string () {
+    let mut context = Context::default();
+
+    let literals = [ "hello", "world", "hello", "hello", "world", "" ];
+
+    let indices: Vec<usize> = literals.iter().map(|literal| context.intern_string(literal)).collect();
+
+    assert_eq!(indices, [ 0, 1, 0, 0, 1, 2 ]);
+    assert_eq!(context.strings, [ "hello", "world", "" ]);
+  }
+
+  #[test]
+  fn validate () {
+    let mut context = Context::new();
+
+    assert_eq!(context.validate(), Ok(()));
+
+    // A binding left behind after its item is removed
+    let main_ns = context.main_ns;
+    let doomed = context.items.insert(Type::new(Some(context.main_mod), Some(main_ns), Some("Doomed".into()), SourceRegion::ANONYMOUS, None).into());
+    context.items.get_mut(main_ns).and_then(ContextItem::mut_namespace).unwrap().local_bindings.set_entry_bound("Doomed", doomed, SourceRegion::ANONYMOUS);
+    context.items.remove(doomed);
+
+    // An anonymous type created without going through get_anon_type
+    let pointer = context.items.insert(Type::new(None, None, None, SourceRegion::ANONYMOUS, Some(TypeData::Pointer(context.bool_ty))).into());
+
+    // A module entry referencing a Namespace
+    context.modules.insert("impostor".into(), main_ns);
+
+    let errors = context.validate().unwrap_err();
+
+    assert!(errors.iter().any(|error| matches!(error, ContextError::DanglingKey { key, .. } if *key == doomed)), "{:#?}", errors);
+    assert!(errors.contains(&ContextError::UnregisteredAnonType { key: pointer }), "{:#?}", errors);
+    assert!(errors.iter().any(|error| matches!(error, ContextError::WrongKind { key, expected: ContextItemKind::Module, found: ContextItemKind::Namespace, .. } if *key == main_ns)), "{:#?}", errors);
+  }
 }
\ No newline at end of file
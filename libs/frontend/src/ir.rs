@@ -5,7 +5,7 @@ use std::{
   ops::{ Deref, },
 };
 
-use mod_common::{ Operator, Constant, };
+use mod_common::{ Operator, Constant, Identifier, };
 
 use crate::{
   source::{ SourceRegion, },
@@ -140,8 +140,9 @@ impl Conditional {
 #[allow(missing_docs)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum StatementData {
-  Expression(Expression),
-  Declaration { ty: ContextKey, initializer: Option<Expression> },
+  Expression { expression: Expression, discard: bool },
+  /// The name of a Declaration is None for temporaries introduced during analysis
+  Declaration { name: Option<Identifier>, ty: ContextKey, initializer: Option<Expression> },
   Assignment { target: Expression, value: Expression },
   ModAssignment { target: Expression, value: Expression, operator: Operator },
 
@@ -154,7 +155,7 @@ pub enum StatementData {
 impl StatementData {
   /// Determine if StatementData can be converted to ExpressionData
   pub fn is_expression (&self) -> bool {
-    matches!(self, StatementData::Expression(_))
+    matches!(self, StatementData::Expression { .. })
   }
 }
 
@@ -222,6 +223,13 @@ pub enum ExpressionData {
 
   Block(Box<Block>),
   Conditional(Box<Conditional>),
+
+  /// Construct a value of an enum type, identified by the tag of one of its variants
+  Variant { tag: usize, payload: Option<Box<Expression>> },
+  /// Determine if a value of an enum type holds a particular variant
+  IsVariant { operand: Box<Expression>, tag: usize },
+  /// Extract the payload of a particular variant from a value of an enum type
+  Payload { operand: Box<Expression>, tag: usize },
 }
 
 /// Lower level IR item, semantic expressions
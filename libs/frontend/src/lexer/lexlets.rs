@@ -1,247 +1,1128 @@
-use mod_common::{ Identifier, Constant, Number, IDENTIFIER_VALUES, SYM_OPERATOR_VALUES, };
-
-use crate::{
-  source::{ SourceRegion, },
-  token::{ Token, TokenData, },
-};
-
-use super::{ Lexer, };
-
-
-/// An unrecognized lexical symbol
-pub struct InvalidLexicalSymbol {
-  /// The invalid symbol
-  pub symbol: char,
-  /// The area of a Source an invalid symbol was found
-  pub origin: SourceRegion,
-}
-
-/// The result of a single step of lexical analysis in a Lexer
-pub enum LexletResult {
-  Some(Token),
-  Err(InvalidLexicalSymbol),
-  None
-}
-
-
-struct NumberBuilder {
-  digits: [u8; Self::MAX_LENGTH],
-  length: usize,
-}
-
-impl Default for NumberBuilder {
-  #[inline] fn default () -> Self { Self::new() }
-}
-
-impl NumberBuilder {
-  const MAX_LENGTH: usize = 256;
-
-  fn new () -> Self {
-    Self {
-      digits: [0u8; Self::MAX_LENGTH],
-      length: 0
-    }
-  }
-
-  fn push (&mut self, d: char) {
-    if self.length < Self::MAX_LENGTH {
-      self.digits[self.length] = d as _;
-    }
-
-    self.length += 1;
-  }
-}
-
-impl AsRef<str> for NumberBuilder {
-  fn as_ref (&self) -> &str { unsafe { std::str::from_utf8_unchecked(std::slice::from_raw_parts(self.digits.as_ptr(), self.length.min(Self::MAX_LENGTH))) } }
-}
-
-
-fn lex_whitespace (lexer: &mut Lexer) -> LexletResult {
-  loop {
-    match lexer.curr_char() {
-      Some(ch) if ch.is_whitespace() => { lexer.advance(); },
-      _ => { return LexletResult::None }
-    }
-  }
-}
-
-
-fn lex_identifier (lexer: &mut Lexer) -> LexletResult {
-  match lexer.curr_char() {
-    Some(ch) if ch.is_ascii_alphabetic() || ch == '_' => {
-      lexer.push_marker();
-
-      let mut ident = Identifier::default();
-
-      ident.append(ch);
-
-      lexer.advance();
-
-      loop {
-        match lexer.curr_char() {
-          Some(ch) if ch.is_ascii_alphanumeric() || ch == '_' => {
-            ident.append(ch);
-            lexer.advance();
-          },
-          _ => break
-        }
-      }
-
-      'id_loop: for (substr, token_data) in IDENTIFIER_VALUES {
-        if ident.len() != substr.len() { continue }
-        
-        for (i, op_char) in substr.chars().enumerate() {  
-          if ident.get(i) != Some(op_char) {
-            continue 'id_loop;
-          }
-        }
-    
-        // If this point has been reached, the current identifier is a full match
-        // Assuming identifiers are sorted from longest to shortest then we can accept this identifier
-        let data: TokenData = token_data.into();
-        return LexletResult::Some(Token::new(
-          data,
-          lexer.pop_marker_region().unwrap()
-        ))
-      }
-
-      LexletResult::Some(Token::new(TokenData::Identifier(ident), lexer.pop_marker_region().unwrap()))
-    },
-    _ => LexletResult::None
-  }
-}
-
-
-fn lex_decimal_number (lexer: &mut Lexer) -> LexletResult {
-  match lexer.curr_char() {
-    Some(ch)
-    if ch.is_ascii_digit()
-    || (ch == '.' && if let Some(nx) = lexer.peek_char() { nx.is_ascii_digit() } else { false })
-    => {
-      lexer.push_marker();
-
-      let mut builder = NumberBuilder::new();
-
-      builder.push(ch);
-      lexer.advance();
-
-      let mut float = ch == '.';
-      let mut allow_underscore = !float;
-      let mut alphabetic_err_start = None;
-
-      if float {
-        builder.push(lexer.curr_char().unwrap());
-        lexer.advance();
-      }
-
-      while let Some(ch) = lexer.curr_char() {
-        if ch.is_ascii_digit() {
-          if alphabetic_err_start.is_none() {
-            allow_underscore = true;
-            builder.push(ch);
-          }
-          lexer.advance();
-        } else if ch == '.' && !float && if let Some(nx) = lexer.peek_char() { nx.is_ascii_digit() } else { false } && alphabetic_err_start.is_none() {
-          allow_underscore = true;
-          float = true;
-          builder.push(ch);
-          lexer.advance();
-          builder.push(lexer.curr_char().unwrap());
-          lexer.advance();
-        } else if ch == '_' && allow_underscore {
-          lexer.advance();
-        } else if ch.is_ascii_alphabetic() { // TODO exponent notation
-          if alphabetic_err_start.is_none() {
-            alphabetic_err_start = Some(lexer.curr_location());
-          }
-          lexer.advance();
-        } else {
-          break
-        }
-      }
-
-      if builder.length > NumberBuilder::MAX_LENGTH {
-        lexer.error(format!("Decimal literal is too long at {} characters, the max length is {}", builder.length, NumberBuilder::MAX_LENGTH));
-      }
-
-      if let Some(start) = alphabetic_err_start {
-        lexer.error_at(SourceRegion { source: lexer.source_key, start, end: lexer.curr_location() }, "Unexpected characters in decimal literal".to_owned());
-      }
-
-      LexletResult::Some(Token::new(
-        TokenData::Constant(Constant::Number(if float {
-          Number::FloatingPoint(match builder.as_ref().parse::<f64>() {
-            Ok(f) => f,
-            Err(e) => {
-              lexer.error(format!("Failed to parse floating point decimal literal: {}", e));
-              0.0
-            }
-          }.into())
-        } else {
-          Number::Integer(match builder.as_ref().parse::<u64>() {
-            Ok(i) => i,
-            Err(e) => {
-              lexer.error(format!("Failed to parse integer decimal literal: {}", e));
-              0
-            }
-          })
-        })),
-        lexer.pop_marker_region().unwrap()
-      ))
-    },
-
-    _ => LexletResult::None
-  }
-}
-
-
-fn lex_operator (lexer: &mut Lexer) -> LexletResult {
-  lexer.push_marker();
-
-  'op_loop: for (substr, operator) in SYM_OPERATOR_VALUES {
-    lexer.save_locale();
-
-    for op_char in substr.chars() {  
-      if lexer.curr_char() != Some(op_char) {
-        lexer.load_locale();
-        continue 'op_loop;
-      } else {
-        lexer.advance();
-      }
-    }
-
-    lexer.discard_saved_locale();
-
-    // If this point has been reached, the current operator is a full match
-    // Assuming operators are sorted from longest to shortest then we can accept this operator
-
-    return LexletResult::Some(Token::new(
-      TokenData::Operator(*operator),
-      lexer.pop_marker_region().unwrap()
-    ))
-  }
-
-  lexer.pop_marker();
-
-  LexletResult::None
-}
-
-
-impl<'a> Lexer<'a> {
-  pub(super) const LEXLETS: &'static [fn (&mut Lexer) -> LexletResult] = &[
-    lex_whitespace,
-    lex_identifier,
-    lex_decimal_number,
-    lex_operator,
-    |lexer: &mut Lexer| -> LexletResult {
-      if let Some(ch) = lexer.curr_char() {
-        lexer.push_marker();
-        lexer.advance();
-        LexletResult::Err(InvalidLexicalSymbol { symbol: ch, origin: lexer.pop_marker_region().unwrap() })
-      } else {
-        LexletResult::None
-      }
-    }
-  ];
+use mod_common::{ Identifier, Constant, Number, IDENTIFIER_VALUES, SYM_OPERATOR_VALUES, };
+
+use crate::{
+  session::{ SESSION, IntegerOverflow, },
+  source::{ SourceRegion, SOURCE_MANAGER, },
+  token::{ Token, TokenData, },
+};
+
+use super::{ Lexer, };
+
+
+/// An unrecognized lexical symbol
+pub struct InvalidLexicalSymbol {
+  /// The invalid symbol
+  pub symbol: char,
+  /// The area of a Source an invalid symbol was found
+  pub origin: SourceRegion,
+}
+
+/// The result of a single step of lexical analysis in a Lexer
+pub enum LexletResult {
+  Some(Token),
+  Err(InvalidLexicalSymbol),
+  None
+}
+
+
+/// The type suffixes which may directly follow a decimal literal, such as `2u32`,
+/// and whether each suffix names a floating point type
+const NUMBER_SUFFIXES: &[(&str, bool)] = &[
+  ("u8", false), ("u16", false), ("u32", false), ("u64", false),
+  ("s8", false), ("s16", false), ("s32", false), ("s64", false),
+  ("f32", true), ("f64", true),
+];
+
+
+struct NumberBuilder {
+  digits: [u8; Self::MAX_LENGTH],
+  length: usize,
+}
+
+impl Default for NumberBuilder {
+  #[inline] fn default () -> Self { Self::new() }
+}
+
+impl NumberBuilder {
+  const MAX_LENGTH: usize = 256;
+
+  fn new () -> Self {
+    Self {
+      digits: [0u8; Self::MAX_LENGTH],
+      length: 0
+    }
+  }
+
+  fn push (&mut self, d: char) {
+    if self.length < Self::MAX_LENGTH {
+      self.digits[self.length] = d as _;
+    }
+
+    self.length += 1;
+  }
+}
+
+impl AsRef<str> for NumberBuilder {
+  fn as_ref (&self) -> &str { unsafe { std::str::from_utf8_unchecked(std::slice::from_raw_parts(self.digits.as_ptr(), self.length.min(Self::MAX_LENGTH))) } }
+}
+
+
+/// Skips whitespace and comments, neither of which produce Tokens
+fn lex_whitespace (lexer: &mut Lexer) -> LexletResult {
+  loop {
+    match lexer.curr_char() {
+      Some(ch) if ch.is_whitespace() => { lexer.advance(); },
+      Some('/') if lexer.peek_char() == Some('/') => skip_line_comment(lexer),
+      Some('/') if lexer.peek_char() == Some('*') => skip_block_comment(lexer),
+      _ => { return LexletResult::None }
+    }
+  }
+}
+
+fn skip_line_comment (lexer: &mut Lexer) {
+  while let Some(ch) = lexer.curr_char() {
+    if ch == '\n' { break }
+    lexer.advance();
+  }
+}
+
+/// Block comments nest, so that code containing a comment can itself be commented out
+fn skip_block_comment (lexer: &mut Lexer) {
+  lexer.push_marker();
+  lexer.advance();
+  lexer.advance();
+
+  let opening = lexer.pop_marker_region().unwrap();
+  let mut depth = 1usize;
+
+  while depth > 0 {
+    match (lexer.curr_char(), lexer.peek_char()) {
+      (Some('/'), Some('*')) => {
+        depth += 1;
+        lexer.advance();
+        lexer.advance();
+      },
+
+      (Some('*'), Some('/')) => {
+        depth -= 1;
+        lexer.advance();
+        lexer.advance();
+      },
+
+      (Some(_), _) => { lexer.advance(); },
+
+      (None, _) => {
+        lexer.error_at(opening, "Unterminated block comment".to_owned());
+        break
+      }
+    }
+  }
+}
+
+
+fn lex_identifier (lexer: &mut Lexer) -> LexletResult {
+  match lexer.curr_char() {
+    Some(ch) if ch.is_ascii_alphabetic() || ch == '_' => {
+      lexer.push_marker();
+
+      let mut ident = Identifier::default();
+
+      ident.append(ch);
+
+      lexer.advance();
+
+      loop {
+        match lexer.curr_char() {
+          Some(ch) if ch.is_ascii_alphanumeric() || ch == '_' => {
+            ident.append(ch);
+            lexer.advance();
+          },
+          _ => break
+        }
+      }
+
+      'id_loop: for (substr, token_data) in IDENTIFIER_VALUES {
+        if ident.len() != substr.len() { continue }
+        
+        for (i, op_char) in substr.chars().enumerate() {  
+          if ident.get(i) != Some(op_char) {
+            continue 'id_loop;
+          }
+        }
+    
+        // If this point has been reached, the current identifier is a full match
+        // Assuming identifiers are sorted from longest to shortest then we can accept this identifier
+        let data: TokenData = token_data.into();
+        return LexletResult::Some(Token::new(
+          data,
+          lexer.pop_marker_region().unwrap()
+        ))
+      }
+
+      LexletResult::Some(Token::new(TokenData::Identifier(ident), lexer.pop_marker_region().unwrap()))
+    },
+    _ => LexletResult::None
+  }
+}
+
+
+fn lex_directive (lexer: &mut Lexer) -> LexletResult {
+  if lexer.curr_char() != Some('#') { return LexletResult::None }
+
+  lexer.push_marker();
+  lexer.advance();
+
+  let mut name = Identifier::default();
+
+  while let Some(ch) = lexer.curr_char() {
+    if !(ch.is_ascii_alphanumeric() || ch == '_') || !name.append(ch) { break }
+
+    lexer.advance();
+  }
+
+  let origin = lexer.pop_marker_region().unwrap();
+
+  if name.is_empty() {
+    LexletResult::Err(InvalidLexicalSymbol { symbol: '#', origin })
+  } else {
+    LexletResult::Some(Token::new(TokenData::Directive(name), origin))
+  }
+}
+
+
+fn lex_string (lexer: &mut Lexer) -> LexletResult {
+  if lexer.curr_char() != Some('"') { return LexletResult::None }
+
+  lexer.push_marker();
+  lexer.advance();
+
+  let mut string = String::new();
+
+  loop {
+    match lexer.curr_char() {
+      Some('"') => {
+        lexer.advance();
+        break
+      },
+
+      Some('\\') => if let Some(ch) = lex_escape(lexer, "string") { string.push(ch) },
+
+      Some('\n') | None => {
+        // The string still produces a token, so that a missing quote is only reported once
+        lexer.error("Unterminated string literal".to_owned());
+        break
+      },
+
+      Some(ch) => {
+        string.push(ch);
+        lexer.advance();
+      }
+    }
+  }
+
+  LexletResult::Some(Token::new(TokenData::Constant(Constant::String(string)), lexer.pop_marker_region().unwrap()))
+}
+
+
+fn lex_character (lexer: &mut Lexer) -> LexletResult {
+  if lexer.curr_char() != Some('\'') { return LexletResult::None }
+
+  lexer.push_marker();
+  lexer.advance();
+
+  let mut chars = Vec::new();
+  let mut reported = false;
+
+  loop {
+    match lexer.curr_char() {
+      Some('\'') => {
+        lexer.advance();
+        break
+      },
+
+      Some('\\') => match lex_escape(lexer, "character") {
+        Some(ch) => chars.push(ch),
+        None => reported = true
+      },
+
+      Some('\n') | None => {
+        lexer.error("Unterminated character literal".to_owned());
+        reported = true;
+        break
+      },
+
+      Some(ch) => {
+        chars.push(ch);
+        lexer.advance();
+      }
+    }
+  }
+
+  let origin = lexer.pop_marker_region().unwrap();
+
+  // Malformed literals still produce a token, so that only the first problem with them is reported
+  let ch = if let [ ch ] = chars.as_slice() { *ch } else {
+    if !reported {
+      lexer.error_at(origin, if chars.is_empty() {
+        "Character literal is empty, expected exactly one character".to_owned()
+      } else {
+        "Character literal contains more than one character, use a string literal instead".to_owned()
+      });
+    }
+
+    '\0'
+  };
+
+  LexletResult::Some(Token::new(TokenData::Constant(Constant::Char(ch)), origin))
+}
+
+/// Lex an escape sequence starting at a `\`, in a string or character literal
+/// 
+/// Reports an error and returns None if the escape sequence is not recognized or is malformed
+fn lex_escape (lexer: &mut Lexer, literal: &str) -> Option<char> {
+  lexer.push_marker();
+  lexer.advance();
+
+  let escaped = match lexer.curr_char() {
+    Some('\\') => Some('\\'),
+    Some('"') => Some('"'),
+    Some('\'') => Some('\''),
+    Some('n') => Some('\n'),
+    Some('t') => Some('\t'),
+    Some('r') => Some('\r'),
+    Some('0') => Some('\0'),
+    _ => None
+  };
+
+  if let Some(ch) = escaped {
+    lexer.advance();
+    lexer.pop_marker();
+    Some(ch)
+  } else if lexer.curr_char() == Some('u') {
+    lexer.advance();
+
+    let ch = lex_unicode_escape(lexer);
+
+    if ch.is_some() {
+      lexer.pop_marker();
+    } else {
+      lexer.error_pop(format!("Malformed unicode escape sequence in {} literal, expected 1 to 6 hexadecimal digits of a unicode scalar value, such as `\\u{{1F600}}`", literal));
+    }
+
+    ch
+  } else {
+    lexer.error_pop(format!("Unrecognized escape sequence in {} literal", literal));
+    None
+  }
+}
+
+
+/// Lex the `{XXXX}` part of a `\u{XXXX}` escape sequence, with the Lexer positioned after the `u`
+/// 
+/// Returns None if the braces or digits are missing, there are more than 6 digits, or the value is not a unicode scalar value.
+/// The closing brace is only consumed if it is found, so a malformed escape never consumes the end of the string literal
+fn lex_unicode_escape (lexer: &mut Lexer) -> Option<char> {
+  if lexer.curr_char() != Some('{') { return None }
+  lexer.advance();
+
+  let mut value = 0u32;
+  let mut digits = 0usize;
+
+  loop {
+    match lexer.curr_char() {
+      Some('}') => {
+        lexer.advance();
+        break
+      },
+
+      Some(ch) if ch.is_ascii_hexdigit() => {
+        if digits < 6 { value = value * 16 + ch.to_digit(16).unwrap() }
+        digits += 1;
+        lexer.advance();
+      },
+
+      _ => return None
+    }
+  }
+
+  if digits == 0 || digits > 6 { return None }
+
+  std::char::from_u32(value)
+}
+
+
+/// Scale a floating point value by a power of two,
+/// in steps small enough that no intermediate factor overflows
+fn scale_by_pow2 (mut value: f64, mut exponent: i64) -> f64 {
+  while exponent > 1000 { value *= 2f64.powi(1000); exponent -= 1000; }
+  while exponent < -1000 { value *= 2f64.powi(-1000); exponent += 1000; }
+
+  value * 2f64.powi(exponent as i32)
+}
+
+fn lex_hexadecimal_number (lexer: &mut Lexer) -> LexletResult {
+  match (lexer.curr_char(), lexer.peek_char()) {
+    (Some('0'), Some('x' | 'X')) => {
+      lexer.push_marker();
+
+      lexer.advance();
+      lexer.advance();
+
+      // Significant bits are accumulated into the mantissa until it is nearly full,
+      // after which integer digits only scale the exponent and fractional digits are dropped
+      const MANTISSA_LIMIT: u64 = 1 << 59;
+
+      let mut mantissa = 0u64;
+      let mut exponent = 0i64;
+      let mut integer = Some(0u64);
+      let mut wrapped = 0u64;
+      let mut digit_count = 0usize;
+      let mut fraction = false;
+      let mut alphabetic_err_start = None;
+
+      while let Some(ch) = lexer.curr_char() {
+        if let Some(digit) = ch.to_digit(16) {
+          if alphabetic_err_start.is_none() {
+            digit_count += 1;
+
+            if !fraction {
+              integer = integer.and_then(|i| i.checked_mul(16)).and_then(|i| i.checked_add(digit as u64));
+              wrapped = (wrapped << 4) | digit as u64;
+            }
+
+            if mantissa < MANTISSA_LIMIT {
+              mantissa = (mantissa << 4) | digit as u64;
+              if fraction { exponent -= 4 }
+            } else if !fraction {
+              exponent += 4
+            }
+          }
+          lexer.advance();
+        } else if ch == '.' && !fraction && if let Some(nx) = lexer.peek_char() { nx.is_ascii_hexdigit() } else { false } && alphabetic_err_start.is_none() {
+          fraction = true;
+          lexer.advance();
+        } else if ch == '_' && digit_count > 0 {
+          lexer.advance();
+        } else if (ch == 'p' || ch == 'P') && alphabetic_err_start.is_none() {
+          break
+        } else if ch.is_ascii_alphabetic() {
+          if alphabetic_err_start.is_none() {
+            alphabetic_err_start = Some(lexer.curr_location());
+          }
+          lexer.advance();
+        } else {
+          break
+        }
+      }
+
+      let mut binary_exponent = None;
+
+      if let Some('p' | 'P') = lexer.curr_char() {
+        lexer.advance();
+
+        let negative = match lexer.curr_char() {
+          Some('-') => { lexer.advance(); true },
+          Some('+') => { lexer.advance(); false },
+          _ => false
+        };
+
+        let mut value = 0i64;
+        let mut exponent_digits = 0usize;
+
+        while let Some(ch) = lexer.curr_char() {
+          if let Some(digit) = ch.to_digit(10) {
+            // Exponents this large always overflow or underflow, so saturating is exact enough
+            value = (value * 10 + digit as i64).min(1 << 20);
+            exponent_digits += 1;
+            lexer.advance();
+          } else if ch == '_' && exponent_digits > 0 {
+            lexer.advance();
+          } else if ch.is_ascii_alphanumeric() {
+            if alphabetic_err_start.is_none() {
+              alphabetic_err_start = Some(lexer.curr_location());
+            }
+            lexer.advance();
+          } else {
+            break
+          }
+        }
+
+        if exponent_digits == 0 {
+          lexer.error("Expected decimal digits for the binary exponent of hexadecimal float literal".to_owned());
+        }
+
+        binary_exponent = Some(if negative { -value } else { value });
+      }
+
+      if let Some(start) = alphabetic_err_start {
+        lexer.error_at(SourceRegion { source: lexer.source_key, start, end: lexer.curr_location() }, "Unexpected characters in hexadecimal literal".to_owned());
+      }
+
+      if digit_count == 0 {
+        lexer.error("Expected hexadecimal digits to follow `0x`".to_owned());
+      }
+
+      let number = if let Some(binary_exponent) = binary_exponent {
+        Number::FloatingPoint(scale_by_pow2(mantissa as f64, exponent + binary_exponent).into())
+      } else {
+        if fraction {
+          lexer.error("Hexadecimal float literal requires a binary exponent, such as `p0`".to_owned());
+          Number::FloatingPoint(scale_by_pow2(mantissa as f64, exponent).into())
+        } else {
+          Number::Integer(integer.unwrap_or_else(|| resolve_integer_overflow(lexer, "hexadecimal", wrapped)))
+        }
+      };
+
+      LexletResult::Some(Token::new(
+        TokenData::Constant(Constant::Number(number)),
+        lexer.pop_marker_region().unwrap()
+      ))
+    },
+
+    _ => LexletResult::None
+  }
+}
+
+
+fn lex_radix_number (lexer: &mut Lexer) -> LexletResult {
+  let (radix, radix_name, valid_digits) = match (lexer.curr_char(), lexer.peek_char()) {
+    (Some('0'), Some('o' | 'O')) => (8, "octal", "`0` to `7`"),
+    (Some('0'), Some('b' | 'B')) => (2, "binary", "`0` and `1`"),
+    _ => return LexletResult::None
+  };
+
+  lexer.push_marker();
+
+  lexer.advance();
+  let prefix = format!("0{}", lexer.curr_char().unwrap());
+  lexer.advance();
+
+  let mut integer = Some(0u64);
+  let mut wrapped = 0u64;
+  let mut digit_count = 0usize;
+  let mut invalid_err_start = None;
+
+  while let Some(ch) = lexer.curr_char() {
+    if let Some(digit) = ch.to_digit(radix) {
+      if invalid_err_start.is_none() {
+        digit_count += 1;
+        integer = integer.and_then(|i| i.checked_mul(radix as u64)).and_then(|i| i.checked_add(digit as u64));
+        wrapped = wrapped.wrapping_mul(radix as u64).wrapping_add(digit as u64);
+      }
+      lexer.advance();
+    } else if ch == '_' && digit_count > 0 {
+      lexer.advance();
+    } else if ch.is_ascii_alphanumeric() {
+      if invalid_err_start.is_none() {
+        invalid_err_start = Some(lexer.curr_location());
+      }
+      lexer.advance();
+    } else {
+      break
+    }
+  }
+
+  let mut value = integer.unwrap_or(0);
+
+  if let Some(start) = invalid_err_start {
+    lexer.error_at(
+      SourceRegion { source: lexer.source_key, start, end: lexer.curr_location() },
+      format!("Unexpected characters in {} literal, only the digits {} are valid", radix_name, valid_digits)
+    );
+  } else if digit_count == 0 {
+    lexer.error(format!("Expected {} digits to follow `{}`", radix_name, prefix));
+  } else if integer.is_none() {
+    value = resolve_integer_overflow(lexer, radix_name, wrapped);
+  }
+
+  LexletResult::Some(Token::new(
+    TokenData::Constant(Constant::Number(Number::Integer(value))),
+    lexer.pop_marker_region().unwrap()
+  ))
+}
+
+
+/// Report an integer literal too large to fit in 64 bits and get its value, according to the `IntegerOverflow` setting of the SESSION
+/// 
+/// `wrapped` is the value of the literal truncated to its lowest 64 bits
+fn resolve_integer_overflow (lexer: &mut Lexer, radix_name: &str, wrapped: u64) -> u64 {
+  match SESSION.integer_overflow() {
+    IntegerOverflow::Error => {
+      lexer.error(format!("The value of this {} integer literal is too large to fit in 64 bits", radix_name));
+      0
+    },
+
+    IntegerOverflow::Saturate => {
+      lexer.warning(format!("The value of this {} integer literal is too large to fit in 64 bits, and has been saturated to {}", radix_name, std::u64::MAX));
+      std::u64::MAX
+    },
+
+    IntegerOverflow::Wrap => {
+      lexer.warning(format!("The value of this {} integer literal is too large to fit in 64 bits, and has been wrapped to {}", radix_name, wrapped));
+      wrapped
+    },
+  }
+}
+
+
+fn lex_decimal_number (lexer: &mut Lexer) -> LexletResult {
+  match lexer.curr_char() {
+    Some(ch)
+    if ch.is_ascii_digit()
+    || (ch == '.' && if let Some(nx) = lexer.peek_char() { nx.is_ascii_digit() } else { false })
+    => {
+      lexer.push_marker();
+
+      let mut builder = NumberBuilder::new();
+
+      builder.push(ch);
+      lexer.advance();
+
+      let mut float = ch == '.';
+      let mut allow_underscore = !float;
+      let mut alphabetic_err_start = None;
+
+      if float {
+        builder.push(lexer.curr_char().unwrap());
+        lexer.advance();
+      }
+
+      while let Some(ch) = lexer.curr_char() {
+        if ch.is_ascii_digit() {
+          if alphabetic_err_start.is_none() {
+            allow_underscore = true;
+            builder.push(ch);
+          }
+          lexer.advance();
+        } else if ch == '.' && !float && if let Some(nx) = lexer.peek_char() { nx.is_ascii_digit() } else { false } && alphabetic_err_start.is_none() {
+          allow_underscore = true;
+          float = true;
+          builder.push(ch);
+          lexer.advance();
+          builder.push(lexer.curr_char().unwrap());
+          lexer.advance();
+        } else if ch == '_' && allow_underscore {
+          lexer.advance();
+        } else if (ch == 'e' || ch == 'E') && alphabetic_err_start.is_none() {
+          break
+        } else if ch.is_ascii_alphabetic() {
+          if alphabetic_err_start.is_none() {
+            alphabetic_err_start = Some(lexer.curr_location());
+          }
+          lexer.advance();
+        } else {
+          break
+        }
+      }
+
+      if let Some('e' | 'E') = lexer.curr_char() {
+        float = true;
+        builder.push('e');
+        lexer.advance();
+
+        if let Some(sign @ ('-' | '+')) = lexer.curr_char() {
+          builder.push(sign);
+          lexer.advance();
+        }
+
+        let mut exponent_digits = 0usize;
+
+        while let Some(ch) = lexer.curr_char() {
+          if ch.is_ascii_digit() && alphabetic_err_start.is_none() {
+            builder.push(ch);
+            exponent_digits += 1;
+            lexer.advance();
+          } else if ch == '_' && exponent_digits > 0 {
+            lexer.advance();
+          } else if ch.is_ascii_alphanumeric() {
+            if alphabetic_err_start.is_none() {
+              alphabetic_err_start = Some(lexer.curr_location());
+            }
+            lexer.advance();
+          } else {
+            break
+          }
+        }
+
+        if exponent_digits == 0 {
+          lexer.error("Expected decimal digits for the exponent of float literal".to_owned());
+          // keeps the literal parseable so only one error is reported
+          builder.push('0');
+        }
+      }
+
+      if builder.length > NumberBuilder::MAX_LENGTH {
+        lexer.error(format!("Decimal literal is too long at {} characters, the max length is {}", builder.length, NumberBuilder::MAX_LENGTH));
+      }
+
+      let mut suffix = None;
+
+      if let Some(start) = alphabetic_err_start {
+        let region = SourceRegion { source: lexer.source_key, start, end: lexer.curr_location() };
+        let text = SOURCE_MANAGER.get_region_text(region).unwrap_or_default();
+
+        match NUMBER_SUFFIXES.iter().find(|&&(spelling, _)| spelling == text) {
+          Some(&(spelling, false)) if float => {
+            lexer.error_at(region, format!("The integer type suffix `{}` cannot be applied to a floating point literal", spelling));
+          },
+
+          Some(&(spelling, suffix_is_float)) => {
+            float |= suffix_is_float;
+            suffix = Some(Identifier::from(spelling));
+          },
+
+          None => {
+            lexer.error_at(region, "Unexpected characters in decimal literal".to_owned());
+          }
+        }
+      }
+
+      let number = if float {
+        Number::FloatingPoint(match builder.as_ref().parse::<f64>() {
+          Ok(f) => f,
+          Err(e) => {
+            lexer.error(format!("Failed to parse floating point decimal literal: {}", e));
+            0.0
+          }
+        }.into())
+      } else {
+        Number::Integer(match builder.as_ref().parse::<u64>() {
+          Ok(i) => i,
+          // the builder only holds digits, so the only way parsing can fail is overflow
+          Err(_) => {
+            let wrapped = builder.as_ref().chars().fold(0u64, |acc, digit| acc.wrapping_mul(10).wrapping_add(digit.to_digit(10).unwrap() as u64));
+            resolve_integer_overflow(lexer, "decimal", wrapped)
+          }
+        })
+      };
+
+      LexletResult::Some(Token::new(
+        if let Some(suffix) = suffix { TokenData::SuffixedNumber(number, suffix) }
+        else { TokenData::Constant(Constant::Number(number)) },
+        lexer.pop_marker_region().unwrap()
+      ))
+    },
+
+    _ => LexletResult::None
+  }
+}
+
+
+fn lex_operator (lexer: &mut Lexer) -> LexletResult {
+  lexer.push_marker();
+
+  'op_loop: for (substr, operator) in SYM_OPERATOR_VALUES {
+    lexer.save_locale();
+
+    for op_char in substr.chars() {  
+      if lexer.curr_char() != Some(op_char) {
+        lexer.load_locale();
+        continue 'op_loop;
+      } else {
+        lexer.advance();
+      }
+    }
+
+    lexer.discard_saved_locale();
+
+    // If this point has been reached, the current operator is a full match
+    // Assuming operators are sorted from longest to shortest then we can accept this operator
+
+    return LexletResult::Some(Token::new(
+      TokenData::Operator(*operator),
+      lexer.pop_marker_region().unwrap()
+    ))
+  }
+
+  lexer.pop_marker();
+
+  LexletResult::None
+}
+
+
+impl<'a> Lexer<'a> {
+  pub(super) const LEXLETS: &'static [fn (&mut Lexer) -> LexletResult] = &[
+    lex_whitespace,
+    lex_identifier,
+    lex_string,
+    lex_character,
+    lex_directive,
+    lex_hexadecimal_number,
+    lex_radix_number,
+    lex_decimal_number,
+    lex_operator,
+    |lexer: &mut Lexer| -> LexletResult {
+      if let Some(ch) = lexer.curr_char() {
+        lexer.push_marker();
+        lexer.advance();
+        LexletResult::Err(InvalidLexicalSymbol { symbol: ch, origin: lexer.pop_marker_region().unwrap() })
+      } else {
+        LexletResult::None
+      }
+    }
+  ];
+}
+
+
+#[cfg(test)]
+mod test {
+  use mod_common::{ FloatingPoint, Operator, };
+
+  use super::*;
+
+  use crate::{
+    session::{ SESSION, MessageKind, DEFAULT_TAB_WIDTH, },
+    source::{ SOURCE_MANAGER, SourceLocation, },
+    test_support::{ lock_singletons, },
+  };
+
+  fn lex_number (content: &str) -> (Option<Number>, usize) {
+    let _guard = lock_singletons();
+
+    let message_base = SESSION.messages().len();
+
+    let source = SOURCE_MANAGER.add_source("hex_literal.ms", content);
+    let tokens = Lexer::new(source).lex_stream();
+
+    let error_count = SESSION.messages()[message_base..].iter().filter(|message| message.kind == MessageKind::Error).count();
+
+    let number = match tokens.first().map(|token| &token.data) {
+      Some(&TokenData::Constant(Constant::Number(number))) => Some(number),
+      _ => None
+    };
+
+    (number, error_count)
+  }
+
+  fn lex_float (content: &str) -> f64 {
+    match lex_number(content) {
+      (Some(Number::FloatingPoint(FloatingPoint::Norm(float))), 0) => float,
+      other => panic!("expected a valid float literal from `{}`, got {:?}", content, other)
+    }
+  }
+
+  #[test]
+  fn hexadecimal_float () {
+    assert_eq!(lex_float("0x1.8p3"), 12.0);
+    assert_eq!(lex_float("0xA.8p0"), 10.5);
+    assert_eq!(lex_float("0x1p-2"), 0.25);
+    assert_eq!(lex_float("0x.8P+1"), 1.0);
+    assert_eq!(lex_float("0x1.fffffffffffffp1023"), std::f64::MAX);
+    assert_eq!(lex_float("0x1p-1074").to_bits(), 1);
+  }
+
+  #[test]
+  fn hexadecimal_integer () {
+    assert_eq!(lex_number("0xff"), (Some(Number::Integer(255)), 0));
+    assert_eq!(lex_number("0xFFFF_FFFF_FFFF_FFFF"), (Some(Number::Integer(std::u64::MAX)), 0));
+  }
+
+  #[test]
+  fn malformed_hexadecimal () {
+    for &content in [ "0x", "0x1.8", "0x1.8p", "0x1g", "0x1_0000_0000_0000_0000" ].iter() {
+      assert_eq!(lex_number(content).1, 1, "expected an error lexing `{}`", content);
+    }
+  }
+
+  #[test]
+  fn suffixed_decimal () {
+    let suffixed = |number: Number, suffix: &str| TokenData::SuffixedNumber(number, suffix.into());
+
+    let tokens = {
+      let _guard = lock_singletons();
+      lex_data("suffixed_decimal.ms", "2u32 1_000s64 255_u8 2f32 1.5f64 1e3f32")
+    };
+
+    assert_eq!(tokens, vec! [
+      suffixed(Number::Integer(2), "u32"),
+      suffixed(Number::Integer(1000), "s64"),
+      suffixed(Number::Integer(255), "u8"),
+      suffixed(2.0.into(), "f32"),
+      suffixed(1.5.into(), "f64"),
+      suffixed(1000.0.into(), "f32"),
+    ]);
+
+    for &content in [ "2u7", "2x32", "1.5u32", "1e3s8", "2u_32" ].iter() {
+      assert_eq!(lex_number(content).1, 1, "expected an error lexing `{}`", content);
+    }
+  }
+
+  #[test]
+  fn exponent_float () {
+    assert_eq!(lex_float("1e3"), 1000.0);
+    assert_eq!(lex_float("1.5E-2"), 0.015);
+    assert_eq!(lex_float("2e+3"), 2000.0);
+    assert_eq!(lex_float("1_000e1_0"), 1e13);
+    assert_eq!(lex_number("1e400"), (Some(Number::FloatingPoint(FloatingPoint::Inf)), 0));
+  }
+
+  #[test]
+  fn malformed_exponent () {
+    for &content in [ "1e", "1.5E-", "2e+", "1e3x" ].iter() {
+      assert_eq!(lex_number(content).1, 1, "expected an error lexing `{}`", content);
+    }
+  }
+
+  #[test]
+  fn integer_overflow_modes () {
+    let binary = format!("0b1{}11", "0".repeat(64));
+
+    let cases = [
+      ("18446744073709551621", 5),
+      ("0x1_0000_0000_0000_0005", 5),
+      ("0o2_000_000_000_000_000_000_005", 5),
+      (binary.as_str(), 3),
+    ];
+
+    let lex_with = |overflow: IntegerOverflow, content: &str| {
+      let _guard = lock_singletons();
+
+      let message_base = SESSION.messages().len();
+
+      SESSION.set_integer_overflow(overflow);
+      let source = SOURCE_MANAGER.add_source("integer_overflow.ms", content);
+      let tokens = Lexer::new(source).lex_stream();
+      SESSION.set_integer_overflow(IntegerOverflow::default());
+
+      let count = |kind: MessageKind| SESSION.messages()[message_base..].iter().filter(|message| message.kind == kind).count();
+
+      (tokens[0].data.clone(), count(MessageKind::Error), count(MessageKind::Warning))
+    };
+
+    let integer = |value: u64| TokenData::Constant(Constant::Number(Number::Integer(value)));
+
+    for &(content, wrapped) in cases.iter() {
+      assert_eq!(lex_with(IntegerOverflow::Error, content), (integer(0), 1, 0), "lexing `{}`", content);
+      assert_eq!(lex_with(IntegerOverflow::Saturate, content), (integer(std::u64::MAX), 0, 1), "lexing `{}`", content);
+      assert_eq!(lex_with(IntegerOverflow::Wrap, content), (integer(wrapped), 0, 1), "lexing `{}`", content);
+    }
+
+    assert_eq!(lex_with(IntegerOverflow::Wrap, "18446744073709551615"), (integer(std::u64::MAX), 0, 0));
+  }
+
+  #[test]
+  fn octal_and_binary_integer () {
+    assert_eq!(lex_number("0o755"), (Some(Number::Integer(0o755)), 0));
+    assert_eq!(lex_number("0O17"), (Some(Number::Integer(15)), 0));
+    assert_eq!(lex_number("0o1_777_777_777_777_777_777_777"), (Some(Number::Integer(std::u64::MAX)), 0));
+
+    assert_eq!(lex_number("0b1010"), (Some(Number::Integer(10)), 0));
+    assert_eq!(lex_number("0B1111_0000"), (Some(Number::Integer(240)), 0));
+    assert_eq!(lex_number(&format!("0b{}", "1".repeat(64))), (Some(Number::Integer(std::u64::MAX)), 0));
+  }
+
+  #[test]
+  fn malformed_octal_and_binary () {
+    let overflowing_binary = format!("0b1{}", "0".repeat(64));
+
+    for &content in [ "0b2", "0b102", "0o8", "0o7a", "0b", "0o", "0o2_000_000_000_000_000_000_000", overflowing_binary.as_str() ].iter() {
+      assert_eq!(lex_number(content).1, 1, "expected an error lexing `{}`", content);
+    }
+  }
+
+  #[test]
+  fn pow_operator () {
+    let _guard = lock_singletons();
+
+    let source = SOURCE_MANAGER.add_source("pow_operator.ms", "a ** b * c");
+    let tokens: Vec<_> = Lexer::new(source).lex_stream().into_iter().map(|token| token.data).collect();
+
+    assert_eq!(tokens[1], TokenData::Operator(Operator::Pow));
+    assert_eq!(tokens[3], TokenData::Operator(Operator::Mul));
+  }
+
+  #[test]
+  fn shift_operators () {
+    let _guard = lock_singletons();
+
+    let source = SOURCE_MANAGER.add_source("shift_operators.ms", "a << b >> c < d <= ~e");
+    let tokens: Vec<_> = Lexer::new(source).lex_stream().into_iter().map(|token| token.data).collect();
+
+    assert_eq!(tokens[1], TokenData::Operator(Operator::ShiftLeft));
+    assert_eq!(tokens[3], TokenData::Operator(Operator::ShiftRight));
+    assert_eq!(tokens[5], TokenData::Operator(Operator::Lesser));
+    assert_eq!(tokens[7], TokenData::Operator(Operator::LesserOrEqual));
+    assert_eq!(tokens[8], TokenData::Operator(Operator::BitNot));
+  }
+
+  fn lex_data (name: &str, content: &str) -> Vec<TokenData> {
+    let source = SOURCE_MANAGER.add_source(name, content);
+    Lexer::new(source).lex_stream().into_iter().map(|token| token.data).collect()
+  }
+
+  #[test]
+  fn comments () {
+    let _guard = lock_singletons();
+
+    let errors_before = SESSION.count_errors();
+
+    let plain = lex_data("plain.ms", "a / b c");
+    let commented = lex_data("comments.ms", "// leading\na /* inline */ / b // trailing\n/* outer /* nested */ still outer */ c //");
+
+    assert_eq!(commented, plain);
+    assert_eq!(lex_data("only_comment.ms", "/* a /* b */ c */"), vec! [ ]);
+    assert_eq!(SESSION.count_errors(), errors_before);
+  }
+
+  #[test]
+  fn unterminated_block_comment () {
+    let _guard = lock_singletons();
+
+    let message_base = SESSION.messages().len();
+
+    let tokens = lex_data("unterminated_comment.ms", "a\n  /* outer /* nested */ b");
+
+    assert_eq!(tokens.len(), 1);
+
+    let errors: Vec<_> = SESSION.messages()[message_base..].iter().filter(|message| message.kind == MessageKind::Error).collect();
+    assert_eq!(errors.len(), 1);
+
+    let origin = errors[0].items[0].origin;
+    assert_eq!((origin.start.line, origin.start.column), (1, 2));
+    assert_eq!(origin.end.index - origin.start.index, 2);
+  }
+
+  #[test]
+  fn multi_line_region_columns () {
+    let _guard = lock_singletons();
+
+    let source = SOURCE_MANAGER.add_source("multi_line_region.ms", "ab\n\tcd");
+
+    SESSION.set_tab_width(4);
+    let mut lexer = Lexer::new(source);
+    SESSION.set_tab_width(DEFAULT_TAB_WIDTH);
+
+    lexer.advance();
+    lexer.push_marker();
+    for _ in 0 .. 4 { lexer.advance(); }
+
+    let region = lexer.pop_marker_region().unwrap();
+
+    assert_eq!(region.start, SourceLocation { index: 1, line: 0, column: 1 });
+    assert_eq!(region.end, SourceLocation { index: 5, line: 1, column: 5 });
+    assert_eq!(format!("{:?}", region), "multi_line_region.ms:1:2-2:6");
+
+    let tokens = Lexer::new(source).lex_stream();
+
+    assert_eq!(tokens[1].origin.start, SourceLocation { index: 4, line: 1, column: 1 });
+    assert_eq!(tokens[1].origin.end, SourceLocation { index: 6, line: 1, column: 3 });
+  }
+
+  fn lex_string_literal (content: &str) -> (Option<String>, usize) {
+    let message_base = SESSION.messages().len();
+
+    let tokens = lex_data("string_escapes.ms", content);
+
+    let error_count = SESSION.messages()[message_base..].iter().filter(|message| message.kind == MessageKind::Error).count();
+
+    let string = match tokens.into_iter().next() {
+      Some(TokenData::Constant(Constant::String(string))) => Some(string),
+      _ => None
+    };
+
+    (string, error_count)
+  }
+
+  #[test]
+  fn string_escapes () {
+    let _guard = lock_singletons();
+
+    for &(content, expected) in [
+      (r#""line\nbreak""#, "line\nbreak"),
+      (r#""a\tb""#, "a\tb"),
+      (r#""a\rb""#, "a\rb"),
+      (r#""back\\slash""#, "back\\slash"),
+      (r#""\"quoted\"""#, "\"quoted\""),
+      (r#""nul\0""#, "nul\0"),
+      (r#""\u{41}\u{e9}\u{1F600}\u{10FFFF}""#, "A\u{e9}\u{1F600}\u{10FFFF}"),
+    ].iter() {
+      assert_eq!(lex_string_literal(content), (Some(expected.to_owned()), 0), "lexing `{}`", content);
+    }
+
+    let message_base = SESSION.messages().len();
+    let tokens = lex_data("string_in_expression.ms", r#"let s = "a\u{42}\t";"#);
+
+    assert_eq!(SESSION.messages().len(), message_base);
+    assert!(tokens.contains(&TokenData::Constant(Constant::String("aB\t".to_owned()))), "{:?}", tokens);
+  }
+
+  #[test]
+  fn malformed_string_escapes () {
+    let _guard = lock_singletons();
+
+    for &content in [
+      r#""\q""#, r#""\u{}""#, r#""\u{110000}""#, r#""\u{D800}""#, r#""\u{1234567}""#, r#""\u41""#, r#""\u{4g}""#,
+      "\"unterminated\nnext", "\"unterminated",
+    ].iter() {
+      assert_eq!(lex_string_literal(content).1, 1, "expected an error lexing `{}`", content);
+    }
+
+    let message_base = SESSION.messages().len();
+    lex_data("bad_escape.ms", r#""ok \u{} ok""#);
+
+    let origin = SESSION.messages()[message_base].items[0].origin;
+    assert_eq!((origin.start.column, origin.end.column), (4, 8));
+  }
+
+  fn lex_character_literal (content: &str) -> (Option<char>, usize) {
+    let message_base = SESSION.messages().len();
+
+    let tokens = lex_data("character_literal.ms", content);
+
+    let error_count = SESSION.messages()[message_base..].iter().filter(|message| message.kind == MessageKind::Error).count();
+
+    let ch = match tokens.into_iter().next() {
+      Some(TokenData::Constant(Constant::Char(ch))) => Some(ch),
+      _ => None
+    };
+
+    (ch, error_count)
+  }
+
+  #[test]
+  fn character_literal () {
+    let _guard = lock_singletons();
+
+    for &(content, expected) in [
+      ("'a'", 'a'),
+      ("'\"'", '"'),
+      ("'\u{e9}'", '\u{e9}'),
+      (r"'\n'", '\n'),
+      (r"'\t'", '\t'),
+      (r"'\\'", '\\'),
+      (r"'\''", '\''),
+      (r"'\0'", '\0'),
+      (r"'\u{41}'", 'A'),
+      (r"'\u{1F600}'", '\u{1F600}'),
+    ].iter() {
+      assert_eq!(lex_character_literal(content), (Some(expected), 0), "lexing `{}`", content);
+    }
+
+    assert_eq!(lex_data("character_sequence.ms", "'a' 'b'").len(), 2);
+  }
+
+  #[test]
+  fn malformed_character_literal () {
+    let _guard = lock_singletons();
+
+    for &content in [ "''", "'ab'", r"'\n\t'", r"'\q'", r"'\u{}'", "'a", "'a\nb" ].iter() {
+      assert_eq!(lex_character_literal(content).1, 1, "expected an error lexing `{}`", content);
+    }
+  }
+
+  #[test]
+  fn string_literal () {
+    let _guard = lock_singletons();
+
+    let source = SOURCE_MANAGER.add_source("string_literal.ms", r#""a \"quoted\" path\n" "unterminated"#);
+    let mut lexer = Lexer::new(source);
+
+    let errors_before = SESSION.count_errors();
+
+    assert_eq!(lexer.lex_token().ok().flatten().map(|token| token.data), Some(TokenData::Constant(Constant::String("a \"quoted\" path\n".to_owned()))));
+    assert_eq!(SESSION.count_errors(), errors_before);
+
+    assert_eq!(lexer.lex_token().ok().flatten().map(|token| token.data), Some(TokenData::Constant(Constant::String("unterminated".to_owned()))));
+    assert_eq!(SESSION.count_errors(), errors_before + 1);
+  }
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 //! Contains Lexer and supporting structures and functions
 
-use mod_utils::{ Unref, };
+use mod_common::{ Keyword, Operator, Constant, };
+use mod_utils::{ Unref, Either, };
 
 use crate::{
   session::{ SESSION, MessageKind, Message, },
@@ -32,6 +33,7 @@ pub struct Lexer<'a> {
   stored_locale: Option<LexerLocale>,
   locale: LexerLocale,
   markers: Vec<SourceLocation>,
+  tab_width: u32,
 }
 
 impl<'a> Lexer<'a> {
@@ -55,6 +57,7 @@ impl<'a> Lexer<'a> {
         next,
       },
       markers: Vec::new(),
+      tab_width: SESSION.tab_width() as _,
     }
   }
 
@@ -75,15 +78,19 @@ impl<'a> Lexer<'a> {
   }
 
   /// Advance the offset of a Lexer
+  /// 
+  /// Tabs advance the column by the tab width of the SESSION at the time the Lexer was created
   pub fn advance (&mut self) -> Option<char> {
     if self.locale.location.index <= self.length {
       self.locale.location.index += 1;
 
-      if self.locale.curr.unwrap() == '\n' {
-        self.locale.location.line += 1;
-        self.locale.location.column = 0;
-      } else {
-        self.locale.location.column += 1;
+      match self.locale.curr.unwrap() {
+        '\n' => {
+          self.locale.location.line += 1;
+          self.locale.location.column = 0;
+        },
+        '\t' => self.locale.location.column += self.tab_width,
+        _ => self.locale.location.column += 1,
       }
     }
 
@@ -320,7 +327,23 @@ impl<'a> Lexer<'a> {
   }
 
   /// Convert an entire Source's content into a TokenStream
+  /// 
+  /// Regions between `#if feature "name"`, an optional `#else`, and `#endif` directives
+  /// are kept or skipped depending on whether the feature is enabled in the SESSION.
+  /// 
+  /// `include "path";` directives are replaced by the tokens of the named file,
+  /// which is resolved relative to the directory of the Source containing the directive.
+  /// Included tokens keep SourceRegions pointing into the included Source,
+  /// and circular includes are reported as errors
   pub fn lex_stream (&mut self) -> Vec<Token> {
+    let tokens = self.lex_tokens();
+    let tokens = self.evaluate_conditionals(tokens);
+    let mut include_stack = vec! [ self.source_key ];
+
+    self.expand_includes(tokens, &mut include_stack)
+  }
+
+  fn lex_tokens (&mut self) -> Vec<Token> {
     let mut tokens = Vec::new();
   
     loop {
@@ -337,4 +360,143 @@ impl<'a> Lexer<'a> {
       }
     }
   }
+
+  fn evaluate_conditionals (&self, tokens: Vec<Token>) -> Vec<Token> {
+    struct Conditional {
+      origin: SourceRegion,
+      active: bool,
+      has_else: bool,
+    }
+
+    let mut conditionals: Vec<Conditional> = Vec::new();
+    let mut kept = Vec::with_capacity(tokens.len());
+    let mut tokens = tokens.into_iter().peekable();
+
+    while let Some(token) = tokens.next() {
+      let directive = if let TokenData::Directive(directive) = &token.data {
+        directive.clone()
+      } else {
+        if conditionals.iter().all(|conditional| conditional.active) { kept.push(token) }
+        continue
+      };
+
+      match directive.as_ref() {
+        "if" => {
+          let feature = match tokens.peek() {
+            Some(Token { data: TokenData::Identifier(identifier), .. }) if identifier.as_ref() == "feature" => {
+              tokens.next();
+
+              if let Some(Token { data: TokenData::Constant(Constant::String(feature)), .. }) = tokens.peek() {
+                let feature = feature.clone();
+                tokens.next();
+                Some(feature)
+              } else {
+                None
+              }
+            },
+
+            _ => None
+          };
+
+          let active = if let Some(feature) = feature {
+            SESSION.is_feature_enabled(&feature)
+          } else {
+            self.error_at(token.origin, "Expected `feature \"name\"` to follow #if directive".to_owned());
+            false
+          };
+
+          conditionals.push(Conditional { origin: token.origin, active, has_else: false });
+        },
+
+        "else" => match conditionals.last_mut() {
+          Some(conditional) if !conditional.has_else => {
+            conditional.active = !conditional.active;
+            conditional.has_else = true;
+          },
+
+          Some(conditional) => {
+            self.error_at(token.origin, "Unexpected #else directive, the enclosing #if already has an #else".to_owned())
+            .append(conditional.origin, "Enclosing #if directive is here".to_owned());
+          },
+
+          None => { self.error_at(token.origin, "Unexpected #else directive without a matching #if".to_owned()); }
+        },
+
+        "endif" => if conditionals.pop().is_none() {
+          self.error_at(token.origin, "Unexpected #endif directive without a matching #if".to_owned());
+        },
+
+        _ => { self.error_at(token.origin, format!("Unknown directive #{}", directive)); }
+      }
+    }
+
+    for conditional in conditionals {
+      self.error_at(conditional.origin, "#if directive is missing a matching #endif".to_owned());
+    }
+
+    kept
+  }
+
+  fn expand_includes (&self, tokens: Vec<Token>, include_stack: &mut Vec<SourceKey>) -> Vec<Token> {
+    let mut expanded = Vec::with_capacity(tokens.len());
+    let mut tokens = tokens.into_iter().peekable();
+
+    while let Some(token) = tokens.next() {
+      match token.data {
+        TokenData::Keyword(Keyword::Include) => { },
+
+        _ => {
+          expanded.push(token);
+          continue
+        }
+      }
+
+      let (path, mut origin) = if let Some(Token { data: TokenData::Constant(Constant::String(path)), origin }) = tokens.peek() {
+        (path.clone(), SourceRegion::merge(token.origin, *origin))
+      } else {
+        self.error_at(token.origin, "Expected a string literal file path to follow `include`".to_owned());
+        continue
+      };
+
+      tokens.next();
+
+      if let Some(&Token { data: TokenData::Operator(Operator::Semi), origin: semi_origin }) = tokens.peek() {
+        origin = SourceRegion::merge(origin, semi_origin);
+        tokens.next();
+      } else {
+        self.error_at(origin.clip_to_end(), "Expected ; to end include directive".to_owned());
+      }
+
+      let including_source = SOURCE_MANAGER.get_source(*include_stack.last().unwrap()).unwrap();
+      let include_path = including_source.path.parent().map(|dir| dir.join(&path)).unwrap_or_else(|| path.into());
+
+      let include_key = match SOURCE_MANAGER.load_source(&include_path) {
+        Ok(key) | Err(Either::A(key)) => key,
+        Err(Either::B(e)) => {
+          self.error_at(origin, format!(
+            "Unexpected error loading included file [{}] from disk: {}",
+            include_path.display(), e
+          ));
+
+          continue
+        }
+      };
+
+      if include_stack.contains(&include_key) {
+        self.error_at(origin, format!("Circular include of file [{}]", include_path.display()));
+
+        continue
+      }
+
+      include_stack.push(include_key);
+
+      let included_tokens = Lexer::new(include_key).lex_tokens();
+      let included_tokens = self.evaluate_conditionals(included_tokens);
+      expanded.extend(self.expand_includes(included_tokens, include_stack));
+
+      include_stack.pop();
+    }
+
+    expanded
+  }
 }
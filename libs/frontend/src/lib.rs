@@ -20,4 +20,9 @@ pub mod ast;
 pub mod parser;
 pub mod ctx;
 pub mod ir;
-pub mod analyzer;
\ No newline at end of file
+pub mod analyzer;
+pub mod timing;
+
+#[doc(hidden)]
+#[cfg(any(test, feature = "test_support"))]
+pub mod test_support;
\ No newline at end of file
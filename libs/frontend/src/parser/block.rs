@@ -1,11 +1,11 @@
 //! The Block Parser function and its dependencies
 
-use mod_common::{ Operator::*, Keyword::*, STATEMENT_KEYWORDS, };
+use mod_common::{ Operator::*, Keyword::{ self, * }, STATEMENT_KEYWORDS, };
 
 use crate::{
   source::{ SourceRegion, },
   token::{ Token, TokenData, },
-  ast::{ Block, StatementData,  ConditionalBranch, Conditional, },
+  ast::{ Block, Statement, StatementData,  ConditionalBranch, Conditional, MatchPattern, MatchArm, Match, },
 };
 
 use super::{ Parser, statement, expression, sync };
@@ -19,7 +19,8 @@ pub fn block (parser: &mut Parser) -> Option<Block> {
     let mut statements = Vec::new();
 
     let mut trailing_expression = None;
-    let mut stmt_ok = true;
+    // The end of the last statement, if it was not terminated by a ;
+    let mut unterminated = None;
 
     loop {
       match parser.curr_tok() {
@@ -38,43 +39,45 @@ pub fn block (parser: &mut Parser) -> Option<Block> {
 
         // Statements/Expressions
         _ => {
-          if stmt_ok {
-            if let Some(stmt) = statement(parser) {
-              if stmt.requires_semi() {
-                if let Some(&Token { data: TokenData::Operator(Semi), .. }) = parser.curr_tok() {
-                  parser.advance();
-                  statements.push(stmt);
-                } else if let StatementData::Expression(expr) = stmt.data {
+          // Only the final expression of a block may omit its ;, becoming the value of the block,
+          // so if another statement follows we issue an error and treat the expression as a statement
+          if let Some(unterminated_end) = unterminated.take() {
+            parser.error_at(unterminated_end, "Expected a ; to end statement, only the final expression of a block may omit it".to_owned());
+
+            if let Some(expr) = trailing_expression.take() {
+              let origin = expr.origin;
+              statements.push(Statement::new(StatementData::Expression(expr), origin));
+            }
+          }
+
+          if let Some(stmt) = statement(parser) {
+            if stmt.requires_semi() {
+              if let Some(&Token { data: TokenData::Operator(Semi), .. }) = parser.curr_tok() {
+                parser.advance();
+                statements.push(stmt);
+              } else {
+                unterminated = Some(stmt.origin.clip_to_end());
+
+                if let StatementData::Expression(expr) = stmt.data {
                   trailing_expression = Some(expr);
-                  stmt_ok = false;
                 } else {
                   statements.push(stmt);
-                  stmt_ok = false;
                 }
-              } else {
-                statements.push(stmt);
               }
+            } else {
+              statements.push(stmt);
+            }
 
-              continue
-            } // else { Error message already provided by statement }
-          } else {
-            parser.error("Expected a ; to separate statements or } to end block".to_owned());
-          }
+            continue
+          } // else { Error message already provided by statement }
 
-          // If we reach here there was some kind of error, either we didnt have a semi after the last statement, or our statement call had an error,
+          // If we reach here our statement call had an error,
           // so we need to try and synchronize to the end of the {block} or the next semi or keyword
           
           if parser.synchronize(sync::close_pair_or(sync::operator(LeftBracket), sync::operator(RightBracket), sync::or(sync::operator(Semi), sync::any_keyword_of(STATEMENT_KEYWORDS)))) {
             match parser.curr_tok().unwrap() {
-              Token { data: TokenData::Operator(Semi), .. } => {
-                parser.advance();
-                trailing_expression = None;
-                stmt_ok = true;
-              },
-              Token { data: TokenData::Keyword(_), .. } => {
-                trailing_expression = None;
-                stmt_ok = true;
-              },
+              Token { data: TokenData::Operator(Semi), .. } => { parser.advance(); },
+              Token { data: TokenData::Keyword(_), .. } => { },
               Token { data: TokenData::Operator(RightBracket), .. } => continue, // The next iteration will handle the closing bracket
               _ => unreachable!("Internal error, unexpected block parselet state post-synchronization")
             }
@@ -184,4 +187,151 @@ pub fn conditional (parser: &mut Parser) -> Option<Conditional> {
   }
 
   Some(Conditional::new(if_branch, else_if_branches, else_block, SourceRegion::merge(start_region, end_region)))
+}
+
+
+/// Parse a single MatchArm
+pub fn match_arm (parser: &mut Parser) -> Option<MatchArm> {
+  // Synchronization must be handled by higher level parselet
+
+  let (pattern, start_region) = match parser.curr_tok() {
+    Some(&Token { data: TokenData::Constant(ref constant), origin }) => {
+      let constant = constant.clone();
+
+      parser.advance();
+
+      (MatchPattern::Constant(constant), origin)
+    },
+    Some(&Token { data: TokenData::Identifier(ref ident), origin }) if ident.as_ref() == "_" => {
+      parser.advance();
+
+      (MatchPattern::Wildcard, origin)
+    },
+    Some(&Token { data: TokenData::Identifier(ref ident), origin }) => {
+      let identifier = ident.clone();
+
+      parser.advance();
+
+      let binding = if let Some(&Token { data: TokenData::Operator(LeftParen), .. }) = parser.curr_tok() {
+        parser.advance();
+
+        let binding = if let Some(&Token { data: TokenData::Identifier(ref binding), .. }) = parser.curr_tok() {
+          binding.clone()
+        } else {
+          parser.error("Expected an identifier to bind the payload of this enum variant".to_owned());
+          return None
+        };
+
+        parser.advance();
+
+        if let Some(&Token { data: TokenData::Operator(RightParen), .. }) = parser.curr_tok() {
+          parser.advance();
+        } else {
+          parser.error("Expected ) to follow enum variant payload binding".to_owned());
+          return None
+        }
+
+        Some(binding)
+      } else {
+        None
+      };
+
+      (MatchPattern::Variant { identifier, binding }, origin)
+    },
+    _ => {
+      parser.error("Expected a constant, enum variant or _ for match arm pattern".to_owned());
+      return None
+    }
+  };
+
+  if let Some(&Token { data: TokenData::Operator(FatArrow), .. }) = parser.curr_tok() {
+    parser.advance();
+  } else {
+    parser.error("Expected => to follow match arm pattern".to_owned());
+    return None
+  }
+
+  let body = expression(parser)?;
+
+  let origin = SourceRegion::merge(start_region, body.origin);
+
+  Some(MatchArm::new(pattern, body, origin))
+}
+
+
+/// Parse a Match expression
+pub fn match_expression (parser: &mut Parser) -> Option<Match> {
+  if let Some(&Token { data: TokenData::Keyword(Keyword::Match), origin: start_region }) = parser.curr_tok() {
+    parser.advance();
+
+    // Synchronization must be handled by higher level parselet
+    let scrutinee = expression(parser)?;
+
+    if let Some(&Token { data: TokenData::Operator(LeftBracket), .. }) = parser.curr_tok() {
+      parser.advance();
+    } else {
+      parser.error("Expected { to begin match arms".to_owned());
+      return None
+    }
+
+    let mut arms = Vec::new();
+    let mut arm_ok = true;
+
+    loop {
+      match parser.curr_tok() {
+        // Unexpected end of input
+        None => {
+          parser.error_at(SourceRegion::merge(start_region, parser.curr_region()), "Unexpected end of input, expected } to close match".to_owned());
+          return None
+        },
+
+        // The end of the arm list
+        Some(&Token { data: TokenData::Operator(RightBracket), origin: end_region }) => {
+          parser.advance();
+
+          if arms.is_empty() {
+            parser.error_at(SourceRegion::merge(start_region, end_region), "Match must have at least one arm".to_owned());
+            return None
+          }
+
+          return Some(Match::new(scrutinee, arms, SourceRegion::merge(start_region, end_region)))
+        },
+
+        // Arms
+        _ => {
+          if arm_ok {
+            if let Some(arm) = match_arm(parser) {
+              arms.push(arm);
+
+              if let Some(&Token { data: TokenData::Operator(Comma), .. }) = parser.curr_tok() {
+                parser.advance();
+                arm_ok = true;
+              } else {
+                arm_ok = false;
+              }
+
+              continue
+            } // else { Error message already provided by match_arm }
+          } else {
+            parser.error("Expected a comma to separate match arms or } to end match".to_owned());
+          }
+
+          // If we reach here there was some kind of error, either we didnt have a comma after the last arm, or our arm call had an error,
+          // so we need to try and synchronize to the end of the {match} or the next comma
+
+          if parser.synchronize(sync::close_pair_or(sync::operator(LeftBracket), sync::operator(RightBracket), sync::operator(Comma))) {
+            if let Some(&Token { data: TokenData::Operator(Comma), .. }) = parser.curr_tok() {
+              parser.advance();
+              arm_ok = true;
+            } // else { The next iteration will handle the closing bracket }
+          } else {
+            // Cannot recover state locally
+            return None
+          }
+        }
+      }
+    }
+  }
+
+  unreachable!("Internal error, match parselet called on non-match token");
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 //! The Statement Parser function and its dependencies
 
-use mod_common::{ Operator::*, Keyword::*, get_binary_precedence, UNARY_PRECEDENCE, };
+use mod_common::{ Operator::*, Keyword::*, get_binary_precedence, is_right_associative, UNARY_PRECEDENCE, NEGATION_PRECEDENCE, };
 use mod_utils::{ Either, };
 
 use crate::{
@@ -9,7 +9,7 @@ use crate::{
   ast::{ Expression, ExpressionData, },
 };
 
-use super::{ Parser, ParseletPredicate, ParseletFunction, sync, block, conditional, path, };
+use super::{ Parser, ParseletPredicate, ParseletFunction, sync, block, conditional, match_expression, path, };
 
 
 
@@ -74,6 +74,16 @@ fn pfx_constant (parser: &mut Parser) -> Option<Expression> {
   unreachable!("Internal error, constant expression parselet called on non-constant token");
 }
 
+fn pfx_suffixed_number (parser: &mut Parser) -> Option<Expression> {
+  if let Some(&Token { data: TokenData::SuffixedNumber(number, ref suffix), origin }) = parser.curr_tok() {
+    let suffix = suffix.clone();
+    parser.advance();
+    return Some(Expression::new(ExpressionData::SuffixedNumber { number, suffix }, origin))
+  }
+
+  unreachable!("Internal error, suffixed number expression parselet called on non-suffixed number token");
+}
+
 fn pfx_syntactic_group (parser: &mut Parser) -> Option<Expression> {
   if let Some(&Token { data: TokenData::Operator(LeftParen), origin: start_region }) = parser.curr_tok() {
     parser.advance();
@@ -126,11 +136,21 @@ fn pfx_conditional (parser: &mut Parser) -> Option<Expression> {
   }
 }
 
+fn pfx_match (parser: &mut Parser) -> Option<Expression> {
+  let match_expr = box match_expression(parser)?;
+
+  let origin = match_expr.origin;
+
+  Some(Expression::new(ExpressionData::Match(match_expr), origin))
+}
+
 fn pfx_unary_operator (parser: &mut Parser) -> Option<Expression> {
   if let Some(&Token { data: TokenData::Operator(operator), origin }) = parser.curr_tok() {
     parser.advance();
     
-    let operand = box pratt(UNARY_PRECEDENCE, parser)?;
+    let precedence = if operator == Sub { NEGATION_PRECEDENCE } else { UNARY_PRECEDENCE };
+
+    let operand = box pratt(precedence, parser)?;
 
     let origin = SourceRegion::merge(origin, operand.origin);
 
@@ -155,10 +175,12 @@ impl PrefixParselet {
     pfx! [
       Identifier(_) | Operator(DoubleColon) => pfx_path_or_ident,
       Constant(_) => pfx_constant,
+      SuffixedNumber(..) => pfx_suffixed_number,
       Operator(LeftParen) => pfx_syntactic_group,
       Operator(LeftBracket) => pfx_block,
       Keyword(If) => pfx_conditional,
-      Operator(AddressOf | Dereference | Not | Sub) => pfx_unary_operator,
+      Keyword(Match) => pfx_match,
+      Operator(AddressOf | Dereference | Not | BitNot | Sub) => pfx_unary_operator,
     ]
   };
 
@@ -181,7 +203,13 @@ impl PrefixParselet {
 fn ifx_binary_operator (left: Expression, parser: &mut Parser) -> Option<Expression> {
   if let Some(&Token { data: TokenData::Operator(operator), .. }) = parser.curr_tok() {
     parser.advance();
-    if let Some(right) = pratt(get_binary_precedence(operator), parser) {
+
+    let precedence = get_binary_precedence(operator);
+
+    // Parsing the right operand at a slightly lower precedence lets it absorb further uses of the same operator
+    let right_precedence = if is_right_associative(operator) { precedence - 1 } else { precedence };
+
+    if let Some(right) = pratt(right_precedence, parser) {
       let origin = SourceRegion::merge(left.origin, right.origin);
 
       return Some(Expression::new(
@@ -294,8 +322,10 @@ impl InfixParselet {
     ifx! [
       (LeftParen) Operator(LeftParen) => ifx_call,
       (Equal) Operator(Equal | NotEqual | Lesser | Greater | LesserOrEqual | GreaterOrEqual) => ifx_binary_operator,
+      (ShiftLeft) Operator(ShiftLeft | ShiftRight) => ifx_binary_operator,
       (Add) Operator(Add | Sub) => ifx_binary_operator,
       (Mul) Operator(Mul | Div | Rem) => ifx_binary_operator,
+      (Pow) Operator(Pow) => ifx_binary_operator,
     ]
   };
   
@@ -6,7 +6,7 @@ use mod_common::{ Keyword::*, Operator::*, ITEM_KEYWORDS, Identifier, };
 use crate::{
   source::{ SourceRegion, SOURCE_MANAGER, },
   token::{ Token, TokenData, },
-  ast::{ Item, ItemData, ExportData, PseudonymData, LocalDeclaration, Path, },
+  ast::{ Item, ItemData, ExportData, PseudonymData, LocalDeclaration, EnumVariant, Path, },
   lexer::{ Lexer, },
 };
 
@@ -36,6 +36,23 @@ pub fn item (parser: &mut Parser) -> Option<Item> {
 fn itm_import (parser: &mut Parser) -> Option<Item> {
   if let Some(&Token { data: TokenData::Keyword(Import), origin: start_region }) = parser.curr_tok() {
     parser.advance();
+
+    if let Some(&Token { data: TokenData::Keyword(Function), .. }) = parser.curr_tok() {
+      let mut function = itm_function(parser)?;
+
+      if let ItemData::Function { body, external, .. } = &mut function.data {
+        if let Some(body) = body {
+          parser.error_at(body.origin, "External function declarations cannot have a body".to_owned());
+          return None
+        }
+
+        *external = true;
+      }
+
+      function.origin = SourceRegion::merge(start_region, function.origin);
+
+      return Some(function)
+    }
     
     let (identifier, end_region) = if let Some(&Token { data: TokenData::Identifier(ref identifier), origin: end_region }) = parser.curr_tok() {
       let data = (identifier.clone(), end_region);
@@ -449,9 +466,18 @@ fn itm_struct (parser: &mut Parser) -> Option<Item> {
     parser.advance();
 
     loop {
-      if let Some(&Token { data: TokenData::Identifier(ref param_ident), origin: param_start }) = parser.curr_tok() {
-        let parameter_name = param_ident.clone();
+      // Structures with no fields are legal, they have a size of 0,
+      // and the last field may be followed by a trailing comma
+      if let Some(&Token { data: TokenData::Operator(RightBracket), origin }) = parser.curr_tok() {
+        parser.advance();
 
+        end_region = origin;
+
+        break;
+      }
+
+      // Keywords are accepted as field names, as no other construct can begin here
+      if let Some((parameter_name, param_start)) = parser.curr_contextual_name() {
         parser.advance();
 
         if let Some(&Token { data: TokenData::Operator(Colon), .. }) = parser.curr_tok() {
@@ -509,6 +535,122 @@ fn itm_struct (parser: &mut Parser) -> Option<Item> {
 }
 
 
+fn itm_enum (parser: &mut Parser) -> Option<Item> {
+  let start_region = if let Some(&Token { data: TokenData::Keyword(Enum), origin }) = parser.curr_tok() {
+    parser.advance();
+    origin
+  } else {
+    unreachable!("Internal error, enum parselet called on non-enum token");
+  };
+
+  let identifier = if let Some(&Token { data: TokenData::Identifier(ref identifier), .. }) = parser.curr_tok() {
+    let identifier = identifier.clone();
+
+    parser.advance();
+
+    identifier
+  } else {
+    parser.error("Expected identifier for enum to follow enum keyword".to_owned());
+    return None;
+  };
+
+  if let Some(&Token { data: TokenData::Operator(LeftBracket), .. }) = parser.curr_tok() {
+    parser.advance();
+  } else {
+    parser.error("Expected { to begin enum variant list".to_owned());
+    return None;
+  }
+
+  let mut variants = Vec::new();
+  let end_region;
+
+  loop {
+    // Enums with no variants are legal, though they cannot be constructed; a trailing comma is also allowed
+    if let Some(&Token { data: TokenData::Operator(RightBracket), origin }) = parser.curr_tok() {
+      parser.advance();
+
+      end_region = origin;
+
+      break;
+    }
+
+    if let Some(&Token { data: TokenData::Identifier(ref variant_ident), origin: variant_start }) = parser.curr_tok() {
+      let variant_name = variant_ident.clone();
+      let mut variant_end = variant_start;
+
+      parser.advance();
+
+      let payload_ok = if let Some(&Token { data: TokenData::Operator(LeftParen), .. }) = parser.curr_tok() {
+        parser.advance();
+
+        if let Some(payload_type) = type_expression(parser) {
+          if let Some(&Token { data: TokenData::Operator(RightParen), origin }) = parser.curr_tok() {
+            parser.advance();
+
+            variant_end = origin;
+            variants.push(EnumVariant::new(variant_name, Some(payload_type), SourceRegion::merge(variant_start, variant_end)));
+
+            true
+          } else {
+            parser.error("Expected ) to follow enum variant payload type".to_owned());
+
+            false
+          }
+        } else {
+          // Error has already been issued by type_expression, fall through to synchronization
+          false
+        }
+      } else {
+        variants.push(EnumVariant::new(variant_name, None, variant_start));
+
+        true
+      };
+
+      if payload_ok {
+        if let Some(&Token { data: TokenData::Operator(op), origin }) = parser.curr_tok() {
+          if op == Comma {
+            parser.advance();
+            
+            continue
+          } else if op == RightBracket {
+            parser.advance();
+
+            end_region = origin;
+
+            break;
+          }
+        }
+
+        parser.error_at(variant_end, "Expected , to separate variants or } to end variant list".to_owned());
+      }
+    } else {
+      parser.error("Expected an identifier for enum variant".to_owned());
+    }
+
+    if parser.synchronize(sync::close_pair_or(sync::operator(LeftBracket), sync::operator(RightBracket), sync::operator(Comma))) {
+      if let Some(&Token { data: TokenData::Operator(op), origin }) = parser.curr_tok() {
+        if op == Comma {
+          parser.advance();
+          continue;
+        } else {
+          parser.advance();
+          end_region = origin;
+          break;
+        }
+      }
+    }
+
+    // Could not recover
+    return None
+  }
+
+  Some(Item::new(
+    ItemData::Enum { identifier, variants },
+    SourceRegion::merge(start_region, end_region)
+  ))
+}
+
+
 fn itm_type (parser: &mut Parser) -> Option<Item> {
   if let Some(&Token { data: TokenData::Keyword(Type), origin: start_region }) = parser.curr_tok() {
     parser.advance();
@@ -550,6 +692,13 @@ fn itm_global (parser: &mut Parser) -> Option<Item> {
   if let Some(&Token { data: TokenData::Keyword(Global), origin: start_region }) = parser.curr_tok() {
     parser.advance();
 
+    let mutable = if let Some(&Token { data: TokenData::Keyword(Mut), .. }) = parser.curr_tok() {
+      parser.advance();
+      true
+    } else {
+      false
+    };
+
     if let Some(&Token { data: TokenData::Identifier(ref identifier), origin: mut end_region }) = parser.curr_tok() {
       let identifier = identifier.clone();
 
@@ -581,7 +730,7 @@ fn itm_global (parser: &mut Parser) -> Option<Item> {
       };
 
       return Some(Item::new(
-        ItemData::Global { identifier, explicit_type, initializer },
+        ItemData::Global { identifier, explicit_type, initializer, mutable },
         SourceRegion::merge(start_region, end_region)
       ))
     } else {
@@ -618,6 +767,15 @@ fn itm_function (parser: &mut Parser) -> Option<Item> {
     parser.advance();
 
     loop {
+      // The parameter list may be empty, and the last parameter may be followed by a trailing comma
+      if let Some(&Token { data: TokenData::Operator(RightParen), origin }) = parser.curr_tok() {
+        parser.advance();
+
+        end_region = origin;
+
+        break;
+      }
+
       if let Some(&Token { data: TokenData::Identifier(ref param_ident), origin: param_start }) = parser.curr_tok() {
         let parameter_name = param_ident.clone();
 
@@ -696,7 +854,7 @@ fn itm_function (parser: &mut Parser) -> Option<Item> {
   };
 
   Some(Item::new(
-    ItemData::Function { identifier, parameters, return_type, body },
+    ItemData::Function { identifier, parameters, return_type, body, external: false },
     SourceRegion::merge(start_region, end_region)
   ))
 }
@@ -761,6 +919,7 @@ impl ItemParselet {
       Keyword(Import) => itm_import,
       Keyword(Namespace) => itm_namespace,
       Keyword(Struct) => itm_struct,
+      Keyword(Enum) => itm_enum,
       Keyword(Type) => itm_type,
       Keyword(Global) => itm_global,
       Keyword(Function) => itm_function,
@@ -1,6 +1,6 @@
 //! Contains Parser and supporting structures and functions
 
-use mod_common::{ Operator::*, ITEM_KEYWORDS, };
+use mod_common::{ Operator::*, ITEM_KEYWORDS, Identifier, };
 
 use crate::{
   session::{ SESSION, MessageKind, Message, },
@@ -180,6 +180,18 @@ impl<'a> Parser<'a> {
     self.locale.curr.unwrap().origin
   }
 
+  /// Get the Parser's current Token as a name, if it is an Identifier or a Keyword
+  /// 
+  /// This is used in positions where a keyword cannot begin any other construct (eg struct fields),
+  /// allowing keywords to be used contextually as names there
+  pub fn curr_contextual_name (&self) -> Option<(Identifier, SourceRegion)> {
+    match self.locale.curr {
+      Some(&Token { data: TokenData::Identifier(ref identifier), origin }) => Some((identifier.clone(), origin)),
+      Some(&Token { data: TokenData::Keyword(keyword), origin }) => Some((Identifier::from(keyword.value()), origin)),
+      _ => None
+    }
+  }
+
 
   // TODO should synchronization leave a saved locale in the failure state?
   // TODO should synchronization's returned bool be must_use?
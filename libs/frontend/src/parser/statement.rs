@@ -85,6 +85,13 @@ fn stm_declaration (parser: &mut Parser) -> Option<Statement> {
   if let Some(&Token { data: TokenData::Keyword(Let), origin: start_region }) = parser.curr_tok() {
     parser.advance();
 
+    let mutable = if let Some(&Token { data: TokenData::Keyword(Mut), .. }) = parser.curr_tok() {
+      parser.advance();
+      true
+    } else {
+      false
+    };
+
     if let Some(&Token { data: TokenData::Identifier(ref identifier), origin: mut end_region }) = parser.curr_tok() {
       let identifier = identifier.clone();
 
@@ -115,7 +122,7 @@ fn stm_declaration (parser: &mut Parser) -> Option<Statement> {
       };
 
       return Some(Statement::new(
-        StatementData::Declaration { identifier, explicit_type, initializer },
+        StatementData::Declaration { identifier, explicit_type, initializer, mutable },
         SourceRegion::merge(start_region, end_region)
       ))
     } else {
@@ -9,7 +9,7 @@ use crate::{
   ast::{ TypeExpression, TypeExpressionData, },
 };
 
-use super::{ Parser, ParseletPredicate, ParseletFunction, path, sync, };
+use super::{ Parser, ParseletPredicate, ParseletFunction, path, expression, sync, };
 
 
 
@@ -55,6 +55,20 @@ fn tpx_pointer (parser: &mut Parser) -> Option<TypeExpression> {
   unreachable!("Internal error, pointer type expression parselet called on non-address-of token");
 }
 
+fn tpx_optional (parser: &mut Parser) -> Option<TypeExpression> {
+  if let Some(&Token { data: TokenData::Operator(Question), origin }) = parser.curr_tok() {
+    parser.advance();
+
+    let payload_texpr = type_expression(parser)?;
+
+    let origin = SourceRegion::merge(origin, payload_texpr.origin);
+
+    return Some(TypeExpression::new(TypeExpressionData::Optional(box payload_texpr), origin))
+  }
+
+  unreachable!("Internal error, optional type expression parselet called on non-question token");
+}
+
 fn tpx_function (parser: &mut Parser) -> Option<TypeExpression> {
   let (start_region, mut end_region) = if let Some(&Token { data: TokenData::Keyword(Function), origin }) = parser.curr_tok() {
     parser.advance();
@@ -70,6 +84,15 @@ fn tpx_function (parser: &mut Parser) -> Option<TypeExpression> {
     parser.advance();
 
     loop {
+      // The parameter list may be empty, and the last parameter may be followed by a trailing comma
+      if let Some(&Token { data: TokenData::Operator(RightParen), origin }) = parser.curr_tok() {
+        parser.advance();
+
+        end_region = origin;
+
+        break;
+      }
+
       if let Some(parameter_type) = type_expression(parser) {
         if let Some(&Token { data: TokenData::Operator(op), origin: param_end }) = parser.curr_tok() {
           parameter_types.push(parameter_type);
@@ -128,6 +151,36 @@ fn tpx_function (parser: &mut Parser) -> Option<TypeExpression> {
   ))
 }
 
+fn tpx_typeof (parser: &mut Parser) -> Option<TypeExpression> {
+  let start_region = if let Some(&Token { data: TokenData::Keyword(TypeOf), origin }) = parser.curr_tok() {
+    parser.advance();
+    origin
+  } else {
+    unreachable!("Internal error, typeof parselet called on non-typeof token");
+  };
+
+  if let Some(&Token { data: TokenData::Operator(LeftParen), .. }) = parser.curr_tok() {
+    parser.advance();
+  } else {
+    parser.error("Expected ( to follow typeof keyword".to_owned());
+    return None
+  }
+
+  if let Some(expr) = expression(parser) {
+    if let Some(&Token { data: TokenData::Operator(RightParen), origin: end_region }) = parser.curr_tok() {
+      parser.advance();
+
+      return Some(TypeExpression::new(TypeExpressionData::TypeOf(box expr), SourceRegion::merge(start_region, end_region)))
+    } else {
+      parser.error("Expected ) to close typeof expression".to_owned());
+    }
+  } // else { Error has already been issued by expression, fall through to synchronization }
+
+  parser.synchronize_unchecked(sync::close_pair(sync::operator(LeftParen), sync::operator(RightParen)));
+
+  None
+}
+
 
 struct TypeExpressionParselet {
   predicate: ParseletPredicate,
@@ -143,7 +196,9 @@ impl TypeExpressionParselet {
     tpx! [
       Identifier(_) | Operator(DoubleColon) => tpx_path_or_ident,
       Operator(AddressOf) => tpx_pointer,
+      Operator(Question) => tpx_optional,
       Keyword(Function) => tpx_function,
+      Keyword(TypeOf) => tpx_typeof,
     ]
   };
 
@@ -1,19 +1,51 @@
 //! The Session structure and singleton, containing all the user-facing messages for a compilation session
 
 use std::{
-  fmt::{ Display, Debug, Formatter, Result as FMTResult, },
+  fmt::{ Display, Debug, Formatter, Result as FMTResult, Write, },
   cell::{ UnsafeCell, },
+  path::{ Path, },
 };
 
 use mod_ansi as ansi;
 
 use mod_utils::{ padding, count_digits, some, };
 
+use mod_common::{ LayoutStrategy, };
+
 use crate::{
-  source::{ SOURCE_MANAGER, SourceRegion, },
+  source::{ SOURCE_MANAGER, Source, SourceRegion, SourceLocation, },
 };
 
 
+/// The terminal width Messages are rendered to when no other width is given
+pub const DEFAULT_RENDER_WIDTH: usize = 80;
+
+/// The number of columns a tab advances by when no other width is given
+pub const DEFAULT_TAB_WIDTH: usize = 1;
+
+/// The type given to integer literals with no other type information when no other type is given
+pub const DEFAULT_INT_TYPE: &str = "s32";
+
+/// The type given to floating point literals with no other type information when no other type is given
+pub const DEFAULT_FLOAT_TYPE: &str = "f32";
+
+/// Determines how the Lexer handles integer literals too large to fit in 64 bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntegerOverflow {
+  /// An Error is reported, and the value of the literal is zero
+  Error,
+  /// A Warning is reported, and the value of the literal is the largest 64 bit integer
+  Saturate,
+  /// A Warning is reported, and the value of the literal is truncated to its lowest 64 bits
+  Wrap,
+}
+
+impl Default for IntegerOverflow { fn default () -> Self { Self::Error } }
+
+/// The narrowest width a source excerpt line is truncated to, regardless of the render width
+const MIN_EXCERPT_WIDTH: usize = 16;
+
+
 /// The kind of content contained in a user-directed message such as Error, or Warning
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -47,6 +79,17 @@ impl MessageKind {
     }
   }
 
+  /// Get the SARIF rule id and result level associated with a MessageKind
+  pub fn get_sarif_rule (self) -> (&'static str, &'static str) {
+    use MessageKind::*;
+
+    match self {
+      Error => ("error", "error"),
+      Warning => ("warning", "warning"),
+      Notice => ("notice", "note"),
+    }
+  }
+
   /// Get a string of whitespace the same length as a MessageKind in str form, minus some offset
   pub fn get_whitespace (self, offset: usize) -> &'static str {
     padding((self.get_name().len() - offset) as _)
@@ -70,18 +113,19 @@ pub struct MessageItem {
 
 impl MessageItem {
   /// Display a MessageItem
+  /// 
+  /// The width of the Formatter (e.g. `{:100}`) sets the terminal width to render to,
+  /// defaulting to `DEFAULT_RENDER_WIDTH`.
+  /// Content is wrapped at whitespace to fit, and excerpt lines are truncated
   pub fn fmt (&self, f: &mut Formatter, kind: MessageKind, is_last: bool) -> FMTResult {
     let line = kind.get_ansi().wrap("|");
+    let width = f.width().unwrap_or(DEFAULT_RENDER_WIDTH);
 
-    writeln!(
-      f,
-      "{} {}\n\
-       {} at: {}",
-      line,
-      self.content,
-      line,
-      self.origin,
-    )?;
+    for content_line in wrap_words(&self.content, width.saturating_sub(2)) {
+      writeln!(f, "{} {}", line, content_line)?;
+    }
+
+    writeln!(f, "{} at: {}", line, self.origin)?;
 
     // TODO control excerpts with flag
     self.excerpt(f, kind, is_last)
@@ -93,6 +137,7 @@ impl MessageItem {
   #[allow(clippy::cognitive_complexity)] // Some functions are just complicated ok?
   pub fn excerpt (&self, f: &mut Formatter, kind: MessageKind, is_last: bool) -> FMTResult {
     let source = some!(SOURCE_MANAGER.get_source(self.origin.source); Ok(()));
+    let width = f.width().unwrap_or(DEFAULT_RENDER_WIDTH);
     let chars = source.chars();
 
     let pre_line = kind.get_ansi().wrap("|");
@@ -130,7 +175,9 @@ impl MessageItem {
       }
     }
 
-    let slice = &chars[start_index..(end_index + 1).min(chars.len())];
+    // tabs are expanded so that each char of the excerpt occupies one of the columns counted by the Lexer
+    let expanded = expand_tabs(&chars[start_index..(end_index + 1).min(chars.len())], SESSION.tab_width());
+    let slice = expanded.as_slice();
     
     let mut num_lines = 1usize;
 
@@ -141,18 +188,26 @@ impl MessageItem {
     if num_lines == 1 {
       let line_num = self.origin.start.line + 1;
       let line_num_digits = count_digits(line_num as _, 10);
+      let (window_start, window_end) = excerpt_window(slice.len(), self.origin.start.column as _, width.saturating_sub(line_num_digits + 6));
       
       write!(f, "{}\n{} {} {}{}  ", pre_line, pre_line, ansi::Foreground::Cyan.wrap(line_num), pre_line, ansi::Foreground::BrightBlack)?;
 
-      for (i, ch) in slice.iter().enumerate() {
+      if window_start > 0 { write!(f, "…")?; }
+
+      for (i, ch) in slice.iter().enumerate().take(window_end).skip(window_start) {
         if i == self.origin.start.column as _ { write!(f, "{}", ansi::Foreground::Reset)?; }
         else if i == self.origin.end.column as _ { write!(f, "{}", ansi::Foreground::BrightBlack)?; }
         write!(f, "{}", ch)?;
       }
 
+      if window_end < slice.len() { write!(f, "{}…", ansi::Foreground::BrightBlack)?; }
+
       write!(f, "\n{}{}{}└──", trailing_line, padding((line_num_digits + 2) as _), kind.get_ansi())?;
 
-      for i in 0..slice.len() as _ {
+      // the leading ellipsis takes the place of the columns before the window
+      if window_start > 0 { write!(f, "─")?; }
+
+      for i in window_start as u32 .. window_end as u32 {
         if i < self.origin.start.column { write!(f, "─")?; }
         else if i < self.origin.end.column { write!(f, "^")?; }
         else { break }
@@ -164,9 +219,12 @@ impl MessageItem {
       let last_line_num_digits = count_digits(last_line_num as _, 10);
       let gap_pad = padding((last_line_num_digits + 2) as _);
 
+      // lines are truncated at the right edge, and the markers are clamped to the truncation point
+      let line_width = width.saturating_sub(last_line_num_digits + 6).max(MIN_EXCERPT_WIDTH) as u32 - 1;
+
       write!(f, "{}│\n│{}┌", kind.get_ansi(), gap_pad,)?;
       
-      for _ in 0..self.origin.start.column + 2 {
+      for _ in 0..self.origin.start.column.min(line_width) + 2 {
         write!(f, "─")?;
       }
       
@@ -181,7 +239,8 @@ impl MessageItem {
         if *ch != '\n' {
           if line_num - 1 == self.origin.start.line && column == self.origin.start.column as _ { write!(f, "{}", ansi::Foreground::Reset)?; }
           else if line_num - 1 == self.origin.end.line && column == self.origin.end.column as _ { write!(f, "{}", ansi::Foreground::BrightBlack)?; }
-          write!(f, "{}", ch)?;
+          if column < line_width { write!(f, "{}", ch)?; }
+          else if column == line_width { write!(f, "…")?; }
           column += 1;
         } else {
           column = 0;
@@ -194,7 +253,7 @@ impl MessageItem {
 
       write!(f, "\n{}{}{}└", trailing_line, gap_pad, kind.get_ansi())?;
 
-      for _ in 0..self.origin.end.column + 1 {
+      for _ in 0..self.origin.end.column.min(line_width) + 1 {
         write!(f, "─")?;
       }
       
@@ -212,6 +271,9 @@ pub struct Message {
   pub kind: MessageKind,
   /// Informational entries detailing a Message
   pub items: Vec<MessageItem>,
+  /// Secondary locations related to a Message, such as a previous definition,
+  /// each with a short label rendered as a note
+  pub related: Vec<(SourceRegion, String)>,
 }
 
 impl Message {
@@ -222,7 +284,8 @@ impl Message {
       items: vec![ MessageItem {
         origin,
         content,
-      } ]
+      } ],
+      related: Vec::default(),
     }
   }
 
@@ -231,13 +294,21 @@ impl Message {
     self.items.push(MessageItem { origin, content });
     self
   }
+
+  /// Add a related location to the end of a message, labeled with a short note
+  pub fn relate (&mut self, origin: SourceRegion, label: String) -> &mut Self {
+    self.related.push((origin, label));
+    self
+  }
 }
 
 impl Display for Message {
   fn fmt (&self, f: &mut Formatter) -> FMTResult {
     writeln!(f, "\n{}", self.kind)?;
+
+    let notes: Vec<MessageItem> = self.related.iter().map(|(origin, label)| MessageItem { origin: *origin, content: format!("note: {}", label) }).collect();
     
-    let mut iter = self.items.iter().peekable();
+    let mut iter = self.items.iter().chain(notes.iter()).peekable();
 
     while let Some(item) = iter.next() {
       let is_last = iter.peek().is_some();
@@ -253,11 +324,26 @@ impl Display for Message {
 
 
 
+/// A function invoked by a Session each time a Message is recorded
+pub type MessageCallback = Box<dyn FnMut(&Message)>;
+
 /// The type of the central repository for Messages created during a compilation session
 /// 
 /// # Safety
 /// This is not a thread safe structure
-pub struct Session (UnsafeCell<Option<Vec<Message>>>);
+pub struct Session {
+  messages: UnsafeCell<Option<Vec<Message>>>,
+  callback: UnsafeCell<Option<MessageCallback>>,
+  /// The number of Messages delivered to the callback so far
+  delivered: UnsafeCell<usize>,
+  features: UnsafeCell<Vec<String>>,
+  render_width: UnsafeCell<usize>,
+  layout_strategy: UnsafeCell<LayoutStrategy>,
+  tab_width: UnsafeCell<usize>,
+  integer_overflow: UnsafeCell<IntegerOverflow>,
+  default_int_type: UnsafeCell<&'static str>,
+  default_float_type: UnsafeCell<&'static str>,
+}
 
 unsafe impl Send for Session { }
 unsafe impl Sync for Session { }
@@ -266,13 +352,29 @@ unsafe impl Sync for Session { }
 /// 
 /// # Safety
 /// This is not a thread safe structure
-pub static SESSION: Session = Session(UnsafeCell::new(None));
+pub static SESSION: Session = Session::new();
 
 
 impl Session {
+  /// Create a new, uninitialized Session
+  pub const fn new () -> Self {
+    Self {
+      messages: UnsafeCell::new(None),
+      callback: UnsafeCell::new(None),
+      delivered: UnsafeCell::new(0),
+      features: UnsafeCell::new(Vec::new()),
+      render_width: UnsafeCell::new(DEFAULT_RENDER_WIDTH),
+      layout_strategy: UnsafeCell::new(LayoutStrategy::COrder),
+      tab_width: UnsafeCell::new(DEFAULT_TAB_WIDTH),
+      integer_overflow: UnsafeCell::new(IntegerOverflow::Error),
+      default_int_type: UnsafeCell::new(DEFAULT_INT_TYPE),
+      default_float_type: UnsafeCell::new(DEFAULT_FLOAT_TYPE),
+    }
+  }
+
   #[allow(clippy::mut_from_ref)]
   unsafe fn inner (&self) -> &mut Option<Vec<Message>> {
-    &mut *self.0.get()
+    &mut *self.messages.get()
   }
 
   #[allow(clippy::mut_from_ref)]
@@ -281,11 +383,179 @@ impl Session {
     inner.as_mut().expect("Internal error: Session not initialized")
   }
 
+  #[allow(clippy::mut_from_ref)]
+  unsafe fn callback (&self) -> &mut Option<MessageCallback> {
+    &mut *self.callback.get()
+  }
+
+  #[allow(clippy::mut_from_ref)]
+  unsafe fn delivered (&self) -> &mut usize {
+    &mut *self.delivered.get()
+  }
+
+  /// Register a function to be invoked synchronously with each Message recorded by a Session after this call,
+  /// replacing any existing callback. Messages are still buffered as usual
+  /// 
+  /// The callback receives each Message once it is complete, including any MessageItems and related locations added to it;
+  /// see `flush_callback`
+  pub fn set_callback<F: FnMut(&Message) + 'static> (&self, callback: F) {
+    self.flush_callback();
+
+    unsafe {
+      *self.delivered() = self.inner().as_ref().map(Vec::len).unwrap_or(0);
+      self.callback().replace(Box::new(callback));
+    }
+  }
+
+  /// Remove the callback registered with a Session, if there is one,
+  /// after delivering any Messages it has not yet received
+  pub fn clear_callback (&self) -> Option<MessageCallback> {
+    self.flush_callback();
+
+    unsafe { self.callback() }.take()
+  }
+
+  /// Deliver each Message which the callback registered with a Session has not yet received
+  /// 
+  /// This happens automatically whenever a new Message is recorded, completing the one before it,
+  /// and whenever the Messages of a Session are read
+  pub fn flush_callback (&self) {
+    // the callback is taken while it runs, so it may record Messages of its own without being re-entered
+    let mut callback = if let Some(callback) = unsafe { self.callback() }.take() { callback } else { return };
+
+    let pending = unsafe { *self.delivered() } .. self.vec().len();
+
+    for index in pending {
+      let message = self.vec()[index].clone();
+
+      callback(&message);
+    }
+
+    // Messages recorded by the callback itself are not delivered back to it
+    unsafe { *self.delivered() = self.vec().len() };
+
+    let slot = unsafe { self.callback() };
+    if slot.is_none() { slot.replace(callback); }
+  }
+
+  #[allow(clippy::mut_from_ref)]
+  unsafe fn features (&self) -> &mut Vec<String> {
+    &mut *self.features.get()
+  }
+
+  /// Enable a feature for conditional compilation directives (`#if feature "name"`)
+  pub fn enable_feature<S: Into<String>> (&self, name: S) {
+    let name = name.into();
+    let features = unsafe { self.features() };
+
+    if !features.contains(&name) { features.push(name) }
+  }
+
+  /// Disable a feature for conditional compilation directives,
+  /// returning whether it was previously enabled
+  pub fn disable_feature (&self, name: &str) -> bool {
+    let features = unsafe { self.features() };
+    let len = features.len();
+
+    features.retain(|feature| feature != name);
+
+    features.len() != len
+  }
+
+  /// Determine if a feature is enabled for conditional compilation directives
+  /// 
+  /// Features that have not been enabled are off
+  pub fn is_feature_enabled (&self, name: &str) -> bool {
+    unsafe { self.features() }.iter().any(|feature| feature == name)
+  }
+
+  /// Set the terminal width Messages are rendered to when printed by a Session
+  pub fn set_render_width (&self, width: usize) {
+    unsafe { *self.render_width.get() = width }
+  }
+
+  /// Get the terminal width Messages are rendered to when printed by a Session,
+  /// `DEFAULT_RENDER_WIDTH` unless set with `set_render_width`
+  pub fn render_width (&self) -> usize {
+    unsafe { *self.render_width.get() }
+  }
+
+  /// Set the LayoutStrategy used to arrange the fields of structures when computing their sizes and offsets
+  pub fn set_layout_strategy (&self, strategy: LayoutStrategy) {
+    unsafe { *self.layout_strategy.get() = strategy }
+  }
+
+  /// Get the LayoutStrategy used to arrange the fields of structures when computing their sizes and offsets,
+  /// `LayoutStrategy::COrder` unless set with `set_layout_strategy`
+  pub fn layout_strategy (&self) -> LayoutStrategy {
+    unsafe { *self.layout_strategy.get() }
+  }
+
+  /// Set the number of columns a tab advances by in the SourceLocations of Lexers created afterwards,
+  /// and in the source excerpts of Messages
+  pub fn set_tab_width (&self, width: usize) {
+    unsafe { *self.tab_width.get() = width }
+  }
+
+  /// Get the number of columns a tab advances by,
+  /// `DEFAULT_TAB_WIDTH` unless set with `set_tab_width`
+  pub fn tab_width (&self) -> usize {
+    unsafe { *self.tab_width.get() }
+  }
+
+  /// Set how integer literals too large to fit in 64 bits are handled by Lexers
+  pub fn set_integer_overflow (&self, overflow: IntegerOverflow) {
+    unsafe { *self.integer_overflow.get() = overflow }
+  }
+
+  /// Get how integer literals too large to fit in 64 bits are handled by Lexers,
+  /// `IntegerOverflow::Error` unless set with `set_integer_overflow`
+  pub fn integer_overflow (&self) -> IntegerOverflow {
+    unsafe { *self.integer_overflow.get() }
+  }
+
+  /// Set the name of the core integer type given to integer literals with no other type information,
+  /// such as in `let x = 1;`, by Analyzers run afterwards
+  /// 
+  /// An Analyzer reports an Error and uses `DEFAULT_INT_TYPE` if the name is not an integer type in the core namespace
+  pub fn set_default_int_type (&self, identifier: &'static str) {
+    unsafe { *self.default_int_type.get() = identifier }
+  }
+
+  /// Get the name of the core integer type given to integer literals with no other type information,
+  /// `DEFAULT_INT_TYPE` unless set with `set_default_int_type`
+  pub fn default_int_type (&self) -> &'static str {
+    unsafe { *self.default_int_type.get() }
+  }
+
+  /// Set the name of the core floating point type given to floating point literals with no other type information,
+  /// such as in `let x = 1.0;`, by Analyzers run afterwards
+  /// 
+  /// An Analyzer reports an Error and uses `DEFAULT_FLOAT_TYPE` if the name is not a floating point type in the core namespace
+  pub fn set_default_float_type (&self, identifier: &'static str) {
+    unsafe { *self.default_float_type.get() = identifier }
+  }
+
+  /// Get the name of the core floating point type given to floating point literals with no other type information,
+  /// `DEFAULT_FLOAT_TYPE` unless set with `set_default_float_type`
+  pub fn default_float_type (&self) -> &'static str {
+    unsafe { *self.default_float_type.get() }
+  }
+
   /// Get a slice of the Messages in a SESSION
+  /// 
+  /// All Messages are considered complete once read, and are delivered to the callback if one is registered
   pub fn messages (&self) -> &[Message] {
+    self.flush_callback();
+
     self.vec().as_slice()
   }
 
+  /// Get all Messages in a SESSION with at least one MessageItem overlapping a given SourceRegion
+  pub fn messages_in_region (&self, region: SourceRegion) -> Vec<&Message> {
+    self.messages().iter().filter(|message| message.items.iter().any(|item| item.origin.overlaps(&region))).collect()
+  }
+
   /// Initialize the Session singleton
   /// 
   /// # Safety
@@ -312,6 +582,9 @@ impl Session {
       println!("New session message caught:\n{}\nat {:?}", &msg, bt);
     }
 
+    // the previous Message is complete once a new one is recorded
+    self.flush_callback();
+
     self.vec().push(msg);
 
     self.vec().last_mut().unwrap()
@@ -349,10 +622,60 @@ impl Session {
   }
 
 
+  /// Serialize all Messages associated with a Session to a minimal SARIF v2.1.0 JSON document
+  pub fn messages_sarif (&self) -> String {
+    let mut out = String::new();
+
+    out.push_str("{\"$schema\":\"https://json.schemastore.org/sarif-2.1.0.json\",\"version\":\"2.1.0\",\"runs\":[{");
+    out.push_str("\"tool\":{\"driver\":{\"name\":\"mod_language\",\"rules\":[");
+
+    for (i, &kind) in [ MessageKind::Error, MessageKind::Warning, MessageKind::Notice ].iter().enumerate() {
+      let (rule_id, level) = kind.get_sarif_rule();
+
+      if i != 0 { out.push(','); }
+
+      write!(
+        out,
+        "{{\"id\":\"{}\",\"name\":\"{}\",\"defaultConfiguration\":{{\"level\":\"{}\"}}}}",
+        rule_id, kind.get_name(), level
+      ).unwrap();
+    }
+
+    out.push_str("]}},\"columnKind\":\"unicodeCodePoints\",\"results\":[");
+
+    for (i, message) in self.vec().iter().enumerate() {
+      let (rule_id, level) = message.kind.get_sarif_rule();
+      let mut items = message.items.iter();
+      let primary = items.next().unwrap();
+
+      if i != 0 { out.push(','); }
+
+      write!(out, "{{\"ruleId\":\"{}\",\"level\":\"{}\",\"message\":{{\"text\":\"", rule_id, level).unwrap();
+      sarif_escape_into(&primary.content, &mut out);
+      out.push_str("\"},\"locations\":[");
+      sarif_location_into(primary.origin, None, &mut out);
+      out.push_str("],\"relatedLocations\":[");
+
+      let related = items.map(|item| (item.origin, &item.content)).chain(message.related.iter().map(|(origin, label)| (*origin, label)));
+
+      for (j, (origin, content)) in related.enumerate() {
+        if j != 0 { out.push(','); }
+        sarif_location_into(origin, Some(content), &mut out);
+      }
+
+      out.push_str("]}");
+    }
+
+    out.push_str("]}]}");
+
+    out
+  }
+
+
   /// Print all Messages associated with a Session
   pub fn print_messages (&self) {
     for message in self.vec().iter() {
-      print!("{}", message)
+      print!("{:1$}", message, self.render_width())
     }
   }
   
@@ -360,21 +683,21 @@ impl Session {
   /// Print all Error Messages associated with a Session
   pub fn print_errors (&self) {
     for message in self.vec().iter() {
-      if message.kind == MessageKind::Error { print!("{}", message) }
+      if message.kind == MessageKind::Error { print!("{:1$}", message, self.render_width()) }
     }
   }
 
   /// Print all Warning Messages associated with a Session
   pub fn print_warnings (&self) {
     for message in self.vec().iter() {
-      if message.kind == MessageKind::Warning { print!("{}", message) }
+      if message.kind == MessageKind::Warning { print!("{:1$}", message, self.render_width()) }
     }
   }
 
   /// Print all Notice Messages associated with a Session
   pub fn print_notices (&self) {
     for message in self.vec().iter() {
-      if message.kind == MessageKind::Notice { print!("{}", message) }
+      if message.kind == MessageKind::Notice { print!("{:1$}", message, self.render_width()) }
     }
   }
 
@@ -404,4 +727,361 @@ impl Session {
       }
       count
     }
+
+  /// Get the process exit code reflecting the Messages of a Session,
+  /// `1` if there were any Errors, otherwise `0`
+  pub fn exit_code (&self) -> i32 {
+    self.exit_code_with(false)
+  }
+
+  /// Get the process exit code reflecting the Messages of a Session,
+  /// `1` if there were any Errors, or any Warnings if `warnings_as_errors` is set, otherwise `0`
+  pub fn exit_code_with (&self, warnings_as_errors: bool) -> i32 {
+    if self.count_errors() > 0 || (warnings_as_errors && self.count_warnings() > 0) { 1 } else { 0 }
+  }
+}
+
+
+/// Split text into lines of at most `width` chars, breaking at spaces and existing line breaks
+/// 
+/// Words longer than `width` are not broken
+fn wrap_words (text: &str, width: usize) -> Vec<&str> {
+  let mut lines = Vec::new();
+
+  for paragraph in text.split('\n') {
+    let mut line_start = 0;
+    let mut line_end = 0;
+    let mut offset = 0;
+
+    for word in paragraph.split(' ') {
+      let word_start = offset;
+      let word_end = offset + word.len();
+      offset = word_end + 1;
+
+      if line_end > line_start && paragraph[line_start..word_end].chars().count() > width {
+        lines.push(&paragraph[line_start..line_end]);
+        line_start = word_start;
+      }
+
+      line_end = word_end;
+    }
+
+    lines.push(&paragraph[line_start..line_end]);
+  }
+
+  lines
+}
+
+/// Replace each tab in a sequence of chars with a number of spaces
+fn expand_tabs (chars: &[char], tab_width: usize) -> Vec<char> {
+  let mut expanded = Vec::with_capacity(chars.len());
+
+  for &ch in chars.iter() {
+    if ch == '\t' {
+      expanded.extend(std::iter::repeat(' ').take(tab_width))
+    } else {
+      expanded.push(ch)
+    }
+  }
+
+  expanded
+}
+
+/// Select the range of chars of a source excerpt line to display in a width, keeping the start column visible
+/// 
+/// When the line does not fit, one column on each side is reserved for an ellipsis
+fn excerpt_window (length: usize, start_column: usize, width: usize) -> (usize, usize) {
+  let width = width.max(MIN_EXCERPT_WIDTH);
+
+  if length <= width { return (0, length) }
+
+  let visible = width - 2;
+
+  // a little of the line before the start column is kept for context
+  let start = start_column.saturating_sub(visible / 4).min(length - visible);
+
+  (start, start + visible)
+}
+
+fn sarif_escape_into (source: &str, dest: &mut String) {
+  for ch in source.chars() {
+    match ch {
+      '\\' => dest.push_str("\\\\"),
+      '"' => dest.push_str("\\\""),
+      '\n' => dest.push_str("\\n"),
+      '\r' => dest.push_str("\\r"),
+      '\t' => dest.push_str("\\t"),
+      '\x00' ..= '\x1f' => write!(dest, "\\u{:04x}", ch as u32).unwrap(),
+      _ => dest.push(ch)
+    }
+  }
+}
+
+/// Write the path of a Source as the URI of a SARIF artifactLocation,
+/// a `file:` URI if the path is absolute, or otherwise a relative reference resolved against `%SRCROOT%`
+/// 
+/// Any byte of the path outside the unreserved characters of RFC 3986 and the path separator is percent-encoded
+fn sarif_artifact_location_into (path: &Path, dest: &mut String) {
+  let path = path.to_string_lossy().replace('\\', "/");
+  let is_absolute = path.starts_with('/') || path.get(1 .. 3) == Some(":/");
+
+  dest.push_str("{\"uri\":\"");
+
+  if is_absolute {
+    dest.push_str("file://");
+    if !path.starts_with('/') { dest.push('/') }
+  }
+
+  for byte in path.bytes() {
+    match byte {
+      | b'A' ..= b'Z' | b'a' ..= b'z' | b'0' ..= b'9'
+      | b'-' | b'.' | b'_' | b'~' | b'/'
+      => dest.push(byte as char),
+
+      // the colon of a drive letter is kept, but in a relative reference it could be mistaken for a scheme
+      b':' if is_absolute => dest.push(':'),
+
+      _ => write!(dest, "%{:02X}", byte).unwrap()
+    }
+  }
+
+  dest.push('"');
+
+  if !is_absolute { dest.push_str(",\"uriBaseId\":\"%SRCROOT%\"") }
+
+  dest.push('}');
+}
+
+/// Get the one-based SARIF column of a SourceLocation, counted in characters from the start of its line
+/// 
+/// This differs from the column of the SourceLocation when the line contains tabs wider than one column
+fn sarif_column (source: Option<&Source>, location: SourceLocation) -> usize {
+  match source {
+    Some(source) if location.index <= source.content.len() => {
+      let line_start = source.content[.. location.index].iter().rposition(|&ch| ch == '\n').map(|newline| newline + 1).unwrap_or(0);
+      location.index - line_start + 1
+    },
+    _ => location.column as usize + 1
+  }
+}
+
+fn sarif_location_into (origin: SourceRegion, message: Option<&str>, dest: &mut String) {
+  let source = SOURCE_MANAGER.get_source(origin.source);
+
+  dest.push_str("{\"physicalLocation\":{\"artifactLocation\":");
+
+  match source {
+    Some(source) => sarif_artifact_location_into(&source.path, dest),
+    None => dest.push_str("{\"uri\":\"\"}"),
+  }
+
+  // SourceRegions end one past their last character, as does a SARIF region's endColumn
+  write!(
+    dest,
+    ",\"region\":{{\"startLine\":{},\"startColumn\":{},\"endLine\":{},\"endColumn\":{}}}}}",
+    origin.start.line + 1, sarif_column(source, origin.start),
+    origin.end.line + 1, sarif_column(source, origin.end)
+  ).unwrap();
+
+  if let Some(message) = message {
+    dest.push_str(",\"message\":{\"text\":\"");
+    sarif_escape_into(message, dest);
+    dest.push_str("\"}");
+  }
+
+  dest.push('}');
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{
+    source::{ SourceLocation, },
+    test_support::lock_singletons,
+  };
+
+  #[test]
+  fn sarif_result () {
+    let _guard = lock_singletons();
+
+    let source = SOURCE_MANAGER.add_source("sarif_test.ms", "global X: s32 = 1;\nglobal Y: s32 = \"bad\";\n");
+
+    let origin = SourceRegion {
+      source,
+      start: SourceLocation { index: 35, line: 1, column: 16 },
+      end: SourceLocation { index: 40, line: 1, column: 21 },
+    };
+
+    SESSION.error(origin, "Expected an \"integer\" for SARIF test".to_owned());
+
+    let sarif = SESSION.messages_sarif();
+
+    assert!(sarif.contains(
+      "{\"ruleId\":\"error\",\"level\":\"error\",\"message\":{\"text\":\"Expected an \\\"integer\\\" for SARIF test\"},\
+       \"locations\":[{\"physicalLocation\":{\"artifactLocation\":{\"uri\":\"sarif_test.ms\",\"uriBaseId\":\"%SRCROOT%\"},\
+       \"region\":{\"startLine\":2,\"startColumn\":17,\"endLine\":2,\"endColumn\":22}}}]"
+    ), "{}", sarif);
+  }
+
+  #[test]
+  fn sarif_columns () {
+    use crate::lexer::Lexer;
+
+    let _guard = lock_singletons();
+
+    SESSION.set_tab_width(4);
+    let source = SOURCE_MANAGER.add_source("sarif_columns.ms", "\tglobal X: s32;");
+    let tokens = Lexer::new(source).lex_stream();
+    SESSION.set_tab_width(DEFAULT_TAB_WIDTH);
+
+    // `global` covers the second through seventh characters, and SARIF's endColumn is one past the last of them
+    SESSION.error(tokens[0].origin, "Columns".to_owned());
+
+    let sarif = SESSION.messages_sarif();
+
+    assert!(sarif.contains("\"columnKind\":\"unicodeCodePoints\""), "{}", sarif);
+    assert!(sarif.contains("\"region\":{\"startLine\":1,\"startColumn\":2,\"endLine\":1,\"endColumn\":8}"), "{}", sarif);
+  }
+
+  #[test]
+  fn sarif_uris () {
+    let uri = |path: &str| {
+      let mut out = String::new();
+      sarif_artifact_location_into(Path::new(path), &mut out);
+      out
+    };
+
+    assert_eq!(uri("src/my module#1.ms"), "{\"uri\":\"src/my%20module%231.ms\",\"uriBaseId\":\"%SRCROOT%\"}");
+    assert_eq!(uri("a:b.ms"), "{\"uri\":\"a%3Ab.ms\",\"uriBaseId\":\"%SRCROOT%\"}");
+    assert_eq!(uri("/home/user/caf\u{e9}.ms"), "{\"uri\":\"file:///home/user/caf%C3%A9.ms\"}");
+    assert_eq!(uri("C:\\src\\main.ms"), "{\"uri\":\"file:///C:/src/main.ms\"}");
+  }
+
+  #[test]
+  fn messages_in_region () {
+    let _guard = lock_singletons();
+
+    let source = SOURCE_MANAGER.add_source("messages_in_region.ms", "global X: s32 = 1;\nglobal Y: s32 = 2;\n");
+    let other_source = SOURCE_MANAGER.add_source("messages_in_region_other.ms", "global X: s32 = 1;\nglobal Y: s32 = 2;\n");
+
+    let region = |source, start: usize, end: usize| SourceRegion {
+      source,
+      start: SourceLocation { index: start, line: 0, column: start as u32 },
+      end: SourceLocation { index: end, line: 0, column: end as u32 },
+    };
+
+    SESSION.error(region(source, 7, 8), "X region".to_owned());
+    SESSION.warning(region(source, 26, 27), "Y region".to_owned());
+    SESSION.error(region(other_source, 7, 8), "Other source".to_owned());
+    SESSION.notice(region(source, 0, 18), "Whole line".to_owned());
+
+    let contents = |query| -> Vec<String> {
+      SESSION.messages_in_region(query).into_iter().map(|message| message.items[0].content.clone()).collect()
+    };
+
+    assert_eq!(contents(region(source, 7, 8)), [ "X region", "Whole line" ]);
+    assert_eq!(contents(region(source, 8, 8)), [ "X region", "Whole line" ]);
+    assert_eq!(contents(region(source, 8, 10)), [ "Whole line" ]);
+    assert_eq!(contents(region(source, 20, 30)), [ "Y region" ]);
+    assert_eq!(contents(region(source, 30, 35)), Vec::<String>::new());
+  }
+
+  #[test]
+  fn exit_code () {
+    // A private Session is used so messages from other tests do not interfere
+    let session = Session::new();
+    session.init();
+
+    assert_eq!(session.exit_code(), 0);
+
+    session.notice(SourceRegion::ANONYMOUS, "Notice".to_owned());
+    assert_eq!(session.exit_code(), 0);
+
+    session.warning(SourceRegion::ANONYMOUS, "Warning".to_owned());
+    assert_eq!(session.exit_code(), 0);
+    assert_eq!(session.exit_code_with(true), 1);
+
+    session.error(SourceRegion::ANONYMOUS, "Error".to_owned());
+    assert_eq!(session.exit_code(), 1);
+  }
+
+  #[test]
+  fn message_callback () {
+    use std::{ rc::Rc, cell::RefCell, };
+
+    let session = Session::new();
+    session.init();
+
+    let seen = Rc::new(RefCell::new(Vec::new()));
+
+    let callback_seen = seen.clone();
+    session.set_callback(move |message| callback_seen.borrow_mut().push(message.clone()));
+
+    session.error(SourceRegion::ANONYMOUS, "First".to_owned()).append(SourceRegion::ANONYMOUS, "Note".to_owned());
+
+    // a message is only delivered once it is complete, so it includes the items appended to it
+    assert!(seen.borrow().is_empty());
+
+    session.warning(SourceRegion::ANONYMOUS, "Second".to_owned());
+
+    assert_eq!(seen.borrow().len(), 1);
+    assert_eq!(seen.borrow()[0].items[1].content, "Note");
+
+    // reading the messages completes the last one, and they are still buffered
+    let messages = session.messages().to_vec();
+
+    assert_eq!(seen.borrow().as_slice(), messages.as_slice());
+    assert_eq!(seen.borrow()[1].kind, MessageKind::Warning);
+    assert_eq!(seen.borrow()[1].items[0].content, "Second");
+
+    session.notice(SourceRegion::ANONYMOUS, "Third".to_owned());
+
+    // clearing the callback delivers any pending message first
+    assert!(session.clear_callback().is_some());
+    assert_eq!(seen.borrow().len(), 3);
+
+    session.notice(SourceRegion::ANONYMOUS, "Fourth".to_owned());
+
+    assert_eq!(seen.borrow().len(), 3);
+    assert_eq!(session.messages().len(), 4);
+  }
+
+  #[test]
+  fn render_width () {
+    let _guard = lock_singletons();
+
+    let content = "global value: s32 = compute_a_very_long_initial_value(1, 2, 3) + another_rather_long_name;\n";
+    let source = SOURCE_MANAGER.add_source("render_width.ms", content);
+
+    let start = content.find("another").unwrap();
+    let end = start + "another_rather_long_name".len();
+
+    let message = Message::new(
+      MessageKind::Error,
+      SourceRegion {
+        source,
+        start: SourceLocation { index: start, line: 0, column: start as u32 },
+        end: SourceLocation { index: end, line: 0, column: end as u32 },
+      },
+      "Undefined variable referenced in the initializer of a global".to_owned(),
+    );
+
+    let rendered = format!("{:40}", message);
+    let lines: Vec<&str> = rendered.lines().collect();
+
+    // content is wrapped at spaces
+    assert!(lines.contains(&"| Undefined variable referenced in the"), "{}", rendered);
+    assert!(lines.contains(&"| initializer of a global"), "{}", rendered);
+
+    // the excerpt is truncated on both sides, and the caret still lines up with the region
+    let excerpt = lines.iter().position(|line| line.starts_with("| 1 |")).unwrap();
+    assert_eq!(lines[excerpt], "| 1 |  …, 3) + another_rather_long_name…");
+    assert_eq!(lines[excerpt + 1], "    └──────────^^^^^^^^^^^^^^^^^^^^^^^^");
+    assert_eq!(lines[excerpt].chars().count(), 40);
+
+    // a wider width fits the whole line
+    let rendered = format!("{:120}", message);
+    assert!(rendered.contains("| 1 |  global value: s32 = compute_a_very_long_initial_value(1, 2, 3) + another_rather_long_name;"), "{}", rendered);
+  }
 }
\ No newline at end of file
@@ -69,6 +69,22 @@ impl SourceRegion {
     }
   }
 
+  /// Determine if two SourceRegions share any part of the same Source
+  /// 
+  /// Zero-width regions, such as cursor positions, overlap any region they touch
+  pub fn overlaps (&self, other: &Self) -> bool {
+    if self.source != other.source { return false }
+
+    let start = self.start.index.max(other.start.index);
+    let end = self.end.index.min(other.end.index);
+
+    if self.start.index == self.end.index || other.start.index == other.end.index {
+      start <= end
+    } else {
+      start < end
+    }
+  }
+
   /// Create a new SourceRegion from the start of an existing one
   pub fn clip_to_start (&self) -> Self {
     Self { 
@@ -100,14 +116,15 @@ impl Display for SourceLocation {
   }
 }
 
+/// Formats as `path:line:column-line:column`, or `path:line:column` for an empty region,
+/// with the path relative to the workspace root of the SOURCE_MANAGER if it has one
 impl Debug for SourceRegion {
   fn fmt (&self, f: &mut Formatter) -> FMTResult {
     if let Some(source) = SOURCE_MANAGER.get_source(self.source) {
-      write!(f, "{}:{}", source.path.display(), self.start)?;
+      write!(f, "{}:{}", SOURCE_MANAGER.relative_path(&source.path).display(), self.start)?;
 
-      if self.end != self.start
-      && !(self.end.line == self.start.line && self.end.column == self.start.column + 1) {
-        write!(f, " to {}", self.end)?;
+      if self.end != self.start {
+        write!(f, "-{}", self.end)?;
       }
     } else {
       write!(f, "UnknownSource")?;
@@ -133,14 +150,35 @@ pub struct Source {
 
 
 impl Source {
+  /// The UTF-8 byte order mark, which is stripped from the start of Source content
+  pub const BYTE_ORDER_MARK: char = '\u{feff}';
+
+  /// Create a new Source from a path and some in-memory content
+  /// 
+  /// A leading byte order mark is discarded, so that offsets begin at the first real character,
+  /// and `\r\n` line endings are normalized to `\n`, so that line and column tracking is the same on all platforms
+  pub fn new<P: Into<PathBuf>> (path: P, content: &str) -> Source {
+    let content = content.strip_prefix(Self::BYTE_ORDER_MARK).unwrap_or(content);
+
+    let mut chars = Vec::with_capacity(content.len());
+    let mut iter = content.chars().peekable();
+
+    while let Some(ch) = iter.next() {
+      if ch == '\r' && iter.peek() == Some(&'\n') { continue }
+      chars.push(ch);
+    }
+
+    Source {
+      path: path.into(),
+      content: chars
+    }
+  }
+
   /// Load a Source from a text file at a path on disk
   pub fn load<P: AsRef<Path>> (path: P) -> IOResult<Source> {
     let content = read_to_string(&path)?;
 
-    Ok(Source {
-      path: path.as_ref().to_path_buf(),
-      content: content.chars().collect()
-    })
+    Ok(Source::new(path.as_ref(), &content))
   }
 
   /// Get an iterator of the chars of the content of a Source
@@ -148,6 +186,11 @@ impl Source {
     self.content.as_slice()
   }
   
+  /// Get the original text of a SourceRegion in a Source, if it is in range
+  pub fn region_text (&self, region: SourceRegion) -> Option<String> {
+    self.content.get(region.start.index..region.end.index).map(|chars| chars.iter().collect())
+  }
+  
   /// Find the index offset of a line and column in a Source, if it is in range
   pub fn line_and_column_to_index (&self, line: u32, column: u32) -> Option<usize> {
     let mut index = 0;
@@ -187,6 +230,7 @@ struct SourceManagerInterior {
   bi_map: BiMap<SourceKey, ASTKey>,
 
   module_dir: PathBuf,
+  workspace_root: Option<PathBuf>,
 }
 
 /// The type of the central repository for Sources processed during a compilation session
@@ -234,6 +278,37 @@ impl SourceManager {
     inner.as_mut().expect("Internal error: SourceManager not initialized").module_dir.as_ref()
   }
 
+  #[allow(clippy::mut_from_ref)]
+  fn workspace_root (&self) -> &mut Option<PathBuf> {
+    let inner = unsafe { self.inner() };
+    &mut inner.as_mut().expect("Internal error: SourceManager not initialized").workspace_root
+  }
+
+  /// Set the directory that Source paths are displayed relative to, replacing any existing workspace root
+  pub fn set_workspace_root<P: Into<PathBuf>> (&self, root: P) {
+    self.workspace_root().replace(root.into());
+  }
+
+  /// Remove the workspace root of the SourceManager, if there is one,
+  /// so that Source paths are displayed as they were given
+  pub fn clear_workspace_root (&self) -> Option<PathBuf> {
+    self.workspace_root().take()
+  }
+
+  /// Get the directory that Source paths are displayed relative to, if one has been set
+  pub fn get_workspace_root (&self) -> Option<&Path> {
+    self.workspace_root().as_deref()
+  }
+
+  /// Get a path relative to the workspace root of the SourceManager,
+  /// or the path unchanged if there is no workspace root or the path is not inside it
+  pub fn relative_path<'p> (&self, path: &'p Path) -> &'p Path {
+    match self.get_workspace_root() {
+      Some(root) => path.strip_prefix(root).unwrap_or(path),
+      None => path
+    }
+  }
+
   /// Initialize the SourceManager singleton
   /// 
   /// # Safety
@@ -242,7 +317,7 @@ impl SourceManager {
     let inner = unsafe { self.inner() };
     assert!(inner.is_none(), "Internal error: SourceManager double initialized");
 
-    inner.replace(SourceManagerInterior { source_map: SlotMap::default(), ast_map: SlotMap::default(), bi_map: BiMap::new(), module_dir });
+    inner.replace(SourceManagerInterior { source_map: SlotMap::default(), ast_map: SlotMap::default(), bi_map: BiMap::new(), module_dir, workspace_root: None });
   }
 
   /// Load a Source from a file path and get a key to it
@@ -259,12 +334,25 @@ impl SourceManager {
     }
   }
 
+  /// Add a Source from in-memory content and get a key to it
+  /// 
+  /// The path is only used for display and lookup purposes, it does not need to exist on disk
+  pub fn add_source<P: Into<PathBuf>> (&self, path: P, content: &str) -> SourceKey {
+    self.source_map().insert(Source::new(path, content))
+  }
+
   /// Convert a SourceKey into a Source reference
   pub fn get_source (&self, key: SourceKey) -> Option<&Source> {
     self.source_map().get(key)
   }
 
 
+  /// Get the original text of a SourceRegion, if its Source exists and the region is in range
+  pub fn get_region_text (&self, region: SourceRegion) -> Option<String> {
+    self.get_source(region.source)?.region_text(region)
+  }
+
+
   /// Reserve a cache slot for an AST
   pub fn reserve_ast_cache (&self) -> ASTKey {
     self.ast_map().insert(None)
@@ -304,4 +392,78 @@ impl SourceManager {
   pub fn get_source_key_from_ast (&self, ast_key: ASTKey) -> Option<SourceKey> {
     self.bi_map().find_key(&ast_key).unref()
   }
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{
+    lexer::{ Lexer, },
+    test_support::lock_singletons,
+  };
+
+  #[test]
+  fn strip_byte_order_mark () {
+    let _guard = lock_singletons();
+
+    let source = SOURCE_MANAGER.add_source("bom_test.ms", "\u{feff}global X: s32;");
+
+    assert_eq!(SOURCE_MANAGER.get_source(source).unwrap().chars()[0], 'g');
+
+    let tokens = Lexer::new(source).lex_stream();
+    let first = tokens.first().unwrap();
+
+    assert_eq!(first.origin.start, SourceLocation { index: 0, line: 0, column: 0 });
+    assert_eq!(first.origin.end, SourceLocation { index: 6, line: 0, column: 6 });
+  }
+
+  #[test]
+  fn normalize_crlf () {
+    let _guard = lock_singletons();
+
+    let source = SOURCE_MANAGER.add_source("crlf_test.ms", "global X: s32;\r\n\r\nglobal Y: s32;\r\n");
+
+    assert!(!SOURCE_MANAGER.get_source(source).unwrap().chars().contains(&'\r'));
+
+    let tokens = Lexer::new(source).lex_stream();
+
+    // global X : s32 ; global Y ...
+    let second_global = &tokens[5];
+
+    assert_eq!(second_global.origin.start, SourceLocation { index: 16, line: 2, column: 0 });
+    assert_eq!(second_global.origin.end, SourceLocation { index: 22, line: 2, column: 6 });
+
+    let y = &tokens[6];
+
+    assert_eq!(y.origin.start, SourceLocation { index: 23, line: 2, column: 7 });
+  }
+
+  #[test]
+  fn region_relative_path () {
+    let _guard = lock_singletons();
+
+    let source = SOURCE_MANAGER.add_source("/workspace/project/src/main.ms", "global X: s32;\nglobal Y: s32;\n");
+
+    let region = SourceRegion {
+      source,
+      start: SourceLocation { index: 22, line: 1, column: 7 },
+      end: SourceLocation { index: 23, line: 1, column: 8 },
+    };
+
+    assert_eq!(format!("{:?}", region), "/workspace/project/src/main.ms:2:8-2:9");
+
+    SOURCE_MANAGER.set_workspace_root("/workspace/project");
+    let relative = format!("{:?}", region);
+    let empty = format!("{:?}", region.clip_to_end());
+
+    SOURCE_MANAGER.set_workspace_root("/elsewhere");
+    let outside = format!("{:?}", region);
+
+    SOURCE_MANAGER.clear_workspace_root();
+
+    assert_eq!(relative, format!("{}:2:8-2:9", Path::new("src").join("main.ms").display()));
+    assert_eq!(empty, format!("{}:2:9", Path::new("src").join("main.ms").display()));
+    assert_eq!(outside, "/workspace/project/src/main.ms:2:8-2:9");
+  }
 }
\ No newline at end of file
@@ -0,0 +1,102 @@
+//! Shared helpers for running the frontend singletons in unit tests
+
+use std::{
+  sync::{ Once, atomic::{ AtomicBool, Ordering, }, },
+  thread::{ yield_now, },
+};
+
+use crate::{
+  session::{ SESSION, MessageKind, Message, },
+  source::{ SOURCE_MANAGER, SourceKey, },
+  lexer::{ Lexer, },
+  parser::{ Parser, },
+  ast::{ Item, },
+  ctx::{ Context, },
+  analyzer::{ Analyzer, },
+};
+
+
+static INIT: Once = Once::new();
+static LOCK: AtomicBool = AtomicBool::new(false);
+
+
+/// Exclusive access to the SESSION and SOURCE_MANAGER singletons,
+/// released when dropped
+pub struct SingletonGuard;
+
+impl Drop for SingletonGuard {
+  fn drop (&mut self) { LOCK.store(false, Ordering::SeqCst) }
+}
+
+/// Initialize the singletons if needed and wait for exclusive access to them
+pub fn lock_singletons () -> SingletonGuard {
+  INIT.call_once(|| {
+    SESSION.init();
+    SOURCE_MANAGER.init("../../test_scripts/modules/".into());
+  });
+
+  while LOCK.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+    yield_now()
+  }
+
+  SingletonGuard
+}
+
+
+/// The results of running the frontend on a single in-memory source
+pub struct TestOutput {
+  /// The key of the Source that was analyzed
+  pub source: SourceKey,
+  /// The Context produced by the Analyzer
+  pub context: Context,
+  /// The transformed ast produced by the Analyzer
+  pub ast: Vec<Item>,
+  /// All Messages created while processing the source
+  pub messages: Vec<Message>,
+}
+
+impl TestOutput {
+  /// Get an iterator over the Messages of a specific kind
+  pub fn of_kind (&self, kind: MessageKind) -> impl Iterator<Item = &Message> {
+    self.messages.iter().filter(move |message| message.kind == kind)
+  }
+
+  /// Count the Error Messages produced
+  pub fn error_count (&self) -> usize {
+    self.of_kind(MessageKind::Error).count()
+  }
+}
+
+
+/// Lex, parse, and analyze a source string,
+/// collecting any Messages created along the way
+pub fn analyze_str (name: &str, content: &str) -> TestOutput {
+  analyze_str_with(Analyzer::new(), name, content)
+}
+
+/// Lex, parse, and analyze a source string using a preconfigured Analyzer,
+/// collecting any Messages created along the way
+pub fn analyze_str_with (analyzer: Analyzer, name: &str, content: &str) -> TestOutput {
+  let _guard = lock_singletons();
+
+  analyze_str_locked(analyzer, name, content)
+}
+
+/// Lex, parse, and analyze a source string using a preconfigured Analyzer,
+/// collecting any Messages created along the way
+/// 
+/// The caller must already hold the guard returned by `lock_singletons`,
+/// such as when SESSION settings need to be changed around the analysis
+pub fn analyze_str_locked (analyzer: Analyzer, name: &str, content: &str) -> TestOutput {
+  let message_base = SESSION.messages().len();
+
+  let source = SOURCE_MANAGER.add_source(name, content);
+
+  let tokens = Lexer::new(source).lex_stream();
+  let ast = Parser::new(&tokens).parse_ast();
+  let (context, ast) = analyzer.analyze(ast);
+
+  let messages = SESSION.messages()[message_base..].to_vec();
+
+  TestOutput { source, context, ast, messages }
+}
@@ -0,0 +1,146 @@
+//! Optional instrumentation for measuring the time spent in, and the output size of, each compiler phase
+
+use std::{
+  fmt::{ Display, Formatter, Result as FMTResult, },
+  time::{ Duration, Instant, },
+};
+
+
+/// Records the duration of named compiler phases such as lexing, parsing and analysis,
+/// as well as named counts such as the number of tokens or instructions produced
+/// 
+/// When disabled, nothing is recorded and no clock is read,
+/// so instrumented code behaves exactly as if it were not instrumented
+#[derive(Debug, Clone, Default)]
+pub struct TimingReport {
+  enabled: bool,
+  /// The duration of each phase timed, in the order they were run
+  pub phases: Vec<(&'static str, Duration)>,
+  /// Each count recorded, in the order they were recorded
+  pub counts: Vec<(&'static str, usize)>,
+}
+
+impl TimingReport {
+  /// Create a new TimingReport which records measurements
+  pub fn enabled () -> Self {
+    Self { enabled: true, .. Self::default() }
+  }
+
+  /// Create a new TimingReport which ignores all measurements
+  pub fn disabled () -> Self {
+    Self::default()
+  }
+
+  /// Determine if a TimingReport records measurements
+  pub fn is_enabled (&self) -> bool {
+    self.enabled
+  }
+
+  /// Determine if a TimingReport has not recorded any measurements
+  pub fn is_empty (&self) -> bool {
+    self.phases.is_empty() && self.counts.is_empty()
+  }
+
+  /// Run a function and record the time it took as a named phase,
+  /// if the TimingReport is enabled
+  pub fn time<T, F: FnOnce () -> T> (&mut self, phase: &'static str, f: F) -> T {
+    if !self.enabled { return f() }
+
+    let start = Instant::now();
+
+    let result = f();
+
+    self.phases.push((phase, start.elapsed()));
+
+    result
+  }
+
+  /// Record a named count, if the TimingReport is enabled
+  pub fn count (&mut self, name: &'static str, value: usize) {
+    if self.enabled { self.counts.push((name, value)) }
+  }
+
+  /// Get the duration recorded for a named phase, if it has been timed
+  pub fn get_phase (&self, phase: &str) -> Option<Duration> {
+    self.phases.iter().find(|&&(name, _)| name == phase).map(|&(_, duration)| duration)
+  }
+
+  /// Get the value recorded for a named count, if it has been recorded
+  pub fn get_count (&self, name: &str) -> Option<usize> {
+    self.counts.iter().find(|&&(count_name, _)| count_name == name).map(|&(_, value)| value)
+  }
+
+  /// Get the sum of the durations of all phases recorded
+  pub fn total (&self) -> Duration {
+    self.phases.iter().map(|&(_, duration)| duration).sum()
+  }
+}
+
+impl Display for TimingReport {
+  fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    for &(phase, duration) in self.phases.iter() {
+      writeln!(f, "{:<16} {:>12.3}ms", phase, duration.as_secs_f64() * 1000.0)?;
+    }
+
+    writeln!(f, "{:<16} {:>12.3}ms", "total", self.total().as_secs_f64() * 1000.0)?;
+
+    for &(name, value) in self.counts.iter() {
+      writeln!(f, "{:<16} {:>12}", name, value)?;
+    }
+
+    Ok(())
+  }
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use crate::{
+    source::{ SOURCE_MANAGER, },
+    lexer::{ Lexer, },
+    parser::{ Parser, },
+    analyzer::{ Analyzer, },
+    test_support::{ lock_singletons, },
+  };
+
+  fn compile (report: &mut TimingReport, name: &str, content: &str) {
+    let _guard = lock_singletons();
+
+    let source = SOURCE_MANAGER.add_source(name, content);
+
+    let tokens = report.time("lex", || Lexer::new(source).lex_stream());
+    report.count("tokens", tokens.len());
+
+    let ast = report.time("parse", || Parser::new(&tokens).parse_ast());
+    report.count("items", ast.len());
+
+    report.time("analyze", || Analyzer::new().analyze(ast));
+  }
+
+  const SOURCE: &str = "export fn add (a: s32, b: s32) -> s32 { a + b } fn id (x: s32) -> s32 { x }";
+
+  #[test]
+  fn timing_report_enabled () {
+    let mut report = TimingReport::enabled();
+
+    compile(&mut report, "timing_enabled.ms", SOURCE);
+
+    assert!(!report.is_empty());
+    assert_eq!(report.phases.iter().map(|&(phase, _)| phase).collect::<Vec<_>>(), [ "lex", "parse", "analyze" ]);
+    assert!(report.get_count("tokens").unwrap() > 0);
+    assert_eq!(report.get_count("items"), Some(2));
+    assert!(report.total() >= report.get_phase("lex").unwrap());
+  }
+
+  #[test]
+  fn timing_report_disabled () {
+    let mut report = TimingReport::disabled();
+
+    compile(&mut report, "timing_disabled.ms", SOURCE);
+
+    assert!(report.is_empty());
+    assert_eq!(report.get_phase("lex"), None);
+  }
+}
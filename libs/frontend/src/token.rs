@@ -5,10 +5,10 @@ use std::{
   ops::{ Deref, },
 };
 
-use mod_common::{ Identifier, Constant, Keyword, Operator, IdentifierValue, };
+use mod_common::{ Identifier, Constant, Number, Keyword, Operator, IdentifierValue, };
 
 use crate::{
-  source::{ SourceRegion, },
+  source::{ SourceRegion, SOURCE_MANAGER, },
 };
 
 
@@ -20,7 +20,8 @@ pub enum TokenKind {
   Identifier,
   Constant,
   Keyword,
-  Operator
+  Operator,
+  Directive,
 }
 
 
@@ -32,8 +33,12 @@ pub enum TokenData {
 
   Identifier(Identifier),
   Constant(Constant),
+  /// A decimal literal with a type suffix naming the primitive type it has, such as `2u32`
+  SuffixedNumber(Number, Identifier),
   Keyword(Keyword),
   Operator(Operator),
+  /// A preprocessor directive such as `#if`, which is consumed by the Lexer and never reaches the Parser
+  Directive(Identifier),
 }
 
 impl TokenData {
@@ -42,9 +47,10 @@ impl TokenData {
     match self {
       TokenData::Invalid => TokenKind::Invalid,
       TokenData::Identifier(_) => TokenKind::Identifier,
-      TokenData::Constant(_) => TokenKind::Constant,
+      TokenData::Constant(_) | TokenData::SuffixedNumber(..) => TokenKind::Constant,
       TokenData::Keyword(_) => TokenKind::Keyword,
       TokenData::Operator(_) => TokenKind::Operator,
+      TokenData::Directive(_) => TokenKind::Directive,
     }
   }
 
@@ -123,6 +129,18 @@ impl Token {
   pub fn no_src (data: TokenData) -> Self {
     Self { data, origin: SourceRegion::ANONYMOUS }
   }
+
+  /// Get the original spelling of a Constant Token, such as its radix prefix and digit separators,
+  /// if the Token has a SourceRegion origin
+  /// 
+  /// Returns None for other variants of Token
+  pub fn literal_text (&self) -> Option<String> {
+    if let TokenData::Constant(_) | TokenData::SuffixedNumber(..) = self.data {
+      SOURCE_MANAGER.get_region_text(self.origin)
+    } else {
+      None
+    }
+  }
 }
 
 impl Deref for Token {
@@ -146,4 +164,37 @@ impl Display for Token {
       self.data
     )
   }
+}
+
+
+#[cfg(test)]
+mod test {
+  use mod_common::{ Constant, Number, };
+
+  use super::*;
+  use crate::{
+    lexer::{ Lexer, },
+    test_support::lock_singletons,
+  };
+
+  #[test]
+  fn literal_text () {
+    let _guard = lock_singletons();
+
+    let source = SOURCE_MANAGER.add_source("literal_text.ms", "1_000 007 0.50 0xFF x");
+    let tokens = Lexer::new(source).lex_stream();
+
+    assert_eq!(tokens[0].data, TokenData::Constant(Constant::Number(Number::Integer(1000))));
+    assert_eq!(tokens[0].literal_text().as_deref(), Some("1_000"));
+
+    assert_eq!(tokens[1].data, TokenData::Constant(Constant::Number(Number::Integer(7))));
+    assert_eq!(tokens[1].literal_text().as_deref(), Some("007"));
+
+    assert_eq!(tokens[2].literal_text().as_deref(), Some("0.50"));
+
+    assert_eq!(tokens[3].data, TokenData::Constant(Constant::Number(Number::Integer(255))));
+    assert_eq!(tokens[3].literal_text().as_deref(), Some("0xFF"));
+
+    assert_eq!(tokens[4].literal_text(), None);
+  }
 }
\ No newline at end of file
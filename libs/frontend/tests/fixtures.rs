@@ -0,0 +1,224 @@
+//! Golden file tests for the frontend
+//! 
+//! Every `.ms` file under `tests/fixtures` is lexed, parsed, and analyzed,
+//! and the resulting Messages are compared against the contents of the `.expected` file beside it.
+//! 
+//! Set the environment variable `MOD_UPDATE_FIXTURES=1` to regenerate the `.expected` files from the current output
+//! 
+//! Alternatively, a fixture may embed its expected Messages inline, as annotations at the end of the line they should fire on:
+//! `//~ ERROR some text`, where the kind is one of `ERROR`, `WARNING`, or `NOTICE`,
+//! and the text must be contained in the Message's content. Each `^` following `//~` moves the expectation up one line.
+//! Fixtures with inline annotations are not compared against `.expected` files,
+//! instead every annotation must be matched by a Message, and every Message must be matched by an annotation
+
+use std::{
+  fmt::{ Write, },
+  fs,
+  path::{ Path, PathBuf, },
+};
+
+use mod_frontend::{
+  session::{ SESSION, Message, MessageItem, MessageKind, },
+  source::{ SOURCE_MANAGER, },
+  lexer::{ Lexer, },
+  parser::{ Parser, },
+  analyzer::{ Analyzer, },
+};
+
+
+const UPDATE_VAR: &str = "MOD_UPDATE_FIXTURES";
+const ANNOTATION: &str = "//~";
+
+
+/// A Message expected to be produced by a fixture, on a specific line
+struct Expectation {
+  line: u32,
+  kind: MessageKind,
+  text: String,
+}
+
+
+/// Extract inline annotations from a fixture source,
+/// replacing them with whitespace so the lexer does not see them and columns are preserved
+fn extract_expectations (path: &Path, content: &str) -> (String, Vec<Expectation>) {
+  let mut stripped = String::with_capacity(content.len());
+  let mut expectations = Vec::new();
+
+  for (line_index, line) in content.split('\n').enumerate() {
+    if line_index != 0 { stripped.push('\n'); }
+
+    let annotation_index = if let Some(index) = line.find(ANNOTATION) { index } else {
+      stripped.push_str(line);
+      continue
+    };
+
+    let (code, annotation) = line.split_at(annotation_index);
+
+    stripped.push_str(code);
+    stripped.extend(annotation.chars().map(|ch| if ch == '\r' { ch } else { ' ' }));
+
+    let annotation = annotation[ANNOTATION.len()..].trim_end_matches('\r');
+    let offset = annotation.chars().take_while(|&ch| ch == '^').count();
+    let annotation = annotation[offset..].trim();
+
+    let (kind_str, text) = annotation.split_at(annotation.find(' ').unwrap_or_else(|| annotation.len()));
+
+    let kind = match kind_str {
+      "ERROR" => MessageKind::Error,
+      "WARNING" => MessageKind::Warning,
+      "NOTICE" => MessageKind::Notice,
+      _ => panic!("{}:{}: invalid annotation kind `{}`", path.display(), line_index + 1, kind_str)
+    };
+
+    assert!(offset <= line_index, "{}:{}: annotation refers to a line before the start of the file", path.display(), line_index + 1);
+
+    expectations.push(Expectation { line: (line_index - offset) as u32, kind, text: text.trim().to_owned() });
+  }
+
+  (stripped, expectations)
+}
+
+/// Match each Message against the expectations, returning a description of any mismatches
+fn check_expectations (path: &Path, messages: &[Message], expectations: &[Expectation]) -> Option<String> {
+  let mut matched = vec! [ false; expectations.len() ];
+  let mut problems = Vec::new();
+
+  'messages: for message in messages.iter() {
+    let item = message.items.first().unwrap();
+
+    for (expectation, matched) in expectations.iter().zip(matched.iter_mut()) {
+      if !*matched
+      && expectation.kind == message.kind
+      && expectation.line == item.origin.start.line
+      && item.content.contains(expectation.text.as_str()) {
+        *matched = true;
+        continue 'messages
+      }
+    }
+
+    problems.push(format!("unexpected {}", render_messages(std::slice::from_ref(message)).trim_end()));
+  }
+
+  for (expectation, matched) in expectations.iter().zip(matched.iter()) {
+    if !matched {
+      problems.push(format!("expected {} [{}] containing `{}` was not produced", expectation.kind.get_name(), expectation.line + 1, expectation.text));
+    }
+  }
+
+  if problems.is_empty() {
+    None
+  } else {
+    Some(format!("{}:\n{}\n", path.display(), problems.join("\n")))
+  }
+}
+
+
+fn fixture_dir () -> PathBuf {
+  Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures")
+}
+
+fn collect_fixtures () -> Vec<PathBuf> {
+  let mut fixtures: Vec<PathBuf> =
+    fs::read_dir(fixture_dir())
+      .expect("Failed to read fixture directory")
+      .map(|entry| entry.expect("Failed to read fixture directory entry").path())
+      .filter(|path| path.extension().map(|ext| ext == "ms").unwrap_or(false))
+      .collect();
+
+  fixtures.sort();
+
+  fixtures
+}
+
+
+fn render_item (out: &mut String, label: &str, item: &MessageItem) {
+  writeln!(
+    out,
+    "{} [{}:{}]: {}",
+    label,
+    item.origin.start.line + 1,
+    item.origin.start.column + 1,
+    item.content
+  ).unwrap();
+}
+
+fn render_messages (messages: &[Message]) -> String {
+  let mut out = String::new();
+
+  if messages.is_empty() {
+    out.push_str("No messages\n");
+  }
+
+  for message in messages.iter() {
+    let mut items = message.items.iter();
+
+    render_item(&mut out, message.kind.get_name(), items.next().unwrap());
+
+    for item in items {
+      render_item(&mut out, "  Note", item);
+    }
+  }
+
+  out
+}
+
+
+fn run_fixture (name: &str, content: &str) -> Vec<Message> {
+  let message_base = SESSION.messages().len();
+
+  let source = SOURCE_MANAGER.add_source(name, content);
+
+  let tokens = Lexer::new(source).lex_stream();
+  let ast = Parser::new(&tokens).parse_ast();
+  let _ = Analyzer::new().analyze(ast);
+
+  SESSION.messages()[message_base..].to_vec()
+}
+
+
+#[test]
+fn fixtures () {
+  SESSION.init();
+  SOURCE_MANAGER.init(fixture_dir().join("modules"));
+
+  let update = std::env::var(UPDATE_VAR).map(|value| value == "1").unwrap_or(false);
+
+  let fixtures = collect_fixtures();
+  assert!(!fixtures.is_empty(), "No fixtures found in {}", fixture_dir().display());
+
+  let mut failures = Vec::new();
+
+  for path in fixtures.iter() {
+    let name = path.file_name().unwrap().to_str().unwrap();
+    let content = fs::read_to_string(path).expect("Failed to read fixture source");
+
+    let (content, expectations) = extract_expectations(path, &content);
+
+    let messages = run_fixture(name, &content);
+
+    if !expectations.is_empty() {
+      if let Some(failure) = check_expectations(path, &messages, &expectations) {
+        failures.push(failure);
+      }
+
+      continue
+    }
+
+    let actual = render_messages(&messages);
+    let expected_path = path.with_extension("expected");
+
+    if update {
+      fs::write(&expected_path, &actual).expect("Failed to write expected output");
+      continue
+    }
+
+    match fs::read_to_string(&expected_path) {
+      Ok(expected) => if expected != actual {
+        failures.push(format!("{}:\n--- expected ---\n{}--- actual ---\n{}", path.display(), expected, actual));
+      },
+      Err(_) => failures.push(format!("{}: missing {}, run with {}=1 to create it", path.display(), expected_path.display(), UPDATE_VAR)),
+    }
+  }
+
+  assert!(failures.is_empty(), "{} fixture(s) failed:\n\n{}", failures.len(), failures.join("\n"));
+}
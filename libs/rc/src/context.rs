@@ -302,7 +302,8 @@ impl Context {
     let mut offsets = Vec::new();
     let mut aligns = Vec::new();
     let mut offset = 0usize;
-    let mut align = if is_packed { 1usize } else { 0usize };
+    // Structures with no fields are legal, they have a size of 0 and an alignment of 1
+    let mut align = 1usize;
 
     for field_ty in types.iter().map(|&tl| self.get_type(tl)) {
       llvm_types.push(field_ty.llvm);
@@ -34,4 +34,16 @@ mod test {
       ctx.tl_structure(field_tys, true)
     );
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn empty_struct () {
+    let mut ctx = context::Context::default();
+
+    for &is_packed in &[ false, true ] {
+      let ty = ctx.get_type(ctx.tl_structure(&[], is_packed));
+
+      assert_eq!(ty.size, 0);
+      assert_eq!(ty.align, 1);
+    }
+  }
+}
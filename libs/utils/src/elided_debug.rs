@@ -0,0 +1,73 @@
+use std::fmt::{ Debug, Formatter, Result as FMTResult, };
+
+
+/// Debug formatting wrapper for slices which prints only the first `threshold` elements of a long slice,
+/// followed by a summary of how many elements were left out
+/// 
+/// Slices with `threshold` elements or fewer are printed fully
+/// 
+/// Only the wrapped slice itself is elided; each printed element uses its own `Debug` implementation,
+/// so any sequences nested inside the elements are still printed in full.
+/// Wrap nested sequences in their own ElidedDebug where they are formatted to elide them as well
+pub struct ElidedDebug<'a, T> {
+  /// The slice being formatted
+  pub items: &'a [T],
+  /// The maximum number of elements printed
+  pub threshold: usize,
+}
+
+impl<'a, T> ElidedDebug<'a, T> {
+  /// The threshold used by `ElidedDebug::new`
+  pub const DEFAULT_THRESHOLD: usize = 32;
+
+  /// Create a new ElidedDebug with the default threshold
+  pub fn new (items: &'a [T]) -> Self {
+    Self::with_threshold(items, Self::DEFAULT_THRESHOLD)
+  }
+
+  /// Create a new ElidedDebug with a custom threshold
+  pub fn with_threshold (items: &'a [T], threshold: usize) -> Self {
+    Self { items, threshold }
+  }
+}
+
+impl<'a, T: Debug> Debug for ElidedDebug<'a, T> {
+  fn fmt (&self, f: &mut Formatter) -> FMTResult {
+    if self.items.len() <= self.threshold {
+      return f.debug_list().entries(self.items.iter()).finish()
+    }
+
+    struct Summary (usize);
+
+    impl Debug for Summary {
+      fn fmt (&self, f: &mut Formatter) -> FMTResult { write!(f, "... ({} more items)", self.0) }
+    }
+
+    f.debug_list()
+      .entries(self.items[..self.threshold].iter())
+      .entry(&Summary(self.items.len() - self.threshold))
+      .finish()
+  }
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn long_slice_elided () {
+    let items: Vec<u32> = (0..10).collect();
+
+    assert_eq!(format!("{:?}", ElidedDebug::with_threshold(&items, 3)), "[0, 1, 2, ... (7 more items)]");
+    assert_eq!(format!("{:#?}", ElidedDebug::with_threshold(&items, 1)), "[\n    0,\n    ... (9 more items),\n]");
+  }
+
+  #[test]
+  fn short_slice_full () {
+    let items = [ 1, 2, 3 ];
+
+    assert_eq!(format!("{:?}", ElidedDebug::with_threshold(&items, 3)), "[1, 2, 3]");
+    assert_eq!(format!("{:?}", ElidedDebug::new(&items)), format!("{:?}", items));
+  }
+}
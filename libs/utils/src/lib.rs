@@ -55,4 +55,7 @@ mod un_escape_str;
 pub use un_escape_str::*;
 
 mod either;
-pub use either::*;
\ No newline at end of file
+pub use either::*;
+
+mod elided_debug;
+pub use elided_debug::ElidedDebug;
\ No newline at end of file